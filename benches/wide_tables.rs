@@ -0,0 +1,55 @@
+//! Conversion time of tables with many keys, which grows linearly as every
+//! entry is written into the same buffer instead of being concatenated into
+//! its parent. Run with `cargo bench --bench wide_tables`
+
+use std::time::{Duration, Instant};
+
+use toml2lua::parse;
+
+/// Runs per size, the fastest one is reported
+const RUNS: usize = 5;
+
+/// Document with the given number of keys, either all in the root table or
+/// each holding a table of its own
+fn wide_table(keys: usize, nested: bool) -> String {
+	(0..keys)
+		.map(|i| {
+			if nested {
+				format!("[table_{}]\nvalue = \"item {}\"\n", i, i)
+			} else {
+				format!("key_{} = {}\n", i, i)
+			}
+		})
+		.collect()
+}
+
+fn fastest(toml: &str) -> Duration {
+	(0..RUNS)
+		.map(|_| {
+			let start = Instant::now();
+			parse(toml).unwrap();
+			start.elapsed()
+		})
+		.min()
+		.unwrap()
+}
+
+fn main() {
+	for nested in [false, true] {
+		let mut previous: Option<Duration> = None;
+
+		println!("{}", if nested { "nested tables" } else { "flat keys" });
+
+		for keys in [10_000, 20_000, 40_000, 80_000] {
+			let elapsed = fastest(&wide_table(keys, nested));
+
+			// Close to 2 when linear, 4 when quadratic
+			let growth = previous
+				.map(|previous| format!("{:.2}x", elapsed.as_secs_f64() / previous.as_secs_f64()))
+				.unwrap_or_default();
+
+			println!("{:>8} keys {:>12.3?} {}", keys, elapsed, growth);
+			previous = Some(elapsed);
+		}
+	}
+}
@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use toml2lua::{parse_bytes, verify_roundtrip, ParseOptions};
+
+// Any input either fails to parse or converts into Lua that loads back into the same values
+fuzz_target!(|data: &[u8]| {
+	let Ok(lua) = parse_bytes(data) else {
+		return;
+	};
+
+	let Ok(toml) = std::str::from_utf8(data) else {
+		return;
+	};
+
+	if let Err(err) = verify_roundtrip(toml.trim_start_matches('\u{FEFF}'), &ParseOptions::default()) {
+		panic!("{}\n{}", err, lua);
+	}
+});
@@ -0,0 +1,97 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use toml::Value;
+use toml2lua::{
+	parse_map, verify_roundtrip, BoolStyle, Indent, Map, NumberMode, ParseOptions, VerifyError,
+	Wrap,
+};
+
+#[derive(Debug, Arbitrary)]
+enum Node {
+	String(String),
+	Integer(i64),
+	Float(f64),
+	Boolean(bool),
+	Array(Vec<Node>),
+	Table(Vec<(String, Node)>),
+}
+
+impl Node {
+	fn into_value(self) -> Value {
+		match self {
+			Node::String(s) => Value::String(s),
+			Node::Integer(i) => Value::Integer(i),
+			Node::Float(f) => Value::Float(f),
+			Node::Boolean(b) => Value::Boolean(b),
+			Node::Array(items) => Value::Array(items.into_iter().map(Node::into_value).collect()),
+			Node::Table(entries) => Value::Table(
+				entries
+					.into_iter()
+					.map(|(key, node)| (key, node.into_value()))
+					.collect(),
+			),
+		}
+	}
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+	document: Vec<(String, Node)>,
+	bare_keys: bool,
+	escape_unicode: bool,
+	trailing_commas: bool,
+	spaces: Option<u8>,
+	number_mode: u8,
+	numeric_bools: bool,
+	numeric_keys_as_array: bool,
+	split_locals: bool,
+}
+
+// Arbitrary value trees always convert into Lua that loads back into the same values
+fuzz_target!(|input: Input| {
+	let map: Map = input
+		.document
+		.into_iter()
+		.map(|(key, node)| (key, node.into_value()))
+		.collect();
+
+	let options = ParseOptions {
+		bare_keys: input.bare_keys,
+		escape_unicode: input.escape_unicode,
+		trailing_commas: input.trailing_commas,
+		indent: match input.spaces {
+			Some(spaces) => Indent::Spaces(usize::from(spaces % 8)),
+			None => Indent::Tabs,
+		},
+		number_mode: match input.number_mode % 3 {
+			0 => NumberMode::Preserve,
+			1 => NumberMode::AllFloat,
+			_ => NumberMode::AllInteger,
+		},
+		bool_style: if input.numeric_bools {
+			BoolStyle::Numeric
+		} else {
+			BoolStyle::Lua
+		},
+		numeric_keys_as_array: input.numeric_keys_as_array,
+		split_locals: input.split_locals,
+		wrap: Wrap::Return,
+		..Default::default()
+	};
+
+	let Ok(lua) = parse_map(&map, &options) else {
+		return;
+	};
+
+	// Compared through TOML text, which is what `verify_roundtrip` accepts
+	let Ok(toml) = toml::to_string(&map) else {
+		return;
+	};
+
+	match verify_roundtrip(&toml, &options) {
+		Ok(()) | Err(VerifyError::Unsupported(_) | VerifyError::Convert(_)) => {}
+		Err(err) => panic!("{}\n{}", err, lua),
+	}
+});
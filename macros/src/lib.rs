@@ -0,0 +1,162 @@
+//! # toml2lua-macros
+//!
+//! Compile-time TOML to Lua table conversion powered by [toml2lua](https://docs.rs/toml2lua)
+//!
+//! ## Example:
+//! ```rust
+//! use toml2lua_macros::include_toml_as_lua;
+//!
+//! const CONFIG: &str = include_toml_as_lua!("tests/fixtures/app.toml", wrap = "return");
+//!
+//! assert!(CONFIG.starts_with("return {"));
+//! ```
+//!
+//! This crate depends on `toml2lua` itself, so it can't be re-exported from it
+//! without creating a dependency cycle and has to be added separately
+//!
+//! Made with <3 by Dervex
+
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, Span};
+use quote::quote;
+use std::{env, fs, path::PathBuf};
+use syn::{
+	parse::{Parse, ParseStream},
+	parse_macro_input, Ident, Lit, LitStr, Token,
+};
+use toml2lua::{parse_with_options, Indent, ParseOptions, Wrap};
+
+/// Convert TOML file into a Lua table string literal at compile time
+///
+/// The path is resolved relative to `CARGO_MANIFEST_DIR`. Options are loaded from
+/// `toml2lua.toml` next to the manifest if there is one, optional `key = value`
+/// arguments override them:
+/// - `indent = "tabs"` or `indent = 4` (number of spaces)
+/// - `wrap = "return"` or `wrap = "local <name>"`
+/// - `bare_keys`, `trailing_commas` and `final_newline` booleans
+///
+/// ```rust
+/// use toml2lua_macros::include_toml_as_lua;
+///
+/// const CONFIG: &str = include_toml_as_lua!("tests/fixtures/app.toml", indent = 2, bare_keys = true);
+///
+/// assert!(CONFIG.contains("  name = \"app\","));
+/// ```
+#[proc_macro]
+pub fn include_toml_as_lua(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as Input);
+
+	match expand(input) {
+		Ok(tokens) => tokens.into(),
+		Err(err) => err.to_compile_error().into(),
+	}
+}
+
+struct Input {
+	path: LitStr,
+	options: ParseOptions,
+}
+
+impl Parse for Input {
+	fn parse(input: ParseStream) -> syn::Result<Self> {
+		let path: LitStr = input.parse()?;
+		let mut options = ParseOptions::discover(&manifest_dir()?)
+			.map_err(|err| syn::Error::new(Span::call_site(), err))?;
+
+		while !input.is_empty() {
+			input.parse::<Token![,]>()?;
+
+			if input.is_empty() {
+				break;
+			}
+
+			let name: Ident = input.parse()?;
+			input.parse::<Token![=]>()?;
+			let value: Lit = input.parse()?;
+
+			match (name.to_string().as_str(), &value) {
+				("indent", Lit::Int(width)) => {
+					options.indent = Indent::Spaces(width.base10_parse()?)
+				}
+				("indent", Lit::Str(style)) if style.value() == "tabs" => {
+					options.indent = Indent::Tabs
+				}
+				("wrap", Lit::Str(wrap)) => options.wrap = parse_wrap(wrap)?,
+				("bare_keys", Lit::Bool(enabled)) => options.bare_keys = enabled.value,
+				("trailing_commas", Lit::Bool(enabled)) => options.trailing_commas = enabled.value,
+				("final_newline", Lit::Bool(enabled)) => options.final_newline = enabled.value,
+				("indent" | "wrap" | "bare_keys" | "trailing_commas" | "final_newline", _) => {
+					return Err(syn::Error::new(
+						value.span(),
+						format!("invalid value for `{}`", name),
+					));
+				}
+				_ => {
+					return Err(syn::Error::new(
+						name.span(),
+						format!("unknown option `{}`", name),
+					))
+				}
+			}
+		}
+
+		Ok(Self { path, options })
+	}
+}
+
+fn parse_wrap(wrap: &LitStr) -> syn::Result<Wrap> {
+	let value = wrap.value();
+
+	match value.split_once(' ') {
+		None if value == "return" => Ok(Wrap::Return),
+		None if value == "none" => Ok(Wrap::None),
+		Some(("local", name)) => Ok(Wrap::Local(name.trim().to_owned())),
+		_ => Err(syn::Error::new(
+			wrap.span(),
+			"expected `return`, `none` or `local <name>`",
+		)),
+	}
+}
+
+fn manifest_dir() -> syn::Result<PathBuf> {
+	env::var("CARGO_MANIFEST_DIR")
+		.map(PathBuf::from)
+		.map_err(|_| syn::Error::new(Span::call_site(), "CARGO_MANIFEST_DIR is not set"))
+}
+
+fn expand(input: Input) -> syn::Result<proc_macro2::TokenStream> {
+	let manifest_dir = manifest_dir()?;
+	let path = manifest_dir.join(input.path.value());
+
+	let toml = fs::read_to_string(&path).map_err(|err| {
+		syn::Error::new(
+			input.path.span(),
+			format!("failed to read `{}`: {}", path.display(), err),
+		)
+	})?;
+
+	let lua = parse_with_options(&toml, &input.options).map_err(|err| {
+		syn::Error::new(
+			input.path.span(),
+			format!("failed to convert `{}`: {}", path.display(), err),
+		)
+	})?;
+
+	let lua = Literal::string(&lua);
+	let path = path.to_string_lossy().into_owned();
+
+	// Referencing the files makes Cargo rebuild when they change, like `include_str!`
+	let config = manifest_dir.join(ParseOptions::CONFIG_FILE);
+	let config = config.is_file().then(|| {
+		let config = config.to_string_lossy().into_owned();
+		quote! { const _: &[u8] = include_bytes!(#config); }
+	});
+
+	Ok(quote! {
+		{
+			const _: &[u8] = include_bytes!(#path);
+			#config
+			#lua
+		}
+	})
+}
@@ -0,0 +1,48 @@
+use toml2lua_macros::include_toml_as_lua;
+
+#[test]
+fn default_options() {
+	const LUA: &str = include_toml_as_lua!("tests/fixtures/app.toml");
+
+	let lua = r#"{
+	["name"] = "app",
+	["port"] = 8080,
+	["database"] = {
+		["url"] = "localhost",
+	},
+}"#;
+
+	assert_eq!(LUA, lua);
+}
+
+#[test]
+fn custom_options() {
+	const LUA: &str = include_toml_as_lua!(
+		"tests/fixtures/app.toml",
+		indent = 2,
+		wrap = "return",
+		bare_keys = true,
+		trailing_commas = false,
+	);
+
+	let lua = r#"return {
+  name = "app",
+  port = 8080,
+  database = {
+    url = "localhost"
+  }
+}"#;
+
+	assert_eq!(LUA, lua);
+}
+
+#[test]
+fn local_wrap() {
+	let lua = include_toml_as_lua!(
+		"tests/fixtures/app.toml",
+		wrap = "local config",
+		indent = "tabs"
+	);
+
+	assert!(lua.starts_with("local config = {\n\t[\"name\"]"));
+}
@@ -0,0 +1,108 @@
+//! Test harness for toml2lua built without its `std` feature
+//!
+//! Run on its own so features are not unified with the rest of the workspace:
+//! `cargo test -p toml2lua-no-std` and `cargo build -p toml2lua-no-std --target thumbv7em-none-eabihf`
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+use toml2lua::{Error, ParseOptions};
+
+/// Convert TOML using the pretty preset, the entry point an embedded caller would use
+pub fn convert(toml: &str) -> Result<String, Error> {
+	toml2lua::parse_with_options(toml, &ParseOptions::pretty())
+}
+
+#[cfg(test)]
+mod test {
+	use alloc::string::{String, ToString};
+	use toml2lua::{
+		merge_maps, parse, parse_bytes, parse_many, Error, Map, MergeOrder, ParseOptions,
+	};
+
+	// Without std keys are sorted, documents below are written in sorted order
+
+	#[test]
+	fn convert() {
+		let toml = r#"
+		int = 420
+		name = "str"
+
+		[object]
+		array = [1, 2.5, true]
+		key = "value"
+"#;
+
+		let lua = r#"{
+    int = 420,
+    name = "str",
+
+    object = {
+        array = {
+            1,
+            2.5,
+            true
+        },
+        key = "value"
+    }
+}
+"#;
+
+		assert_eq!(super::convert(toml).unwrap(), lua);
+		assert_eq!(parse("a = 1").unwrap(), "{\n\t[\"a\"] = 1,\n}");
+	}
+
+	#[test]
+	fn bytes_and_errors() {
+		assert_eq!(
+			parse_bytes(b"\xEF\xBB\xBFa = 'b'").unwrap(),
+			"{\n\t[\"a\"] = \"b\",\n}"
+		);
+		assert!(matches!(
+			parse_bytes(b"a = '\xFF'"),
+			Err(Error::InvalidUtf8 { .. })
+		));
+		assert_eq!(parse("a =").unwrap_err().path(), Some("a"));
+
+		let options = ParseOptions {
+			homogeneous_arrays: true,
+			..Default::default()
+		};
+
+		assert_eq!(
+			toml2lua::parse_with_options("a = [1, 'b']", &options)
+				.unwrap_err()
+				.to_string(),
+			"array `a` mixes integer and string elements"
+		);
+	}
+
+	#[test]
+	fn maps() {
+		let docs = [
+			(String::from("b"), String::from("x = 1")),
+			(String::from("a"), String::from("x = 2")),
+		];
+
+		assert_eq!(
+			parse_many(docs, &ParseOptions::default()).unwrap(),
+			"{\n\t[\"b\"] = {\n\t\t[\"x\"] = 1,\n\t},\n\t[\"a\"] = {\n\t\t[\"x\"] = 2,\n\t},\n}"
+		);
+
+		let a = Map::from_iter([(String::from("y"), 1.into()), (String::from("x"), 2.into())]);
+		let b = Map::from_iter([(String::from("y"), 3.into())]);
+
+		// Sorted keys are in the same order, so workspace builds enabling `preserve_order` pass too
+		let merged = merge_maps(a, b, MergeOrder::LastSeen);
+
+		assert_eq!(
+			merged
+				.keys()
+				.map(String::as_str)
+				.collect::<alloc::vec::Vec<_>>(),
+			["x", "y"]
+		);
+	}
+}
@@ -0,0 +1,175 @@
+use alloc::{format, string::String};
+
+use crate::{AccessorModule, Indent, ParseOptions, Wrap};
+
+/// Names the module code declares or calls, locals of the document can't take them
+pub(crate) const NAMES: &[&str] = &["data", "M", "string", "type", "tonumber", "error"];
+
+const GET: &str = r#"
+local M = { data = data }
+
+function M.get(path)
+	local value = data
+
+	for key in string.gmatch(path, "[^.]+") do
+		local nested = nil
+
+		if type(value) == "table" then
+			nested = value[key]
+
+			if nested == nil and tonumber(key) then
+				nested = value[tonumber(key)]
+			end
+		end
+
+		if nested == nil then
+			error("no value at `" .. path .. "`, key `" .. key .. "` is missing", 2)
+		end
+
+		value = nested
+	end
+
+	return value
+end
+"#;
+
+/// Lua following `local data = { ... }` that builds and returns the module, `getters`
+/// holds the name and member access of every top-level getter
+pub(crate) fn module_code(
+	getters: &[(String, String)],
+	module: &AccessorModule,
+	options: &ParseOptions,
+) -> String {
+	let mut lua = String::from(GET);
+
+	if module.getters {
+		for (name, member) in getters {
+			lua.push_str(&format!(
+				"\nfunction M.{}()\n\treturn data{}\nend\n",
+				name, member
+			));
+		}
+	}
+
+	lua.push_str("\nreturn M");
+
+	match options.indent {
+		Indent::Tabs => lua,
+		Indent::Spaces(width) => lua.replace('\t', &" ".repeat(width)),
+	}
+}
+
+/// Lua following the table that declares the function of [`ParseOptions::with_accessor`]
+pub(crate) fn accessor_function(name: &str, options: &ParseOptions) -> String {
+	let table = match &options.wrap {
+		Wrap::Local(table) => table.as_str(),
+		_ => "data",
+	};
+
+	let mut lua = format!(
+		"\nlocal function {}(key)\n\treturn {}[key]\nend",
+		name, table
+	);
+
+	if options.wrap == Wrap::Return {
+		lua.push_str(&format!("\n\nreturn {}", name));
+	}
+
+	match options.indent {
+		Indent::Tabs => lua,
+		Indent::Spaces(width) => lua.replace('\t', &" ".repeat(width)),
+	}
+}
+
+#[cfg(all(test, any(feature = "verify", feature = "validate")))]
+mod test {
+	use mlua::Lua;
+
+	use crate::{parse_with_options, AccessorModule, Indent, ParseOptions, Wrap};
+
+	#[test]
+	fn accessors() {
+		let toml = r#"
+		name = "server"
+		get = "not a getter"
+		"with space" = 1
+		enabled = false
+
+		[limits]
+		memory = 512
+
+		[[users]]
+		name = "root"
+"#;
+
+		for options in [
+			ParseOptions {
+				accessor_module: Some(AccessorModule { getters: true }),
+				..Default::default()
+			},
+			ParseOptions {
+				accessor_module: Some(AccessorModule { getters: true }),
+				indent: Indent::Spaces(2),
+				split_locals: true,
+				bare_keys: true,
+				..Default::default()
+			},
+		] {
+			let lua = parse_with_options(toml, &options).unwrap();
+
+			Lua::new()
+				.load(format!(
+					r#"
+					local M = (function() {} end)()
+
+					assert(M.get("name") == "server")
+					assert(M.get("enabled") == false)
+					assert(M.get("limits.memory") == 512)
+					assert(M.get("users.1.name") == "root")
+					assert(M.get("with space") == 1)
+					assert(M.data.limits == M.limits())
+					assert(M.name() == "server" and M.users()[1].name == "root")
+					assert(type(M.get) == "function" and M.enabled() == false)
+
+					local ok, err = pcall(M.get, "limits.memroy")
+					assert(not ok and err:find("no value at `limits.memroy`, key `memroy` is missing", 1, true), err)
+
+					ok, err = pcall(M.get, "name.first")
+					assert(not ok and err:find("key `first` is missing", 1, true), err)
+					"#,
+					lua
+				))
+				.set_name("accessors")
+				.exec()
+				.unwrap_or_else(|err| panic!("{}\n{}", err, lua));
+		}
+	}
+
+	#[test]
+	fn accessor_function() {
+		let options = ParseOptions {
+			with_accessor: Some(String::from("lookup")),
+			wrap: Wrap::Return,
+			split_locals: true,
+			..Default::default()
+		};
+
+		let toml = "title = \"Hello\"\n[menu]\nquit = \"Quit\"";
+		let lua = parse_with_options(toml, &options).unwrap();
+
+		Lua::new()
+			.load(format!(
+				r#"
+				local lookup = (function() {} end)()
+
+				assert(lookup("title") == "Hello")
+				assert(lookup("menu").quit == "Quit")
+				assert(lookup("missing") == nil)
+				"#,
+				lua
+			))
+			.set_name("accessor")
+			.exec()
+			.unwrap_or_else(|err| panic!("{}\n{}", err, lua));
+	}
+}
@@ -0,0 +1,219 @@
+use std::{
+	io,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+use tokio::{
+	fs,
+	io::{AsyncWrite, AsyncWriteExt},
+	sync::mpsc,
+	task,
+};
+
+use crate::{parse_dir, parse_file_contents, Error, ParseChunks, ParseOptions};
+
+/// Chunks converted ahead of the writer, bounding the memory held when it is slow
+const CHUNKS_AHEAD: usize = 16;
+
+/// Same as [`parse_file`](crate::parse_file), reading the file with `tokio::fs`
+/// and converting it on the blocking thread pool
+///
+/// ```rust
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// use toml2lua::{parse_file_async, ParseOptions};
+///
+/// let path = std::env::temp_dir().join(format!(
+/// 	"toml2lua_parse_file_async_doc_{}.toml",
+/// 	std::process::id()
+/// ));
+/// tokio::fs::write(&path, "key = 1").await.unwrap();
+///
+/// let lua = parse_file_async(&path, &ParseOptions::default()).await.unwrap();
+///
+/// assert_eq!(lua, "{\n\t[\"key\"] = 1,\n}");
+/// # });
+/// ```
+pub async fn parse_file_async(path: &Path, options: &ParseOptions) -> Result<String, Error> {
+	let toml = fs::read_to_string(path).await.map_err(|error| Error::Io {
+		path: path.to_owned(),
+		error,
+	})?;
+
+	let path = path.to_owned();
+	let options = options.clone();
+
+	blocking(move || parse_file_contents(&toml, &path, &options)).await
+}
+
+/// Same as [`parse_dir`](crate::parse_dir), reading the directory tree and
+/// converting it on the blocking thread pool
+pub async fn parse_dir_async(root: &Path, options: &ParseOptions) -> Result<String, Error> {
+	let root: PathBuf = root.to_owned();
+	let options = options.clone();
+
+	blocking(move || parse_dir(&root, &options)).await
+}
+
+/// Convert TOML into a Lua table written to `writer` one top-level entry at a
+/// time like [`ParseChunks`], so the whole output is never held in memory
+///
+/// Chunks are converted on the blocking thread pool and at most a few of them
+/// wait for the writer. Nothing is written when the document fails to parse,
+/// after a later error the writer holds the chunks written before it
+pub async fn parse_to_writer_async<W>(
+	toml: &str,
+	writer: &mut W,
+	options: &ParseOptions,
+) -> Result<(), Error>
+where
+	W: AsyncWrite + Unpin,
+{
+	let toml: Arc<str> = toml.into();
+	let options = options.clone();
+	let (sender, mut receiver) = mpsc::channel(CHUNKS_AHEAD);
+
+	let converter = task::spawn_blocking(move || {
+		for chunk in ParseChunks::new(&toml, &options) {
+			let failed = chunk.is_err();
+
+			// The writer failed and stopped receiving
+			if sender.blocking_send(chunk).is_err() || failed {
+				break;
+			}
+		}
+	});
+
+	while let Some(chunk) = receiver.recv().await {
+		writer
+			.write_all(chunk?.as_bytes())
+			.await
+			.map_err(Error::Stream)?;
+	}
+
+	writer.flush().await.map_err(Error::Stream)?;
+
+	converter.await.map_err(join_error)
+}
+
+/// Run the conversion on the blocking thread pool, panics are resumed in the caller
+async fn blocking<F>(convert: F) -> Result<String, Error>
+where
+	F: FnOnce() -> Result<String, Error> + Send + 'static,
+{
+	task::spawn_blocking(convert).await.map_err(join_error)?
+}
+
+fn join_error(error: task::JoinError) -> Error {
+	match error.try_into_panic() {
+		Ok(panic) => std::panic::resume_unwind(panic),
+		// Only happens while the runtime shuts down
+		Err(error) => Error::Stream(io::Error::other(error)),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{
+		io,
+		pin::Pin,
+		task::{Context, Poll},
+	};
+	use tokio::io::AsyncWrite;
+
+	use super::{parse_dir_async, parse_file_async, parse_to_writer_async};
+	use crate::{parse, Error, ParseOptions};
+
+	#[tokio::test]
+	async fn file() {
+		let dir = crate::test::temp_dir("async_file");
+		tokio::fs::create_dir_all(dir.join("nested")).await.unwrap();
+		tokio::fs::write(dir.join("config.toml"), "key = 1")
+			.await
+			.unwrap();
+		tokio::fs::write(dir.join("nested/more.toml"), "key = 2")
+			.await
+			.unwrap();
+
+		let options = ParseOptions::default();
+
+		assert_eq!(
+			parse_file_async(&dir.join("config.toml"), &options)
+				.await
+				.unwrap(),
+			"{\n\t[\"key\"] = 1,\n}"
+		);
+		assert_eq!(
+			parse_dir_async(&dir, &options).await.unwrap(),
+			crate::parse_dir(&dir, &options).unwrap()
+		);
+
+		let missing = dir.join("missing.toml");
+
+		assert!(matches!(
+			parse_file_async(&missing, &options).await,
+			Err(Error::Io { path, .. }) if path == missing
+		));
+	}
+
+	/// Writer recording the size of every write
+	#[derive(Default)]
+	struct Recorder {
+		written: Vec<u8>,
+		writes: Vec<usize>,
+	}
+
+	impl AsyncWrite for Recorder {
+		fn poll_write(
+			mut self: Pin<&mut Self>,
+			_: &mut Context<'_>,
+			buf: &[u8],
+		) -> Poll<io::Result<usize>> {
+			self.written.extend_from_slice(buf);
+			self.writes.push(buf.len());
+			Poll::Ready(Ok(buf.len()))
+		}
+
+		fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+			Poll::Ready(Ok(()))
+		}
+
+		fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+			Poll::Ready(Ok(()))
+		}
+	}
+
+	#[tokio::test]
+	async fn writer() {
+		let toml: String = (0..2000)
+			.map(|i| {
+				format!(
+					"[section_{}]\nname = \"item {}\"\nvalues = [1, 2, 3]\n",
+					i, i
+				)
+			})
+			.collect();
+
+		let mut recorder = Recorder::default();
+
+		parse_to_writer_async(&toml, &mut recorder, &ParseOptions::default())
+			.await
+			.unwrap();
+
+		assert_eq!(
+			String::from_utf8(recorder.written).unwrap(),
+			parse(&toml).unwrap()
+		);
+
+		// Written a section at a time rather than all at once
+		assert_eq!(recorder.writes.len(), 2000);
+		assert!(recorder.writes.iter().all(|len| *len < 200));
+
+		let mut recorder = Recorder::default();
+
+		assert!(matches!(
+			parse_to_writer_async("key =", &mut recorder, &ParseOptions::default()).await,
+			Err(Error::Toml { .. })
+		));
+		assert!(recorder.written.is_empty());
+	}
+}
@@ -0,0 +1,124 @@
+use alloc::string::String;
+
+use crate::{locate::from_str, merge::merge_deep, parse_map, Error, Map, ParseOptions};
+
+/// Builds a single Lua table out of several TOML documents merged at the top level
+///
+/// Keys of later documents replace the same keys of earlier ones, except for
+/// tables present in both which are merged key by key. Keys keep the position
+/// of their first occurrence
+///
+/// ```rust
+/// use toml2lua::{LuaTableBuilder, ParseOptions};
+///
+/// let mut builder = LuaTableBuilder::new();
+///
+/// builder
+/// 	.add_toml("name = 'base'\n[server]\nhost = 'localhost'")
+/// 	.unwrap()
+/// 	.add_toml("[server]\nport = 80")
+/// 	.unwrap();
+///
+/// let options = ParseOptions {
+/// 	bare_keys: true,
+/// 	..Default::default()
+/// };
+///
+/// assert_eq!(
+/// 	builder.build(&options).unwrap(),
+/// 	"{\n\tname = \"base\",\n\tserver = {\n\t\thost = \"localhost\",\n\t\tport = 80,\n\t},\n}"
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LuaTableBuilder {
+	map: Map,
+}
+
+impl LuaTableBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Merge a TOML document into the table, the table is left untouched when it fails to parse
+	pub fn add_toml(&mut self, toml: &str) -> Result<&mut Self, Error> {
+		let map: Map = from_str(toml)?;
+
+		merge_deep(&mut self.map, map);
+
+		Ok(self)
+	}
+
+	/// Document merged so far
+	pub fn map(&self) -> &Map {
+		&self.map
+	}
+
+	/// Convert the merged document into a Lua table
+	pub fn build(&self, options: &ParseOptions) -> Result<String, Error> {
+		parse_map(&self.map, options)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use toml::Value;
+
+	use super::LuaTableBuilder;
+
+	#[test]
+	fn override_values() {
+		let mut builder = LuaTableBuilder::new();
+
+		builder
+			.add_toml("name = 'first'\nports = [80, 443]\nmode = { fast = true }")
+			.unwrap()
+			.add_toml("ports = [8080]\nmode = 'slow'")
+			.unwrap();
+
+		let map = builder.map();
+
+		assert_eq!(map["name"].as_str(), Some("first"));
+		assert_eq!(map["ports"], Value::Array(vec![Value::from(8080)]));
+		assert_eq!(map["mode"].as_str(), Some("slow"));
+
+		// Failed documents don't change the table
+		assert!(builder.add_toml("name = ").is_err());
+		assert_eq!(builder.map()["name"].as_str(), Some("first"));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn deep_merge() {
+		use crate::ParseOptions;
+
+		let mut builder = LuaTableBuilder::new();
+
+		builder
+			.add_toml("[server]\nport = 80\n[server.tls]\ncert = 'a.pem'\nkey = 'a.key'\n[log]\nlevel = 'info'")
+			.unwrap()
+			.add_toml("[server.tls]\nkey = 'b.key'\n[server]\nhost = 'example.com'")
+			.unwrap();
+
+		let options = ParseOptions {
+			bare_keys: true,
+			..Default::default()
+		};
+
+		assert_eq!(
+			builder.build(&options).unwrap(),
+			r#"{
+	server = {
+		port = 80,
+		tls = {
+			cert = "a.pem",
+			key = "b.key",
+		},
+		host = "example.com",
+	},
+	log = {
+		level = "info",
+	},
+}"#
+		);
+	}
+}
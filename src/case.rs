@@ -0,0 +1,129 @@
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+use crate::{KeyCase, ParseOptions};
+
+/// Key as Lua sees it, after [`ParseOptions::key_case`]
+pub(crate) fn lua_key<'k>(key: &'k str, options: &ParseOptions) -> Cow<'k, str> {
+	match options.key_case {
+		KeyCase::Preserve => Cow::Borrowed(key),
+		case => Cow::Owned(convert_case(key, case)),
+	}
+}
+
+pub(crate) fn convert_case(key: &str, case: KeyCase) -> String {
+	let words = words(key);
+	let mut converted = String::new();
+
+	for (i, word) in words.iter().enumerate() {
+		match case {
+			KeyCase::Snake => {
+				if i > 0 {
+					converted.push('_');
+				}
+
+				converted.extend(word.chars().flat_map(char::to_lowercase));
+			}
+			KeyCase::Camel if i == 0 => converted.extend(word.chars().flat_map(char::to_lowercase)),
+			KeyCase::Camel | KeyCase::Pascal => {
+				let mut chars = word.chars();
+
+				if let Some(first) = chars.next() {
+					converted.extend(first.to_uppercase());
+					converted.extend(chars.flat_map(char::to_lowercase));
+				}
+			}
+			KeyCase::Preserve => converted.push_str(word),
+		}
+	}
+
+	converted
+}
+
+/// Words of the key, separated by `_`, `-` or spaces and before an uppercase
+/// letter following a lowercase letter or digit
+fn words(key: &str) -> Vec<&str> {
+	let mut words = Vec::new();
+	let mut start = 0;
+	let mut previous: Option<char> = None;
+
+	for (i, char) in key.char_indices() {
+		if matches!(char, '_' | '-' | ' ') {
+			if start < i {
+				words.push(&key[start..i]);
+			}
+
+			start = i + char.len_utf8();
+		} else if char.is_uppercase()
+			&& previous.is_some_and(|previous| previous.is_lowercase() || previous.is_ascii_digit())
+			&& start < i
+		{
+			words.push(&key[start..i]);
+			start = i;
+		}
+
+		previous = Some(char);
+	}
+
+	if start < key.len() {
+		words.push(&key[start..]);
+	}
+
+	words
+}
+
+#[cfg(test)]
+mod test {
+	use super::convert_case;
+	use crate::KeyCase;
+
+	#[test]
+	fn cases() {
+		let keys = [
+			"my_key",
+			"my-key",
+			"myKey",
+			"MyKey",
+			"HTTP server",
+			"v2Name",
+			"x",
+		];
+		let convert = |case| keys.map(|key| convert_case(key, case));
+
+		assert_eq!(
+			convert(KeyCase::Camel),
+			[
+				"myKey",
+				"myKey",
+				"myKey",
+				"myKey",
+				"httpServer",
+				"v2Name",
+				"x"
+			]
+		);
+		assert_eq!(
+			convert(KeyCase::Pascal),
+			[
+				"MyKey",
+				"MyKey",
+				"MyKey",
+				"MyKey",
+				"HttpServer",
+				"V2Name",
+				"X"
+			]
+		);
+		assert_eq!(
+			convert(KeyCase::Snake),
+			[
+				"my_key",
+				"my_key",
+				"my_key",
+				"my_key",
+				"http_server",
+				"v2_name",
+				"x"
+			]
+		);
+	}
+}
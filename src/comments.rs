@@ -0,0 +1,94 @@
+use std::collections::{BTreeMap, BTreeSet};
+use toml_edit::{DocumentMut, ImDocument, Item, RawString, Value};
+
+use crate::{
+	source::{visit_all_values, visit_values},
+	walk::path_segment,
+};
+
+/// Collect comments placed directly above top-level keys and `[sections]`
+///
+/// Comments inside nested tables or at the end of a line are not collected
+pub(crate) fn leading_comments(toml: &str) -> BTreeMap<String, Vec<String>> {
+	let mut comments = BTreeMap::new();
+
+	// Invalid documents are rejected by the regular deserializer beforehand
+	let Ok(document) = toml.parse::<DocumentMut>() else {
+		return comments;
+	};
+
+	for (key, item) in document.iter() {
+		let prefix = match item {
+			Item::Table(table) => table.decor().prefix(),
+			Item::ArrayOfTables(array) => array.get(0).and_then(|table| table.decor().prefix()),
+			_ => document.key(key).and_then(|key| key.leaf_decor().prefix()),
+		};
+
+		let lines = prefix.map(comment_lines).unwrap_or_default();
+
+		if !lines.is_empty() {
+			comments.insert(path_segment(key), lines);
+		}
+	}
+
+	comments
+}
+
+fn comment_lines(prefix: &RawString) -> Vec<String> {
+	prefix
+		.as_str()
+		.unwrap_or_default()
+		.lines()
+		.filter_map(|line| line.trim().strip_prefix('#'))
+		.map(|comment| {
+			comment
+				.strip_prefix(' ')
+				.unwrap_or(comment)
+				.trim_end()
+				.to_owned()
+		})
+		.collect()
+}
+
+/// Line and column, both starting at 1, of every scalar value keyed by key path
+pub(crate) fn value_positions(toml: &str) -> BTreeMap<Vec<String>, (usize, usize)> {
+	let mut positions = BTreeMap::new();
+
+	let Ok(document) = ImDocument::parse(toml) else {
+		return positions;
+	};
+
+	visit_values(&document, &mut |path, value| {
+		if let Some(span) = value.span() {
+			let before = &toml[..span.start];
+			let line = before.matches('\n').count() + 1;
+			let column = before
+				.rsplit('\n')
+				.next()
+				.unwrap_or_default()
+				.chars()
+				.count() + 1;
+
+			positions.insert(path.to_vec(), (line, column));
+		}
+	});
+
+	positions
+}
+
+/// Key paths of the tables written inline in the source, like `point = { x = 1 }`
+pub(crate) fn inline_tables(toml: &str) -> BTreeSet<Vec<String>> {
+	let mut paths = BTreeSet::new();
+
+	let Ok(document) = ImDocument::parse(toml) else {
+		return paths;
+	};
+
+	visit_all_values(&document, &mut |path, value| {
+		if let Value::InlineTable(_) = value {
+			paths.insert(path.to_vec());
+		}
+	});
+
+	paths
+}
@@ -0,0 +1,363 @@
+use std::{
+	collections::BTreeMap,
+	fs,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, Mutex,
+	},
+};
+
+use toml::Value;
+
+use crate::{
+	header::sha256_hex, parse_map, parse_with_options, render_value, Error, Map, ParseOptions,
+};
+
+/// Converts documents with the same options, reusing the output of documents
+/// it has seen before
+///
+/// Outputs are cached by the SHA-256 hashes of the TOML text and of the options, with
+/// [`with_cache_dir`](Self::with_cache_dir) they are also kept on disk for
+/// other processes. Options holding callbacks or `interpolation` can't be
+/// told apart by their hash, so documents are always converted with them
+///
+/// ```rust
+/// use toml2lua::{Converter, ParseOptions};
+///
+/// let converter = Converter::new(ParseOptions::default());
+///
+/// assert_eq!(&*converter.convert("key = 1").unwrap(), "{\n\t[\"key\"] = 1,\n}");
+/// converter.convert("key = 1").unwrap();
+///
+/// assert_eq!(converter.stats().hits, 1);
+/// ```
+#[derive(Debug)]
+pub struct Converter {
+	options: ParseOptions,
+	/// Hash of the options, `None` when they can't be cached
+	options_hash: Option<String>,
+	/// Directory of the cached outputs of this crate version
+	dir: Option<PathBuf>,
+	/// Outputs keyed by content hash
+	entries: Mutex<BTreeMap<String, Arc<str>>>,
+	hits: AtomicUsize,
+	misses: AtomicUsize,
+}
+
+/// How often a [`Converter`] found the output in its cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+	/// Documents whose output was cached, in memory or on disk
+	pub hits: usize,
+	/// Documents converted and added to the cache
+	pub misses: usize,
+}
+
+impl Converter {
+	pub fn new(options: ParseOptions) -> Self {
+		Self {
+			options_hash: options_hash(&options),
+			options,
+			dir: None,
+			entries: Mutex::new(BTreeMap::new()),
+			hits: AtomicUsize::new(0),
+			misses: AtomicUsize::new(0),
+		}
+	}
+
+	/// Also keep outputs as files in the given directory, shared by every
+	/// converter using it. Outputs of other versions of the crate are not used
+	pub fn with_cache_dir(mut self, dir: &Path) -> Self {
+		self.dir = Some(dir.join(env!("CARGO_PKG_VERSION")));
+		self
+	}
+
+	pub fn options(&self) -> &ParseOptions {
+		&self.options
+	}
+
+	/// Convert the document like [`parse_with_options`], or return its cached output
+	pub fn convert(&self, toml: &str) -> Result<Arc<str>, Error> {
+		let Some(options_hash) = &self.options_hash else {
+			return Ok(parse_with_options(toml, &self.options)?.into());
+		};
+
+		let hash = sha256_hex(toml.as_bytes());
+
+		let path = self
+			.dir
+			.as_ref()
+			.map(|dir| dir.join(format!("{}-{}.lua", hash, options_hash)));
+
+		let cached = self.lock().get(&hash).cloned();
+		let cached = cached.or_else(|| {
+			let lua: Arc<str> = fs::read_to_string(path.as_ref()?).ok()?.into();
+			self.lock().insert(hash.clone(), lua.clone());
+			Some(lua)
+		});
+
+		if let Some(lua) = cached {
+			self.hits.fetch_add(1, Ordering::Relaxed);
+			return Ok(lua);
+		}
+
+		let lua: Arc<str> = parse_with_options(toml, &self.options)?.into();
+
+		self.misses.fetch_add(1, Ordering::Relaxed);
+		self.lock().insert(hash, lua.clone());
+
+		if let Some(path) = path {
+			write_entry(&path, &lua)?;
+		}
+
+		Ok(lua)
+	}
+
+	/// Convert the value like [`render_value`] at the top level, outputs of values aren't cached
+	pub fn convert_value(&self, value: &Value) -> Result<String, Error> {
+		render_value(value, &self.options, 0)
+	}
+
+	/// Convert the deserialized document like [`parse_map`], outputs of maps aren't cached
+	pub fn convert_map(&self, map: &Map) -> Result<String, Error> {
+		parse_map(map, &self.options)
+	}
+
+	pub fn stats(&self) -> CacheStats {
+		CacheStats {
+			hits: self.hits.load(Ordering::Relaxed),
+			misses: self.misses.load(Ordering::Relaxed),
+		}
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Arc<str>>> {
+		// Entries are only ever inserted whole, so a poisoned map is still consistent
+		self.entries
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+/// Hash of everything in the options that affects the output, `None` if
+/// some of it can't be hashed
+fn options_hash(options: &ParseOptions) -> Option<String> {
+	if options.bare_key_predicate.is_some()
+		|| options.integer_format.is_some()
+		|| options.float_format.is_some()
+		|| options.value_transform.is_some()
+		|| options.key_order.is_some()
+		|| options.interpolation.is_some()
+	{
+		return None;
+	}
+
+	// Every option but the callbacks is serialized with its name
+	let serialized = toml::to_string(options).ok()?;
+
+	Some(sha256_hex(serialized.as_bytes()))
+}
+
+/// Write the file through a temporary one, so other processes never read a partial output
+fn write_entry(path: &Path, lua: &str) -> Result<(), Error> {
+	let write_error = |path: &Path| {
+		let path = path.to_owned();
+		move |error| Error::Write { path, error }
+	};
+
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir).map_err(write_error(dir))?;
+	}
+
+	let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+
+	fs::write(&temporary, lua).map_err(write_error(&temporary))?;
+	fs::rename(&temporary, path).map_err(write_error(path))
+}
+
+#[cfg(test)]
+mod test {
+	use std::fs;
+
+	use super::{options_hash, CacheStats, Converter};
+	use crate::ParseOptions;
+
+	#[test]
+	fn cache() {
+		let converter = Converter::new(ParseOptions::default());
+
+		let first = converter.convert("key = 1").unwrap();
+		let second = converter.convert("key = 1").unwrap();
+
+		assert_eq!(first, second);
+		assert_eq!(converter.stats(), CacheStats { hits: 1, misses: 1 });
+
+		converter.convert("key = 2").unwrap();
+		assert!(converter.convert("key =").is_err());
+
+		assert_eq!(converter.stats(), CacheStats { hits: 1, misses: 2 });
+	}
+
+	#[test]
+	fn cache_dir() {
+		let dir = crate::test::temp_dir("cache");
+
+		let options = ParseOptions {
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let converter = Converter::new(options.clone()).with_cache_dir(&dir);
+
+		assert_eq!(&*converter.convert("key = 1").unwrap(), "{\n\tkey = 1,\n}");
+		assert_eq!(converter.stats(), CacheStats { hits: 0, misses: 1 });
+
+		// Another converter reads what the first one wrote
+		let converter = Converter::new(options.clone()).with_cache_dir(&dir);
+
+		assert_eq!(&*converter.convert("key = 1").unwrap(), "{\n\tkey = 1,\n}");
+		assert_eq!(converter.stats(), CacheStats { hits: 1, misses: 0 });
+
+		// Different options don't use the output
+		let converter = Converter::new(ParseOptions {
+			bare_keys: false,
+			..options
+		})
+		.with_cache_dir(&dir);
+
+		assert_eq!(
+			&*converter.convert("key = 1").unwrap(),
+			"{\n\t[\"key\"] = 1,\n}"
+		);
+		assert_eq!(converter.stats(), CacheStats { hits: 0, misses: 1 });
+
+		let entries = fs::read_dir(dir.join(env!("CARGO_PKG_VERSION"))).unwrap();
+		assert_eq!(entries.count(), 2);
+	}
+
+	#[test]
+	fn reuse() {
+		use crate::Map;
+		use toml::Value;
+
+		let converter = Converter::new(ParseOptions {
+			bare_keys: true,
+			..Default::default()
+		});
+
+		let mut map = Map::default();
+		map.insert(String::from("key"), Value::from(1));
+
+		for _ in 0..2 {
+			assert_eq!(&*converter.convert("key = 1").unwrap(), "{\n\tkey = 1,\n}");
+			assert_eq!(converter.convert_map(&map).unwrap(), "{\n\tkey = 1,\n}");
+			assert_eq!(converter.convert_value(&Value::from("a")).unwrap(), "\"a\"");
+			assert_eq!(
+				converter
+					.convert_value(&Value::Array(vec![Value::from(1)]))
+					.unwrap(),
+				"{\n\t1,\n}"
+			);
+		}
+
+		// Only documents given as text are cached
+		assert_eq!(converter.stats(), CacheStats { hits: 1, misses: 1 });
+	}
+
+	#[test]
+	fn uncached_options() {
+		let converter = Converter::new(ParseOptions {
+			integer_format: Some(crate::IntegerFormat::new(|integer| {
+				format!("{:#x}", integer)
+			})),
+			..Default::default()
+		});
+
+		converter.convert("key = 1").unwrap();
+		converter.convert("key = 1").unwrap();
+
+		assert_eq!(converter.stats(), CacheStats::default());
+	}
+
+	#[test]
+	fn callback_options() {
+		use crate::{FloatFormat, IntegerFormat, KeyOrder, KeyPredicate, ValueTransform};
+
+		// Callbacks can't be loaded from config files, so the options skipped by
+		// serde are the ones holding callbacks, which can't be hashed
+		let debug = format!("{:#?}", ParseOptions::default());
+		let skipped: Vec<&str> = debug
+			.lines()
+			.filter_map(|line| line.strip_prefix("    ")?.split_once(": "))
+			.map(|(name, _)| name)
+			.filter(|name| !name.starts_with(' '))
+			.filter(|name| {
+				toml::from_str::<ParseOptions>(&format!("{} = 0", name))
+					.is_err_and(|err| err.message().contains("unknown field"))
+			})
+			.collect();
+
+		assert_eq!(
+			skipped,
+			[
+				"bare_key_predicate",
+				"integer_format",
+				"float_format",
+				"value_transform",
+				"key_order",
+			]
+		);
+
+		let options = [
+			ParseOptions {
+				bare_key_predicate: Some(KeyPredicate::new(|_| true)),
+				..Default::default()
+			},
+			ParseOptions {
+				integer_format: Some(IntegerFormat::new(|integer| integer.to_string())),
+				..Default::default()
+			},
+			ParseOptions {
+				float_format: Some(FloatFormat::new(|float| float.to_string())),
+				..Default::default()
+			},
+			ParseOptions {
+				value_transform: Some(ValueTransform::new(|_, _| None)),
+				..Default::default()
+			},
+			ParseOptions {
+				key_order: Some(KeyOrder::new(|a, b| a.cmp(b))),
+				..Default::default()
+			},
+		];
+
+		for options in options {
+			assert_eq!(options_hash(&options), None, "{:?}", options);
+		}
+	}
+
+	#[test]
+	fn options_hash_differs() {
+		use crate::{LuaTarget, Wrap};
+
+		let hashes = [
+			ParseOptions::default(),
+			ParseOptions::pretty(),
+			ParseOptions::for_target(LuaTarget::Luau),
+			ParseOptions {
+				wrap: Wrap::Local(String::from("a")),
+				..Default::default()
+			},
+			ParseOptions {
+				wrap: Wrap::Local(String::from("b")),
+				..Default::default()
+			},
+		]
+		.map(|options| options_hash(&options).unwrap());
+
+		for (i, hash) in hashes.iter().enumerate() {
+			assert_eq!(hash.len(), 64);
+			assert!(!hashes[..i].contains(hash), "{}", i);
+		}
+	}
+}
@@ -0,0 +1,273 @@
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+use serde::{Deserialize, Serialize};
+use toml::value::{Date, Datetime, Offset, Time};
+
+/// Which of the four forms of TOML datetimes a value has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatetimeKind {
+	/// Date and time with a UTC offset, e.g. `1979-05-27T07:32:00Z`
+	OffsetDatetime,
+	/// Date and time without an offset, e.g. `1979-05-27T07:32:00`
+	LocalDatetime,
+	/// Date alone, e.g. `1979-05-27`
+	LocalDate,
+	/// Time of day alone, e.g. `07:32:00`
+	LocalTime,
+}
+
+impl DatetimeKind {
+	pub fn of(datetime: &Datetime) -> Self {
+		match (datetime.date, datetime.time, datetime.offset) {
+			(Some(_), Some(_), Some(_)) => DatetimeKind::OffsetDatetime,
+			(Some(_), Some(_), None) => DatetimeKind::LocalDatetime,
+			(Some(_), None, _) => DatetimeKind::LocalDate,
+			(None, _, _) => DatetimeKind::LocalTime,
+		}
+	}
+}
+
+impl Display for DatetimeKind {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			DatetimeKind::OffsetDatetime => "offset datetime",
+			DatetimeKind::LocalDatetime => "local datetime",
+			DatetimeKind::LocalDate => "local date",
+			DatetimeKind::LocalTime => "local time",
+		})
+	}
+}
+
+/// Fields of the table a datetime is written as, named like the ones of `os.date("*t")`
+///
+/// Dates have `year`, `month` and `day`, times `hour`, `min`, `sec` and `nsec`
+/// and offset datetimes also `utc_offset` in minutes
+pub(crate) fn fields(datetime: &Datetime) -> Vec<(&'static str, i64)> {
+	let mut fields = Vec::new();
+
+	if let Some(date) = datetime.date {
+		fields.extend([
+			("year", i64::from(date.year)),
+			("month", i64::from(date.month)),
+			("day", i64::from(date.day)),
+		]);
+	}
+
+	if let Some(time) = datetime.time {
+		fields.extend([
+			("hour", i64::from(time.hour)),
+			("min", i64::from(time.minute)),
+			("sec", i64::from(time.second)),
+			("nsec", i64::from(time.nanosecond)),
+		]);
+	}
+
+	if let Some(offset) = datetime.offset {
+		fields.push(("utc_offset", i64::from(offset_minutes(offset))));
+	}
+
+	fields
+}
+
+/// Whole seconds and nanoseconds since the Unix epoch for offset datetimes or
+/// since midnight for local times, other kinds lack the time zone to tell
+pub(crate) fn epoch_seconds(datetime: &Datetime) -> Option<(i64, u32)> {
+	let time = datetime.time?;
+	let of_day = i64::from(time.hour) * 3600 + i64::from(time.minute) * 60 + i64::from(time.second);
+
+	let seconds = match (datetime.date, datetime.offset) {
+		(None, _) => of_day,
+		(Some(date), Some(offset)) => {
+			let days = days_from_civil(
+				i64::from(date.year),
+				i64::from(date.month),
+				i64::from(date.day),
+			);
+
+			days * 86400 + of_day - i64::from(offset_minutes(offset)) * 60
+		}
+		(Some(_), None) => return None,
+	};
+
+	Some((seconds, time.nanosecond))
+}
+
+/// Same instant with a `Z` offset, the seconds and their fraction are kept as
+/// they are. `None` for datetimes without an offset or when the date leaves
+/// the years TOML can write
+pub(crate) fn to_utc(datetime: &Datetime) -> Option<Datetime> {
+	let (Some(date), Some(time), Some(offset)) = (datetime.date, datetime.time, datetime.offset)
+	else {
+		return None;
+	};
+
+	let days = days_from_civil(
+		i64::from(date.year),
+		i64::from(date.month),
+		i64::from(date.day),
+	);
+
+	// Only hours and minutes shift, which keeps leap seconds intact
+	let minutes = days * 1440 + i64::from(time.hour) * 60 + i64::from(time.minute)
+		- i64::from(offset_minutes(offset));
+	let (year, month, day) = civil_from_days(minutes.div_euclid(1440));
+	let of_day = minutes.rem_euclid(1440);
+
+	Some(Datetime {
+		date: Some(Date {
+			year: u16::try_from(year).ok().filter(|year| *year <= 9999)?,
+			month: month as u8,
+			day: day as u8,
+		}),
+		time: Some(Time {
+			hour: (of_day / 60) as u8,
+			minute: (of_day % 60) as u8,
+			..time
+		}),
+		offset: Some(Offset::Z),
+	})
+}
+
+/// Unix epoch in the same form as the datetime, the date 1970-01-01, the time
+/// midnight and the offset `Z`
+pub(crate) fn epoch(datetime: &Datetime) -> Datetime {
+	Datetime {
+		date: datetime.date.map(|_| Date {
+			year: 1970,
+			month: 1,
+			day: 1,
+		}),
+		time: datetime.time.map(|_| Time {
+			hour: 0,
+			minute: 0,
+			second: 0,
+			nanosecond: 0,
+		}),
+		offset: datetime.offset.map(|_| Offset::Z),
+	}
+}
+
+fn offset_minutes(offset: Offset) -> i16 {
+	match offset {
+		Offset::Z => 0,
+		Offset::Custom { minutes } => minutes,
+	}
+}
+
+/// Days between 1970-01-01 and the given date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+	// Years start in March so the leap day is the last day of the year
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+	era * 146_097 + day_of_era - 719_468
+}
+
+/// Year, month and day of the given number of days since 1970-01-01, the
+/// inverse of [`days_from_civil`]
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+	let days = days + 719_468;
+	let era = days.div_euclid(146_097);
+	let day_of_era = days - era * 146_097;
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let shifted_month = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+	let month = if shifted_month < 10 {
+		shifted_month + 3
+	} else {
+		shifted_month - 9
+	};
+	let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+	(year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+	use toml::value::Datetime;
+
+	use super::{epoch_seconds, fields, to_utc, DatetimeKind};
+
+	#[test]
+	fn epoch() {
+		let epoch = |datetime: &str| epoch_seconds(&datetime.parse::<Datetime>().unwrap());
+
+		assert_eq!(epoch("1970-01-01T00:00:00Z"), Some((0, 0)));
+		assert_eq!(epoch("1979-05-27T07:32:00Z"), Some((296_638_320, 0)));
+		assert_eq!(epoch("1979-05-27T00:32:00-07:00"), Some((296_638_320, 0)));
+		assert_eq!(
+			epoch("2000-02-29T12:00:00.5+01:00"),
+			Some((951_822_000, 500_000_000))
+		);
+		assert_eq!(epoch("1969-12-31T23:59:59Z"), Some((-1, 0)));
+		assert_eq!(epoch("07:32:00.25"), Some((27_120, 250_000_000)));
+		assert_eq!(epoch("1979-05-27T07:32:00"), None);
+		assert_eq!(epoch("1979-05-27"), None);
+	}
+
+	#[test]
+	fn kinds() {
+		let kind = |datetime: &str| DatetimeKind::of(&datetime.parse::<Datetime>().unwrap());
+
+		assert_eq!(kind("1979-05-27T07:32:00Z"), DatetimeKind::OffsetDatetime);
+		assert_eq!(kind("1979-05-27T07:32:00"), DatetimeKind::LocalDatetime);
+		assert_eq!(kind("1979-05-27"), DatetimeKind::LocalDate);
+		assert_eq!(kind("07:32:00"), DatetimeKind::LocalTime);
+	}
+
+	#[test]
+	fn utc() {
+		let utc = |datetime: &str| {
+			to_utc(&datetime.parse::<Datetime>().unwrap()).map(|datetime| datetime.to_string())
+		};
+
+		let utc = |datetime| utc(datetime).unwrap_or_default();
+
+		assert_eq!(utc("1979-05-27T00:32:00-07:00"), "1979-05-27T07:32:00Z");
+		assert_eq!(utc("1979-05-27T07:32:00+05:30"), "1979-05-27T02:02:00Z");
+		assert_eq!(utc("1979-05-27T07:32:00Z"), "1979-05-27T07:32:00Z");
+		assert_eq!(
+			utc("2000-03-01T00:15:00.123456+01:00"),
+			"2000-02-29T23:15:00.123456Z"
+		);
+		assert_eq!(utc("2016-12-31T23:59:60-01:00"), "2017-01-01T00:59:60Z");
+		assert_eq!(
+			utc("2017-01-01T00:59:59.999+01:00"),
+			"2016-12-31T23:59:59.999Z"
+		);
+		assert_eq!(utc("9999-12-31T23:00:00-02:00"), "");
+		assert_eq!(utc("1979-05-27T07:32:00"), "");
+	}
+
+	#[test]
+	fn fields_by_kind() {
+		let names = |datetime: &str| -> Vec<&str> {
+			fields(&datetime.parse::<Datetime>().unwrap())
+				.into_iter()
+				.map(|(name, _)| name)
+				.collect()
+		};
+
+		let date = ["year", "month", "day"];
+		let time = ["hour", "min", "sec", "nsec"];
+
+		// A `Z` offset is still an offset, telling it apart from local datetimes
+		assert_eq!(names("1979-05-27T07:32:00Z").last(), Some(&"utc_offset"));
+		assert_eq!(
+			fields(&"1979-05-27T07:32:00Z".parse::<Datetime>().unwrap()).last(),
+			Some(&("utc_offset", 0))
+		);
+		assert_eq!(
+			names("1979-05-27T07:32:00"),
+			[&date[..], &time[..]].concat()
+		);
+		assert_eq!(names("1979-05-27"), date);
+		assert_eq!(names("07:32:00"), time);
+	}
+}
@@ -0,0 +1,52 @@
+use alloc::{borrow::Cow, format, string::String, vec, vec::Vec};
+use core::str;
+
+use crate::{Error, Utf8Mode, Warning, WarningKind};
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Decode UTF-8 input, dropping a leading byte order mark
+pub(crate) fn decode_utf8(
+	input: &[u8],
+	mode: Utf8Mode,
+) -> Result<(Cow<'_, str>, Vec<Warning>), Error> {
+	if is_utf16(input) {
+		return Err(Error::Utf16);
+	}
+
+	let (skipped, input) = match input.strip_prefix(BOM) {
+		Some(input) => (BOM.len(), input),
+		None => (0, input),
+	};
+
+	match str::from_utf8(input) {
+		Ok(toml) => Ok((Cow::Borrowed(toml), Vec::new())),
+		Err(err) => {
+			let offset = skipped + err.valid_up_to();
+
+			if mode == Utf8Mode::Strict {
+				return Err(Error::InvalidUtf8 { offset });
+			}
+
+			let warning = Warning {
+				kind: WarningKind::InvalidUtf8,
+				path: String::new(),
+				message: format!(
+					"input is not valid UTF-8 starting at offset {}, invalid bytes were replaced with U+FFFD",
+					offset
+				),
+			};
+
+			Ok((String::from_utf8_lossy(input), vec![warning]))
+		}
+	}
+}
+
+/// Byte order mark or zero bytes interleaved with ASCII, UTF-8 text never starts like that
+fn is_utf16(input: &[u8]) -> bool {
+	match input {
+		[0xFF, 0xFE, ..] | [0xFE, 0xFF, ..] => true,
+		[first, second, ..] => (*first == 0) != (*second == 0),
+		_ => false,
+	}
+}
@@ -0,0 +1,88 @@
+use std::{
+	collections::HashSet,
+	fs,
+	path::{Path, PathBuf},
+};
+
+use crate::{locate::from_str, read_file, Error, Map};
+
+/// Entry of a directory tree of TOML documents
+pub(crate) enum DirEntry {
+	Directory(Vec<(String, DirEntry)>),
+	Document(Map),
+}
+
+/// Recursively load every `*.toml` file under `root`, sorted by name
+pub(crate) fn load_dir(root: &Path) -> Result<Vec<(String, DirEntry)>, Error> {
+	let mut ancestors = HashSet::new();
+
+	load(root, &mut ancestors)
+}
+
+fn load(dir: &Path, ancestors: &mut HashSet<PathBuf>) -> Result<Vec<(String, DirEntry)>, Error> {
+	let canonical = fs::canonicalize(dir).map_err(|error| io_error(dir, error))?;
+
+	// Directory is already being loaded higher up the tree, so it's a symlink cycle
+	if !ancestors.insert(canonical.clone()) {
+		return Ok(Vec::new());
+	}
+
+	let mut paths = fs::read_dir(dir)
+		.map_err(|error| io_error(dir, error))?
+		.map(|entry| entry.map(|entry| entry.path()))
+		.collect::<Result<Vec<PathBuf>, _>>()
+		.map_err(|error| io_error(dir, error))?;
+
+	paths.sort();
+
+	let mut entries: Vec<(String, DirEntry)> = Vec::new();
+	let mut sources: Vec<PathBuf> = Vec::new();
+
+	for path in paths {
+		let entry = if path.is_dir() {
+			let children = load(&path, ancestors)?;
+
+			if children.is_empty() {
+				continue;
+			}
+
+			let name = path.file_name().unwrap_or_default();
+
+			(
+				name.to_string_lossy().into_owned(),
+				DirEntry::Directory(children),
+			)
+		} else if path.extension().is_some_and(|ext| ext == "toml") {
+			let document = from_str(&read_file(&path)?)?;
+			let name = path.file_stem().unwrap_or_default();
+
+			(
+				name.to_string_lossy().into_owned(),
+				DirEntry::Document(document),
+			)
+		} else {
+			continue;
+		};
+
+		if let Some(index) = entries.iter().position(|(name, _)| *name == entry.0) {
+			return Err(Error::NameCollision {
+				first: sources.swap_remove(index),
+				second: path,
+			});
+		}
+
+		entries.push(entry);
+		sources.push(path);
+	}
+
+	ancestors.remove(&canonical);
+
+	Ok(entries)
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> Error {
+	Error::Io {
+		path: path.to_owned(),
+		error,
+	}
+}
@@ -0,0 +1,437 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::fmt::{self, Display, Formatter};
+
+use crate::{DatetimeKind, LuaTarget, Violation, Warning};
+#[cfg(feature = "std")]
+use std::{io, path::PathBuf};
+
+/// Errors that can occur while converting TOML to Lua
+#[derive(Debug)]
+pub enum Error {
+	/// Input is not valid TOML, `path` is the key or table header the error
+	/// is on when it can be told, like `servers.2.limits.max`
+	Toml {
+		path: Option<String>,
+		error: Box<toml::de::Error>,
+	},
+	/// Input given to `parse_value` is not a single TOML value
+	InvalidValue(toml::de::Error),
+	/// Value given to `from_serialize` can't be represented in TOML
+	Serialize(toml::ser::Error),
+	/// Two keys of the same table resolve to the same Lua key
+	DuplicateKey { path: String, key: String },
+	/// Two keys of a table are written as the same Lua `key`, `first` and `second` are their paths
+	KeyCollision {
+		first: String,
+		second: String,
+		key: String,
+	},
+	/// Key can't be a field of a generated Teal record, it is not an ASCII identifier
+	InvalidIdentifier { path: String, key: String },
+	/// Key is reserved by the enabled options
+	ReservedKey { path: String, key: String },
+	/// Array mixes elements of different types while `homogeneous_arrays` is enabled
+	MixedArray {
+		path: String,
+		first: &'static str,
+		second: &'static str,
+	},
+	/// Array holds the `nil_sentinel` or an empty string written as `nil` while
+	/// `sparse_arrays` is disabled, the resulting hole would break the length
+	/// operator and `ipairs`
+	NilInArray { path: String },
+	/// Datetime can't be expressed in the `datetime_mode` while `datetime_fallback` is `Error`
+	UnrepresentableDatetime { path: String, kind: DatetimeKind },
+	/// Datetime has no offset to convert it to UTC by while `local_datetimes` is `Error`
+	NotNormalizable { path: String, kind: DatetimeKind },
+	/// Document has no keys while `empty_document` is `EmptyMode::Error`
+	EmptyDocument,
+	/// Table listed in `force_array_paths` has keys other than `"1"` to `"n"`
+	NotSequence { path: String },
+	/// Dotted path given to `parse_path` leads to no value, `path` is the
+	/// longest part of it that exists
+	PathNotFound { path: String, missing: String },
+	/// Entry of an array listed in `key_by` is not a table or lacks the key field,
+	/// `index` starts at 1
+	MissingKeyField {
+		path: String,
+		index: usize,
+		field: String,
+	},
+	/// Key field of an entry of an array listed in `key_by` is neither a string nor an integer
+	InvalidKeyField {
+		path: String,
+		index: usize,
+		found: &'static str,
+	},
+	/// Entry of an array listed in `sort_arrays_by` is not a table or lacks the
+	/// sort field while missing fields are errors, `index` starts at 1
+	MissingSortField {
+		path: String,
+		index: usize,
+		field: String,
+	},
+	/// Sort field of an entry of an array listed in `sort_arrays_by` has a type
+	/// its comparison doesn't handle
+	InvalidSortField {
+		path: String,
+		index: usize,
+		found: &'static str,
+		expected: &'static str,
+	},
+	/// Two entries of an array listed in `key_by` have the same key
+	DuplicateKeyField {
+		path: String,
+		index: usize,
+		key: String,
+	},
+	/// Enabled options produce output the target Lua version can't load
+	Unsupported {
+		target: LuaTarget,
+		feature: &'static str,
+	},
+	/// Two enabled options can't be used together
+	ConflictingOptions {
+		first: &'static str,
+		second: &'static str,
+	},
+	/// Integer can't be represented exactly while `integer_overflow` is `Error`
+	IntegerOverflow { path: String, value: i64 },
+	/// Document doesn't match the schema given to `parse_with_schema`, holds every violation
+	Schema(Vec<Violation>),
+	/// `value_transform` replaced a scalar with a table or array
+	InvalidTransform { path: String },
+	/// String at a path listed in the `coerce` paths is not an integer, float or boolean literal
+	NotCoercible { path: String, value: String },
+	/// Value matched by the `redact` paths is not a string while `non_strings` is `Error`
+	NotRedactable { path: String, found: &'static str },
+	/// Output grew past `max_output_bytes`
+	OutputTooLarge { limit: usize },
+	/// Table or array at `path` nests deeper than `max_depth`
+	TooDeep { path: String, limit: usize },
+	/// Lua given to `Splicer` has no region with the given name
+	MissingMarker { name: String },
+	/// Begin marker at the given line of the Lua given to `Splicer` is never
+	/// closed, or the end marker there closes no region
+	UnmatchedMarker { line: usize },
+	/// Begin marker at the given line of the Lua given to `Splicer` is inside another region
+	NestedMarker { line: usize },
+	/// Lua given to `Splicer` has several regions with the same name
+	DuplicateMarker { name: String },
+	/// Conversion produced a warning while `deny_warnings` is enabled
+	DeniedWarning(Warning),
+	/// Input bytes are not valid UTF-8, `offset` is the first invalid byte
+	InvalidUtf8 { offset: usize },
+	/// Input bytes are UTF-16 encoded
+	Utf16,
+	/// Lua given to `lua_to_toml` is invalid or uses unsupported expressions
+	#[cfg(feature = "lua2toml")]
+	Lua(String),
+	/// Output of `parse_checked` failed to load as a Lua chunk or the one of
+	/// `parse_to_luau_bytecode` failed to compile, holds the message of the parser
+	#[cfg(any(feature = "validate", feature = "luau"))]
+	InvalidLua(String),
+	/// File could not be read
+	#[cfg(feature = "std")]
+	Io { path: PathBuf, error: io::Error },
+	/// File or directory could not be written
+	#[cfg(feature = "std")]
+	Write { path: PathBuf, error: io::Error },
+	/// Two files or directories map to the same key
+	#[cfg(feature = "std")]
+	NameCollision { first: PathBuf, second: PathBuf },
+	/// Placeholder variable has no value while `interpolation` rejects unresolved ones
+	#[cfg(feature = "std")]
+	UnresolvedVariable { path: String, variable: String },
+	/// Keys `first` and `second` are both written to the module file `module` by a `Splitter`
+	#[cfg(feature = "std")]
+	ModuleCollision {
+		first: String,
+		second: String,
+		module: String,
+	},
+	/// Key given to `Splitter::split_array` is not a top-level array of tables
+	#[cfg(feature = "std")]
+	NotArrayOfTables { key: String },
+	/// Reading from or writing to a stream failed
+	#[cfg(feature = "std")]
+	Stream(io::Error),
+	/// Top-level array of tables is continued after other arrays of tables while streaming
+	#[cfg(feature = "std")]
+	NotStreamable { key: String },
+}
+
+impl Error {
+	/// Dotted key path of the value or table the error is about, if it is about one
+	pub fn path(&self) -> Option<&str> {
+		match self {
+			Error::Toml { path, .. } => path.as_deref(),
+			Error::DuplicateKey { path, .. }
+			| Error::InvalidIdentifier { path, .. }
+			| Error::ReservedKey { path, .. }
+			| Error::MixedArray { path, .. }
+			| Error::NilInArray { path }
+			| Error::UnrepresentableDatetime { path, .. }
+			| Error::NotNormalizable { path, .. }
+			| Error::NotSequence { path }
+			| Error::MissingKeyField { path, .. }
+			| Error::InvalidKeyField { path, .. }
+			| Error::MissingSortField { path, .. }
+			| Error::InvalidSortField { path, .. }
+			| Error::DuplicateKeyField { path, .. }
+			| Error::IntegerOverflow { path, .. }
+			| Error::InvalidTransform { path }
+			| Error::NotCoercible { path, .. }
+			| Error::NotRedactable { path, .. }
+			| Error::TooDeep { path, .. } => Some(path),
+			#[cfg(feature = "std")]
+			Error::UnresolvedVariable { path, .. } => Some(path),
+			Error::DeniedWarning(warning) if !warning.path.is_empty() => Some(&warning.path),
+			_ => None,
+		}
+	}
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Toml { path, error } => match path {
+				Some(path) => write!(f, "`{}`: {}", path, error),
+				None => write!(f, "{}", error),
+			},
+			Error::InvalidValue(err) => write!(f, "input is not a single TOML value: {}", err),
+			Error::Serialize(err) => write!(f, "value can't be converted to TOML: {}", err),
+			Error::DuplicateKey { path, key } => {
+				if path.is_empty() {
+					write!(f, "duplicate key `{}` in root table", key)
+				} else {
+					write!(f, "duplicate key `{}` in table `{}`", key, path)
+				}
+			}
+			Error::InvalidIdentifier { path, key } => {
+				if path.is_empty() {
+					write!(
+						f,
+						"key `{}` in root table is not a valid Teal identifier",
+						key
+					)
+				} else {
+					write!(
+						f,
+						"key `{}` in table `{}` is not a valid Teal identifier",
+						key, path
+					)
+				}
+			}
+			Error::ReservedKey { path, key } => {
+				if path.is_empty() {
+					write!(f, "key `{}` in root table is reserved", key)
+				} else {
+					write!(f, "key `{}` in table `{}` is reserved", key, path)
+				}
+			}
+			Error::MixedArray {
+				path,
+				first,
+				second,
+			} => write!(
+				f,
+				"array `{}` mixes {} and {} elements",
+				path, first, second
+			),
+			Error::NilInArray { path } => write!(
+				f,
+				"array `{}` holds a value written as nil, enable `sparse_arrays` to keep the indices after it",
+				path
+			),
+			Error::UnrepresentableDatetime { path, kind } => write!(
+				f,
+				"{} at `{}` can't be written in the datetime mode, it has no time zone",
+				kind, path
+			),
+			Error::NotNormalizable { path, kind } => write!(
+				f,
+				"{} at `{}` can't be normalized to UTC, it has no offset",
+				kind, path
+			),
+			Error::EmptyDocument => write!(f, "document is empty"),
+			Error::PathNotFound { path, missing } if path.is_empty() => {
+				write!(f, "document has no key `{}`", missing)
+			}
+			Error::PathNotFound { path, missing } => {
+				write!(f, "`{}` has no key or index `{}`", path, missing)
+			}
+			Error::NotSequence { path } => write!(
+				f,
+				"table `{}` can't be written as an array, its keys are not 1 to n",
+				path
+			),
+			Error::MissingKeyField { path, index, field } => write!(
+				f,
+				"entry {} of `{}` has no `{}` field to key it by",
+				index, path, field
+			),
+			Error::InvalidKeyField { path, index, found } => write!(
+				f,
+				"entry {} of `{}` is keyed by a {}, expected a string or an integer",
+				index, path, found
+			),
+			Error::MissingSortField { path, index, field } => write!(
+				f,
+				"entry {} of `{}` has no `{}` field to sort it by",
+				index, path, field
+			),
+			Error::InvalidSortField {
+				path,
+				index,
+				found,
+				expected,
+			} => write!(
+				f,
+				"entry {} of `{}` is sorted by a {}, expected {}",
+				index, path, found, expected
+			),
+			Error::KeyCollision { first, second, key } => write!(
+				f,
+				"keys `{}` and `{}` are both written as `{}`",
+				first, second, key
+			),
+			Error::DuplicateKeyField { path, index, key } => {
+				write!(f, "entry {} of `{}` repeats the key {}", index, path, key)
+			}
+			Error::Unsupported { target, feature } => {
+				write!(f, "{} is not available in {}", feature, target)
+			}
+			Error::ConflictingOptions { first, second } => {
+				write!(f, "{} can't be combined with {}", first, second)
+			}
+			Error::IntegerOverflow { path, value } => write!(
+				f,
+				"integer `{}` at `{}` can't be represented exactly without an integer type",
+				value, path
+			),
+			Error::Schema(violations) => {
+				write!(f, "document doesn't match the schema:")?;
+
+				for violation in violations {
+					write!(f, "\n- {}", violation)?;
+				}
+
+				Ok(())
+			}
+			Error::NotCoercible { path, value } => write!(
+				f,
+				"string {:?} at `{}` is not an integer, float or boolean",
+				value, path
+			),
+			Error::NotRedactable { path, found } => write!(
+				f,
+				"`{}` is a {} and can't be redacted, only strings can",
+				path, found
+			),
+			Error::InvalidTransform { path } => write!(
+				f,
+				"value transform replaced `{}` with a table or array, only scalars are allowed",
+				path
+			),
+			Error::MissingMarker { name } => write!(f, "no region named `{}` to splice", name),
+			Error::UnmatchedMarker { line } => write!(f, "marker on line {} is unmatched", line),
+			Error::NestedMarker { line } => {
+				write!(f, "begin marker on line {} is inside another region", line)
+			}
+			Error::DuplicateMarker { name } => {
+				write!(f, "region `{}` is marked several times", name)
+			}
+			Error::DeniedWarning(warning) => write!(f, "denied warning: {}", warning),
+			Error::OutputTooLarge { limit } => {
+				write!(f, "output exceeds the limit of {} bytes", limit)
+			}
+			Error::TooDeep { path, limit } => {
+				write!(
+					f,
+					"`{}` is nested deeper than the limit of {} levels",
+					path, limit
+				)
+			}
+			Error::InvalidUtf8 { offset } => {
+				write!(f, "input is not UTF-8, invalid byte at offset {}", offset)
+			}
+			Error::Utf16 => write!(f, "input is not UTF-8, it looks like UTF-16"),
+			#[cfg(feature = "lua2toml")]
+			Error::Lua(message) => write!(f, "invalid Lua table: {}", message),
+			#[cfg(any(feature = "validate", feature = "luau"))]
+			Error::InvalidLua(message) => write!(f, "output is not valid Lua: {}", message),
+			#[cfg(feature = "std")]
+			Error::Io { path, error } => {
+				write!(f, "failed to read `{}`: {}", path.display(), error)
+			}
+			#[cfg(feature = "std")]
+			Error::Write { path, error } => {
+				write!(f, "failed to write `{}`: {}", path.display(), error)
+			}
+			#[cfg(feature = "std")]
+			Error::NameCollision { first, second } => write!(
+				f,
+				"`{}` and `{}` map to the same key",
+				first.display(),
+				second.display()
+			),
+			#[cfg(feature = "std")]
+			Error::ModuleCollision {
+				first,
+				second,
+				module,
+			} => write!(
+				f,
+				"keys `{}` and `{}` are both written to the module `{}`",
+				first, second, module
+			),
+			#[cfg(feature = "std")]
+			Error::NotArrayOfTables { key } => {
+				write!(f, "`{}` is not a top-level array of tables", key)
+			}
+			#[cfg(feature = "std")]
+			Error::UnresolvedVariable { path, variable } => {
+				write!(f, "variable `{}` used in `{}` is not set", variable, path)
+			}
+			#[cfg(feature = "std")]
+			Error::Stream(error) => write!(f, "stream failed: {}", error),
+			#[cfg(feature = "std")]
+			Error::NotStreamable { key } => write!(
+				f,
+				"array of tables `{}` is not contiguous, it can't be streamed",
+				key
+			),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Toml { error, .. } => Some(error),
+			Error::InvalidValue(error) => Some(error),
+			Error::Serialize(err) => Some(err),
+			Error::Io { error, .. } => Some(error),
+			Error::Write { error, .. } => Some(error),
+			Error::Stream(error) => Some(error),
+			_ => None,
+		}
+	}
+}
+
+impl From<toml::de::Error> for Error {
+	fn from(error: toml::de::Error) -> Self {
+		Error::Toml {
+			path: None,
+			error: Box::new(error),
+		}
+	}
+}
+
+impl From<toml::ser::Error> for Error {
+	fn from(err: toml::ser::Error) -> Self {
+		Error::Serialize(err)
+	}
+}
@@ -0,0 +1,165 @@
+use alloc::{
+	format,
+	string::{String, ToString},
+};
+use toml::Value;
+
+use crate::{
+	walk::{escape_string, float_literal, is_nil},
+	EmptyStringMode, Indent, NilEntries, ParseOptions,
+};
+
+/// Fennel table of the document's entries, see [`Dialect::Fennel`](crate::Dialect::Fennel)
+pub(crate) fn document<'v, I>(entries: I, options: &ParseOptions) -> String
+where
+	I: IntoIterator<Item = (&'v String, &'v Value)>,
+{
+	let mut fennel = String::new();
+	table(&mut fennel, entries, 0, options);
+	fennel
+}
+
+/// Turn the `--` comments of the header into `;;` ones
+pub(crate) fn comments(lua: &str) -> String {
+	lua.lines()
+		.map(|line| match line.strip_prefix("--") {
+			Some(text) => format!(";;{}\n", text),
+			None => format!("{}\n", line),
+		})
+		.collect()
+}
+
+fn table<'v, I>(fennel: &mut String, entries: I, depth: usize, options: &ParseOptions)
+where
+	I: IntoIterator<Item = (&'v String, &'v Value)>,
+{
+	let mut entries = entries
+		.into_iter()
+		.filter(|(_, value)| !is_dropped(value, options))
+		.peekable();
+
+	if entries.peek().is_none() {
+		fennel.push_str("{}");
+		return;
+	}
+
+	fennel.push_str("{\n");
+
+	for (key, value) in entries {
+		push_indent(fennel, depth + 1, options);
+
+		if is_keyword(key) {
+			fennel.push(':');
+			fennel.push_str(key);
+		} else {
+			push_string(fennel, key, options);
+		}
+
+		fennel.push(' ');
+		value_literal(fennel, value, depth + 1, options);
+		fennel.push('\n');
+	}
+
+	push_indent(fennel, depth, options);
+	fennel.push('}');
+}
+
+fn value_literal(fennel: &mut String, value: &Value, depth: usize, options: &ParseOptions) {
+	if is_nil(value, options) {
+		fennel.push_str("nil");
+		return;
+	}
+
+	match value {
+		Value::String(string) => push_string(fennel, string, options),
+		// `-9223372036854775808` reads as a float like in Lua
+		Value::Integer(i64::MIN) => fennel.push_str("(- -9223372036854775807 1)"),
+		Value::Integer(integer) => fennel.push_str(&integer.to_string()),
+		// Divisions by zero like in Lua, older Fennel versions lack literals for these
+		Value::Float(float) if float.is_nan() => fennel.push_str("(/ 0 0)"),
+		Value::Float(float) if float.is_infinite() => {
+			fennel.push_str(if *float > 0.0 { "(/ 1 0)" } else { "(/ -1 0)" })
+		}
+		Value::Float(float) => fennel.push_str(&float_literal(*float)),
+		Value::Boolean(boolean) => fennel.push_str(&boolean.to_string()),
+		Value::Datetime(datetime) => push_string(fennel, &datetime.to_string(), options),
+		Value::Table(entries) => table(fennel, entries, depth, options),
+		Value::Array(items) if items.iter().any(|item| item.is_table() || item.is_array()) => {
+			fennel.push_str("[\n");
+
+			for item in items {
+				push_indent(fennel, depth + 1, options);
+				value_literal(fennel, item, depth + 1, options);
+				fennel.push('\n');
+			}
+
+			push_indent(fennel, depth, options);
+			fennel.push(']');
+		}
+		Value::Array(items) => {
+			fennel.push('[');
+
+			for (i, item) in items.iter().enumerate() {
+				if i > 0 {
+					fennel.push(' ');
+				}
+
+				value_literal(fennel, item, depth, options);
+			}
+
+			fennel.push(']');
+		}
+	}
+}
+
+/// Whether the entry is left out of its table like in Lua output
+fn is_dropped(value: &Value, options: &ParseOptions) -> bool {
+	match value {
+		Value::String(string) if string.is_empty() => {
+			options.empty_string == EmptyStringMode::Omit
+				|| options.nil_entries == NilEntries::Drop && is_nil(value, options)
+		}
+		_ => options.nil_entries == NilEntries::Drop && is_nil(value, options),
+	}
+}
+
+/// Fennel strings only have double quotes and take Lua's escapes
+fn push_string(fennel: &mut String, string: &str, options: &ParseOptions) {
+	fennel.push('"');
+	fennel.push_str(&escape_string(string, '"', options));
+	fennel.push('"');
+}
+
+fn push_indent(fennel: &mut String, depth: usize, options: &ParseOptions) {
+	match options.indent {
+		Indent::Tabs => fennel.push_str(&"\t".repeat(depth)),
+		Indent::Spaces(width) => fennel.push_str(&" ".repeat(width * depth)),
+	}
+}
+
+/// Whether the key can be written as `:key`, which Fennel reads as a string.
+/// Kept to identifier characters and `-` so no key is read as another token
+fn is_keyword(key: &str) -> bool {
+	key.chars()
+		.next()
+		.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+		&& key
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod test {
+	use super::is_keyword;
+
+	#[test]
+	fn keywords() {
+		assert!(is_keyword("name"));
+		assert!(is_keyword("max-width"));
+		assert!(is_keyword("_private"));
+		assert!(!is_keyword("2d"));
+		assert!(!is_keyword("a.b"));
+		assert!(!is_keyword("my key"));
+		assert!(!is_keyword(""));
+	}
+}
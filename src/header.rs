@@ -0,0 +1,112 @@
+use alloc::{format, string::String};
+use core::hash::Hasher;
+
+use crate::{map::FnvHasher, Header, ParseOptions};
+
+/// What is known about the converted document, used by [`ParseOptions::header`]
+#[derive(Debug, Default)]
+pub(crate) struct Source {
+	name: Option<String>,
+	hash: Option<u64>,
+	/// Hexadecimal SHA-256 hash of the contents, see [`ParseOptions::content_hash`]
+	#[cfg(feature = "content_hash")]
+	sha256: Option<String>,
+}
+
+impl Source {
+	/// Describe a document by its file name and contents, hashing them only if the header needs it
+	pub fn new(name: Option<&str>, toml: Option<&str>, options: &ParseOptions) -> Self {
+		let hash = toml.filter(|_| options.header_hash).map(|toml| {
+			let mut hasher = FnvHasher::default();
+			hasher.write(toml.as_bytes());
+			hasher.finish()
+		});
+
+		Self {
+			name: name.map(String::from),
+			hash,
+			#[cfg(feature = "content_hash")]
+			sha256: toml
+				.filter(|_| options.content_hash)
+				.map(|toml| sha256_hex(toml.as_bytes())),
+		}
+	}
+}
+
+/// Hexadecimal SHA-256 hash of the bytes
+#[cfg(any(feature = "std", feature = "content_hash"))]
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+	use core::fmt::Write;
+	use sha2::{Digest, Sha256};
+
+	let mut hex = String::with_capacity(64);
+
+	for byte in Sha256::digest(bytes) {
+		let _ = write!(hex, "{:02x}", byte);
+	}
+
+	hex
+}
+
+/// Comment ending the output, empty unless [`ParseOptions::content_hash`] is
+/// enabled and the source is known
+#[cfg(feature = "content_hash")]
+pub(crate) fn footer(source: &Source) -> String {
+	source
+		.sha256
+		.as_ref()
+		.map(|hash| format!("\n-- sha256:{}", hash))
+		.unwrap_or_default()
+}
+
+/// Lines of the text like [`str::lines`], but also split at a lone `\r`, which
+/// Lua reads as a line break that would end a comment
+pub(crate) fn lines(text: &str) -> impl Iterator<Item = &str> {
+	let trimmed = text
+		.strip_suffix("\r\n")
+		.or_else(|| text.strip_suffix(['\n', '\r']))
+		.unwrap_or(text);
+
+	(!text.is_empty())
+		.then_some(trimmed)
+		.into_iter()
+		.flat_map(|text| text.split("\r\n"))
+		.flat_map(|line| line.split(['\n', '\r']))
+}
+
+/// Comment block written above everything else, empty without a header
+pub(crate) fn header(options: &ParseOptions, source: &Source) -> String {
+	let Some(header) = &options.header else {
+		return String::new();
+	};
+
+	let text = match (header, &source.name) {
+		(Header::Generated, Some(name)) => format!(
+			"Generated by toml2lua v{} from {}; do not edit.",
+			env!("CARGO_PKG_VERSION"),
+			name
+		),
+		(Header::Generated, None) => format!(
+			"Generated by toml2lua v{}; do not edit.",
+			env!("CARGO_PKG_VERSION")
+		),
+		(Header::Custom(text), _) => text.clone(),
+	};
+
+	let mut lua = String::new();
+
+	for line in lines(&text) {
+		// Space keeps lines starting with `[[` from opening a block comment
+		if line.is_empty() {
+			lua.push_str("--\n");
+		} else {
+			lua.push_str(&format!("-- {}\n", line));
+		}
+	}
+
+	if let Some(hash) = source.hash {
+		lua.push_str(&format!("-- Source FNV-1a hash: {:016x}\n", hash));
+	}
+
+	lua
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::HashMap, env};
+
+/// Replace `${VAR}` placeholders in string values, see [`ParseOptions::interpolation`](crate::ParseOptions::interpolation)
+///
+/// `${VAR:-default}` falls back to `default` when the variable is unset or
+/// empty and `$$` writes a literal `$`. Substituted values are escaped like
+/// the rest of the string
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use toml2lua::{parse_with_options, Interpolation, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	interpolation: Some(Interpolation::from_map(HashMap::from([(
+/// 		String::from("API_HOST"),
+/// 		String::from("example.com"),
+/// 	)]))),
+/// 	..Default::default()
+/// };
+///
+/// let toml = r#"url = "https://${API_HOST}/v2?cost=$$5&port=${PORT:-443}""#;
+///
+/// assert_eq!(
+/// 	parse_with_options(toml, &options).unwrap(),
+/// 	"{\n\t[\"url\"] = \"https://example.com/v2?cost=$5&port=443\",\n}"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Interpolation {
+	/// Where variable values come from
+	pub variables: Variables,
+	/// What happens to variables without a value or default
+	#[serde(default)]
+	pub unresolved: Unresolved,
+}
+
+/// Source of the values substituted by [`Interpolation`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variables {
+	/// Variables of the current process
+	Environment,
+	/// Given variables only
+	Map(HashMap<String, String>),
+}
+
+/// What happens to a placeholder whose variable is unset and has no default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unresolved {
+	/// Leave the placeholder as written
+	#[default]
+	Keep,
+	/// Replace it with an empty string
+	Empty,
+	/// Return [`Error::UnresolvedVariable`](crate::Error::UnresolvedVariable)
+	Error,
+}
+
+impl Interpolation {
+	/// Substitute variables of the current process, keeping unresolved placeholders
+	pub fn from_env() -> Self {
+		Self {
+			variables: Variables::Environment,
+			unresolved: Unresolved::Keep,
+		}
+	}
+
+	/// Substitute the given variables, keeping unresolved placeholders
+	pub fn from_map(variables: HashMap<String, String>) -> Self {
+		Self {
+			variables: Variables::Map(variables),
+			unresolved: Unresolved::Keep,
+		}
+	}
+
+	fn get(&self, name: &str) -> Option<String> {
+		match &self.variables {
+			Variables::Environment => env::var(name).ok(),
+			Variables::Map(variables) => variables.get(name).cloned(),
+		}
+	}
+}
+
+/// Substitute the placeholders of the string, returning the unresolved variable on error
+pub(crate) fn interpolate<'s>(
+	value: &'s str,
+	interpolation: &Interpolation,
+) -> Result<Cow<'s, str>, String> {
+	if !value.contains('$') {
+		return Ok(Cow::Borrowed(value));
+	}
+
+	let mut interpolated = String::with_capacity(value.len());
+	let mut rest = value;
+
+	while let Some(i) = rest.find('$') {
+		interpolated.push_str(&rest[..i]);
+		rest = &rest[i..];
+
+		if let Some(after) = rest.strip_prefix("$$") {
+			interpolated.push('$');
+			rest = after;
+			continue;
+		}
+
+		let Some(end) = rest.strip_prefix("${").and_then(|inner| inner.find('}')) else {
+			interpolated.push('$');
+			rest = &rest[1..];
+			continue;
+		};
+
+		let placeholder = &rest[..end + 3];
+		let (name, default) = match rest[2..end + 2].split_once(":-") {
+			Some((name, default)) => (name, Some(default)),
+			None => (&rest[2..end + 2], None),
+		};
+
+		match (interpolation.get(name), default) {
+			(Some(value), _) if !value.is_empty() => interpolated.push_str(&value),
+			(_, Some(default)) => interpolated.push_str(default),
+			(Some(_), None) => {}
+			(None, None) => match interpolation.unresolved {
+				Unresolved::Keep => interpolated.push_str(placeholder),
+				Unresolved::Empty => {}
+				Unresolved::Error => return Err(name.to_owned()),
+			},
+		}
+
+		rest = &rest[placeholder.len()..];
+	}
+
+	interpolated.push_str(rest);
+
+	Ok(Cow::Owned(interpolated))
+}
@@ -13,9 +13,9 @@
 //! use toml2lua::parse;
 //!
 //! let toml = r#"
-//! string = "toml2lua",
-//! int = 420,
-//! bool = true,
+//! string = "toml2lua"
+//! int = 420
+//! bool = true
 //!
 //! [object]
 //! key = "value"
@@ -33,12 +33,158 @@
 //! // }
 //! ```
 //!
+//! ## Features:
+//! - `std` (default) - file and directory helpers, variable interpolation, the caching [`Converter`]
+//!   and the [`Splitter`] writing a document as several modules
+//! - `preserve_order` (default) - keys keep their document order, enables `preserve_order` of
+//!   `toml` which needs `std`. Without it `indexmap` is not used and keys of every table are
+//!   written in sorted order
+//! - `comments` - `parse_with_comments`, `parse_with_source_map` and the `source_comments` and
+//!   `keep_inline_tables` options, implies `std`
+//! - `radix` - `parse_preserving_radix` keeping hexadecimal, binary and octal integers, implies `std`
+//! - `wasm` - `parse_wasm` exported through `wasm-bindgen`
+//! - `lua2toml` - `lua_to_toml` converting emitted tables back to TOML, implies `std`
+//! - `verify` - `verify_roundtrip` loading the output in an embedded Lua 5.4, implies `std`
+//! - `validate` - `parse_checked` compiling the output in an embedded Lua 5.4, implies `std`
+//! - `luau` - `parse_to_luau_bytecode` compiling the output into Luau bytecode, implies `std`.
+//!   The embedded Luau can't be combined with the Lua 5.4 of `verify`, `validate` or `module`,
+//!   enabling them together is a compile error
+//! - `content_hash` - the `content_hash` option ending the output with the SHA-256 hash of the source
+//! - `module` - builds the crate as a native Lua 5.4 module, `require("toml2lua")` then returns
+//!   the table of [`lua_module`], implies `std`. Lua is provided by the host so it can't be
+//!   combined with `verify` or `validate`, build it with
+//!   `cargo rustc --release --no-default-features --features module,preserve_order --crate-type cdylib`
+//!   and put `target/release/libtoml2lua.so` on `package.cpath` as `toml2lua.so`
+//! - `watch` - `watch` keeping a directory of Lua files in sync with TOML sources, implies `std`
+//! - `parallel` - `parse_files_parallel` converting files on a `rayon` thread pool, implies `std`
+//! - `tokio` - `parse_file_async`, `parse_dir_async` and `parse_to_writer_async` for `tokio`
+//!   runtimes, converting on the blocking thread pool, implies `std`
+//!
+//! Without `std` and `preserve_order` the crate is `no_std` and only needs `alloc`
+//!
 //! Made with <3 by Dervex
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![allow(clippy::tabs_in_doc_comments)]
 
-use indexmap::IndexMap;
-use toml::{de::Error, from_str, Value};
+extern crate alloc;
+
+// mlua links a single Lua, name the features of this crate rather than the ones of mlua
+#[cfg(all(
+	feature = "luau",
+	any(feature = "verify", feature = "validate", feature = "module")
+))]
+compile_error!("the `luau` feature can't be combined with `verify`, `validate` or `module`");
+
+mod accessors;
+#[cfg(feature = "tokio")]
+mod async_io;
+mod builder;
+mod case;
+#[cfg(feature = "comments")]
+mod comments;
+#[cfg(feature = "std")]
+mod converter;
+mod datetime;
+mod decode;
+#[cfg(feature = "std")]
+mod dir;
+mod error;
+mod fennel;
+mod header;
+#[cfg(feature = "std")]
+mod interpolate;
+mod locate;
+#[cfg(feature = "lua2toml")]
+mod lua2toml;
+mod map;
+mod mapping;
+mod merge;
+#[cfg(any(feature = "module", feature = "verify", feature = "validate"))]
+mod module;
+mod options;
+mod pack;
+#[cfg(feature = "radix")]
+mod radix;
+mod schema;
+mod serialize;
+mod share;
+mod sort;
+#[cfg(any(feature = "comments", feature = "radix"))]
+mod source;
+mod splice;
+#[cfg(feature = "std")]
+mod split;
+mod stats;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "std")]
+mod sync;
+mod types;
+#[cfg(feature = "verify")]
+mod verify;
+mod walk;
+mod warning;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "tokio")]
+pub use async_io::{parse_dir_async, parse_file_async, parse_to_writer_async};
+pub use builder::LuaTableBuilder;
+#[cfg(feature = "std")]
+pub use converter::{CacheStats, Converter};
+pub use datetime::DatetimeKind;
+pub use error::Error;
+#[cfg(feature = "std")]
+pub use interpolate::{Interpolation, Unresolved, Variables};
+#[cfg(feature = "lua2toml")]
+pub use lua2toml::lua_to_toml;
+#[cfg(all(feature = "preserve_order", not(feature = "std")))]
+pub use map::FnvHasher;
+pub use map::Map;
+pub use mapping::Mapping;
+pub use merge::{merge_maps, MergeOrder};
+#[cfg(any(feature = "module", feature = "verify", feature = "validate"))]
+pub use module::lua_module;
+#[cfg(feature = "luau")]
+pub use options::LuauBytecode;
+pub use options::{
+	AccessorModule, BoolStyle, Coerce, CommentStyle, DatetimeFallback, DatetimeMode, Dedupe,
+	Dialect, EmptyMode, EmptyStringMode, FloatFormat, Freeze, GroupOrder, Header, Indent,
+	IntegerFormat, IntegerOverflow, KeyCase, KeyCollisions, KeyOrder, KeyPredicate, LocalDatetimes,
+	LuaTarget, MissingSortField, Newline, NilEntries, NumberMode, OrderMetadata, PackNumbers,
+	PackPrecision, ParseOptions, QuoteStyle, Radix, ReadonlyMode, Redact, RedactNonStrings,
+	Separator, SortBy, SortCompare, TypeTags, Utf8Mode, ValueTransform, WhitespacePolicy, Wrap,
+};
+pub use schema::{Schema, UnknownKeys, ValueType, Violation};
+pub use splice::Splicer;
+#[cfg(feature = "std")]
+pub use split::{SplitFile, Splitter};
+pub use stats::{ConversionStats, Stats};
+#[cfg(feature = "std")]
+pub use stream::{write_lua_streaming, ParseChunks};
+#[cfg(feature = "watch")]
+pub use sync::watch;
+#[cfg(feature = "std")]
+pub use sync::{DirSync, SyncEvent, SyncStatus};
+pub use types::{EmmyLuaTypes, LuauTypes, MixedArrays, TealTypes};
+#[cfg(feature = "verify")]
+pub use verify::{verify_roundtrip, VerifyError};
+pub use warning::{Warning, WarningKind};
+#[cfg(feature = "wasm")]
+pub use wasm::parse_wasm;
+
+use alloc::{format, string::String, vec::Vec};
+use locate::from_str;
+use serde::ser::Error as _;
+use serde::Serialize;
+#[cfg(feature = "parallel")]
+use std::path::PathBuf;
+#[cfg(feature = "std")]
+use std::{fs, path::Path};
+use toml::Value;
+use types::Shape;
+use walk::Walker;
 
 /// Parse TOML string into a Lua table
 ///
@@ -63,151 +209,7799 @@ use toml::{de::Error, from_str, Value};
 /// 	},
 /// }"#;
 ///
+/// # #[cfg(all(feature = "std", feature = "preserve_order"))]
 /// assert_eq!(parse(toml).unwrap(), lua);
 /// ```
 pub fn parse(toml: &str) -> Result<String, Error> {
-	let toml: IndexMap<String, Value> = from_str(toml)?;
-	let mut lua = String::from("{\n");
+	parse_with_options(toml, &ParseOptions::default())
+}
 
-	for (key, value) in toml {
-		lua.push_str(&walk(Some(&validate_string(&key)), &value, 1));
-	}
+/// Parse TOML string into a readable Lua table using [`ParseOptions::pretty`]
+///
+/// ```rust
+/// use toml2lua::parse_pretty;
+///
+/// let toml = r#"
+/// string = "abc"
+/// int = 123
+///
+/// [object]
+/// key = "value"
+/// "#;
+///
+/// let lua = r#"{
+///     string = "abc",
+///     int = 123,
+///
+///     object = {
+///         key = "value"
+///     }
+/// }
+/// "#;
+///
+/// # #[cfg(all(feature = "std", feature = "preserve_order"))]
+/// assert_eq!(parse_pretty(toml).unwrap(), lua);
+/// ```
+pub fn parse_pretty(toml: &str) -> Result<String, Error> {
+	parse_with_options(toml, &ParseOptions::pretty())
+}
 
-	lua.push('}');
+/// Parse TOML string into a Lua table using custom options
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	check_duplicate_keys: true,
+/// 	..Default::default()
+/// };
+///
+/// assert_eq!(parse_with_options("a = 1", &options).unwrap(), "{\n\t[\"a\"] = 1,\n}");
+/// ```
+pub fn parse_with_options(toml: &str, options: &ParseOptions) -> Result<String, Error> {
+	let map: Map = from_str(toml)?;
 
-	Ok(lua)
+	convert(
+		&map,
+		options,
+		Walker::new(options).with_source(None, Some(toml)),
+	)
+	.map(|(lua, _)| lua)
+}
+
+/// Convert a single TOML value, like an array or an inline table, into a Lua expression
+///
+/// The expression has no document wrapping, header or trailing comma, while
+/// formatting options still apply to the arrays and tables in it. Input that is
+/// not exactly one value, surrounding whitespace aside, returns [`Error::InvalidValue`]
+///
+/// ```rust
+/// use toml2lua::{parse_value, ParseOptions};
+///
+/// let options = ParseOptions::default();
+///
+/// assert_eq!(parse_value("'abc'", &options).unwrap(), "\"abc\"");
+/// assert_eq!(parse_value("[1, 2]", &options).unwrap(), "{\n\t1,\n\t2,\n}");
+/// assert!(parse_value("key = 1", &options).is_err());
+/// ```
+pub fn parse_value(input: &str, options: &ParseOptions) -> Result<String, Error> {
+	let value: Value = input.trim().parse().map_err(Error::InvalidValue)?;
+
+	render_value(&value, options, 0)
 }
 
-fn walk(key: Option<&str>, value: &Value, depth: usize) -> String {
-	let mut lua = String::new();
+/// Convert only the value at a dotted path of the document, like `database`
+/// or `servers.1.ports`, into a Lua expression like [`parse_value`]
+///
+/// Array items are addressed by their index starting at 1 and keys holding a `.`
+/// are quoted, like `"example.com".port`. A path leading to no value returns
+/// [`Error::PathNotFound`] naming the segment that wasn't found
+///
+/// ```rust
+/// use toml2lua::parse_path;
+///
+/// let toml = r#"
+/// [database]
+/// port = 5432
+/// "#;
+///
+/// assert_eq!(parse_path(toml, "database").unwrap(), "{\n\t[\"port\"] = 5432,\n}");
+/// assert_eq!(parse_path(toml, "database.port").unwrap(), "5432");
+/// assert!(parse_path(toml, "database.host").is_err());
+///
+/// let toml = r#"
+/// ["example.com"]
+/// port = 80
+/// "#;
+///
+/// assert_eq!(parse_path(toml, r#""example.com".port"#).unwrap(), "80");
+/// ```
+pub fn parse_path(toml: &str, path: &str) -> Result<String, Error> {
+	let map: Map = from_str(toml)?;
+
+	let segments = walk::split_path(path);
+
+	let not_found = |len: usize| Error::PathNotFound {
+		path: walk::join_path(&segments[..len]),
+		missing: walk::path_segment(&segments[len]),
+	};
 
-	lua.push_str(&get_indent(depth));
+	let mut value = map.get(&segments[0]).ok_or_else(|| not_found(0))?;
 
-	if let Some(key) = key {
-		lua.push_str(&format!("[\"{}\"] = ", validate_string(key)));
+	for (i, segment) in segments.iter().enumerate().skip(1) {
+		let next = match value {
+			Value::Table(table) => table.get(segment),
+			Value::Array(items) => segment
+				.parse::<usize>()
+				.ok()
+				.and_then(|index| items.get(index.checked_sub(1)?)),
+			_ => None,
+		};
+
+		value = next.ok_or_else(|| not_found(i))?;
 	}
 
-	match value {
-		Value::String(s) => lua.push_str(&format!("\"{}\"", &validate_string(s))),
-		Value::Integer(i) => lua.push_str(&i.to_string()),
-		Value::Float(f) => lua.push_str(&f.to_string()),
-		Value::Boolean(b) => lua.push_str(&b.to_string()),
-		Value::Datetime(d) => lua.push_str(&format!("\"{}\"", d)),
-		Value::Array(a) => {
-			lua.push_str("{\n");
+	render_value(value, &ParseOptions::default(), 0)
+}
 
-			for v in a {
-				lua.push_str(&walk(None, v, depth + 1));
-			}
+/// Write a single value as a Lua expression with every option applied, like
+/// [`parse_value`] without parsing
+///
+/// The braces of tables and arrays are placed at `depth`, so the lines inside
+/// them are indented one level further and the closing brace at `depth`. The
+/// first line is not indented, the expression goes wherever the caller's cursor is
+///
+/// ```rust
+/// use toml::{toml, Value};
+/// use toml2lua::{render_value, ParseOptions};
+///
+/// let value = Value::Table(toml! {
+/// 	name = "abc"
+/// 	ports = [80, 443]
+/// });
+///
+/// let options = ParseOptions {
+/// 	bare_keys: true,
+/// 	max_width: Some(80),
+/// 	..Default::default()
+/// };
+///
+/// assert_eq!(
+/// 	render_value(&value, &options, 0).unwrap(),
+/// 	"{\n\tname = \"abc\",\n\tports = { 80, 443 },\n}"
+/// );
+/// assert_eq!(render_value(&Value::from(1), &options, 2).unwrap(), "1");
+/// ```
+pub fn render_value(value: &Value, options: &ParseOptions, depth: usize) -> Result<String, Error> {
+	let mut walker = Walker::new(options);
 
-			lua.push_str(&get_indent(depth));
-			lua.push('}');
-		}
-		Value::Table(t) => {
-			lua.push_str("{\n");
+	walker.walk_fragment(value, depth)?;
 
-			for (k, v) in t {
-				lua.push_str(&walk(Some(k), v, depth + 1));
-			}
+	Ok(walker.finish())
+}
+
+/// Parse TOML string into a Lua table and return the warnings of the conversion
+///
+/// ```rust
+/// use toml2lua::{parse_with_warnings, Coerce, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	coerce: Some(Coerce::default()),
+/// 	..Default::default()
+/// };
+///
+/// let (lua, warnings) = parse_with_warnings("enabled = 'true'", &options).unwrap();
+///
+/// assert_eq!(lua, "{\n\t[\"enabled\"] = true,\n}");
+/// assert_eq!(warnings[0].to_string(), "`enabled`: coerced string \"true\" to boolean");
+/// ```
+pub fn parse_with_warnings(
+	toml: &str,
+	options: &ParseOptions,
+) -> Result<(String, Vec<Warning>), Error> {
+	let map: Map = from_str(toml)?;
+	let walker = Walker::new(options).with_source(None, Some(toml));
+	let mut walker = with_types(&map, options, walker)?;
+
+	walker.walk_root(&map)?;
+
+	let warnings = walker.take_warnings();
+
+	Ok((walker.finish(), warnings))
+}
+
+/// Check that TOML string converts with the options, returning the error
+/// [`parse_with_options`] would
+///
+/// The document is converted one top-level entry at a time like
+/// [`ParseChunks`] and every chunk is dropped once written, so the whole
+/// output is never held, validating many files in CI cheaply
+///
+/// ```rust
+/// use toml2lua::{validate, Error, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	max_depth: Some(2),
+/// 	..Default::default()
+/// };
+///
+/// assert!(validate("[server]\nport = 80", &options).is_ok());
+/// assert!(matches!(
+/// 	validate("[server.limits]\nmemory = 512", &options),
+/// 	Err(Error::TooDeep { .. })
+/// ));
+/// ```
+#[cfg(feature = "std")]
+pub fn validate(toml: &str, options: &ParseOptions) -> Result<(), Error> {
+	ParseChunks::new(toml, options).try_for_each(|chunk| chunk.map(drop))
+}
+
+/// Parse TOML string into a Lua table and check that it compiles in an embedded Lua 5.4
+///
+/// The table is compiled as `return <table>` without being run, output the Lua
+/// parser rejects returns [`Error::InvalidLua`] instead of being handed out
+///
+/// ```rust
+/// use toml2lua::parse_checked;
+///
+/// assert_eq!(parse_checked("limit = inf").unwrap(), "{\n\t[\"limit\"] = 1/0,\n}");
+/// ```
+#[cfg(feature = "validate")]
+pub fn parse_checked(toml: &str) -> Result<String, Error> {
+	let lua = parse(toml)?;
+
+	mlua::Lua::new()
+		.load(format!("return {}", lua))
+		.set_name("toml2lua")
+		.into_function()
+		.map_err(|err| Error::InvalidLua(err.to_string()))?;
+
+	Ok(lua)
+}
 
-			lua.push_str(&get_indent(depth));
-			lua.push('}');
+/// Convert TOML into a Lua chunk returning the table and compile it into Luau
+/// bytecode, which loads faster than the source
+///
+/// The target defaults to [`LuaTarget::Luau`] and other targets return
+/// [`Error::Unsupported`]. A table without [`Wrap`] is compiled as `return { ... }`,
+/// [`ParseOptions::luau_bytecode`] sets the optimization and debug levels
+///
+/// ```rust
+/// use toml2lua::{parse_to_luau_bytecode, ParseOptions};
+///
+/// let bytecode = parse_to_luau_bytecode("port = 80", &ParseOptions::default()).unwrap();
+///
+/// let lua = mlua::Lua::new();
+/// let table: mlua::Table = lua.load(bytecode).eval().unwrap();
+///
+/// assert_eq!(table.get::<u16>("port").unwrap(), 80);
+/// ```
+#[cfg(feature = "luau")]
+pub fn parse_to_luau_bytecode(toml: &str, options: &ParseOptions) -> Result<Vec<u8>, Error> {
+	let options = match options.target {
+		None => ParseOptions {
+			target: Some(LuaTarget::Luau),
+			..options.clone()
+		},
+		Some(LuaTarget::Luau) => options.clone(),
+		Some(target) => {
+			return Err(Error::Unsupported {
+				target,
+				feature: "Luau bytecode",
+			})
 		}
+	};
+
+	let mut lua = parse_with_options(toml, &options)?;
+
+	if options.wrap == Wrap::None && options.accessor_module.is_none() {
+		lua.insert_str(0, "return ");
 	}
 
-	lua.push_str(",\n");
+	mlua::Compiler::new()
+		.set_optimization_level(options.luau_bytecode.optimization_level)
+		.set_debug_level(options.luau_bytecode.debug_level)
+		.compile(lua)
+		.map_err(|err| Error::InvalidLua(err.to_string()))
+}
+
+/// Parse TOML string into a Lua table and also return the deserialized map
+///
+/// ```rust
+/// use toml2lua::{parse_with_value, ParseOptions};
+///
+/// let (lua, map) = parse_with_value("key = 'value'", &ParseOptions::default()).unwrap();
+///
+/// assert_eq!(lua, "{\n\t[\"key\"] = \"value\",\n}");
+/// assert_eq!(map["key"].as_str(), Some("value"));
+/// ```
+pub fn parse_with_value(toml: &str, options: &ParseOptions) -> Result<(String, Map), Error> {
+	let map: Map = from_str(toml)?;
+	let walker = Walker::new(options).with_source(None, Some(toml));
+	let (lua, _) = convert(&map, options, walker)?;
 
-	lua
+	Ok((lua, map))
 }
 
-fn get_indent(depth: usize) -> String {
-	let mut indent = String::new();
+/// Parse TOML string into a Lua table after checking it against a schema
+///
+/// Every violation is returned at once in [`Error::Schema`], unknown keys are
+/// reported in the returned warnings with [`UnknownKeys::Warn`]
+///
+/// ```rust
+/// use toml2lua::{parse_with_schema, Error, ParseOptions, Schema, ValueType};
+///
+/// let schema = Schema::new()
+/// 	.required("name", ValueType::String)
+/// 	.range("port", 1.0..=65535.0);
+///
+/// let options = ParseOptions::default();
+///
+/// let (lua, _) = parse_with_schema("name = 'abc'\nport = 80", &schema, &options).unwrap();
+/// assert_eq!(lua, "{\n\t[\"name\"] = \"abc\",\n\t[\"port\"] = 80,\n}");
+///
+/// let Err(Error::Schema(violations)) = parse_with_schema("port = 0", &schema, &options) else {
+/// 	panic!("document is invalid");
+/// };
+///
+/// assert_eq!(violations[0].to_string(), "`name`: expected string, found nothing");
+/// assert_eq!(violations[1].to_string(), "`port`: expected number >= 1 and <= 65535, found 0");
+/// ```
+pub fn parse_with_schema(
+	toml: &str,
+	schema: &Schema,
+	options: &ParseOptions,
+) -> Result<(String, Vec<Warning>), Error> {
+	let map: Map = from_str(toml)?;
+	let (violations, warnings) = schema.validate(&map);
 
-	for _ in 0..depth {
-		indent.push('\t');
+	if !violations.is_empty() {
+		return Err(Error::Schema(violations));
 	}
 
-	indent
+	warning::check_denied(&warnings, options.deny_warnings)?;
+
+	let walker = Walker::new(options).with_source(None, Some(toml));
+	let (lua, _) = convert(&map, options, walker)?;
+
+	Ok((lua, warnings))
+}
+
+/// Parse TOML bytes into a Lua table
+///
+/// A leading UTF-8 byte order mark is skipped, input that is not valid UTF-8
+/// (including UTF-16) is rejected
+///
+/// ```rust
+/// use toml2lua::parse_bytes;
+///
+/// assert_eq!(parse_bytes(b"\xEF\xBB\xBFkey = 1").unwrap(), "{\n\t[\"key\"] = 1,\n}");
+/// ```
+pub fn parse_bytes(input: &[u8]) -> Result<String, Error> {
+	parse_bytes_with_options(input, &ParseOptions::default()).map(|(lua, _)| lua)
+}
+
+/// Parse TOML bytes into a Lua table using custom options
+///
+/// With [`Utf8Mode::Lossy`] invalid UTF-8 sequences are replaced with U+FFFD
+/// and reported in the returned warnings instead of failing
+///
+/// ```rust
+/// use toml2lua::{parse_bytes_with_options, ParseOptions, Utf8Mode};
+///
+/// let options = ParseOptions {
+/// 	utf8_mode: Utf8Mode::Lossy,
+/// 	..Default::default()
+/// };
+///
+/// let (lua, warnings) = parse_bytes_with_options(b"key = \"\xFF\"", &options).unwrap();
+///
+/// assert_eq!(lua, "{\n\t[\"key\"] = \"\u{FFFD}\",\n}");
+/// assert_eq!(warnings.len(), 1);
+/// ```
+pub fn parse_bytes_with_options(
+	input: &[u8],
+	options: &ParseOptions,
+) -> Result<(String, Vec<Warning>), Error> {
+	let (toml, warnings) = decode::decode_utf8(input, options.utf8_mode)?;
+
+	warning::check_denied(&warnings, options.deny_warnings)?;
+
+	Ok((parse_with_options(&toml, options)?, warnings))
 }
 
-fn validate_string(string: &str) -> String {
-	let mut validated = String::new();
+/// Parse TOML string into a Lua table, keeping comments as Lua comments
+///
+/// Only comments placed directly above top-level keys and `[sections]` are kept,
+/// comments inside nested tables or at the end of a line are dropped
+///
+/// ```rust
+/// use toml2lua::{parse_with_comments, ParseOptions};
+///
+/// let toml = r#"
+/// ## Player name
+/// name = "Dervex"
+/// "#;
+///
+/// let lua = r#"{
+/// 	-- Player name
+/// 	["name"] = "Dervex",
+/// }"#;
+///
+/// assert_eq!(parse_with_comments(toml, &ParseOptions::default()).unwrap(), lua);
+/// ```
+#[cfg(feature = "comments")]
+pub fn parse_with_comments(toml: &str, options: &ParseOptions) -> Result<String, Error> {
+	let map: Map = from_str(toml)?;
 
-	for char in string.chars() {
-		match char {
-			'\n' => validated.push_str("\\n"),
-			'\t' => validated.push_str("\\t"),
-			'\r' => validated.push_str("\\r"),
-			'\\' => validated.push_str("\\\\"),
-			'"' => validated.push_str("\\\""),
-			_ => validated.push(char),
-		}
-	}
+	let mut walker = Walker::new(options)
+		.with_source(None, Some(toml))
+		.with_comments(comments::leading_comments(toml));
+	walker.walk_root(&map)?;
 
-	validated
+	Ok(walker.finish())
 }
 
-#[cfg(test)]
-mod test {
-	#[test]
-	fn all_values() {
-		use crate::parse;
+/// Parse TOML string into a Lua table and map the line of every written scalar back to the source
+///
+/// Mappings are recorded while writing, so they account for every option
+/// changing the layout of the output. Values that are not written as scalars,
+/// like tables and arrays, have no mapping
+///
+/// ```rust
+/// use toml2lua::{parse_with_source_map, Mapping, ParseOptions};
+///
+/// let toml = r#"
+/// [server]
+/// port = 8080
+/// "#;
+///
+/// let (lua, mappings) = parse_with_source_map(toml, &ParseOptions::default()).unwrap();
+///
+/// assert_eq!(lua.lines().nth(2), Some("\t\t[\"port\"] = 8080,"));
+/// assert_eq!(
+/// 	mappings,
+/// 	[Mapping {
+/// 		lua_line: 3,
+/// 		toml_line: 3,
+/// 		key_path: String::from("server.port"),
+/// 	}]
+/// );
+/// ```
+#[cfg(feature = "comments")]
+pub fn parse_with_source_map(
+	toml: &str,
+	options: &ParseOptions,
+) -> Result<(String, Vec<Mapping>), Error> {
+	let map: Map = from_str(toml)?;
 
-		let toml = r#"
-		string = "str"
-		int = 420
-		float = 6.9
-		bool = true
-		datetime = 1979-05-27T00:32:00.999999-07:00
-		array = [
-			"string",
-			12345,
-			false
-		]
+	let walker = Walker::new(options)
+		.with_source(None, Some(toml))
+		.with_source_map(toml);
 
-		[object]
-		key = "value"
-"#;
+	let mut walker = with_types(&map, options, walker)?;
+	walker.walk_root(&map)?;
 
-		let lua = r#"{
-	["string"] = "str",
-	["int"] = 420,
-	["float"] = 6.9,
-	["bool"] = true,
-	["datetime"] = "1979-05-27T00:32:00.999999-07:00",
-	["array"] = {
-		"string",
-		12345,
-		false,
-	},
-	["object"] = {
-		["key"] = "value",
-	},
-}"#;
+	let mappings = walker.mappings();
 
-		assert_eq!(parse(toml).unwrap(), lua);
-	}
+	Ok((walker.finish(), mappings))
+}
 
-	#[test]
-	fn malformed_strings() {
-		use crate::parse;
+/// Parse TOML string into a Lua table, writing integers in the radix of the source
+///
+/// Hexadecimal integers stay hexadecimal, binary ones too when targeting Luau.
+/// Octal has no Lua literal so it is written in decimal followed by the source
+/// in a comment, as is binary outside of Luau. Digit separators are only kept
+/// in Luau. Ignored for integers converted by [`NumberMode::AllFloat`]
+///
+/// ```rust
+/// use toml2lua::{parse_preserving_radix, ParseOptions};
+///
+/// let toml = r#"
+/// mask = 0xFF_FF
+/// mode = 0o755
+/// "#;
+///
+/// let lua = parse_preserving_radix(toml, &ParseOptions::default()).unwrap();
+///
+/// assert_eq!(lua, "{\n\t[\"mask\"] = 0xFFFF,\n\t[\"mode\"] = 493, -- 0o755\n}");
+/// ```
+#[cfg(feature = "radix")]
+pub fn parse_preserving_radix(toml: &str, options: &ParseOptions) -> Result<String, Error> {
+	let map: Map = from_str(toml)?;
 
-		let toml = r#"
-  1 = "..\n.."
-  2 = "..\t.."
-  3 = "..\r.."
-  4 = "..\\.."
-  5 = "..\".."
-"#;
+	let mut walker = Walker::new(options)
+		.with_source(None, Some(toml))
+		.with_integer_literals(radix::integer_literals(toml, options));
+	walker.walk_root(&map)?;
 
-		let lua = r#"{
-	["1"] = "..\n..",
-	["2"] = "..\t..",
-	["3"] = "..\r..",
-	["4"] = "..\\..",
-	["5"] = "..\"..",
-}"#;
+	Ok(walker.finish())
+}
 
-		assert_eq!(parse(toml).unwrap(), lua);
+/// Convert already deserialized TOML map into a Lua table
+///
+/// ```rust
+/// use toml::Value;
+/// use toml2lua::{parse_map, Map, ParseOptions};
+///
+/// let mut map = Map::default();
+/// map.insert(String::from("key"), Value::from("value"));
+///
+/// assert_eq!(parse_map(&map, &ParseOptions::default()).unwrap(), "{\n\t[\"key\"] = \"value\",\n}");
+/// ```
+pub fn parse_map(map: &Map, options: &ParseOptions) -> Result<String, Error> {
+	convert(map, options, Walker::new(options)).map(|(lua, _)| lua)
+}
+
+/// Convert a serializable value into a Lua table through [`toml::Value`], without producing TOML text
+///
+/// The value must serialize to a table, like a struct or a map with string keys.
+/// `None` fields are skipped, values TOML can't represent, like `None` in an
+/// array, a unit or a map with other keys, return [`Error::Serialize`]
+///
+/// ```rust
+/// use serde::Serialize;
+/// use toml2lua::from_serialize;
+///
+/// #[derive(Serialize)]
+/// struct Config {
+/// 	name: String,
+/// 	port: u16,
+/// }
+///
+/// let config = Config {
+/// 	name: String::from("abc"),
+/// 	port: 80,
+/// };
+///
+/// assert_eq!(from_serialize(&config).unwrap(), "{\n\t[\"name\"] = \"abc\",\n\t[\"port\"] = 80,\n}");
+/// ```
+pub fn from_serialize<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+	serialize::check_none(value)?;
+
+	match Value::try_from(value)? {
+		Value::Table(table) => parse_map(&table.into_iter().collect(), &ParseOptions::default()),
+		_ => Err(Error::Serialize(toml::ser::Error::custom(
+			"only tables can be converted",
+		))),
+	}
+}
+
+/// Parse TOML string into Lua assignment statements, one for every leaf value
+///
+/// Keys that are valid identifiers are accessed with `.key`, others with `["key"]`
+/// and array items with `[index]`. Empty tables and arrays are assigned `{}`,
+/// other tables along the paths must already exist when the statements run
+///
+/// ```rust
+/// use toml2lua::parse_assignments;
+///
+/// let toml = r#"
+/// name = "abc"
+///
+/// [object]
+/// key = "value"
+/// "with space" = [1, 2]
+/// "#;
+///
+/// let lua = r#"config.name = "abc"
+/// config.object.key = "value"
+/// config.object["with space"][1] = 1
+/// config.object["with space"][2] = 2
+/// "#;
+///
+/// # #[cfg(all(feature = "std", feature = "preserve_order"))]
+/// assert_eq!(parse_assignments(toml, "config").unwrap(), lua);
+/// ```
+pub fn parse_assignments(toml: &str, root: &str) -> Result<String, Error> {
+	let map: Map = from_str(toml)?;
+	let options = ParseOptions::default();
+
+	let mut walker = Walker::new(&options);
+	walker.walk_assignments(root, &map)?;
+
+	Ok(walker.finish())
+}
+
+/// Parse TOML string into a Lua table and count what was produced
+///
+/// ```rust
+/// use toml2lua::{parse_with_stats, ParseOptions};
+///
+/// let (_, stats) = parse_with_stats("list = [1, 2]", &ParseOptions::default()).unwrap();
+///
+/// assert_eq!((stats.tables, stats.arrays, stats.scalars, stats.max_depth), (1, 1, 2, 2));
+/// ```
+pub fn parse_with_stats(
+	toml: &str,
+	options: &ParseOptions,
+) -> Result<(String, ConversionStats), Error> {
+	let walker = Walker::new(options).with_source(None, Some(toml));
+
+	convert(&from_str(toml)?, options, walker)
+}
+
+/// Count what a TOML document holds without converting it
+///
+/// ```rust
+/// use toml2lua::{analyze, parse, ParseOptions};
+///
+/// let toml = "name = 'stats'\nports = [80, 443]";
+/// let stats = analyze(toml).unwrap();
+///
+/// assert_eq!((stats.keys, stats.integers, stats.max_array_len, stats.max_depth), (2, 2, 2, 2));
+/// assert_eq!(
+/// 	stats.estimated_output_len(&ParseOptions::default()),
+/// 	parse(toml).unwrap().len()
+/// );
+/// ```
+pub fn analyze(toml: &str) -> Result<Stats, Error> {
+	Ok(Stats::of(&from_str(toml)?))
+}
+
+/// Walk the map with the given walker, adding the Luau type declaration if enabled
+fn convert(
+	map: &Map,
+	options: &ParseOptions,
+	walker: Walker,
+) -> Result<(String, ConversionStats), Error> {
+	let mut walker = with_types(map, options, walker)?;
+
+	walker.walk_root(map)?;
+
+	let stats = walker.stats();
+
+	Ok((walker.finish(), stats))
+}
+
+/// Add the Luau type declaration, LuaLS annotations or Teal record of the map to the walker if enabled
+fn with_types<'a>(
+	map: &Map,
+	options: &ParseOptions,
+	mut walker: Walker<'a>,
+) -> Result<Walker<'a>, Error> {
+	if options.luau_types.is_none()
+		&& options.emmylua_types.is_none()
+		&& options.teal_types.is_none()
+	{
+		return Ok(walker);
+	}
+
+	let mut shape = match map.values().next() {
+		Some(value) if options.unwrap_single && map.len() == 1 => Shape::of(value),
+		_ => Shape::of_document(map, options),
+	};
+
+	if let Some(root_key) = &options.root_key {
+		shape = Shape::wrap(root_key, shape);
+	}
+
+	if let Some(types) = &options.luau_types {
+		walker = walker.with_preamble(format!(
+			"{}\n",
+			types::luau_declaration(&shape, types, options)
+		));
+
+		if types.annotate {
+			walker = walker.with_type_annotation(types.name.clone());
+		}
+	} else if let Some(types) = &options.emmylua_types {
+		let (classes, root) = types::emmylua_declaration(&shape, types, options);
+
+		walker = walker.with_preamble(classes).with_type_comment(root);
+	} else if let Some(types) = &options.teal_types {
+		walker = walker
+			.with_preamble(format!(
+				"{}\n",
+				types::teal_declaration(&shape, &types.name, options)?
+			))
+			.with_type_annotation(types.name.clone());
+	}
+
+	Ok(walker)
+}
+
+/// Generate Luau type declaration describing the given TOML document
+///
+/// Integers and floats become `number`, datetimes `string` and arrays mixing
+/// several types become a union of them. Use [`ParseOptions::luau_types`] to
+/// emit the declaration together with the table
+///
+/// ```rust
+/// use toml2lua::generate_luau_types;
+///
+/// let toml = r#"
+/// name = "abc"
+/// ports = [80, 443]
+/// "#;
+///
+/// let luau = r#"export type Config = {
+/// 	name: string,
+/// 	ports: { number },
+/// }
+/// "#;
+///
+/// assert_eq!(generate_luau_types(toml, "Config").unwrap(), luau);
+/// ```
+pub fn generate_luau_types(toml: &str, type_name: &str) -> Result<String, Error> {
+	let map: Map = from_str(toml)?;
+
+	let options = ParseOptions::default();
+
+	Ok(types::luau_declaration(
+		&Shape::of_document(&map, &options),
+		&LuauTypes::new(type_name),
+		&options,
+	))
+}
+
+/// Generate Teal record declaration describing the given TOML document
+///
+/// Tables become nested records named after their key in PascalCase, arrays
+/// `{T}` and arrays mixing several types a union of them, e.g. `{string | number}`.
+/// Teal records only have identifier fields, other keys return [`Error::InvalidIdentifier`]
+///
+/// ```rust
+/// use toml2lua::generate_teal_types;
+///
+/// let toml = r#"
+/// name = "abc"
+/// ports = [80, 443]
+///
+/// [limits]
+/// memory = 0.5
+/// "#;
+///
+/// let teal = r#"local record Config
+/// 	name: string
+/// 	ports: {integer}
+/// 	record Limits
+/// 		memory: number
+/// 	end
+/// 	limits: Limits
+/// end
+/// "#;
+///
+/// # #[cfg(feature = "preserve_order")]
+/// assert_eq!(generate_teal_types(toml, "Config").unwrap(), teal);
+/// ```
+pub fn generate_teal_types(toml: &str, name: &str) -> Result<String, Error> {
+	let map: Map = from_str(toml)?;
+
+	let options = ParseOptions::default();
+
+	types::teal_declaration(&Shape::of_document(&map, &options), name, &options)
+}
+
+/// Convert several named TOML documents into one Lua table keyed by name
+///
+/// Names are escaped like any other key, must be unique and keep their order
+///
+/// ```rust
+/// use toml2lua::{parse_many, ParseOptions};
+///
+/// let docs = vec![
+/// 	(String::from("sword"), String::from("damage = 10")),
+/// 	(String::from("bow"), String::from("damage = 6")),
+/// ];
+///
+/// let lua = r#"{
+/// 	["sword"] = {
+/// 		["damage"] = 10,
+/// 	},
+/// 	["bow"] = {
+/// 		["damage"] = 6,
+/// 	},
+/// }"#;
+///
+/// assert_eq!(parse_many(docs, &ParseOptions::default()).unwrap(), lua);
+/// ```
+pub fn parse_many<I>(docs: I, options: &ParseOptions) -> Result<String, Error>
+where
+	I: IntoIterator<Item = (String, String)>,
+{
+	let docs = docs
+		.into_iter()
+		.map(|(name, toml)| Ok((name, from_str(&toml)?)))
+		.collect::<Result<Vec<(String, Map)>, Error>>()?;
+
+	let mut walker = Walker::new(options);
+	walker.walk_documents(docs.iter().map(|(name, doc)| (name, doc)))?;
+
+	Ok(walker.finish())
+}
+
+/// Same as [`parse_many`] but reads each document from the given file
+#[cfg(feature = "std")]
+pub fn parse_many_files<I, P>(files: I, options: &ParseOptions) -> Result<String, Error>
+where
+	I: IntoIterator<Item = (String, P)>,
+	P: AsRef<Path>,
+{
+	let docs = files
+		.into_iter()
+		.map(|(name, path)| Ok((name, read_file(path.as_ref())?)))
+		.collect::<Result<Vec<_>, Error>>()?;
+
+	parse_many(docs, options)
+}
+
+/// Convert TOML string into a `return { ... }` module per top-level table and
+/// an `init.lua` module requiring them, keyed by file name
+///
+/// Shorthand for [`Splitter::split`] without a require prefix
+///
+/// ```rust
+/// use toml2lua::{parse_split, ParseOptions};
+///
+/// let files = parse_split("[server]\nport = 80", &ParseOptions::default()).unwrap();
+///
+/// assert_eq!(files["init.lua"], "return {\n\t[\"server\"] = require(\"server\"),\n}");
+/// assert_eq!(files["server.lua"], "return {\n\t[\"port\"] = 80,\n}");
+/// ```
+#[cfg(feature = "std")]
+pub fn parse_split(
+	toml: &str,
+	options: &ParseOptions,
+) -> Result<std::collections::HashMap<String, String>, Error> {
+	let files = Splitter::new(options.clone()).split(toml)?;
+
+	Ok(files
+		.into_iter()
+		.map(|file| (file.path.to_string_lossy().into_owned(), file.lua))
+		.collect())
+}
+
+/// Convert a directory tree of TOML files into one nested Lua table
+///
+/// Every `*.toml` file becomes a key named after its file stem and every
+/// subdirectory a nested table named after the directory. Other files and
+/// directories without any TOML files are ignored, entries are sorted by name
+/// and symlinks pointing back into the tree being walked are skipped
+#[cfg(feature = "std")]
+pub fn parse_dir(root: &Path, options: &ParseOptions) -> Result<String, Error> {
+	let entries = dir::load_dir(root)?;
+
+	let mut walker = Walker::new(options);
+	walker.walk_directory(&entries)?;
+
+	Ok(walker.finish())
+}
+
+/// Read a TOML file and convert it into a Lua table
+///
+/// Same as [`parse_with_options`], but [`Header::Generated`] also names the file
+#[cfg(feature = "std")]
+pub fn parse_file(path: &Path, options: &ParseOptions) -> Result<String, Error> {
+	parse_file_contents(&read_file(path)?, path, options)
+}
+
+/// Convert the contents of the TOML file at `path` like [`parse_file`]
+#[cfg(feature = "std")]
+pub(crate) fn parse_file_contents(
+	toml: &str,
+	path: &Path,
+	options: &ParseOptions,
+) -> Result<String, Error> {
+	let map: Map = from_str(toml)?;
+	let name = path.file_name().and_then(|name| name.to_str());
+
+	convert(
+		&map,
+		options,
+		Walker::new(options).with_source(name, Some(toml)),
+	)
+	.map(|(lua, _)| lua)
+}
+
+/// Check that the Lua file holds what [`parse_file`] generates from the TOML file, without writing anything
+///
+/// Meant for CI, catching generated files that drifted from their sources. The
+/// options must be the ones the files were generated with. Missing Lua files
+/// are not up to date, while `\r\n` line endings, like the ones of files checked
+/// out by Git on Windows, are considered equal to the `\n` written by the conversion
+///
+/// ```rust
+/// use std::fs;
+/// use toml2lua::{is_up_to_date, parse_file, ParseOptions};
+///
+/// let dir = std::env::temp_dir().join(format!("toml2lua_is_up_to_date_doc_{}", std::process::id()));
+/// let (toml, lua) = (dir.join("config.toml"), dir.join("config.lua"));
+/// let options = ParseOptions::default();
+///
+/// fs::create_dir_all(&dir).unwrap();
+/// fs::write(&toml, "key = 1").unwrap();
+/// fs::write(&lua, parse_file(&toml, &options).unwrap()).unwrap();
+///
+/// assert!(is_up_to_date(&toml, &lua, &options).unwrap());
+/// ```
+#[cfg(feature = "std")]
+pub fn is_up_to_date(
+	toml_path: &Path,
+	lua_path: &Path,
+	options: &ParseOptions,
+) -> Result<bool, Error> {
+	let lua = parse_file(toml_path, options)?;
+
+	let existing = match fs::read(lua_path) {
+		Ok(existing) => existing,
+		Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+		Err(error) => {
+			return Err(Error::Io {
+				path: lua_path.to_owned(),
+				error,
+			})
+		}
+	};
+
+	Ok(match String::from_utf8(existing) {
+		Ok(existing) => existing == lua || existing.replace("\r\n", "\n") == lua,
+		Err(_) => false,
+	})
+}
+
+/// Convert many TOML files across a thread pool, see [`parse_file`]
+///
+/// Results are returned in the order of `paths` and a failing file doesn't stop the others
+///
+/// ```rust
+/// use std::path::PathBuf;
+/// use toml2lua::{parse_files_parallel, ParseOptions};
+///
+/// let paths = vec![PathBuf::from("missing.toml")];
+/// let results = parse_files_parallel(&paths, &ParseOptions::default());
+///
+/// assert_eq!(results[0].0, paths[0]);
+/// assert!(results[0].1.is_err());
+/// ```
+#[cfg(feature = "parallel")]
+pub fn parse_files_parallel(
+	paths: &[PathBuf],
+	options: &ParseOptions,
+) -> Vec<(PathBuf, Result<String, Error>)> {
+	use rayon::prelude::*;
+
+	paths
+		.par_iter()
+		.map(|path| (path.clone(), parse_file(path, options)))
+		.collect()
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn read_file(path: &Path) -> Result<String, Error> {
+	fs::read_to_string(path).map_err(|error| Error::Io {
+		path: path.to_owned(),
+		error,
+	})
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	/// Empty directory of the named test, unique to the process so concurrent test runs don't share it
+	pub(crate) fn temp_dir(name: &str) -> std::path::PathBuf {
+		let dir = std::env::temp_dir().join(format!("toml2lua_{}_{}", name, std::process::id()));
+
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(&dir).unwrap();
+
+		dir
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn all_values() {
+		use crate::parse;
+
+		let toml = r#"
+		string = "str"
+		int = 420
+		float = 6.9
+		bool = true
+		datetime = 1979-05-27T00:32:00.999999-07:00
+		array = [
+			"string",
+			12345,
+			false
+		]
+
+		[object]
+		key = "value"
+"#;
+
+		let lua = r#"{
+	["string"] = "str",
+	["int"] = 420,
+	["float"] = 6.9,
+	["bool"] = true,
+	["datetime"] = "1979-05-27T00:32:00.999999-07:00",
+	["array"] = {
+		"string",
+		12345,
+		false,
+	},
+	["object"] = {
+		["key"] = "value",
+	},
+}"#;
+
+		assert_eq!(parse(toml).unwrap(), lua);
+	}
+
+	#[test]
+	fn malformed_strings() {
+		use crate::parse;
+
+		let toml = r#"
+  1 = "..\n.."
+  2 = "..\t.."
+  3 = "..\r.."
+  4 = "..\\.."
+  5 = "..\".."
+  6 = "..\b.."
+  7 = "..\f.."
+"#;
+
+		let lua = r#"{
+	["1"] = "..\n..",
+	["2"] = "..\t..",
+	["3"] = "..\r..",
+	["4"] = "..\\..",
+	["5"] = "..\"..",
+	["6"] = "..\b..",
+	["7"] = "..\f..",
+}"#;
+
+		assert_eq!(parse(toml).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn duplicate_keys_check() {
+		use crate::{parse_map, Error, KeyCase, KeyCollisions, Map, ParseOptions};
+		use toml::Value;
+
+		let options = ParseOptions {
+			check_duplicate_keys: true,
+			..Default::default()
+		};
+
+		let mut map = Map::default();
+		map.insert(String::from("a"), Value::from(1));
+		map.insert(String::from("A"), Value::from(2));
+
+		let lua = r#"{
+	["a"] = 1,
+	["A"] = 2,
+}"#;
+
+		assert_eq!(parse_map(&map, &options).unwrap(), lua);
+
+		// Keys converted to the same Lua key are rejected whatever `key_collisions` is
+		let mut map = Map::default();
+		map.insert(String::from("my_key"), Value::from(1));
+		map.insert(String::from("my-key"), Value::from(2));
+
+		for key_collisions in [KeyCollisions::Error, KeyCollisions::KeepFirst] {
+			let options = ParseOptions {
+				check_duplicate_keys: true,
+				key_case: KeyCase::Snake,
+				key_collisions,
+				..Default::default()
+			};
+
+			assert!(matches!(
+				parse_map(&map, &options),
+				Err(Error::DuplicateKey { key, .. }) if key == "my_key"
+			));
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn pretty() {
+		use crate::parse_pretty;
+
+		let toml = r#"
+		name = "pretty"
+		end = true
+		list = [1, 2]
+
+		[first]
+		key = "value"
+		"with space" = 1
+
+		[[items]]
+		id = 1
+
+		[[items]]
+		id = 2
+"#;
+
+		let lua = r#"{
+    name = "pretty",
+    ["end"] = true,
+    list = {
+        1,
+        2
+    },
+
+    first = {
+        key = "value",
+        ["with space"] = 1
+    },
+
+    items = {
+        {
+            id = 1
+        },
+        {
+            id = 2
+        }
+    }
+}
+"#;
+
+		assert_eq!(parse_pretty(toml).unwrap(), lua);
+	}
+
+	#[test]
+	fn many() {
+		use crate::{parse_many, Error, ParseOptions};
+
+		let docs = vec![
+			(String::from("goblin"), String::from("hp = 10")),
+			(
+				String::from("dark elf"),
+				String::from("hp = 25\n[loot]\ngold = 5"),
+			),
+		];
+
+		let lua = r#"{
+	goblin = {
+		hp = 10,
+	},
+	["dark elf"] = {
+		hp = 25,
+		loot = {
+			gold = 5,
+		},
+	},
+}"#;
+
+		let options = ParseOptions {
+			bare_keys: true,
+			..Default::default()
+		};
+
+		assert_eq!(parse_many(docs, &options).unwrap(), lua);
+
+		let docs = vec![
+			(String::from("goblin"), String::from("hp = 10")),
+			(String::from("goblin"), String::from("hp = 12")),
+		];
+
+		assert!(matches!(
+			parse_many(docs, &options),
+			Err(Error::DuplicateKey { key, .. }) if key == "goblin"
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "std")]
+	fn many_files() {
+		use crate::{parse_many_files, Error, ParseOptions};
+		use std::fs;
+
+		let dir = temp_dir("many_files");
+		fs::write(dir.join("a.toml"), "value = 1").unwrap();
+		fs::write(dir.join("b.toml"), "value = 2").unwrap();
+
+		let files = vec![
+			(String::from("b"), dir.join("b.toml")),
+			(String::from("a"), dir.join("a.toml")),
+		];
+
+		let lua = r#"{
+	["b"] = {
+		["value"] = 2,
+	},
+	["a"] = {
+		["value"] = 1,
+	},
+}"#;
+
+		assert_eq!(
+			parse_many_files(files, &ParseOptions::default()).unwrap(),
+			lua
+		);
+
+		let files = vec![(String::from("missing"), dir.join("missing.toml"))];
+
+		assert!(matches!(
+			parse_many_files(files, &ParseOptions::default()),
+			Err(Error::Io { .. })
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "std")]
+	#[cfg(feature = "preserve_order")]
+	fn dir() {
+		use crate::{parse_dir, Error, ParseOptions};
+		use std::fs;
+
+		let root = temp_dir("dir");
+
+		fs::create_dir_all(root.join("npcs/friendly")).unwrap();
+		fs::create_dir_all(root.join("empty")).unwrap();
+		fs::write(root.join("items.toml"), "count = 2").unwrap();
+		fs::write(root.join("readme.md"), "# not toml").unwrap();
+		fs::write(root.join("npcs/goblin.toml"), "hp = 10").unwrap();
+		fs::write(
+			root.join("npcs/friendly/merchant.toml"),
+			"hp = 5\ngold = 100",
+		)
+		.unwrap();
+
+		#[cfg(unix)]
+		std::os::unix::fs::symlink(&root, root.join("npcs/loop")).unwrap();
+
+		let lua = r#"{
+	["items"] = {
+		["count"] = 2,
+	},
+	["npcs"] = {
+		["friendly"] = {
+			["merchant"] = {
+				["hp"] = 5,
+				["gold"] = 100,
+			},
+		},
+		["goblin"] = {
+			["hp"] = 10,
+		},
+	},
+}"#;
+
+		assert_eq!(parse_dir(&root, &ParseOptions::default()).unwrap(), lua);
+
+		fs::create_dir_all(root.join("items")).unwrap();
+		fs::write(root.join("items/sword.toml"), "damage = 10").unwrap();
+
+		assert!(matches!(
+			parse_dir(&root, &ParseOptions::default()),
+			Err(Error::NameCollision { .. })
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "comments")]
+	fn comments() {
+		use crate::{parse_with_comments, ParseOptions};
+
+		let toml = r#"
+# Server name
+#
+# Shown in the browser
+name = "main"
+port = 8080 # not kept
+
+# Database settings
+[database]
+# not kept either
+url = "localhost"
+
+# Admins
+[[admins]]
+name = "Dervex"
+"#;
+
+		let lua = r#"{
+	-- Server name
+	--
+	-- Shown in the browser
+	["name"] = "main",
+	["port"] = 8080,
+	-- Database settings
+	["database"] = {
+		["url"] = "localhost",
+	},
+	-- Admins
+	["admins"] = {
+		{
+			["name"] = "Dervex",
+		},
+	},
+}"#;
+
+		assert_eq!(
+			parse_with_comments(toml, &ParseOptions::default()).unwrap(),
+			lua
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn root_key() {
+		use crate::{parse_with_options, ParseOptions, Wrap};
+
+		let toml = r#"
+		sword = 10
+
+		[bow]
+		damage = 6
+"#;
+
+		let options = ParseOptions {
+			root_key: Some(String::from("weapons")),
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["weapons"] = {
+		["sword"] = 10,
+		["bow"] = {
+			["damage"] = 6,
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			root_key: Some(String::from("weapon list")),
+			wrap: Wrap::Return,
+			bare_keys: true,
+			trailing_commas: false,
+			..Default::default()
+		};
+
+		let lua = r#"return {
+	["weapon list"] = {
+		sword = 10,
+		bow = {
+			damage = 6
+		}
+	}
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			root_key: Some(String::from("weapons")),
+			wrap: Wrap::Local(String::from("registry")),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"local registry = {
+	weapons = {
+		sword = 10,
+		bow = {
+			damage = 6,
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn number_modes() {
+		use crate::{parse_with_options, NumberMode, ParseOptions};
+
+		let toml = r#"
+		int = 42
+		negative = -7
+		float = 6.9
+		whole = 3.0
+		below = -2.7
+"#;
+
+		let options = ParseOptions {
+			number_mode: NumberMode::Preserve,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["int"] = 42,
+	["negative"] = -7,
+	["float"] = 6.9,
+	["whole"] = 3.0,
+	["below"] = -2.7,
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			number_mode: NumberMode::AllFloat,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["int"] = 42.0,
+	["negative"] = -7.0,
+	["float"] = 6.9,
+	["whole"] = 3.0,
+	["below"] = -2.7,
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			number_mode: NumberMode::AllInteger,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["int"] = 42,
+	["negative"] = -7,
+	["float"] = 6,
+	["whole"] = 3,
+	["below"] = -2,
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	fn datetime_wrapper() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = r#"
+		created = 1979-05-27T07:32:00Z
+		dates = [1979-05-27, 07:32:00]
+"#;
+
+		let options = ParseOptions {
+			datetime_wrapper: Some(String::from("DateTime.fromIso")),
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["created"] = DateTime.fromIso("1979-05-27T07:32:00Z"),
+	["dates"] = {
+		DateTime.fromIso("1979-05-27"),
+		DateTime.fromIso("07:32:00"),
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn order_metadata() {
+		use crate::{parse_with_options, Error, OrderMetadata, ParseOptions};
+
+		let toml = r#"
+		zebra = 1
+		apple = 2
+
+		[nested]
+		b = true
+		a = false
+"#;
+
+		let options = ParseOptions {
+			order_metadata: OrderMetadata::Field,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	zebra = 1,
+	apple = 2,
+	nested = {
+		b = true,
+		a = false,
+		__order = { "b", "a" },
+	},
+	__order = { "zebra", "apple", "nested" },
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			order_metadata: OrderMetadata::Metatable,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"setmetatable({
+	zebra = 1,
+	apple = 2,
+	nested = setmetatable({
+		b = true,
+		a = false,
+	}, { __order = { "b", "a" } }),
+}, { __order = { "zebra", "apple", "nested" } })"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let toml = r#"
+		[nested]
+		__order = 1
+"#;
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::ReservedKey { path, .. }) if path == "nested"
+		));
+	}
+
+	#[test]
+	fn freeze() {
+		use crate::{parse_with_options, Freeze, ParseOptions, Wrap};
+
+		let toml = r#"
+		list = [1, [2]]
+
+		[nested]
+		key = "value"
+"#;
+
+		let options = ParseOptions {
+			freeze: Freeze::Shallow,
+			wrap: Wrap::Return,
+			..Default::default()
+		};
+
+		let lua = r#"return table.freeze({
+	["list"] = {
+		1,
+		{
+			2,
+		},
+	},
+	["nested"] = {
+		["key"] = "value",
+	},
+})"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			freeze: Freeze::Deep,
+			wrap: Wrap::Return,
+			trailing_commas: false,
+			..Default::default()
+		};
+
+		let lua = r#"return table.freeze({
+	["list"] = table.freeze({
+		1,
+		table.freeze({
+			2
+		})
+	}),
+	["nested"] = table.freeze({
+		["key"] = "value"
+	})
+})"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			freeze: Freeze::Shallow,
+			root_key: Some(String::from("root")),
+			..Default::default()
+		};
+
+		let lua = r#"table.freeze({
+	["root"] = {
+		["list"] = {
+			1,
+			{
+				2,
+			},
+		},
+		["nested"] = {
+			["key"] = "value",
+		},
+	},
+})"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn luau_types() {
+		use crate::{
+			generate_luau_types, parse_with_options, LuauTypes, MixedArrays, ParseOptions, Wrap,
+		};
+
+		let toml = r#"
+		name = "server"
+		"max players" = 16
+		mixed = ["a", 1, 2.5, true]
+		empty = []
+
+		[[items]]
+		id = 1
+		tags = ["rare"]
+
+		[[items]]
+		id = 2
+		weight = 0.5
+"#;
+
+		let luau = r#"export type Config = {
+	name: string,
+	["max players"]: number,
+	mixed: { string | number | boolean },
+	empty: { any },
+	items: { {
+		id: number,
+		tags: { string }?,
+		weight: number?,
+	} },
+}
+"#;
+
+		assert_eq!(generate_luau_types(toml, "Config").unwrap(), luau);
+
+		let options = ParseOptions {
+			wrap: Wrap::Return,
+			luau_types: Some(LuauTypes {
+				name: String::from("Config"),
+				mixed_arrays: MixedArrays::Any,
+				annotate: true,
+			}),
+			..Default::default()
+		};
+
+		let toml = r#"
+		mixed = ["a", 1]
+		numbers = [1, 2.5]
+		[nested]
+		ok = true
+"#;
+
+		let lua = r#"export type Config = {
+	mixed: { any },
+	numbers: { number },
+	nested: {
+		ok: boolean,
+	},
+}
+
+return {
+	["mixed"] = {
+		"a",
+		1,
+	},
+	["numbers"] = {
+		1,
+		2.5,
+	},
+	["nested"] = {
+		["ok"] = true,
+	},
+} :: Config"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			wrap: Wrap::Local(String::from("config")),
+			luau_types: Some(LuauTypes::new("Config")),
+			..Default::default()
+		};
+
+		assert!(parse_with_options(toml, &options)
+			.unwrap()
+			.contains("local config: Config = {"));
+	}
+
+	#[test]
+	fn unicode_separators() {
+		use crate::{parse, parse_with_options, ParseOptions};
+
+		let toml = "text = \"line\u{2028}paragraph\u{2029}end\"";
+
+		let options = ParseOptions {
+			escape_unicode: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["text"] = "line\u{2028}paragraph\u{2029}end",
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+		assert!(parse(toml)
+			.unwrap()
+			.contains("line\u{2028}paragraph\u{2029}end"));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn with_value() {
+		use crate::{parse, parse_with_value, ParseOptions};
+		use toml::Value;
+
+		let toml = r#"
+		name = "server"
+		port = 8080
+
+		[database]
+		url = "localhost"
+"#;
+
+		let (lua, map) = parse_with_value(toml, &ParseOptions::default()).unwrap();
+
+		assert_eq!(lua, parse(toml).unwrap());
+		assert_eq!(map.keys().collect::<Vec<_>>(), ["name", "port", "database"]);
+		assert_eq!(map["port"], Value::Integer(8080));
+		assert_eq!(map["database"]["url"].as_str(), Some("localhost"));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn brace_on_new_line() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = r#"
+		name = "braces"
+		list = [1, [2]]
+
+		[outer]
+		[outer.inner]
+		key = "value"
+"#;
+
+		let options = ParseOptions {
+			brace_on_new_line: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["name"] = "braces",
+	["list"] =
+	{
+		1,
+		{
+			2,
+		},
+	},
+	["outer"] =
+	{
+		["inner"] =
+		{
+			["key"] = "value",
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	fn bytes_input() {
+		use crate::{parse, parse_bytes, parse_bytes_with_options, Error, ParseOptions, Utf8Mode};
+
+		let lua = "{\n\t[\"name\"] = \"bom\",\n}";
+
+		assert_eq!(parse_bytes(b"\xEF\xBB\xBFname = 'bom'").unwrap(), lua);
+		assert_eq!(parse("\u{feff}name = 'bom'").unwrap(), lua);
+
+		let invalid = b"\xEF\xBB\xBFname = \"a\xFFb\"";
+
+		assert!(matches!(
+			parse_bytes(invalid),
+			Err(Error::InvalidUtf8 { offset: 12 })
+		));
+
+		let options = ParseOptions {
+			utf8_mode: Utf8Mode::Lossy,
+			..Default::default()
+		};
+
+		let (lossy, warnings) = parse_bytes_with_options(invalid, &options).unwrap();
+
+		assert_eq!(lossy, "{\n\t[\"name\"] = \"a\u{FFFD}b\",\n}");
+		assert_eq!(warnings.len(), 1);
+		assert!(warnings[0].path.is_empty());
+		assert!(warnings[0].message.contains("offset 12"));
+
+		let (_, warnings) = parse_bytes_with_options(b"name = 'ok'", &options).unwrap();
+
+		assert!(warnings.is_empty());
+
+		let utf16: Vec<u8> = "name = 'bom'"
+			.encode_utf16()
+			.flat_map(u16::to_le_bytes)
+			.collect();
+		let with_bom = [&[0xFF, 0xFE][..], &utf16].concat();
+
+		for input in [&utf16, &with_bom] {
+			assert!(matches!(parse_bytes(input), Err(Error::Utf16)));
+			assert!(matches!(
+				parse_bytes_with_options(input, &options),
+				Err(Error::Utf16)
+			));
+		}
+
+		assert_eq!(
+			parse_bytes(&utf16).unwrap_err().to_string(),
+			"input is not UTF-8, it looks like UTF-16"
+		);
+	}
+
+	#[test]
+	fn bool_style() {
+		use crate::{parse_with_options, BoolStyle, ParseOptions};
+
+		let toml = r#"
+		enabled = true
+		flags = [false, true]
+"#;
+
+		let options = ParseOptions {
+			bool_style: BoolStyle::Numeric,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	enabled = 1,
+	flags = {
+		0,
+		1,
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			bool_style: BoolStyle::Custom(String::from("True"), String::from("False")),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	enabled = True,
+	flags = {
+		False,
+		True,
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	fn homogeneous_arrays() {
+		use crate::{parse_with_options, Error, ParseOptions};
+
+		let options = ParseOptions {
+			homogeneous_arrays: true,
+			..Default::default()
+		};
+
+		let err =
+			parse_with_options("[nested]\narray = ['string', 12345, false]", &options).unwrap_err();
+
+		assert!(matches!(
+			&err,
+			Error::MixedArray { path, first: "string", second: "integer" } if path == "nested.array"
+		));
+		assert_eq!(
+			err.to_string(),
+			"array `nested.array` mixes string and integer elements"
+		);
+
+		let numbers = "numbers = [1, 2.5]";
+
+		assert!(matches!(
+			parse_with_options(numbers, &options),
+			Err(Error::MixedArray {
+				first: "integer",
+				second: "float",
+				..
+			})
+		));
+
+		let options = ParseOptions {
+			homogeneous_arrays: true,
+			mixed_numbers: true,
+			..Default::default()
+		};
+
+		assert!(parse_with_options(numbers, &options).is_ok());
+
+		let tables = r#"
+		[[items]]
+		name = "sword"
+
+		[[items]]
+		damage = 10
+"#;
+
+		assert!(parse_with_options(tables, &options).is_ok());
+		assert!(parse_with_options("nested = [[1], ['a']]", &options).is_ok());
+		assert!(matches!(
+			parse_with_options("nested = [[1], [2, 'a']]", &options),
+			Err(Error::MixedArray { path, .. }) if path == "nested.2"
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn numeric_tables() {
+		use crate::{parse_with_options, Error, ParseOptions};
+
+		let toml = r#"
+		[levels]
+		2 = "second"
+		1 = "first"
+
+		[ranks]
+		1 = "gold"
+		3 = "bronze"
+"#;
+
+		let options = ParseOptions {
+			numeric_keys_as_array: true,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	levels = {
+		"first",
+		"second",
+	},
+	ranks = {
+		["1"] = "gold",
+		["3"] = "bronze",
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			numeric_keys_as_array: true,
+			force_map_paths: vec![String::from("levels")],
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	levels = {
+		["2"] = "second",
+		["1"] = "first",
+	},
+	ranks = {
+		["1"] = "gold",
+		["3"] = "bronze",
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			force_array_paths: vec![String::from("levels")],
+			bare_keys: true,
+			..Default::default()
+		};
+
+		assert!(parse_with_options(toml, &options)
+			.unwrap()
+			.contains("levels = {\n\t\t\"first\",\n\t\t\"second\",\n\t},"));
+
+		let options = ParseOptions {
+			force_array_paths: vec![String::from("ranks")],
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::NotSequence { path }) if path == "ranks"
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn semicolons() {
+		use crate::{parse_with_options, ParseOptions, Separator};
+
+		let toml = r#"
+		name = "semicolons"
+		list = [1, 2]
+"#;
+
+		let options = ParseOptions {
+			separator: Separator::Semicolon,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	name = "semicolons";
+	list = {
+		1;
+		2;
+	};
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			trailing_commas: false,
+			..options
+		};
+
+		let lua = r#"{
+	name = "semicolons";
+	list = {
+		1;
+		2
+	}
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	fn lua_targets() {
+		use crate::{parse_with_options, Error, Freeze, IntegerOverflow, LuaTarget, ParseOptions};
+
+		let text = "price = '5 €'";
+		let big = "big = 9007199254740993";
+
+		let escaped = |target| {
+			let options = ParseOptions {
+				escape_unicode: true,
+				..ParseOptions::for_target(target)
+			};
+
+			parse_with_options(text, &options).unwrap()
+		};
+
+		assert_eq!(
+			escaped(LuaTarget::Lua51),
+			"{\n\t[\"price\"] = \"5 \\226\\130\\172\",\n}"
+		);
+
+		for target in [
+			LuaTarget::Lua53,
+			LuaTarget::Lua54,
+			LuaTarget::LuaJit,
+			LuaTarget::Luau,
+		] {
+			assert_eq!(escaped(target), "{\n\t[\"price\"] = \"5 \\u{20AC}\",\n}");
+		}
+
+		for target in [LuaTarget::Lua51, LuaTarget::LuaJit, LuaTarget::Luau] {
+			assert!(matches!(
+				parse_with_options(big, &ParseOptions::for_target(target)),
+				Err(Error::IntegerOverflow {
+					value: 9007199254740993,
+					..
+				})
+			));
+		}
+
+		for target in [LuaTarget::Lua53, LuaTarget::Lua54] {
+			assert!(parse_with_options(big, &ParseOptions::for_target(target))
+				.unwrap()
+				.contains("9007199254740993"));
+		}
+
+		let options = ParseOptions {
+			integer_overflow: IntegerOverflow::Allow,
+			..ParseOptions::for_target(LuaTarget::Lua51)
+		};
+
+		assert!(parse_with_options(big, &options).is_ok());
+
+		let frozen = |target| ParseOptions {
+			freeze: Freeze::Shallow,
+			..ParseOptions::for_target(target)
+		};
+
+		assert!(parse_with_options(text, &frozen(LuaTarget::Luau)).is_ok());
+		assert_eq!(
+			parse_with_options(text, &frozen(LuaTarget::Lua54))
+				.unwrap_err()
+				.to_string(),
+			"`table.freeze` is not available in Lua 5.4"
+		);
+	}
+
+	#[test]
+	fn stats() {
+		use crate::{parse_with_stats, ConversionStats, ParseOptions};
+
+		let toml = r#"
+		name = "stats"
+		tags = ["a", "b"]
+
+		[server]
+		port = 8080
+
+		[[server.routes]]
+		path = "/"
+		methods = ["GET"]
+"#;
+
+		let (lua, stats) = parse_with_stats(toml, &ParseOptions::default()).unwrap();
+
+		assert_eq!(lua, crate::parse(toml).unwrap());
+		assert_eq!(
+			stats,
+			ConversionStats {
+				tables: 3,
+				arrays: 3,
+				scalars: 6,
+				max_depth: 5,
+				fields: 11,
+			}
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn stylua_default() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = r#"
+		name = "say \"hi\""
+		plain = 'say "hi" and "bye"'
+		apostrophe = "it's"
+		ports = [80, 443]
+		empty = {}
+
+		[server]
+		host = "localhost"
+		timeout = 1.5
+
+		[server.tls]
+		enabled = true
+
+		[[users]]
+		name = "a"
+
+		[[users]]
+		name = "b"
+		tags = ["x", "y"]
+"#;
+
+		// Output of `stylua -` (2.6.0) over the converted document, which must change nothing
+		let lua = r#"return {
+	["name"] = 'say "hi"',
+	["plain"] = 'say "hi" and "bye"',
+	["apostrophe"] = "it's",
+	["ports"] = {
+		80,
+		443,
+	},
+	["empty"] = {},
+	["server"] = {
+		["host"] = "localhost",
+		["timeout"] = 1.5,
+		["tls"] = {
+			["enabled"] = true,
+		},
+	},
+	["users"] = {
+		{
+			["name"] = "a",
+		},
+		{
+			["name"] = "b",
+			["tags"] = {
+				"x",
+				"y",
+			},
+		},
+	},
+}
+"#;
+
+		assert_eq!(
+			parse_with_options(toml, &ParseOptions::stylua_default()).unwrap(),
+			lua
+		);
+	}
+
+	#[test]
+	fn ascii_identifiers() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = r#"
+		"café" = "au lait"
+		plain = 1
+"#;
+
+		let options = ParseOptions {
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["café"] = "au lait",
+	plain = 1,
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			ascii_identifiers_only: false,
+			..options
+		};
+
+		let lua = r#"{
+	café = "au lait",
+	plain = 1,
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "radix")]
+	fn integer_radix() {
+		use crate::{parse_preserving_radix, LuaTarget, ParseOptions, ValueTransform};
+		use toml::Value;
+
+		let toml = r#"
+		hex = 0xFF_FF
+		octal = 0o755
+		binary = 0b1010
+		decimal = 1_000_000
+		plain = 42
+		masks = [0xF0, 0o17]
+
+		[[flags]]
+		bits = 0b11
+"#;
+
+		let lua = r#"{
+	["hex"] = 0xFFFF,
+	["octal"] = 493, -- 0o755
+	["binary"] = 10, -- 0b1010
+	["decimal"] = 1000000,
+	["plain"] = 42,
+	["masks"] = {
+		0xF0,
+		15 -- 0o17
+	},
+	["flags"] = {
+		{
+			["bits"] = 3 -- 0b11
+		}
+	}
+}"#;
+
+		let options = ParseOptions {
+			trailing_commas: false,
+			..Default::default()
+		};
+
+		assert_eq!(parse_preserving_radix(toml, &options).unwrap(), lua);
+
+		let lua = r#"{
+	["hex"] = 0xFF_FF,
+	["octal"] = 493, -- 0o755
+	["binary"] = 0b1010,
+	["decimal"] = 1_000_000,
+	["plain"] = 42,
+	["masks"] = {
+		0xF0,
+		15, -- 0o17
+	},
+	["flags"] = {
+		{
+			["bits"] = 0b11,
+		},
+	},
+}"#;
+
+		assert_eq!(
+			parse_preserving_radix(toml, &ParseOptions::for_target(LuaTarget::Luau)).unwrap(),
+			lua
+		);
+
+		// Dotted keys aren't confused with nested tables
+		let toml = "\"a.b\" = 0x10\n\n[a]\nb = 5";
+
+		let lua = r#"{
+	["a.b"] = 0x10,
+	["a"] = {
+		["b"] = 5,
+	},
+}"#;
+
+		assert_eq!(
+			parse_preserving_radix(toml, &ParseOptions::default()).unwrap(),
+			lua
+		);
+
+		// Literals are only kept for the values they spell
+		let options = ParseOptions {
+			value_transform: Some(ValueTransform::new(|_, value| match value {
+				Value::Integer(integer) => Some(Value::Integer(integer + 1)),
+				_ => None,
+			})),
+			..Default::default()
+		};
+
+		assert_eq!(
+			parse_preserving_radix("mask = 0xFF", &options).unwrap(),
+			"{\n\t[\"mask\"] = 256,\n}"
+		);
+	}
+
+	#[test]
+	fn max_output_bytes() {
+		use crate::{parse, parse_with_options, Error, ParseOptions};
+
+		let toml = r#"
+		[[items]]
+		name = "first"
+
+		[[items]]
+		name = "second"
+"#;
+
+		let len = parse(toml).unwrap().len();
+
+		let options = ParseOptions {
+			max_output_bytes: Some(len),
+			..Default::default()
+		};
+
+		assert!(parse_with_options(toml, &options).is_ok());
+
+		let options = ParseOptions {
+			max_output_bytes: Some(len - 1),
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::OutputTooLarge { .. })
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn flatten() {
+		use crate::{parse_with_options, Error, ParseOptions};
+
+		let toml = r#"
+		name = "flat"
+		ports = [80, 443]
+		empty = {}
+
+		[server.http]
+		host = "localhost"
+
+		[[users]]
+		name = "a"
+		tags = ["x"]
+"#;
+
+		let options = ParseOptions {
+			flatten: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["name"] = "flat",
+	["ports"] = {
+		80,
+		443,
+	},
+	["empty"] = {
+	},
+	["server.http.host"] = "localhost",
+	["users"] = {
+		{
+			["name"] = "a",
+			["tags"] = {
+				"x",
+			},
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			flatten_arrays: true,
+			..options
+		};
+
+		let lua = r#"{
+	["name"] = "flat",
+	["ports.1"] = 80,
+	["ports.2"] = 443,
+	["empty"] = {
+	},
+	["server.http.host"] = "localhost",
+	["users.1.name"] = "a",
+	["users.1.tags.1"] = "x",
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			flatten_separator: String::from("/"),
+			bare_keys: true,
+			..options
+		};
+
+		let lua = r#"{
+	name = "flat",
+	["ports/1"] = 80,
+	["ports/2"] = 443,
+	empty = {
+	},
+	["server/http/host"] = "localhost",
+	["users/1/name"] = "a",
+	["users/1/tags/1"] = "x",
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		// Keys containing the separator can collide with nested ones
+		for (toml, options) in [
+			("\"a.b\" = 1\n[a]\nb = 2", ParseOptions::default()),
+			("\"list/1\" = 1\nlist = [2]", options),
+		] {
+			let options = ParseOptions {
+				flatten: true,
+				..options
+			};
+
+			assert!(
+				matches!(
+					parse_with_options(toml, &options),
+					Err(Error::KeyCollision { key, .. }) if key == "a.b" || key == "list/1"
+				),
+				"{}",
+				toml
+			);
+		}
+
+		let options = ParseOptions {
+			flatten: true,
+			..Default::default()
+		};
+
+		assert!(parse_with_options("\"list.1\" = 1\nlist = [2]", &options).is_ok());
+	}
+
+	#[test]
+	fn header() {
+		use crate::{parse_with_options, Header, ParseOptions, Wrap};
+
+		let toml = "value = 1";
+
+		assert_eq!(
+			parse_with_options(toml, &ParseOptions::default()).unwrap(),
+			"{\n\t[\"value\"] = 1,\n}"
+		);
+
+		let options = ParseOptions {
+			header: Some(Header::Custom(String::from(
+				"Game config\n\n[[edit]] the TOML",
+			))),
+			wrap: Wrap::Return,
+			..Default::default()
+		};
+
+		let lua = r#"-- Game config
+--
+-- [[edit]] the TOML
+return {
+	["value"] = 1,
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		// Lua ends a comment at a lone carriage return too
+		let options = ParseOptions {
+			header: Some(Header::Custom(String::from("first\rreturn 1\r\nlast\r"))),
+			wrap: Wrap::Return,
+			..Default::default()
+		};
+
+		let lua = parse_with_options(toml, &options).unwrap();
+		assert_eq!(
+			lua,
+			"-- first\n-- return 1\n-- last\nreturn {\n\t[\"value\"] = 1,\n}"
+		);
+
+		#[cfg(any(feature = "verify", feature = "validate"))]
+		mlua::Lua::new().load(&lua).exec().unwrap();
+
+		let options = ParseOptions {
+			header: Some(Header::Generated),
+			header_hash: true,
+			..Default::default()
+		};
+
+		let lua = format!(
+			"-- Generated by toml2lua v{}; do not edit.\n-- Source FNV-1a hash: 94821d7f7d8a639e\n{{\n\t[\"value\"] = 1,\n}}",
+			env!("CARGO_PKG_VERSION")
+		);
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "std")]
+	fn file_header() {
+		use crate::{parse_file, Header, ParseOptions};
+		use std::fs;
+
+		let dir = temp_dir("file_header");
+		fs::write(dir.join("config.toml"), "value = 1").unwrap();
+
+		let options = ParseOptions {
+			header: Some(Header::Generated),
+			..Default::default()
+		};
+
+		let lua = format!(
+			"-- Generated by toml2lua v{} from config.toml; do not edit.\n{{\n\t[\"value\"] = 1,\n}}",
+			env!("CARGO_PKG_VERSION")
+		);
+
+		assert_eq!(parse_file(&dir.join("config.toml"), &options).unwrap(), lua);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn split_locals() {
+		use crate::{parse_with_options, Error, ParseOptions, ReadonlyMode, Wrap};
+
+		let toml = r#"
+		name = "game"
+		version = 3
+
+		[server]
+		port = 8080
+
+		[server-config]
+		debug = true
+
+		[server_config]
+		debug = false
+
+		[end]
+		table = { nested = 1 }
+"#;
+
+		let options = ParseOptions {
+			split_locals: true,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"local server = {
+	port = 8080,
+}
+local server_config = {
+	debug = true,
+}
+local server_config_2 = {
+	debug = false,
+}
+local end_ = {
+	table = {
+		nested = 1,
+	},
+}
+return {
+	name = "game",
+	version = 3,
+	server = server,
+	["server-config"] = server_config,
+	server_config = server_config_2,
+	["end"] = end_,
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			wrap: Wrap::Local(String::from("config")),
+			..options
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::ConflictingOptions { .. })
+		));
+
+		// Globals of other options aren't shadowed
+		let options = ParseOptions {
+			wrap: Wrap::None,
+			readonly: ReadonlyMode::Metatable,
+			..options
+		};
+
+		let lua = parse_with_options("[error]\ncode = 1", &options).unwrap();
+		assert!(lua.starts_with("local error_2 = {"), "{}", lua);
+
+		// Lua functions can't have more than 200 locals
+		let toml: String = (0..250)
+			.map(|i| format!("[section_{}]\nvalue = {}\n", i, i))
+			.collect();
+
+		let lua = parse_with_options(&toml, &options).unwrap();
+
+		assert_eq!(lua.matches("local ").count(), 180);
+		assert!(lua.contains("\tsection_179 = section_179,\n"));
+		assert!(lua.contains("\tsection_180 = {\n\t\tvalue = 180,\n\t},\n"));
+
+		#[cfg(any(feature = "verify", feature = "validate"))]
+		mlua::Lua::new().load(&lua).exec().unwrap();
+	}
+
+	#[test]
+	#[cfg(feature = "comments")]
+	fn source_comments() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = r#"name = "game"
+
+[server]
+port = 8080
+hosts = ["a", "b"]
+"#;
+
+		let options = ParseOptions {
+			source_comments: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["name"] = "game", -- toml:1:8
+	["server"] = {
+		["port"] = 8080, -- toml:4:8
+		["hosts"] = {
+			"a", -- toml:5:10
+			"b", -- toml:5:15
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		// Dotted keys aren't confused with nested tables
+		let toml = "\"a.b\" = 1\n\n[a]\nb = 2";
+
+		let lua = r#"{
+	["a.b"] = 1, -- toml:1:9
+	["a"] = {
+		["b"] = 2, -- toml:4:5
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn special_values() {
+		use crate::{
+			parse, parse_with_options, parse_with_warnings, NumberMode, ParseOptions, WarningKind,
+		};
+
+		let toml = r#"
+		floats = [inf, -inf, nan]
+		control = "null \u0000 bell \u0007 delete \u007f"
+"#;
+
+		let lua = r#"{
+	["floats"] = {
+		1/0,
+		-1/0,
+		0/0,
+	},
+	["control"] = "null \000 bell \007 delete \127",
+}"#;
+
+		assert_eq!(parse(toml).unwrap(), lua);
+
+		let options = ParseOptions {
+			number_mode: NumberMode::AllInteger,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["floats"] = {
+		9223372036854775807,
+		(-9223372036854775807 - 1),
+		0,
+	},
+	["control"] = "null \000 bell \007 delete \127",
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		// Saturated integers also warn about their precision, left out here
+		let (_, warnings) = parse_with_warnings(toml, &options).unwrap();
+		let warnings: Vec<String> = warnings
+			.iter()
+			.filter(|warning| warning.kind == WarningKind::NonFinite)
+			.map(ToString::to_string)
+			.collect();
+
+		assert_eq!(
+			warnings,
+			[
+				"`floats.1`: inf written as 9223372036854775807",
+				"`floats.2`: -inf written as -9223372036854775808",
+				"`floats.3`: NaN written as 0",
+			]
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn schema() {
+		use crate::{
+			parse_with_schema, Error, ParseOptions, Schema, UnknownKeys, ValueType, Violation,
+		};
+
+		let schema = Schema::new()
+			.required("name", ValueType::String)
+			.one_of("mode", ["debug", "release"])
+			.one_of("level", [1, 2, 3])
+			.optional("database", ValueType::Table)
+			.required("database.url", ValueType::String)
+			.required("servers.*.host", ValueType::String)
+			.range("servers.*.port", 1.0..65536.0)
+			.unknown_keys(UnknownKeys::Error);
+
+		let toml = r#"
+		name = 1
+		mode = "test"
+		level = 2
+		extra = true
+
+		[[servers]]
+		host = "a"
+		port = 0
+
+		[[servers]]
+		port = "80"
+"#;
+
+		let violation = |path: &str, expected: &str, found: &str| Violation {
+			path: String::from(path),
+			expected: String::from(expected),
+			found: String::from(found),
+		};
+
+		let Err(Error::Schema(violations)) =
+			parse_with_schema(toml, &schema, &ParseOptions::default())
+		else {
+			panic!("document is invalid");
+		};
+
+		assert_eq!(
+			violations,
+			[
+				violation("name", "string", "integer"),
+				violation("mode", "one of \"debug\", \"release\"", "\"test\""),
+				violation("extra", "no key", "boolean"),
+				violation("servers.1.port", "number >= 1 and < 65536", "0"),
+				violation("servers.2.host", "string", "nothing"),
+				violation("servers.2.port", "number", "string"),
+			]
+		);
+
+		// Tables without rules below them may contain anything
+		let schema = schema.unknown_keys(UnknownKeys::Warn);
+		let toml = r#"
+		name = "abc"
+		other = 1
+
+		[database]
+		url = "postgres://"
+
+		[free]
+		anything = true
+"#;
+
+		let (_, warnings) = parse_with_schema(toml, &schema, &ParseOptions::default()).unwrap();
+		let paths: Vec<&str> = warnings
+			.iter()
+			.map(|warning| warning.path.as_str())
+			.collect();
+
+		assert_eq!(paths, ["other", "free"]);
+	}
+
+	#[test]
+	#[cfg(feature = "std")]
+	#[cfg(feature = "preserve_order")]
+	fn interpolation() {
+		use std::collections::HashMap;
+
+		use crate::{parse, parse_with_options, Error, Interpolation, ParseOptions, Unresolved};
+
+		let toml = r#"
+		url = "https://${HOST}/v2"
+		port = "${PORT:-8080}"
+		empty = "${EMPTY:-fallback}|${EMPTY}"
+		quoted = "say ${QUOTE}"
+		literal = "$$HOST costs $5 ${unclosed"
+		missing = "[${MISSING}]"
+"#;
+
+		let variables = HashMap::from(
+			[
+				("HOST", "example.com"),
+				("EMPTY", ""),
+				("QUOTE", "\"hi\"\n"),
+			]
+			.map(|(name, value)| (String::from(name), String::from(value))),
+		);
+
+		let lua = |missing: &str| {
+			format!(
+				r#"{{
+	["url"] = "https://example.com/v2",
+	["port"] = "8080",
+	["empty"] = "fallback|",
+	["quoted"] = "say \"hi\"\n",
+	["literal"] = "$HOST costs $5 ${{unclosed",
+	["missing"] = "{}",
+}}"#,
+				missing
+			)
+		};
+
+		for (unresolved, missing) in [
+			(Unresolved::Keep, "[${MISSING}]"),
+			(Unresolved::Empty, "[]"),
+		] {
+			let options = ParseOptions {
+				interpolation: Some(Interpolation {
+					unresolved,
+					..Interpolation::from_map(variables.clone())
+				}),
+				..Default::default()
+			};
+
+			assert_eq!(parse_with_options(toml, &options).unwrap(), lua(missing));
+		}
+
+		let options = ParseOptions {
+			interpolation: Some(Interpolation {
+				unresolved: Unresolved::Error,
+				..Interpolation::from_map(variables)
+			}),
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options("[server]\nhosts = ['${HOST}', '${MISSING}']", &options),
+			Err(Error::UnresolvedVariable { path, variable })
+				if path == "server.hosts.2" && variable == "MISSING"
+		));
+
+		// Strings are kept as written without interpolation
+		assert!(parse("a = '${HOST}'").unwrap().contains("${HOST}"));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn max_width() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = r#"
+		list = [1, 2, 3]
+		grid = [[1, 2], [3, 4], []]
+		items = [{ a = 1 }]
+"#;
+
+		let options = |max_width| ParseOptions {
+			max_width: Some(max_width),
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["list"] = { 1, 2, 3 },
+	["grid"] = { { 1, 2 }, { 3, 4 }, {} },
+	["items"] = {
+		{
+			["a"] = 1,
+		},
+	},
+}"#;
+
+		// `\t["grid"] = { { 1, 2 }, { 3, 4 }, {} },` is 42 columns wide
+		assert_eq!(parse_with_options(toml, &options(42)).unwrap(), lua);
+
+		let lua = r#"{
+	["list"] = { 1, 2, 3 },
+	["grid"] = {
+		{ 1, 2 },
+		{ 3, 4 },
+		{},
+	},
+	["items"] = {
+		{
+			["a"] = 1,
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options(41)).unwrap(), lua);
+
+		// `\t["list"] = { 1, 2, 3 },` is 27 columns wide
+		let list = |max_width| parse_with_options("list = [1, 2, 3]", &options(max_width));
+
+		assert_eq!(list(27).unwrap(), "{\n\t[\"list\"] = { 1, 2, 3 },\n}");
+		assert_eq!(
+			list(26).unwrap(),
+			"{\n\t[\"list\"] = {\n\t\t1,\n\t\t2,\n\t\t3,\n\t},\n}"
+		);
+
+		// The last entry has no separator without trailing commas
+		let options = ParseOptions {
+			trailing_commas: false,
+			..options(26)
+		};
+
+		assert_eq!(
+			parse_with_options("list = [1, 2, 3]", &options).unwrap(),
+			"{\n\t[\"list\"] = { 1, 2, 3 }\n}"
+		);
+	}
+
+	#[test]
+	fn serialize() {
+		use serde::Serialize;
+		use std::collections::BTreeMap;
+
+		use crate::{from_serialize, parse, Error};
+
+		#[derive(Serialize)]
+		struct Config {
+			name: &'static str,
+			debug: Option<bool>,
+			server: Server,
+			users: Vec<User>,
+		}
+
+		#[derive(Serialize)]
+		struct Server {
+			port: u16,
+			ratio: f32,
+		}
+
+		#[derive(Serialize)]
+		struct User {
+			name: &'static str,
+			tags: Vec<&'static str>,
+		}
+
+		let config = Config {
+			name: "game",
+			debug: None,
+			server: Server {
+				port: 8080,
+				ratio: 0.5,
+			},
+			users: vec![User {
+				name: "a",
+				tags: vec!["x", "y"],
+			}],
+		};
+
+		let toml = r#"
+		name = "game"
+
+		[server]
+		port = 8080
+		ratio = 0.5
+
+		[[users]]
+		name = "a"
+		tags = ["x", "y"]
+"#;
+
+		assert_eq!(from_serialize(&config).unwrap(), parse(toml).unwrap());
+
+		assert!(matches!(
+			from_serialize(&BTreeMap::from([("list", vec![Some(1), None])])),
+			Err(Error::Serialize(_))
+		));
+		assert!(matches!(
+			from_serialize(&BTreeMap::from([((1, 2), "a")])),
+			Err(Error::Serialize(_))
+		));
+
+		assert!(matches!(
+			from_serialize(&BTreeMap::from([(1, "a")])),
+			Err(Error::Serialize(_))
+		));
+		assert!(matches!(
+			from_serialize(&BTreeMap::from([("list", vec![vec![None::<i32>]])])),
+			Err(Error::Serialize(_))
+		));
+		assert!(matches!(from_serialize(&[1, 2]), Err(Error::Serialize(_))));
+		assert!(matches!(
+			from_serialize(&None::<Config>),
+			Err(Error::Serialize(_))
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn serialize_order() {
+		use serde::Serialize;
+
+		use crate::from_serialize;
+
+		#[derive(Serialize)]
+		struct Outer {
+			name: &'static str,
+			inner: Inner,
+			list: Vec<Option<u8>>,
+		}
+
+		#[derive(Serialize)]
+		struct Inner {
+			z: bool,
+			a: Option<bool>,
+		}
+
+		let outer = Outer {
+			name: "x",
+			inner: Inner { z: true, a: None },
+			list: vec![Some(1), Some(2)],
+		};
+
+		// Fields keep their declared order, even a table before an array
+		assert_eq!(
+			from_serialize(&outer).unwrap(),
+			"{\n\t[\"name\"] = \"x\",\n\t[\"inner\"] = {\n\t\t[\"z\"] = true,\n\t},\n\t[\"list\"] = {\n\t\t1,\n\t\t2,\n\t},\n}"
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn assignments() {
+		use crate::parse_assignments;
+
+		let toml = r#"
+		title = "game"
+
+		[window]
+		width = 800
+		"full screen" = false
+
+		[window.position]
+		x = 0
+		y = 0
+
+		[[levels]]
+		name = "intro"
+		spawn = [1, 2]
+
+		[empty]
+"#;
+
+		let lua = r#"config.title = "game"
+config.window.width = 800
+config.window["full screen"] = false
+config.window.position.x = 0
+config.window.position.y = 0
+config.levels[1].name = "intro"
+config.levels[1].spawn[1] = 1
+config.levels[1].spawn[2] = 2
+config.empty = {}
+"#;
+
+		assert_eq!(parse_assignments(toml, "config").unwrap(), lua);
+	}
+	#[test]
+	fn options_file() {
+		use crate::{
+			BoolStyle, Error, Header, Indent, Interpolation, LuaTarget, LuauTypes, ParseOptions,
+			Wrap,
+		};
+		use std::fs;
+
+		let options = ParseOptions {
+			indent: Indent::Spaces(2),
+			bare_keys: true,
+			max_width: Some(80),
+			bool_style: BoolStyle::Custom(String::from("yes"), String::from("no")),
+			interpolation: Some(Interpolation::from_env()),
+			force_array_paths: vec![String::from("list")],
+			wrap: Wrap::Local(String::from("config")),
+			header: Some(Header::Custom(String::from("Generated"))),
+			target: Some(LuaTarget::Luau),
+			luau_types: Some(LuauTypes::new("Config")),
+			..ParseOptions::pretty()
+		};
+
+		let toml = toml::to_string(&options).unwrap();
+		let loaded = ParseOptions::from_toml_str(&toml).unwrap();
+
+		assert_eq!(format!("{:?}", loaded), format!("{:?}", options));
+
+		let loaded = ParseOptions::from_toml_str(
+			r#"
+			indent = "tabs"
+			bool_style = "numeric"
+
+			[luau_types]
+			name = "Config"
+"#,
+		)
+		.unwrap();
+
+		assert_eq!(loaded.bool_style, BoolStyle::Numeric);
+		assert_eq!(loaded.luau_types, Some(LuauTypes::new("Config")));
+		assert!(loaded.trailing_commas);
+
+		assert!(matches!(
+			ParseOptions::from_toml_str("trailing_comma = false"),
+			Err(Error::Toml { error, .. }) if error.to_string().contains("unknown field")
+		));
+
+		let dir = temp_dir("options_file");
+
+		assert!(!ParseOptions::discover(&dir).unwrap().bare_keys);
+
+		fs::write(dir.join(ParseOptions::CONFIG_FILE), "bare_keys = true").unwrap();
+
+		assert!(ParseOptions::discover(&dir).unwrap().bare_keys);
+	}
+	#[test]
+	fn unwrap_single() {
+		use crate::{parse_with_options, LuauTypes, ParseOptions, Wrap};
+
+		let options = ParseOptions {
+			unwrap_single: true,
+			wrap: Wrap::Return,
+			..Default::default()
+		};
+
+		assert_eq!(
+			parse_with_options("value = 42", &options).unwrap(),
+			"return 42"
+		);
+		assert_eq!(
+			parse_with_options("list = [1, 2]", &options).unwrap(),
+			"return {\n\t1,\n\t2,\n}"
+		);
+		assert_eq!(
+			parse_with_options("[object]\nkey = 'value'", &options).unwrap(),
+			"return {\n\t[\"key\"] = \"value\",\n}"
+		);
+		assert_eq!(
+			parse_with_options("a = 1\nb = 2", &options).unwrap(),
+			"return {\n\t[\"a\"] = 1,\n\t[\"b\"] = 2,\n}"
+		);
+
+		let options = ParseOptions {
+			unwrap_single: true,
+			root_key: Some(String::from("max")),
+			luau_types: Some(LuauTypes::new("Max")),
+			wrap: Wrap::Local(String::from("limits")),
+			..Default::default()
+		};
+
+		let lua = r#"export type Max = {
+	max: number,
+}
+
+local limits: Max = {
+	["max"] = 10,
+}"#;
+
+		assert_eq!(parse_with_options("players = 10", &options).unwrap(), lua);
+
+		// Comments end the line after everything closing the value
+		let options = ParseOptions {
+			unwrap_single: true,
+			type_comments: true,
+			luau_types: Some(LuauTypes::new("Max")),
+			wrap: Wrap::Return,
+			..Default::default()
+		};
+
+		assert_eq!(
+			parse_with_options("players = 10", &options).unwrap(),
+			"export type Max = number\n\nreturn 10 :: Max -- integer"
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "parallel")]
+	fn files_parallel() {
+		use crate::{parse_file, parse_files_parallel, ParseOptions};
+		use std::fs;
+
+		fn assert_send_sync<T: Send + Sync>() {}
+		assert_send_sync::<ParseOptions>();
+
+		let dir = temp_dir("files_parallel");
+
+		let paths: Vec<_> = (0..300)
+			.map(|i| {
+				let path = dir.join(format!("{}.toml", i));
+
+				let toml = if i % 50 == 7 {
+					String::from("broken =")
+				} else {
+					format!(
+						"id = {}\nname = \"item {}\"\n[stats]\nweight = {}.5",
+						i, i, i
+					)
+				};
+
+				fs::write(&path, toml).unwrap();
+				path
+			})
+			.collect();
+
+		let options = ParseOptions::pretty();
+		let results = parse_files_parallel(&paths, &options);
+
+		assert_eq!(results.len(), paths.len());
+
+		for ((path, result), expected) in results.iter().zip(&paths) {
+			assert_eq!(path, expected);
+
+			match parse_file(path, &options) {
+				Ok(lua) => assert_eq!(result.as_ref().unwrap(), &lua),
+				Err(_) => assert!(result.is_err()),
+			}
+		}
+
+		assert_eq!(
+			results.iter().filter(|(_, result)| result.is_err()).count(),
+			6
+		);
+	}
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn hex_floats() {
+		use crate::{parse_with_options, Error, LuaTarget, NumberMode, ParseOptions};
+
+		let toml = r#"
+		tenth = 0.1
+		whole = 3.0
+		negative = -100.5
+		zero = -0.0
+		tiny = 5e-324
+		int = 2
+		special = [inf, nan]
+"#;
+
+		let options = ParseOptions {
+			hex_floats: true,
+			number_mode: NumberMode::AllFloat,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["tenth"] = 0x1.999999999999ap-4,
+	["whole"] = 0x1.8p+1,
+	["negative"] = -0x1.92p+6,
+	["zero"] = -0x0p+0,
+	["tiny"] = 0x0.0000000000001p-1022,
+	["int"] = 2.0,
+	["special"] = {
+		1/0,
+		0/0,
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			hex_floats: true,
+			target: Some(LuaTarget::Lua51),
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::Unsupported { .. })
+		));
+	}
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn value_transform() {
+		use crate::{parse_with_options, Error, ParseOptions, ValueTransform};
+		use toml::Value;
+
+		let toml = r#"
+		password = "hunter2"
+		retries = 3
+
+		[database]
+		user = "admin"
+		password = "secret"
+		timeouts = [1, 2]
+"#;
+
+		let options = ParseOptions {
+			value_transform: Some(ValueTransform::new(|path, value| {
+				if path.last() == Some(&"password") {
+					Some(Value::from("***"))
+				} else if path == ["database", "timeouts", "2"] {
+					value
+						.as_integer()
+						.map(|seconds| Value::from(seconds * 1000))
+				} else {
+					None
+				}
+			})),
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["password"] = "***",
+	["retries"] = 3,
+	["database"] = {
+		["user"] = "admin",
+		["password"] = "***",
+		["timeouts"] = {
+			1,
+			2000,
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			value_transform: Some(ValueTransform::new(|_, _| Some(Value::Array(Vec::new())))),
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::InvalidTransform { path }) if path == "password"
+		));
+	}
+	#[test]
+	#[cfg(feature = "comments")]
+	fn source_map() {
+		use crate::{parse_with_source_map, Header, ParseOptions};
+
+		let toml = r#"name = "game"
+
+[server]
+port = 8080
+hosts = ["a", "b"]
+
+[[players]]
+id = 1
+"#;
+
+		// Key path, TOML line and the Lua line it was mapped to
+		let lines = |options: &ParseOptions| {
+			let (lua, mappings) = parse_with_source_map(toml, options).unwrap();
+			let lua: Vec<String> = lua.lines().map(|line| line.trim().to_owned()).collect();
+
+			mappings
+				.into_iter()
+				.map(|mapping| {
+					(
+						mapping.key_path,
+						mapping.toml_line,
+						lua[mapping.lua_line - 1].clone(),
+					)
+				})
+				.collect::<Vec<_>>()
+		};
+
+		let expected = |entries: &[(&str, usize, &str)]| {
+			entries
+				.iter()
+				.map(|(path, line, lua)| (path.to_string(), *line, lua.to_string()))
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			lines(&ParseOptions::default()),
+			expected(&[
+				("name", 1, "[\"name\"] = \"game\","),
+				("server.port", 4, "[\"port\"] = 8080,"),
+				("server.hosts.1", 5, "\"a\","),
+				("server.hosts.2", 5, "\"b\","),
+				("players.1.id", 8, "[\"id\"] = 1,"),
+			])
+		);
+
+		let options = ParseOptions {
+			header: Some(Header::Custom(String::from("Generated\nDo not edit"))),
+			max_width: Some(80),
+			brace_on_new_line: true,
+			..ParseOptions::pretty()
+		};
+
+		assert_eq!(
+			lines(&options),
+			expected(&[
+				("name", 1, "name = \"game\","),
+				("server.port", 4, "port = 8080,"),
+				("server.hosts.1", 5, "{ \"a\", \"b\" }"),
+				("server.hosts.2", 5, "{ \"a\", \"b\" }"),
+				("players.1.id", 8, "id = 1"),
+			])
+		);
+
+		let (_, mappings) = parse_with_source_map(toml, &options).unwrap();
+
+		assert_eq!(
+			mappings
+				.iter()
+				.map(|mapping| mapping.lua_line)
+				.collect::<Vec<_>>(),
+			[4, 8, 10, 10, 16]
+		);
+	}
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn chunking() {
+		use crate::{parse_with_options, Error, OrderMetadata, ParseOptions, Wrap};
+		use core::num::NonZeroUsize;
+
+		let toml = r#"
+		list = [1, 2, 3, 4, 5]
+
+		[small]
+		a = 1
+
+		[big]
+		a = 1
+		b = 2
+		"c d" = [3]
+"#;
+
+		let options = ParseOptions {
+			chunking: NonZeroUsize::new(2),
+			bare_keys: true,
+			wrap: Wrap::Return,
+			..Default::default()
+		};
+
+		let lua = r#"return (function()
+	local t = {
+		list = (function()
+			local t = {
+				1,
+				2,
+			}
+			;(function()
+				t[3] = 3
+				t[4] = 4
+			end)()
+			;(function()
+				t[5] = 5
+			end)()
+			return t
+		end)(),
+		small = {
+			a = 1,
+		},
+	}
+	;(function()
+		t.big = (function()
+			local t = {
+				a = 1,
+				b = 2,
+			}
+			;(function()
+				t["c d"] = {
+					3,
+				}
+			end)()
+			return t
+		end)()
+	end)()
+	return t
+end)()"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			chunking: NonZeroUsize::new(2),
+			order_metadata: OrderMetadata::Field,
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::ConflictingOptions { .. })
+		));
+	}
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn sparse_arrays() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = r#"
+		slots = ["sword", {}, "bow", { name = "shield" }, {}]
+		holes = [{}, {}]
+"#;
+
+		let options = ParseOptions {
+			sparse_arrays: true,
+			trailing_commas: false,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["slots"] = {
+		"sword",
+		[3] = "bow",
+		[4] = {
+			["name"] = "shield"
+		}
+	},
+	["holes"] = {
+	}
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn dedupe() {
+		use crate::{parse_with_options, Dedupe, LuaTarget, ParseOptions, Wrap};
+
+		let toml = r#"
+		[[monsters]]
+		name = "goblin"
+		stats = { hp = 10, armor = 2, tags = ["melee", "green"] }
+
+		[[monsters]]
+		name = "orc"
+		stats = { hp = 10, armor = 2, tags = ["melee", "green"] }
+
+		[[monsters]]
+		name = "troll"
+		stats = { hp = 10, armor = 2, tags = ["melee", "green"] }
+		lore = { tags = ["melee", "green"] }
+"#;
+
+		let options = ParseOptions {
+			dedupe: Some(Dedupe {
+				min_size: 24,
+				clone: false,
+			}),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"local __shared_1 = {
+	hp = 10,
+	armor = 2,
+	tags = {
+		"melee",
+		"green",
+	},
+}
+return {
+	monsters = {
+		{
+			name = "goblin",
+			stats = __shared_1,
+		},
+		{
+			name = "orc",
+			stats = __shared_1,
+		},
+		{
+			name = "troll",
+			stats = __shared_1,
+			lore = {
+				tags = {
+					"melee",
+					"green",
+				},
+			},
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		// Repeats nested in a shared table are counted once, so the tags are shared
+		// on their own only when they also occur elsewhere
+		let options = ParseOptions {
+			dedupe: Some(Dedupe {
+				min_size: 8,
+				clone: true,
+			}),
+			wrap: Wrap::Local(String::from("monsters")),
+			..ParseOptions::for_target(LuaTarget::Luau)
+		};
+
+		let lua = parse_with_options(toml, &options).unwrap();
+
+		assert!(lua.starts_with("local __shared_1 = {\n\t\"melee\",\n\t\"green\",\n}\n"));
+		assert!(lua.contains("local __shared_2 = {\n\t[\"hp\"] = 10,\n\t[\"armor\"] = 2,\n\t[\"tags\"] = table.clone(__shared_1),\n}\n"));
+		assert!(lua.contains("[\"stats\"] = table.clone(__shared_2),"));
+		assert!(lua.contains("[\"tags\"] = table.clone(__shared_1),"));
+
+		assert!(parse_with_options(toml, &ParseOptions::for_target(LuaTarget::Lua54)).is_ok());
+		assert!(parse_with_options(
+			toml,
+			&ParseOptions {
+				target: Some(LuaTarget::Lua54),
+				..options
+			}
+		)
+		.is_err());
+
+		// Heavy duplication shrinks to one copy of every repeated value
+		let description = "A long description repeated by every item of the shop";
+		let toml: String = (0..1000)
+			.map(|i| {
+				format!(
+					"[[items]]\nid = {}\ndescription = '{}'\nstats = {{ damage = 5, speed = 1.5, tags = ['common'] }}\n",
+					i, description
+				)
+			})
+			.collect();
+
+		let options = ParseOptions {
+			dedupe: Some(Dedupe::default()),
+			..Default::default()
+		};
+
+		let plain = parse_with_options(&toml, &ParseOptions::default()).unwrap();
+		let deduped = parse_with_options(&toml, &options).unwrap();
+
+		assert_eq!(deduped.matches(description).count(), 1);
+		assert_eq!(deduped.matches("local __shared_").count(), 2);
+		assert!(deduped.len() * 2 < plain.len());
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn align_assignments() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = r#"
+		id = 1
+		display_name = "Sword"
+		"max stack" = 16
+		tags = ["a", "b"]
+
+		[stats]
+		hp = 10
+		attack_speed = 1.5
+"#;
+
+		let options = ParseOptions {
+			align_assignments: true,
+			bare_keys: true,
+			max_width: Some(80),
+			..Default::default()
+		};
+
+		let lua = r#"{
+	id            = 1,
+	display_name  = "Sword",
+	["max stack"] = 16,
+	tags          = { "a", "b" },
+	stats         = {
+		hp           = 10,
+		attack_speed = 1.5,
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			align_assignments: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["longer"] = 3,
+	["a"]      = {
+		["b"]      = 2,
+		["nested"] = {
+			["x"] = 1,
+		},
+	},
+}"#;
+
+		let toml = "longer = 3\n[a]\nb = 2\nnested = { x = 1 }";
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn json_compat() {
+		use crate::{parse_with_options, Error, ParseOptions};
+
+		let toml = r#"
+		name = "scores"
+		[by_id]
+		1 = "first"
+		2 = "second"
+		[mixed]
+		12 = true
+		key = false
+"#;
+
+		let options = ParseOptions {
+			json_compat: true,
+			bare_keys: true,
+			numeric_keys_as_array: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["name"] = "scores",
+	["by_id"] = {
+		["1"] = "first",
+		["2"] = "second",
+	},
+	["mixed"] = {
+		["12"] = true,
+		["key"] = false,
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			force_array_paths: vec![String::from("by_id")],
+			..options
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::ConflictingOptions { .. })
+		));
+	}
+
+	#[test]
+	fn nested_arrays() {
+		use crate::{parse, parse_with_options, Indent, ParseOptions};
+
+		let lua = r#"{
+	["deep"] = {
+		{
+			{
+				1,
+			},
+		},
+	},
+}"#;
+
+		assert_eq!(parse("deep = [[[1]]]").unwrap(), lua);
+
+		// Every closing brace is indented like the line opening it
+		let check = |lua: &str| {
+			let mut open = Vec::new();
+
+			for line in lua.lines() {
+				let indent = line.len() - line.trim_start().len();
+
+				if line.trim_start().starts_with('}') {
+					assert_eq!(open.pop(), Some(indent), "{}", lua);
+				}
+
+				if line.ends_with('{') {
+					open.push(indent);
+				}
+			}
+
+			assert!(open.is_empty(), "{}", lua);
+		};
+
+		let toml = "deep = [[[1, 2], []], [[[]]], [[{ key = [[3]] }]]]";
+
+		for options in [
+			ParseOptions::default(),
+			ParseOptions::pretty(),
+			ParseOptions {
+				brace_on_new_line: true,
+				indent: Indent::Spaces(3),
+				..Default::default()
+			},
+		] {
+			check(&parse_with_options(toml, &options).unwrap());
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn nil_sentinel() {
+		use crate::{parse_with_options, Error, NilEntries, ParseOptions};
+
+		let toml = r#"
+		name = "@nil"
+		lookalikes = { upper = "@NIL", spaced = " @nil", longer = "@nil!", empty = "" }
+		items = ["a", "@nil", "c"]
+"#;
+
+		let options = ParseOptions {
+			nil_sentinel: Some(String::from("@nil")),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::NilInArray { path }) if path == "items"
+		));
+
+		let options = ParseOptions {
+			sparse_arrays: true,
+			max_width: Some(80),
+			..options
+		};
+
+		let lua = r#"{
+	name = nil,
+	lookalikes = {
+		upper = "@NIL",
+		spaced = " @nil",
+		longer = "@nil!",
+		empty = "",
+	},
+	items = {
+		"a",
+		[3] = "c",
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			nil_entries: NilEntries::Drop,
+			..options
+		};
+
+		let lua = parse_with_options(toml, &options).unwrap();
+
+		assert!(lua.starts_with("{\n\tlookalikes = {"));
+		assert!(!lua.contains("= nil"));
+	}
+
+	#[test]
+	#[cfg(feature = "validate")]
+	fn checked() {
+		use crate::{parse_checked, Error};
+
+		let toml = r#"
+		nan = nan
+		negative_nan = -nan
+		infinities = [inf, -inf, +inf]
+		zero = -0.0
+		min = -9223372036854775808
+		control = "bell\u0007 null\u0000 delete\u007f"
+		"end" = "keyword key"
+		"]]" = "]]"
+"#;
+
+		let lua = parse_checked(toml).unwrap();
+
+		assert!(lua.contains("[\"infinities\"] = {\n\t\t1/0,\n\t\t-1/0,\n\t\t1/0,\n\t},"));
+		assert!(lua.contains("[\"min\"] = (-9223372036854775807 - 1),"));
+
+		assert!(matches!(parse_checked("key ="), Err(Error::Toml { .. })));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn datetime_modes() {
+		use crate::{
+			parse_with_options, DatetimeFallback, DatetimeKind, DatetimeMode, Error, ParseOptions,
+		};
+
+		let toml = r#"
+		offset = 1979-05-27T00:32:00.5-07:00
+		local_datetime = 1979-05-27T07:32:00
+		local_date = 1979-05-27
+		local_time = 07:32:00
+"#;
+
+		let options = ParseOptions {
+			datetime_mode: DatetimeMode::Table,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	offset = { year = 1979, month = 5, day = 27, hour = 0, min = 32, sec = 0, nsec = 500000000, utc_offset = -420 },
+	local_datetime = { year = 1979, month = 5, day = 27, hour = 7, min = 32, sec = 0, nsec = 0 },
+	local_date = { year = 1979, month = 5, day = 27 },
+	local_time = { hour = 7, min = 32, sec = 0, nsec = 0 },
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			datetime_mode: DatetimeMode::Epoch,
+			datetime_fallback: DatetimeFallback::String,
+			..options
+		};
+
+		let lua = r#"{
+	offset = 296638320.5,
+	local_datetime = "1979-05-27T07:32:00",
+	local_date = "1979-05-27",
+	local_time = 27120,
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			datetime_fallback: DatetimeFallback::Error,
+			..options
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::UnrepresentableDatetime { path, kind: DatetimeKind::LocalDatetime })
+				if path == "local_datetime"
+		));
+		assert!(matches!(
+			parse_with_options("date = 1979-05-27", &options),
+			Err(Error::UnrepresentableDatetime {
+				kind: DatetimeKind::LocalDate,
+				..
+			})
+		));
+
+		// Strings stay the default
+		assert!(parse_with_options(toml, &ParseOptions::default())
+			.unwrap()
+			.contains("[\"local_time\"] = \"07:32:00\","));
+	}
+
+	#[test]
+	fn empty_document() {
+		use crate::{parse, parse_with_options, EmptyMode, Error, ParseOptions, Wrap};
+
+		let inputs = ["", "  \n\t\n", "# only a comment\n"];
+
+		for toml in inputs {
+			assert_eq!(parse(toml).unwrap(), "{\n}");
+
+			let options = ParseOptions {
+				empty_document: EmptyMode::CompactEmpty,
+				wrap: Wrap::Return,
+				..Default::default()
+			};
+
+			assert_eq!(parse_with_options(toml, &options).unwrap(), "return {}");
+
+			let options = ParseOptions {
+				empty_document: EmptyMode::Error,
+				..Default::default()
+			};
+
+			assert!(matches!(
+				parse_with_options(toml, &options),
+				Err(Error::EmptyDocument)
+			));
+		}
+
+		// Only the document itself is affected, not empty tables in it
+		let options = ParseOptions {
+			empty_document: EmptyMode::CompactEmpty,
+			..Default::default()
+		};
+
+		assert_eq!(
+			parse_with_options("[empty]", &options).unwrap(),
+			"{\n\t[\"empty\"] = {\n\t},\n}"
+		);
+	}
+
+	#[test]
+	fn packed_arrays() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = r#"
+		args = ["a", "b", "c"]
+		empty = []
+		grid = [[1, 2], [3]]
+		items = [{ id = 1 }, {}, { id = 3 }]
+"#;
+
+		let options = ParseOptions {
+			packed_arrays: true,
+			sparse_arrays: true,
+			compact_empty: true,
+			bare_keys: true,
+			max_width: Some(40),
+			..Default::default()
+		};
+
+		let lua = r#"{
+	args = { "a", "b", "c", n = 3 },
+	empty = { n = 0 },
+	grid = {
+		{ 1, 2, n = 2 },
+		{ 3, n = 1 },
+		n = 2,
+	},
+	items = {
+		{
+			id = 1,
+		},
+		[3] = {
+			id = 3,
+		},
+		n = 3,
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	fn analyze() {
+		use crate::{analyze, parse_with_options, Indent, Newline, ParseOptions};
+
+		let documents = [
+			"",
+			r#"
+			title = "Server \"main\""
+			tags = []
+			ratio = 0.25
+			enabled = true
+			started = 1979-05-27T07:32:00Z
+
+			[owner]
+			name = "Tom"
+			"with space" = 'C:\path'
+
+			[[owner.pets]]
+			kind = "cat"
+			ages = [[1, 2], [3]]
+
+			[[owner.pets]]
+			end = -9
+"#,
+			r#"
+			grid = [[1, 2, 3], [4, 5, 6], [7, 8, 9]]
+			lines = """
+first
+	second
+"""
+			[deep.a.b.c]
+			empty = {}
+"#,
+		];
+
+		let option_sets = [
+			ParseOptions::default(),
+			ParseOptions {
+				bare_keys: true,
+				indent: Indent::Spaces(4),
+				..Default::default()
+			},
+			ParseOptions {
+				compact_empty: true,
+				trailing_commas: false,
+				final_newline: true,
+				..Default::default()
+			},
+			ParseOptions {
+				compact_empty: true,
+				final_newline: true,
+				newline: Newline::CrLf,
+				..Default::default()
+			},
+		];
+
+		for toml in documents {
+			let stats = analyze(toml).unwrap();
+
+			for options in &option_sets {
+				assert_eq!(
+					stats.estimated_output_len(options),
+					parse_with_options(toml, options).unwrap().len(),
+					"{:?}",
+					toml
+				);
+			}
+		}
+
+		let stats = analyze(documents[1]).unwrap();
+
+		assert_eq!(
+			(
+				stats.keys,
+				stats.tables,
+				stats.arrays,
+				stats.max_depth,
+				stats.max_array_len
+			),
+			(12, 4, 5, 6, 2)
+		);
+		assert_eq!(
+			(
+				stats.strings,
+				stats.integers,
+				stats.floats,
+				stats.booleans,
+				stats.datetimes
+			),
+			(4, 4, 1, 1, 1)
+		);
+		assert_eq!(stats.string_bytes, 26);
+
+		// Escapes of control characters are only roughly accounted for
+		let toml = "bell = \"\\u0007\\u0007\"";
+		let estimate = analyze(toml)
+			.unwrap()
+			.estimated_output_len(&ParseOptions::default());
+		let len = crate::parse(toml).unwrap().len();
+
+		assert!(estimate <= len && len - estimate <= 8);
+
+		assert!(analyze("key =").is_err());
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn long_strings() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let options = ParseOptions {
+			long_strings: true,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let toml = r#"
+		leading = "\nfirst line is empty"
+		closing = "a ]] b\n]"
+		nested = "[[ and\n]=]"
+		single = "one line"
+		carriage = "a\r\nb"
+"#;
+
+		let lua = r#"{
+	leading = [[
+
+first line is empty]],
+	closing = [=[
+a ]] b
+]]=],
+	nested = [==[
+[[ and
+]=]]==],
+	single = "one line",
+	carriage = "a\r\nb",
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn key_by() {
+		use crate::{parse_with_options, Error, ParseOptions};
+
+		let options = ParseOptions {
+			key_by: [
+				(String::from("items"), String::from("id")),
+				(String::from("levels"), String::from("number")),
+			]
+			.into(),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let toml = r#"
+		[[items]]
+		id = "sword"
+		damage = 10
+
+		[[items]]
+		id = "magic wand"
+		damage = 4
+
+		[[levels]]
+		number = 3
+		name = "cave"
+
+		[[levels]]
+		number = 1
+		name = "forest"
+"#;
+
+		let lua = r#"{
+	items = {
+		sword = {
+			id = "sword",
+			damage = 10,
+		},
+		["magic wand"] = {
+			id = "magic wand",
+			damage = 4,
+		},
+	},
+	levels = {
+		[3] = {
+			number = 3,
+			name = "cave",
+		},
+		[1] = {
+			number = 1,
+			name = "forest",
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let removed = ParseOptions {
+			key_by_remove_field: true,
+			..options.clone()
+		};
+
+		assert_eq!(
+			parse_with_options("[[items]]\nid = 'a'\nx = 1", &removed).unwrap(),
+			"{\n\titems = {\n\t\ta = {\n\t\t\tx = 1,\n\t\t},\n\t},\n}"
+		);
+
+		assert!(matches!(
+			parse_with_options("[[items]]\nid = 'a'\n[[items]]\nid = 'b'\n[[items]]\nid = 'a'", &options),
+			Err(Error::DuplicateKeyField { index: 3, ref key, .. }) if key == "a"
+		));
+		assert!(matches!(
+			parse_with_options("[[items]]\nid = 'a'\n[[items]]\nname = 'b'", &options),
+			Err(Error::MissingKeyField { index: 2, ref path, .. }) if path == "items"
+		));
+		assert!(matches!(
+			parse_with_options("[[items]]\nid = 1.5", &options),
+			Err(Error::InvalidKeyField {
+				index: 1,
+				found: "float",
+				..
+			})
+		));
+		assert!(matches!(
+			parse_with_options("items = [1]", &options),
+			Err(Error::MissingKeyField { index: 1, .. })
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn number_formats() {
+		use crate::{parse_with_options, FloatFormat, IntegerFormat, LuaTarget, ParseOptions};
+
+		// Digit separators are only valid in Luau
+		let grouped = IntegerFormat::new(|integer| {
+			let digits = integer.unsigned_abs().to_string();
+			let mut grouped = String::new();
+
+			for (i, digit) in digits.chars().enumerate() {
+				if i > 0 && (digits.len() - i) % 3 == 0 {
+					grouped.push('_');
+				}
+
+				grouped.push(digit);
+			}
+
+			if integer < 0 {
+				format!("-{}", grouped)
+			} else {
+				grouped
+			}
+		});
+
+		let options = ParseOptions {
+			integer_format: Some(grouped),
+			float_format: Some(FloatFormat::new(|float| format!("{:e}", float))),
+			target: Some(LuaTarget::Luau),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let toml = r#"
+		population = 1234567
+		debt = -1000
+		small = 999
+		ratios = [0.25, 1500.0]
+		"#;
+
+		let lua = r#"{
+	population = 1_234_567,
+	debt = -1_000,
+	small = 999,
+	ratios = {
+		2.5e-1,
+		1.5e3,
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn coerce() {
+		use crate::{parse_with_options, parse_with_warnings, Coerce, Error, ParseOptions};
+
+		let toml = r#"
+		port = "8080"
+		offset = "-12"
+		ratio = "0.75"
+		big = "1.5e3"
+		enabled = "true"
+		debug = "false"
+		zip = "007"
+		version = "1."
+		name = "server"
+		ports = ["80", "443"]
+"#;
+
+		let options = ParseOptions {
+			coerce: Some(Coerce::default()),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	port = 8080,
+	offset = -12,
+	ratio = 0.75,
+	big = 1500.0,
+	enabled = true,
+	debug = false,
+	zip = "007",
+	version = "1.",
+	name = "server",
+	ports = {
+		80,
+		443,
+	},
+}"#;
+
+		let (output, warnings) = parse_with_warnings(toml, &options).unwrap();
+
+		assert_eq!(output, lua);
+		assert_eq!(
+			warnings
+				.iter()
+				.map(|warning| warning.path.as_str())
+				.collect::<Vec<_>>(),
+			["port", "offset", "ratio", "big", "enabled", "debug", "ports.1", "ports.2"]
+		);
+		assert_eq!(warnings[2].message, "coerced string \"0.75\" to float");
+
+		// Coercion is off by default
+		assert_eq!(
+			parse_with_warnings(toml, &ParseOptions::default())
+				.unwrap()
+				.1,
+			[]
+		);
+
+		let restricted = ParseOptions {
+			coerce: Some(Coerce {
+				paths: vec![String::from("port"), String::from("ports.*")],
+			}),
+			..options.clone()
+		};
+
+		let (output, warnings) = parse_with_warnings(toml, &restricted).unwrap();
+
+		assert!(output.contains("port = 8080,"));
+		assert!(output.contains("enabled = \"true\","));
+		assert!(output.contains("ratio = \"0.75\","));
+		assert_eq!(warnings.len(), 3);
+
+		let required = ParseOptions {
+			coerce: Some(Coerce {
+				paths: vec![String::from("name")],
+			}),
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &required),
+			Err(Error::NotCoercible { ref path, ref value }) if path == "name" && value == "server"
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn key_collisions() {
+		use crate::{
+			parse_with_options, parse_with_warnings, Error, KeyCase, KeyCollisions, ParseOptions,
+		};
+
+		let toml = r#"
+		[server]
+		max_connections = 10
+		max-connections = 20
+		host_name = "a"
+"#;
+
+		let camel = ParseOptions {
+			key_case: KeyCase::Camel,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &camel),
+			Err(Error::KeyCollision { ref first, ref second, ref key })
+				if first == "server.max_connections"
+					&& second == "server.max-connections"
+					&& key == "maxConnections"
+		));
+
+		let keep_first = ParseOptions {
+			key_collisions: KeyCollisions::KeepFirst,
+			..camel.clone()
+		};
+
+		let (lua, warnings) = parse_with_warnings(toml, &keep_first).unwrap();
+
+		assert_eq!(
+			lua,
+			"{\n\tserver = {\n\t\tmaxConnections = 10,\n\t\thostName = \"a\",\n\t},\n}"
+		);
+		assert_eq!(warnings.len(), 1);
+		assert_eq!(
+			warnings[0].to_string(),
+			"`server.max-connections`: left out, its key `maxConnections` is taken by `server.max_connections`"
+		);
+
+		// Flattened keys joined with the separator
+		let flatten = ParseOptions {
+			flatten: true,
+			flatten_separator: String::from("_"),
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options("a_b = 1\n[a]\nb = 2", &flatten),
+			Err(Error::KeyCollision { ref first, ref second, ref key })
+				if first == "a_b" && second == "a.b" && key == "a_b"
+		));
+
+		// Distinct TOML keys stay distinct without a transformation
+		assert_eq!(
+			parse_with_options(toml, &ParseOptions::default()).unwrap(),
+			"{\n\t[\"server\"] = {\n\t\t[\"max_connections\"] = 10,\n\t\t[\"max-connections\"] = 20,\n\t\t[\"host_name\"] = \"a\",\n\t},\n}"
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn type_tags() {
+		use crate::{parse_with_options, Error, ParseOptions, TypeTags};
+
+		let toml = r#"
+		name = "world"
+
+		[player]
+		hp = 10
+
+		[player.inventory]
+		slots = 4
+
+		[[enemies]]
+		kind = "goblin"
+
+		[[enemies]]
+		kind = "orc"
+		stats = { speed = 2 }
+"#;
+
+		let options = ParseOptions {
+			type_tags: Some(TypeTags {
+				field: String::from("__type"),
+				tags: [(String::from("enemies.*"), String::from("Enemy"))].into(),
+			}),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	name = "world",
+	player = {
+		hp = 10,
+		inventory = {
+			slots = 4,
+			__type = "inventory",
+		},
+		__type = "player",
+	},
+	enemies = {
+		{
+			kind = "goblin",
+			__type = "Enemy",
+		},
+		{
+			kind = "orc",
+			stats = {
+				speed = 2,
+				__type = "stats",
+			},
+			__type = "Enemy",
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			type_tags: Some(TypeTags::default()),
+			..Default::default()
+		};
+
+		assert_eq!(
+			parse_with_options("[[list]]\n[[list]]\nx = 1", &options).unwrap(),
+			"{\n\t[\"list\"] = {\n\t\t{\n\t\t\t[\"__type\"] = \"list\",\n\t\t},\n\t\t{\n\t\t\t[\"x\"] = 1,\n\t\t\t[\"__type\"] = \"list\",\n\t\t},\n\t},\n}"
+		);
+		assert!(matches!(
+			parse_with_options("[item]\n__type = 'taken'", &options),
+			Err(Error::ReservedKey { ref path, .. }) if path == "item"
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn float_precision() {
+		use crate::parse;
+
+		let toml = r#"
+		tenth = 0.1
+		sum = 0.30000000000000004
+		large = 1e308
+		max = 1.7976931348623157e308
+		tiny = 5e-324
+		min_normal = 2.2250738585072014e-308
+		exact = 9007199254740993.0
+		millionth = -1e-6
+		whole = 1e15
+"#;
+
+		let lua = r#"{
+	["tenth"] = 0.1,
+	["sum"] = 0.30000000000000004,
+	["large"] = 1e308,
+	["max"] = 1.7976931348623157e308,
+	["tiny"] = 5e-324,
+	["min_normal"] = 2.2250738585072014e-308,
+	["exact"] = 9007199254740992.0,
+	["millionth"] = -1e-6,
+	["whole"] = 1000000000000000.0,
+}"#;
+
+		assert_eq!(parse(toml).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn parse_value() {
+		use crate::{parse_value, Error, ParseOptions};
+
+		let options = ParseOptions::default();
+		let value = |input: &str| parse_value(input, &options).unwrap();
+
+		assert_eq!(value("\"line\\nbreak\""), "\"line\\nbreak\"");
+		assert_eq!(value("-42"), "-42");
+		assert_eq!(value("0x10"), "16");
+		assert_eq!(value("2.5"), "2.5");
+		assert_eq!(value("inf"), "1/0");
+		assert_eq!(value("false"), "false");
+		assert_eq!(value("1979-05-27T07:32:00Z"), "\"1979-05-27T07:32:00Z\"");
+		assert_eq!(value("  [1, 'two']  "), "{\n\t1,\n\t\"two\",\n}");
+		assert_eq!(value("[]"), "{\n}");
+		assert_eq!(
+			value("{ name = 'sword' }"),
+			"{\n\t[\"name\"] = \"sword\",\n}"
+		);
+
+		let options = ParseOptions::pretty();
+
+		assert_eq!(
+			parse_value("{ stats = { damage = 10, tags = ['sharp'] }, name = 'sword' }", &options).unwrap(),
+			"{\n    stats = {\n        damage = 10,\n        tags = {\n            \"sharp\"\n        }\n    },\n    name = \"sword\"\n}"
+		);
+
+		for input in ["", "key = 1", "[table]", "1 2", "'unterminated"] {
+			assert!(
+				matches!(parse_value(input, &options), Err(Error::InvalidValue(_))),
+				"{}",
+				input
+			);
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn dedupe_leaf_tables() {
+		use crate::{parse_with_options, Dedupe, ParseOptions};
+
+		let toml = r#"
+		[weapons.sword]
+		name = "Sword"
+		stats = { damage = 10, speed = 1.5, two_handed = false }
+
+		[weapons.axe]
+		name = "Axe"
+		stats = { damage = 10, speed = 1.5, two_handed = false }
+
+		[weapons.bow]
+		name = "Bow"
+		stats = { damage = 10, speed = 2.0, two_handed = true }
+"#;
+
+		let options = ParseOptions {
+			dedupe: Some(Dedupe::default()),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"local __shared_1 = {
+	damage = 10,
+	speed = 1.5,
+	two_handed = false,
+}
+return {
+	weapons = {
+		sword = {
+			name = "Sword",
+			stats = __shared_1,
+		},
+		axe = {
+			name = "Axe",
+			stats = __shared_1,
+		},
+		bow = {
+			name = "Bow",
+			stats = {
+				damage = 10,
+				speed = 2.0,
+				two_handed = true,
+			},
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn teal_types() {
+		use crate::{generate_teal_types, Error};
+
+		let toml = r#"
+		name = "server"
+		mixed = ["a", 1, 2.5, true]
+		empty = []
+		matrix = [[1, 2], [3]]
+
+		[limits]
+		memory = 512
+		ratio = 0.5
+
+		[limits.network]
+		ports = [80, 443]
+
+		[[items]]
+		id = 1
+		tags = ["rare"]
+		limits = { weight = 2 }
+
+		[[items]]
+		id = 2
+		weight = 0.5
+"#;
+
+		let teal = r#"local record Config
+	name: string
+	mixed: {string | number | boolean}
+	empty: {any}
+	matrix: {{integer}}
+	record Limits
+		memory: integer
+		ratio: number
+		record Network
+			ports: {integer}
+		end
+		network: Network
+	end
+	limits: Limits
+	record Items
+		id: integer
+		tags: {string}
+		record Limits
+			weight: integer
+		end
+		limits: Limits
+		weight: number
+	end
+	items: {Items}
+end
+"#;
+
+		assert_eq!(generate_teal_types(toml, "Config").unwrap(), teal);
+
+		// Record names stay unique within a record, tables of different shapes can't share a union
+		let toml = r#"
+		max_hp = { base = 1 }
+		maxHp = { base = 2 }
+		either = [{ a = 1 }, [1]]
+"#;
+
+		let teal = r#"local record Config
+	record MaxHp
+		base: integer
+	end
+	max_hp: MaxHp
+	record MaxHp2
+		base: integer
+	end
+	maxHp: MaxHp2
+	either: {any}
+end
+"#;
+
+		assert_eq!(generate_teal_types(toml, "Config").unwrap(), teal);
+
+		for (toml, path, key) in [
+			("\"max players\" = 16", "", "max players"),
+			("[server]\nend = 1", "server", "end"),
+			("[[items]]\n[items.\"1st\"]\nid = 1", "items", "1st"),
+		] {
+			match generate_teal_types(toml, "Config") {
+				Err(Error::InvalidIdentifier {
+					path: found,
+					key: found_key,
+				}) => {
+					assert_eq!((found.as_str(), found_key.as_str()), (path, key))
+				}
+				result => panic!("{:?}", result),
+			}
+		}
+
+		assert_eq!(
+			generate_teal_types("\"max players\" = 16", "Config")
+				.unwrap_err()
+				.to_string(),
+			"key `max players` in root table is not a valid Teal identifier"
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn key_order() {
+		use crate::{parse_with_options, KeyOrder, OrderMetadata, ParseOptions};
+
+		let toml = r#"
+		version = 2
+		name = "server"
+
+		[[users]]
+		role = "admin"
+		id = 1
+		name = "root"
+
+		[limits]
+		memory = 512
+		id = "default"
+		cpu = 2
+"#;
+
+		// `name` and `id` go first, other keys keep their order
+		let pinned = ["name", "id"];
+		let rank = move |key: &str| pinned.iter().position(|pinned| *pinned == key);
+
+		let mut options = ParseOptions {
+			key_order: Some(KeyOrder::new(move |a, b| {
+				rank(a)
+					.unwrap_or(pinned.len())
+					.cmp(&rank(b).unwrap_or(pinned.len()))
+			})),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	name = "server",
+	version = 2,
+	users = {
+		{
+			name = "root",
+			id = 1,
+			role = "admin",
+		},
+	},
+	limits = {
+		id = "default",
+		memory = 512,
+		cpu = 2,
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		options.order_metadata = OrderMetadata::Field;
+
+		let lua = parse_with_options("b = 1\nid = 2\na = 3", &options).unwrap();
+
+		assert_eq!(
+			lua,
+			"{\n\tid = 2,\n\tb = 1,\n\ta = 3,\n\t__order = { \"id\", \"b\", \"a\" },\n}"
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn emmylua_types() {
+		use crate::{
+			parse_with_options, EmmyLuaTypes, Error, Header, LuauTypes, MixedArrays, ParseOptions,
+			Wrap,
+		};
+
+		let toml = r#"
+		name = "server"
+		"max players" = 16
+		mixed = ["a", 1, 2.5]
+		empty = []
+
+		[limits]
+		ratio = 0.5
+
+		[limits.network]
+		ports = [80, 443]
+
+		[[items]]
+		id = 1
+		tags = ["rare"]
+
+		[[items]]
+		id = 2
+		weight = 0.5
+"#;
+
+		let mut options = ParseOptions {
+			emmylua_types: Some(EmmyLuaTypes::new("Config")),
+			header: Some(Header::Custom(String::from("Generated"))),
+			wrap: Wrap::Return,
+			bare_keys: true,
+			max_width: Some(80),
+			..Default::default()
+		};
+
+		let lua = r#"-- Generated
+---@class Config
+---@field name string
+---@field ["max players"] integer
+---@field mixed (string|integer|number)[]
+---@field empty any[]
+---@field limits Config.limits
+---@field items Config.items[]
+
+---@class Config.limits
+---@field ratio number
+---@field network Config.limits.network
+
+---@class Config.limits.network
+---@field ports integer[]
+
+---@class Config.items
+---@field id integer
+---@field tags string[]
+---@field weight number
+
+---@type Config
+return {
+	name = "server",
+	["max players"] = 16,
+	mixed = { "a", 1, 2.5 },
+	empty = {},
+	limits = {
+		ratio = 0.5,
+		network = {
+			ports = { 80, 443 },
+		},
+	},
+	items = {
+		{
+			id = 1,
+			tags = { "rare" },
+		},
+		{
+			id = 2,
+			weight = 0.5,
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		// The annotation stays right above the table, below the locals
+		options.emmylua_types = Some(EmmyLuaTypes {
+			name: String::from("Config"),
+			mixed_arrays: MixedArrays::Any,
+		});
+		options.header = None;
+		options.split_locals = true;
+
+		let lua = r#"---@class Config
+---@field mixed any[]
+---@field server Config.server
+
+---@class Config.server
+---@field host string
+
+local server = {
+	host = "localhost",
+}
+---@type Config
+return {
+	mixed = { 1, "a" },
+	server = server,
+}"#;
+
+		assert_eq!(
+			parse_with_options("mixed = [1, 'a']\n[server]\nhost = 'localhost'", &options).unwrap(),
+			lua
+		);
+
+		options.luau_types = Some(LuauTypes::new("Config"));
+
+		assert!(matches!(
+			parse_with_options("a = 1", &options),
+			Err(Error::ConflictingOptions { .. })
+		));
+	}
+
+	#[test]
+	fn up_to_date() {
+		use std::fs;
+
+		use crate::{is_up_to_date, parse_file, Error, Header, ParseOptions};
+
+		let dir = temp_dir("up_to_date");
+		let (toml, lua) = (dir.join("config.toml"), dir.join("config.lua"));
+
+		let options = ParseOptions {
+			header: Some(Header::Generated),
+			..Default::default()
+		};
+
+		fs::write(&toml, "name = 'server'\nport = 80").unwrap();
+
+		// Missing output
+		assert!(!is_up_to_date(&toml, &lua, &options).unwrap());
+
+		let generated = parse_file(&toml, &options).unwrap();
+		fs::write(&lua, &generated).unwrap();
+
+		assert!(is_up_to_date(&toml, &lua, &options).unwrap());
+		assert!(!is_up_to_date(&toml, &lua, &ParseOptions::default()).unwrap());
+
+		// Line endings converted on checkout are not a change
+		fs::write(&lua, generated.replace('\n', "\r\n")).unwrap();
+		assert!(is_up_to_date(&toml, &lua, &options).unwrap());
+
+		// Stale output
+		fs::write(&toml, "name = 'server'\nport = 8080").unwrap();
+		assert!(!is_up_to_date(&toml, &lua, &options).unwrap());
+
+		fs::write(&lua, [0xFF, 0xFE]).unwrap();
+		assert!(!is_up_to_date(&toml, &lua, &options).unwrap());
+
+		fs::remove_file(&toml).unwrap();
+		assert!(matches!(
+			is_up_to_date(&toml, &lua, &options),
+			Err(Error::Io { .. })
+		));
+	}
+
+	#[test]
+	fn root_metatable() {
+		use crate::{parse, parse_with_options, Error, Freeze, ParseOptions, Wrap};
+
+		let toml = "name = 'server'\n[limits]\nmemory = 512";
+
+		let mut options = ParseOptions {
+			root_metatable: Some(String::from("{ __index = Defaults }")),
+			wrap: Wrap::Return,
+			..Default::default()
+		};
+
+		let lua = parse_with_options(toml, &options).unwrap();
+
+		assert_eq!(
+			lua,
+			format!(
+				"return setmetatable({}, {{ __index = Defaults }})",
+				parse(toml).unwrap()
+			)
+		);
+
+		options.root_key = Some(String::from("config"));
+
+		assert_eq!(
+			parse_with_options("a = 1", &options).unwrap(),
+			"return setmetatable({\n\t[\"config\"] = {\n\t\t[\"a\"] = 1,\n\t},\n}, { __index = Defaults })"
+		);
+
+		options.freeze = Freeze::Shallow;
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::ConflictingOptions { .. })
+		));
+
+		// A single value can only be given a metatable if it is a table
+		let mut options = ParseOptions {
+			root_metatable: Some(String::from("{}")),
+			unwrap_single: true,
+			type_comments: true,
+			wrap: Wrap::Return,
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options("x = 1", &options),
+			Err(Error::ConflictingOptions {
+				first: "`unwrap_single`",
+				second: "`root_metatable`"
+			})
+		));
+
+		options.type_comments = false;
+
+		let lua = parse_with_options("x = [1]", &options).unwrap();
+		assert_eq!(lua, "return setmetatable({\n\t1,\n}, {})");
+
+		#[cfg(any(feature = "verify", feature = "validate"))]
+		mlua::Lua::new().load(&lua).exec().unwrap();
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn bracket_keys() {
+		use crate::{
+			parse_assignments, parse_with_options, OrderMetadata, ParseOptions, QuoteStyle,
+		};
+
+		let toml = r#"
+		"a]b" = 1
+		'a"b' = 2
+		"a[b" = 3
+		"]]" = 4
+"#;
+
+		let mut options = ParseOptions {
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	["a]b"] = 1,
+	["a\"b"] = 2,
+	["a[b"] = 3,
+	["]]"] = 4,
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		options.quote_style = QuoteStyle::PreferDouble;
+		options.order_metadata = OrderMetadata::Field;
+
+		let lua = r#"{
+	["a]b"] = 1,
+	['a"b'] = 2,
+	["a[b"] = 3,
+	["]]"] = 4,
+	__order = { "a]b", 'a"b', "a[b", "]]" },
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let lua = r#"config["a]b"] = 1
+config["a\"b"] = 2
+config["a[b"] = 3
+config["]]"] = 4
+"#;
+
+		assert_eq!(parse_assignments(toml, "config").unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn teal_output() {
+		use crate::{parse_with_options, Error, ParseOptions, TealTypes, Wrap};
+
+		let toml = r#"
+		name = "server"
+		port = 80
+		ratio = 0.5
+		enabled = true
+		tags = ["a", "b"]
+
+		[limits]
+		memory = 512
+"#;
+
+		let mut options = ParseOptions {
+			teal_types: Some(TealTypes::new("Config")),
+			wrap: Wrap::Return,
+			bare_keys: true,
+			max_width: Some(80),
+			..Default::default()
+		};
+
+		let teal = r#"local record Config
+	name: string
+	port: integer
+	ratio: number
+	enabled: boolean
+	tags: {string}
+	record Limits
+		memory: integer
+	end
+	limits: Limits
+end
+
+return {
+	name = "server",
+	port = 80,
+	ratio = 0.5,
+	enabled = true,
+	tags = { "a", "b" },
+	limits = {
+		memory = 512,
+	},
+} as Config"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), teal);
+
+		options.unwrap_single = true;
+
+		assert_eq!(
+			parse_with_options("items = [{ id = 1 }]", &options).unwrap(),
+			"local record ConfigItem\n\tid: integer\nend\nlocal type Config = {ConfigItem}\n\nreturn {\n\t{\n\t\tid = 1,\n\t},\n} as Config"
+		);
+
+		assert!(matches!(
+			parse_with_options("\"max players\" = 16\nother = 1", &options),
+			Err(Error::InvalidIdentifier { .. })
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn group_order() {
+		use crate::{parse_with_options, GroupOrder, KeyOrder, ParseOptions};
+
+		let toml = r#"
+		name = "server"
+		limits = { memory = 512, ports = [80], cpu = 2 }
+		tags = ["a"]
+		port = 80
+		users = [{ name = "root" }]
+		enabled = true
+"#;
+
+		let mut options = ParseOptions {
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	name = "server",
+	limits = {
+		memory = 512,
+		ports = {
+			80,
+		},
+		cpu = 2,
+	},
+	tags = {
+		"a",
+	},
+	port = 80,
+	users = {
+		{
+			name = "root",
+		},
+	},
+	enabled = true,
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		options.group_order = GroupOrder::ScalarsFirst;
+
+		let lua = r#"{
+	name = "server",
+	port = 80,
+	enabled = true,
+	tags = {
+		"a",
+	},
+	users = {
+		{
+			name = "root",
+		},
+	},
+	limits = {
+		memory = 512,
+		cpu = 2,
+		ports = {
+			80,
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		#[allow(deprecated)]
+		let scalars_first = ParseOptions {
+			group_order: GroupOrder::None,
+			group_scalars_first: true,
+			..options.clone()
+		};
+
+		assert_eq!(parse_with_options(toml, &scalars_first).unwrap(), lua);
+
+		let loaded =
+			ParseOptions::from_toml_str("bare_keys = true\ngroup_scalars_first = true").unwrap();
+		assert_eq!(parse_with_options(toml, &loaded).unwrap(), lua);
+
+		// Sorted within each group
+		options.key_order = Some(KeyOrder::new(|a, b| a.cmp(b)));
+
+		let lua = r#"{
+	enabled = true,
+	name = "server",
+	port = 80,
+	tags = {
+		"a",
+	},
+	users = {
+		{
+			name = "root",
+		},
+	},
+	limits = {
+		cpu = 2,
+		memory = 512,
+		ports = {
+			80,
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		options.group_order = GroupOrder::TablesFirst;
+
+		let lua = r#"{
+	limits = {
+		ports = {
+			80,
+		},
+		cpu = 2,
+		memory = 512,
+	},
+	tags = {
+		"a",
+	},
+	users = {
+		{
+			name = "root",
+		},
+	},
+	enabled = true,
+	name = "server",
+	port = 80,
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn doc_comments() {
+		use std::collections::BTreeMap;
+
+		use crate::{parse_with_warnings, ParseOptions};
+
+		let toml = r#"
+		name = "server"
+
+		[limits]
+		memory = 512
+
+		[[users]]
+		name = "root"
+
+		[[users]]
+		name = "guest"
+"#;
+
+		let options = ParseOptions {
+			doc_comments: BTreeMap::from([
+				(String::from("name"), String::from("Shown in the title bar")),
+				(
+					String::from("limits"),
+					String::from("Resource limits\n\nApplied on restart"),
+				),
+				(
+					String::from("limits.memory"),
+					String::from("In megabytes\rmemory = 0"),
+				),
+				(
+					String::from("users.2"),
+					String::from("Account without a password"),
+				),
+				(String::from("users.2.name"), String::from("Login")),
+				(
+					String::from("limits.cpu"),
+					String::from("Not in the document"),
+				),
+			]),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	-- Shown in the title bar
+	name = "server",
+	-- Resource limits
+	--
+	-- Applied on restart
+	limits = {
+		-- In megabytes
+		-- memory = 0
+		memory = 512,
+	},
+	users = {
+		{
+			name = "root",
+		},
+		-- Account without a password
+		{
+			-- Login
+			name = "guest",
+		},
+	},
+}"#;
+
+		let (output, warnings) = parse_with_warnings(toml, &options).unwrap();
+
+		assert_eq!(output, lua);
+		assert_eq!(warnings.len(), 1);
+		assert_eq!(
+			warnings[0].to_string(),
+			"`limits.cpu`: doc comment matches no entry"
+		);
+
+		#[cfg(any(feature = "verify", feature = "validate"))]
+		mlua::Lua::new()
+			.load(format!("return {}", output).as_str())
+			.exec()
+			.unwrap();
+	}
+
+	#[test]
+	fn flatten_array_of_tables_indent() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = r#"
+		[[items]]
+		name = "sword"
+		stats = [{ damage = 10 }]
+
+		[[items]]
+		name = "bow"
+		tags = ["ranged"]
+"#;
+
+		let mut options = ParseOptions {
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	items = {
+		{
+			name = "sword",
+			stats = {
+				{
+					damage = 10,
+				},
+			},
+		},
+		{
+			name = "bow",
+			tags = {
+				"ranged",
+			},
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		options.flatten_array_of_tables_indent = true;
+
+		let lua = r#"{
+	items = {{
+		name = "sword",
+		stats = {{
+			damage = 10,
+		}},
+	}, {
+		name = "bow",
+		tags = {
+			"ranged",
+		},
+	}},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn empty_string() {
+		use crate::{parse_with_options, EmptyStringMode, Error, ParseOptions};
+
+		let toml = r#"
+		name = ""
+		title = "x"
+		nested = { empty = "" }
+		items = ["a", "", "c"]
+"#;
+
+		let options = ParseOptions {
+			bare_keys: true,
+			sparse_arrays: true,
+			max_width: Some(80),
+			..Default::default()
+		};
+
+		assert_eq!(
+			parse_with_options(toml, &options).unwrap(),
+			"{\n\tname = \"\",\n\ttitle = \"x\",\n\tnested = {\n\t\tempty = \"\",\n\t},\n\titems = { \"a\", \"\", \"c\" },\n}"
+		);
+
+		let options = ParseOptions {
+			empty_string: EmptyStringMode::Nil,
+			..options
+		};
+
+		assert_eq!(
+			parse_with_options(toml, &options).unwrap(),
+			"{\n\tname = nil,\n\ttitle = \"x\",\n\tnested = {\n\t\tempty = nil,\n\t},\n\titems = {\n\t\t\"a\",\n\t\t[3] = \"c\",\n\t},\n}"
+		);
+
+		let options = ParseOptions {
+			empty_string: EmptyStringMode::Omit,
+			..options
+		};
+
+		assert_eq!(
+			parse_with_options(toml, &options).unwrap(),
+			"{\n\ttitle = \"x\",\n\tnested = {\n\t},\n\titems = {\n\t\t\"a\",\n\t\t[3] = \"c\",\n\t},\n}"
+		);
+
+		let options = ParseOptions {
+			sparse_arrays: false,
+			..options
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::NilInArray { path }) if path == "items"
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "comments")]
+	fn keep_inline_tables() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let inline = r#"
+		name = "shape"
+		point = { x = 1, y = 2 }
+		items = [{ id = 1 }]
+
+		[style]
+		color = "red"
+		border = { width = 2, dash = [1, 2], corner = { radius = 4 } }
+"#;
+
+		let sections = r#"
+		name = "shape"
+
+		[point]
+		x = 1
+		y = 2
+
+		[[items]]
+		id = 1
+
+		[style]
+		color = "red"
+
+		[style.border]
+		width = 2
+		dash = [1, 2]
+
+		[style.border.corner]
+		radius = 4
+"#;
+
+		let options = ParseOptions {
+			bare_keys: true,
+			keep_inline_tables: true,
+			..Default::default()
+		};
+
+		let lua = r#"{
+	name = "shape",
+	point = { x = 1, y = 2 },
+	items = {
+		{ id = 1 },
+	},
+	style = {
+		color = "red",
+		border = { width = 2, dash = { 1, 2 }, corner = { radius = 4 } },
+	},
+}"#;
+
+		assert_eq!(parse_with_options(inline, &options).unwrap(), lua);
+
+		let spread = parse_with_options(sections, &options).unwrap();
+
+		assert!(spread.contains("\tpoint = {\n\t\tx = 1,\n\t\ty = 2,\n\t},"));
+		assert!(spread.contains("\t\tborder = {\n\t\t\twidth = 2,"));
+
+		// Too wide for a single line
+		let options = ParseOptions {
+			max_width: Some(40),
+			..options
+		};
+
+		let lua = parse_with_options(inline, &options).unwrap();
+
+		assert!(lua.contains("\tpoint = { x = 1, y = 2 },"));
+		assert!(lua.contains("\t\tborder = {\n\t\t\twidth = 2,\n\t\t\tdash = { 1, 2 },\n\t\t\tcorner = { radius = 4 },\n\t\t},"));
+
+		let options = ParseOptions {
+			keep_inline_tables: false,
+			..options
+		};
+
+		assert_eq!(
+			parse_with_options(inline, &options).unwrap(),
+			parse_with_options(sections, &options).unwrap()
+		);
+
+		// Dotted keys aren't confused with nested tables
+		let options = ParseOptions {
+			keep_inline_tables: true,
+			..options
+		};
+
+		assert_eq!(
+			parse_with_options("\"a.b\" = { x = 1 }\n\n[a.b]\ny = 2", &options).unwrap(),
+			"{\n\t[\"a.b\"] = { x = 1 },\n\ta = {\n\t\tb = {\n\t\t\ty = 2,\n\t\t},\n\t},\n}"
+		);
+	}
+
+	#[test]
+	fn render_value() {
+		use crate::{render_value, ParseOptions};
+		use toml::Value;
+
+		let value: Value = "{ a = 1, b = [true] }".parse().unwrap();
+		let options = ParseOptions::default();
+
+		assert_eq!(
+			render_value(&value, &options, 1).unwrap(),
+			"{\n\t\t[\"a\"] = 1,\n\t\t[\"b\"] = {\n\t\t\ttrue,\n\t\t},\n\t}"
+		);
+
+		// Nested in a table written by the caller
+		let lua = format!(
+			"{{\n\t[\"nested\"] = {},\n}}",
+			render_value(&value, &options, 1).unwrap()
+		);
+
+		assert_eq!(
+			lua,
+			crate::parse_with_options("nested = { a = 1, b = [true] }", &options).unwrap()
+		);
+	}
+
+	#[test]
+	fn empty_keys() {
+		use crate::{parse_map, parse_with_options, KeyCase, Map, ParseOptions};
+		use toml::{Table, Value};
+
+		let mut nested = Table::new();
+		nested.insert(String::new(), Value::from(2));
+
+		let mut map = Map::default();
+		map.insert(String::new(), Value::from(1));
+		map.insert(String::from("nested"), Value::Table(nested));
+
+		let options = ParseOptions {
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = "{\n\t[\"\"] = 1,\n\tnested = {\n\t\t[\"\"] = 2,\n\t},\n}";
+
+		assert_eq!(parse_map(&map, &options).unwrap(), lua);
+		assert_eq!(
+			parse_with_options("\"\" = 1\nnested = { \"\" = 2 }", &options).unwrap(),
+			lua
+		);
+
+		let options = ParseOptions {
+			key_case: KeyCase::Camel,
+			..options
+		};
+
+		assert_eq!(parse_map(&map, &options).unwrap(), lua);
+
+		let options = ParseOptions {
+			split_locals: true,
+			..Default::default()
+		};
+
+		map.insert(String::new(), map["nested"].clone());
+
+		assert_eq!(
+			parse_map(&map, &options).unwrap(),
+			"local _ = {\n\t[\"\"] = 2,\n}\nlocal nested = {\n\t[\"\"] = 2,\n}\nreturn {\n\t[\"\"] = _,\n\t[\"nested\"] = nested,\n}"
+		);
+	}
+
+	#[test]
+	fn error_paths() {
+		use crate::{parse, parse_bytes, Error};
+
+		let error = parse("name = \"abc\"\nport = 80x").unwrap_err();
+
+		assert!(matches!(&error, Error::Toml { path: Some(path), .. } if path == "port"));
+		assert!(error
+			.to_string()
+			.starts_with("`port`: TOML parse error at line 2"));
+
+		let nested = r#"
+		[server]
+		host = "a"
+
+		[server.limits]
+		max = 10
+		max = 20
+"#;
+
+		assert_eq!(parse(nested).unwrap_err().path(), Some("server.limits.max"));
+
+		let array = r#"
+		[[servers]]
+		name = "a"
+
+		[[servers]]
+		name = "b"
+
+		[servers.limits]
+		max = [1, 2
+"#;
+
+		assert_eq!(
+			parse_bytes(array.as_bytes()).unwrap_err().path(),
+			Some("servers.2.limits.max")
+		);
+
+		// Nothing to point at before the first key
+		assert_eq!(parse("= 1").unwrap_err().path(), None);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn json_bridge() {
+		use crate::{parse_with_options, Error, OrderMetadata, ParseOptions};
+
+		let toml = r#"
+		tags = []
+		nested = [[], [1]]
+		inline = { list = [] }
+		[settings]
+		[limits]
+		max = 1
+"#;
+
+		let options = ParseOptions {
+			json_bridge: true,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let array = r#"setmetatable({}, { __jsontype = "array" })"#;
+		let object = r#"setmetatable({}, { __jsontype = "object" })"#;
+
+		let lua = format!(
+			"{{\n\ttags = {array},\n\tnested = {{\n\t\t{array},\n\t\t{{\n\t\t\t1,\n\t\t}},\n\t}},\n\tinline = {{\n\t\tlist = {array},\n\t}},\n\tsettings = {object},\n\tlimits = {{\n\t\tmax = 1,\n\t}},\n}}"
+		);
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		// Same on a single line
+		let options = ParseOptions {
+			max_width: Some(100),
+			..options
+		};
+
+		assert!(parse_with_options(toml, &options)
+			.unwrap()
+			.contains(&format!("\tnested = {{ {array}, {{ 1 }} }},")));
+
+		assert_eq!(
+			parse_with_options("", &options).unwrap(),
+			format!("{object}")
+		);
+
+		let options = ParseOptions {
+			order_metadata: OrderMetadata::Metatable,
+			..options
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::ConflictingOptions { .. })
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn normalize_datetimes_utc() {
+		use crate::{parse_with_options, DatetimeMode, Error, LocalDatetimes, ParseOptions};
+
+		let toml = r#"
+		west = 1979-05-27T00:32:00.999-07:00
+		east = 1979-05-27T09:02:00.999+01:30
+		date = 1979-05-27
+"#;
+
+		let options = ParseOptions {
+			normalize_datetimes_utc: true,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		assert_eq!(
+			parse_with_options(toml, &options).unwrap(),
+			"{\n\twest = \"1979-05-27T07:32:00.999Z\",\n\teast = \"1979-05-27T07:32:00.999Z\",\n\tdate = \"1979-05-27\",\n}"
+		);
+
+		let options = ParseOptions {
+			datetime_mode: DatetimeMode::Table,
+			..options
+		};
+
+		let lua = parse_with_options("west = 1979-05-27T00:32:00-07:00", &options).unwrap();
+
+		assert!(lua.contains("hour = 7, min = 32, sec = 0, nsec = 0, utc_offset = 0"));
+
+		// The instant doesn't change
+		let epoch = |normalize_datetimes_utc| {
+			let options = ParseOptions {
+				datetime_mode: DatetimeMode::Epoch,
+				normalize_datetimes_utc,
+				..Default::default()
+			};
+
+			parse_with_options("west = 1979-05-27T00:32:00-07:00", &options).unwrap()
+		};
+
+		assert_eq!(epoch(true), epoch(false));
+
+		let options = ParseOptions {
+			datetime_mode: DatetimeMode::String,
+			datetime_wrapper: Some(String::from("DateTime")),
+			..options
+		};
+
+		let toml = "local = 1979-05-27T07:32:00\ntime = 07:32:00";
+
+		assert_eq!(
+			parse_with_options(toml, &options).unwrap(),
+			"{\n\t[\"local\"] = DateTime(\"1979-05-27T07:32:00\"),\n\ttime = DateTime(\"07:32:00\"),\n}"
+		);
+
+		let options = ParseOptions {
+			local_datetimes: LocalDatetimes::Error,
+			..options
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::NotNormalizable { path, .. }) if path == "local"
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn splitter() {
+		use crate::{Error, ParseOptions, Splitter};
+		use std::fs;
+
+		let toml = r#"
+[swords]
+damage = 10
+
+[[bows]]
+range = 30
+
+[[bows]]
+range = 40
+"#;
+
+		let files = Splitter::new(ParseOptions::default())
+			.split_array("bows")
+			.split(toml)
+			.unwrap();
+
+		let paths: Vec<&str> = files
+			.iter()
+			.map(|file| file.path.to_str().unwrap())
+			.collect();
+		assert_eq!(paths, ["init.lua", "bows/1.lua", "bows/2.lua"]);
+		assert_eq!(
+			files[0].lua,
+			"return {\n\t[\"swords\"] = {\n\t\t[\"damage\"] = 10,\n\t},\n\t[\"bows\"] = {\n\t\trequire(\"bows.1\"),\n\t\trequire(\"bows.2\"),\n\t},\n}"
+		);
+		assert_eq!(files[2].lua, "return {\n\t[\"range\"] = 40,\n}");
+
+		let dir = temp_dir("splitter");
+
+		Splitter::new(ParseOptions::default())
+			.require_prefix("data")
+			.write_dir(toml, &dir)
+			.unwrap();
+
+		assert_eq!(
+			fs::read_to_string(dir.join("swords.lua")).unwrap(),
+			"return {\n\t[\"damage\"] = 10,\n}"
+		);
+		assert!(fs::read_to_string(dir.join("init.lua"))
+			.unwrap()
+			.contains("[\"swords\"] = require(\"data.swords\")"));
+
+		let err = Splitter::new(ParseOptions::default())
+			.split("[\"a b\"]\n[a_b]")
+			.unwrap_err();
+		assert!(matches!(err, Error::ModuleCollision { module, .. } if module == "a_b"));
+
+		let err = Splitter::new(ParseOptions::default())
+			.split("[Init]")
+			.unwrap_err();
+		assert!(matches!(err, Error::ReservedKey { .. }));
+
+		let err = Splitter::new(ParseOptions::default())
+			.split_array("swords")
+			.split(toml)
+			.unwrap_err();
+		assert!(matches!(err, Error::NotArrayOfTables { key } if key == "swords"));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn integer_radix_option() {
+		use crate::{parse_with_options, LuaTarget, ParseOptions, Radix};
+
+		let toml = "mask = 255\noffset = -255\nmin = -9223372036854775808";
+
+		let convert = |integer_radix, target| {
+			let options = ParseOptions {
+				integer_radix,
+				target,
+				bare_keys: true,
+				..Default::default()
+			};
+
+			parse_with_options(toml, &options).unwrap()
+		};
+
+		assert_eq!(
+			convert(Radix::Decimal, None),
+			"{\n\tmask = 255,\n\toffset = -255,\n\tmin = (-9223372036854775807 - 1),\n}"
+		);
+		assert_eq!(
+			convert(Radix::Hex, None),
+			"{\n\tmask = 0xFF,\n\toffset = -0xFF,\n\tmin = (-9223372036854775807 - 1),\n}"
+		);
+		assert_eq!(
+			convert(Radix::Binary, None),
+			"{\n\tmask = 0xFF, -- 0b11111111\n\toffset = -0xFF, -- -0b11111111\n\tmin = (-9223372036854775807 - 1),\n}"
+		);
+		assert_eq!(
+			convert(Radix::Binary, Some(LuaTarget::Luau)),
+			"{\n\tmask = 0b11111111,\n\toffset = -0b11111111,\n\tmin = (-9223372036854775807 - 1),\n}"
+		);
+
+		let options = ParseOptions::from_toml_str("integer_radix = \"hex\"").unwrap();
+		assert_eq!(options.integer_radix, Radix::Hex);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn redact() {
+		use crate::{parse_with_warnings, Error, ParseOptions, Redact, RedactNonStrings};
+
+		let toml = r#"
+		password = "top"
+
+		[auth.github]
+		token = "ghp_abc"
+		user = "bot"
+
+		[auth.gitlab]
+		token = "glpat_abc"
+		expires = 1979-05-27
+
+		[[db]]
+		password = "first"
+		port = 5432
+
+		[[db]]
+		password = "second"
+
+		[db.replica]
+		password = "third"
+
+		[keys]
+		signing = { id = 7, secret = "abc" }
+"#;
+
+		let options = |paths: &[&str], non_strings| ParseOptions {
+			redact: Some(Redact {
+				paths: paths.iter().map(|path| String::from(*path)).collect(),
+				placeholder: String::from("***"),
+				non_strings,
+			}),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let redacted = |paths: &[&str]| {
+			let (lua, warnings) =
+				parse_with_warnings(toml, &options(paths, RedactNonStrings::Neutral)).unwrap();
+
+			let paths: Vec<String> = warnings
+				.iter()
+				.map(|warning| format!("{} {}", warning.path, warning.message))
+				.collect();
+
+			(lua, paths)
+		};
+
+		// Nested matches, `*` stays within one key
+		let (lua, paths) = redacted(&["auth.*.token"]);
+		assert!(lua.contains("token = \"***\","));
+		assert!(lua.contains("user = \"bot\","));
+		assert_eq!(
+			paths,
+			["auth.github.token redacted", "auth.gitlab.token redacted"]
+		);
+
+		// Array of tables items are numbered, `**` crosses any number of keys
+		let (lua, paths) = redacted(&["db.*.password", "**.replica"]);
+		assert!(lua.contains("port = 5432,"));
+		assert_eq!(
+			paths,
+			[
+				"db.1.password redacted",
+				"db.2.password redacted",
+				"db.2.replica.password redacted"
+			]
+		);
+
+		let (_, paths) = redacted(&["**.password"]);
+		assert_eq!(paths.len(), 4);
+		assert_eq!(paths[0], "password redacted");
+
+		// Values nested in a matched table get a neutral value of their type
+		let (lua, paths) = redacted(&["keys.signing", "auth.gitlab"]);
+		assert!(lua.contains("id = 0,") && lua.contains("secret = \"***\","));
+		assert!(lua.contains("expires = \"1970-01-01\","));
+		assert_eq!(paths.len(), 4);
+
+		// Matching is case sensitive and patterns matching nothing are reported
+		let (lua, paths) = redacted(&["PASSWORD", "auth.*"]);
+		assert!(lua.contains("password = \"top\","));
+		assert_eq!(paths.len(), 5);
+		assert_eq!(paths[4], "PASSWORD redact pattern matches no value");
+
+		let err = parse_with_warnings(toml, &options(&["db.1.port"], RedactNonStrings::Error))
+			.unwrap_err();
+		assert!(matches!(
+			err,
+			Error::NotRedactable { path, found: "integer" } if path == "db.1.port"
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn parse_split() {
+		use crate::{parse_split, ParseOptions};
+
+		let toml = r#"
+		name = "game"
+
+		[server]
+		port = 80
+
+		[client.window]
+		width = 800
+"#;
+
+		let files = parse_split(toml, &ParseOptions::default()).unwrap();
+
+		let mut names: Vec<&str> = files.keys().map(String::as_str).collect();
+		names.sort();
+		assert_eq!(names, ["client.lua", "init.lua", "server.lua"]);
+
+		assert_eq!(
+			files["init.lua"],
+			"return {\n\t[\"name\"] = \"game\",\n\t[\"server\"] = require(\"server\"),\n\t[\"client\"] = require(\"client\"),\n}"
+		);
+		assert_eq!(
+			files["client.lua"],
+			"return {\n\t[\"window\"] = {\n\t\t[\"width\"] = 800,\n\t},\n}"
+		);
+	}
+
+	#[test]
+	fn pack_numbers() {
+		use crate::{
+			parse_with_options, Error, IntegerOverflow, LuaTarget, NumberMode, PackNumbers,
+			ParseOptions, ValueTransform,
+		};
+
+		let toml = r#"
+		samples = [0.5, 1.5, 2.5]
+		ids = [1, 2, 3]
+		mixed = [1, 2.5, 3]
+		short = [0.5]
+"#;
+
+		let options = ParseOptions {
+			pack_numbers: Some(PackNumbers {
+				min_len: 2,
+				..Default::default()
+			}),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let lua = parse_with_options(toml, &options).unwrap();
+
+		assert!(lua.contains("samples = (function(s)"));
+		assert!(lua.contains("ids = (function(s)"));
+		assert!(lua.contains("string.unpack(\"<i8\", s, i)"));
+		assert!(lua.contains("mixed = {\n\t\t1,\n\t\t2.5,\n\t\t3,\n\t},"));
+		assert!(lua.contains("short = {\n\t\t0.5,\n\t},"));
+
+		let luau = parse_with_options(
+			toml,
+			&ParseOptions {
+				target: Some(LuaTarget::Luau),
+				..options.clone()
+			},
+		)
+		.unwrap();
+		assert!(luau.contains("buffer.readf64(b, i)"));
+
+		let err = parse_with_options(
+			toml,
+			&ParseOptions {
+				target: Some(LuaTarget::Lua51),
+				..options.clone()
+			},
+		)
+		.unwrap_err();
+		assert!(matches!(
+			err,
+			Error::Unsupported {
+				target: LuaTarget::Lua51,
+				feature: "`string.unpack`"
+			}
+		));
+
+		// Options converting or checking numbers apply to every item
+		for options in [
+			ParseOptions {
+				number_mode: NumberMode::AllFloat,
+				..options.clone()
+			},
+			ParseOptions {
+				integer_overflow: IntegerOverflow::Error,
+				..options.clone()
+			},
+			ParseOptions {
+				value_transform: Some(ValueTransform::new(|_, _| None)),
+				..options.clone()
+			},
+		] {
+			let lua = parse_with_options(toml, &options).unwrap();
+			assert!(!lua.contains("(function(s)"), "{}", lua);
+		}
+
+		assert!(matches!(
+			parse_with_options(
+				"ids = [1, 9007199254740993]",
+				&ParseOptions {
+					integer_overflow: IntegerOverflow::Error,
+					..options.clone()
+				}
+			),
+			Err(Error::IntegerOverflow { .. })
+		));
+
+		// Locals don't shadow the globals the decoders call
+		let options = ParseOptions {
+			split_locals: true,
+			..options
+		};
+
+		let lua =
+			parse_with_options("[string]\nids = [1, 2]\n[tonumber]\nx = 1", &options).unwrap();
+
+		assert!(lua.contains("local string_2 = {"), "{}", lua);
+		assert!(lua.contains("local tonumber_2 = {"), "{}", lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn warning_kinds() {
+		use crate::{
+			parse_with_options, parse_with_warnings, DatetimeFallback, DatetimeMode, Error,
+			KeyCase, KeyCollisions, ParseOptions, WarningKind,
+		};
+
+		let toml = r#"
+		created = 1979-05-27T07:32:00Z
+		updated = 1979-05-27T07:32:00
+		birthday = 1979-05-27
+		max_size = 1
+		maxSize = 2
+"#;
+
+		let options = ParseOptions {
+			datetime_mode: DatetimeMode::Epoch,
+			datetime_fallback: DatetimeFallback::String,
+			key_case: KeyCase::Camel,
+			key_collisions: KeyCollisions::KeepFirst,
+			..Default::default()
+		};
+
+		let (lua, warnings) = parse_with_warnings(toml, &options).unwrap();
+
+		assert!(lua.contains("[\"created\"] = 296638320,"));
+		assert!(lua.contains("[\"updated\"] = \"1979-05-27T07:32:00\","));
+
+		let kinds: Vec<(WarningKind, &str)> = warnings
+			.iter()
+			.map(|warning| (warning.kind, warning.path.as_str()))
+			.collect();
+
+		assert_eq!(
+			kinds,
+			[
+				(WarningKind::KeyLeftOut, "maxSize"),
+				(WarningKind::DatetimeAsString, "updated"),
+				(WarningKind::DatetimeAsString, "birthday"),
+			]
+		);
+		assert_eq!(
+			warnings[2].to_string(),
+			"`birthday`: local date written as a string"
+		);
+
+		let options = ParseOptions {
+			deny_warnings: true,
+			..options
+		};
+
+		let err = parse_with_options(toml, &options).unwrap_err();
+
+		assert!(matches!(
+			&err,
+			Error::DeniedWarning(warning) if warning.kind == WarningKind::KeyLeftOut
+		));
+		assert_eq!(err.path(), Some("maxSize"));
+		assert!(parse_with_options("created = 1979-05-27T07:32:00Z", &options).is_ok());
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn type_comments() {
+		use crate::{parse_with_options, ParseOptions, Radix};
+
+		let toml = r#"
+		name = "server"
+		port = 8080
+		ratio = 0.5
+		enabled = true
+		started = 1979-05-27T07:32:00Z
+		ports = [80, 443]
+"#;
+
+		let options = ParseOptions {
+			type_comments: true,
+			bare_keys: true,
+			max_width: Some(80),
+			..Default::default()
+		};
+
+		let lua = r#"{
+	name = "server", -- string
+	port = 8080, -- integer
+	ratio = 0.5, -- float
+	enabled = true, -- boolean
+	started = "1979-05-27T07:32:00Z", -- datetime
+	ports = {
+		80, -- integer
+		443, -- integer
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		// Other comments of the literal follow the type
+		let options = ParseOptions {
+			integer_radix: Radix::Binary,
+			..options
+		};
+
+		assert_eq!(
+			parse_with_options("mask = 5", &options).unwrap(),
+			"{\n\tmask = 0x5, -- integer, 0b101\n}"
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn crlf_newlines() {
+		use crate::{parse_with_options, Header, Newline, ParseChunks, ParseOptions};
+
+		let toml = r#"
+name = "abc"
+text = """
+first
+second"""
+
+[server]
+ports = [80, 443]
+"#;
+
+		let options = ParseOptions {
+			newline: Newline::CrLf,
+			long_strings: true,
+			header: Some(Header::Custom(String::from(
+				"Generated\r\nfrom config.toml",
+			))),
+			final_newline: true,
+			..Default::default()
+		};
+
+		let lua = parse_with_options(toml, &options).unwrap();
+
+		assert_eq!(
+			lua,
+			"-- Generated\r\n-- from config.toml\r\n{\r\n\t[\"name\"] = \"abc\",\r\n\t[\"text\"] = [[\r\nfirst\r\nsecond]],\r\n\t[\"server\"] = {\r\n\t\t[\"ports\"] = {\r\n\t\t\t80,\r\n\t\t\t443,\r\n\t\t},\r\n\t},\r\n}\r\n"
+		);
+		assert!(!lua.replace("\r\n", "").contains(['\r', '\n']));
+
+		let chunks: Vec<String> = ParseChunks::new(toml, &options)
+			.collect::<Result<_, _>>()
+			.unwrap();
+		assert_eq!(chunks.concat(), lua);
+		// Both bytes of every line break count toward the limit
+		for (limit, fits) in [(lua.len(), true), (lua.len() - 1, false)] {
+			let options = ParseOptions {
+				max_output_bytes: Some(limit),
+				..options.clone()
+			};
+
+			assert_eq!(parse_with_options(toml, &options).is_ok(), fits);
+			assert_eq!(
+				ParseChunks::new(toml, &options).all(|chunk| chunk.is_ok()),
+				fits
+			);
+		}
+	}
+
+	#[cfg(feature = "luau")]
+	#[test]
+	fn luau_bytecode() {
+		use crate::{parse_to_luau_bytecode, Error, LuaTarget, LuauBytecode, ParseOptions, Wrap};
+
+		let toml = r#"
+name = "server"
+ratio = 0.25
+enabled = true
+
+[limits]
+ports = [80, 443]
+"#;
+
+		let lua = mlua::Lua::new();
+
+		for (options, wrap) in [
+			(LuauBytecode::default(), Wrap::None),
+			(
+				LuauBytecode {
+					optimization_level: 2,
+					debug_level: 0,
+				},
+				Wrap::Return,
+			),
+		] {
+			let options = ParseOptions {
+				luau_bytecode: options,
+				wrap,
+				..Default::default()
+			};
+
+			let bytecode = parse_to_luau_bytecode(toml, &options).unwrap();
+			let table: mlua::Table = lua.load(bytecode).eval().unwrap();
+			let limits: mlua::Table = table.get("limits").unwrap();
+
+			assert_eq!(table.get::<String>("name").unwrap(), "server");
+			assert_eq!(table.get::<f64>("ratio").unwrap(), 0.25);
+			assert!(table.get::<bool>("enabled").unwrap());
+			assert_eq!(limits.get::<Vec<u16>>("ports").unwrap(), [80, 443]);
+		}
+
+		let options = ParseOptions {
+			target: Some(LuaTarget::Lua54),
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_to_luau_bytecode(toml, &options),
+			Err(Error::Unsupported {
+				target: LuaTarget::Lua54,
+				..
+			})
+		));
+
+		let options = ParseOptions {
+			root_metatable: Some(String::from("{ __index =")),
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_to_luau_bytecode(toml, &options),
+			Err(Error::InvalidLua(_))
+		));
+	}
+
+	#[test]
+	fn explicit_array_indices() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = r#"
+ports = [80, 443]
+users = [{ name = "a" }, { name = "b" }]
+"#;
+
+		let implicit = r#"{
+	["ports"] = { 80, 443 },
+	["users"] = {
+		{
+			["name"] = "a",
+		},
+		{
+			["name"] = "b",
+		},
+	},
+}"#;
+
+		let explicit = r#"{
+	["ports"] = {
+		[1] = 80,
+		[2] = 443,
+	},
+	["users"] = {
+		[1] = {
+			["name"] = "a",
+		},
+		[2] = {
+			["name"] = "b",
+		},
+	},
+}"#;
+
+		let options = ParseOptions {
+			max_width: Some(80),
+			..Default::default()
+		};
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), implicit);
+
+		let options = ParseOptions {
+			explicit_array_indices: true,
+			..options
+		};
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), explicit);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn key_by_nested_path() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let options = ParseOptions {
+			key_by: [
+				(String::from("plugins"), String::from("name")),
+				(String::from("server.plugins"), String::from("name")),
+			]
+			.into(),
+			..Default::default()
+		};
+
+		let toml = r#"
+		[[plugins]]
+		name = "x"
+		enabled = true
+
+		[server]
+		plugins = [{ name = "y", port = 80 }, { name = "z", port = 81 }]
+"#;
+
+		let lua = r#"{
+	["plugins"] = {
+		["x"] = {
+			["name"] = "x",
+			["enabled"] = true,
+		},
+	},
+	["server"] = {
+		["plugins"] = {
+			["y"] = {
+				["name"] = "y",
+				["port"] = 80,
+			},
+			["z"] = {
+				["name"] = "z",
+				["port"] = 81,
+			},
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	fn sort_arrays_by() {
+		use crate::{
+			parse_with_options, Error, MissingSortField, NumberMode, ParseOptions, SortBy,
+			SortCompare,
+		};
+
+		let toml = r#"
+		[[plugins]]
+		name = "b"
+		priority = 10
+
+		[[plugins]]
+		priority = 2
+
+		[[plugins]]
+		name = "a"
+		priority = 2.5
+
+		[[plugins]]
+		name = "b"
+		priority = -1
+"#;
+
+		let order = |sort: SortBy| -> Result<Vec<i64>, Error> {
+			let options = ParseOptions {
+				sort_arrays_by: [(String::from("plugins"), sort)].into(),
+				bare_keys: true,
+				number_mode: NumberMode::AllInteger,
+				..Default::default()
+			};
+
+			let lua = parse_with_options(toml, &options)?;
+
+			Ok(lua
+				.lines()
+				.filter_map(|line| line.trim().strip_prefix("priority = "))
+				.map(|priority| priority.trim_end_matches(',').parse().unwrap())
+				.collect())
+		};
+
+		// Equal names keep their document order, the entry without one comes last
+		assert_eq!(order(SortBy::new("name")).unwrap(), [2, 10, -1, 2]);
+		assert_eq!(
+			order(SortBy {
+				descending: true,
+				..SortBy::new("name")
+			})
+			.unwrap(),
+			[10, -1, 2, 2]
+		);
+
+		let numeric = SortBy {
+			compare: SortCompare::Numeric,
+			..SortBy::new("priority")
+		};
+
+		assert_eq!(order(numeric.clone()).unwrap(), [-1, 2, 2, 10]);
+		assert_eq!(
+			order(SortBy {
+				descending: true,
+				..numeric
+			})
+			.unwrap(),
+			[10, 2, 2, -1]
+		);
+
+		assert!(matches!(
+			order(SortBy {
+				missing: MissingSortField::Error,
+				..SortBy::new("name")
+			}),
+			Err(Error::MissingSortField { index: 2, ref path, ref field }) if path == "plugins" && field == "name"
+		));
+		assert!(matches!(
+			order(SortBy::new("priority")),
+			Err(Error::InvalidSortField {
+				index: 1,
+				found: "integer",
+				expected: "a string",
+				..
+			})
+		));
+	}
+
+	#[test]
+	fn lossy_number_warnings() {
+		use crate::{parse_with_warnings, LuaTarget, ParseOptions, WarningKind};
+
+		let toml = "safe = 9007199254740992\nlarge = 9007199254740993\n[limits]\nmax = inf\nmin = -inf\nratio = 0.5\nunknown = nan";
+
+		let (lua, warnings) = parse_with_warnings(toml, &ParseOptions::default()).unwrap();
+
+		assert!(lua.contains("[\"large\"] = 9007199254740993,"));
+		assert!(lua.contains("[\"max\"] = 1/0,"));
+
+		let warnings: Vec<(WarningKind, String)> = warnings
+			.into_iter()
+			.map(|warning| (warning.kind, warning.to_string()))
+			.collect();
+
+		assert_eq!(
+			warnings,
+			[
+				(
+					WarningKind::PrecisionLoss,
+					String::from(
+						"`large`: integer 9007199254740993 loses precision in Lua versions without integers"
+					)
+				),
+				(
+					WarningKind::NonFinite,
+					String::from("`limits.max`: inf written as 1/0")
+				),
+				(
+					WarningKind::NonFinite,
+					String::from("`limits.min`: -inf written as -1/0")
+				),
+				(
+					WarningKind::NonFinite,
+					String::from("`limits.unknown`: NaN written as 0/0")
+				),
+			]
+		);
+
+		// Lua 5.4 loads the integer exactly
+		let options = ParseOptions::for_target(LuaTarget::Lua54);
+		let (_, warnings) = parse_with_warnings("large = 9007199254740993", &options).unwrap();
+
+		assert!(warnings.is_empty());
+	}
+
+	#[cfg(feature = "content_hash")]
+	#[test]
+	fn content_hash() {
+		use crate::{parse_with_options, ParseOptions, Wrap};
+
+		let options = ParseOptions {
+			content_hash: true,
+			wrap: Wrap::Return,
+			final_newline: true,
+			..Default::default()
+		};
+
+		let lua = parse_with_options("key = 1", &options).unwrap();
+
+		assert_eq!(
+			lua,
+			"return {\n\t[\"key\"] = 1,\n}\n-- sha256:3fd87bf59eb5a5091581fd86ba746fe79ded445d683c70ffa84f243e16fa8656\n"
+		);
+		assert_eq!(parse_with_options("key = 1", &options).unwrap(), lua);
+		assert_ne!(parse_with_options("key = 2", &options).unwrap(), lua);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn fennel_dialect() {
+		use crate::{
+			parse_with_options, Dialect, EmptyStringMode, Error, Header, ParseOptions, Redact, Wrap,
+		};
+
+		let toml = r#"
+name = "abc \"quoted\""
+enabled = true
+ratio = 0.5
+limit = inf
+missing = "NIL"
+"max-width" = 80
+"two words" = 1
+ports = [80, 443]
+empty = []
+
+[[servers]]
+host = "a"
+
+[owner]
+born = 1979-05-27
+"#;
+
+		let options = ParseOptions {
+			dialect: Dialect::Fennel,
+			nil_sentinel: Some(String::from("NIL")),
+			header: Some(Header::Custom(String::from("Generated"))),
+			final_newline: true,
+			..Default::default()
+		};
+
+		let fennel = r#";; Generated
+{
+	:name "abc \"quoted\""
+	:enabled true
+	:ratio 0.5
+	:limit (/ 1 0)
+	:missing nil
+	:max-width 80
+	"two words" 1
+	:ports [80 443]
+	:empty []
+	:servers [
+		{
+			:host "a"
+		}
+	]
+	:owner {
+		:born "1979-05-27"
+	}
+}
+"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), fennel);
+
+		let options = ParseOptions {
+			wrap: Wrap::Return,
+			..options
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::ConflictingOptions {
+				second: "`wrap`",
+				..
+			})
+		));
+
+		// Options Fennel output doesn't apply are rejected rather than ignored
+		let options = ParseOptions {
+			wrap: Wrap::None,
+			redact: Some(Redact {
+				paths: vec![String::from("name")],
+				..Default::default()
+			}),
+			..options
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &options),
+			Err(Error::ConflictingOptions {
+				second: "`redact`",
+				..
+			})
+		));
+
+		let options = ParseOptions {
+			redact: None,
+			empty_string: EmptyStringMode::Omit,
+			..options
+		};
+
+		assert_eq!(
+			parse_with_options("a = \"\"\nb = 1\n[c]\nd = \"\"", &options).unwrap(),
+			";; Generated\n{\n\t:b 1\n\t:c {}\n}\n"
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn columnar_comments() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let options = ParseOptions {
+			columnar_comments: true,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let toml = r#"
+people = [{ name = "a", age = 30 }, { age = 41, name = "b" }]
+mixed = [{ name = "a" }, { age = 41 }]
+numbers = [1]
+
+[[rows]]
+x = 1
+"#;
+
+		let lua = r#"{
+	-- name, age
+	people = {
+		{
+			name = "a",
+			age = 30,
+		},
+		{
+			age = 41,
+			name = "b",
+		},
+	},
+	mixed = {
+		{
+			name = "a",
+		},
+		{
+			age = 41,
+		},
+	},
+	numbers = {
+		1,
+	},
+	-- x
+	rows = {
+		{
+			x = 1,
+		},
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		let toml = r#"
+[[items]]
+"x\n[\"pwned\"] = error('code ran from a TOML key')," = 1
+
+[[items]]
+"x\n[\"pwned\"] = error('code ran from a TOML key')," = 2
+"#;
+
+		let lua = parse_with_options(toml, &options).unwrap();
+
+		assert!(lua.contains(
+			r#"-- "x\n[\"pwned\"] = error('code ran from a TOML key'),"
+"#
+		));
+
+		#[cfg(any(feature = "verify", feature = "validate"))]
+		mlua::Lua::new()
+			.load(format!("return {}", lua))
+			.exec()
+			.unwrap();
+	}
+
+	#[test]
+	fn parse_path() {
+		use crate::{parse_path, Error};
+
+		let toml = r#"
+[database]
+host = "localhost"
+
+[database.pool]
+size = 4
+
+[[servers]]
+ports = [80, 443]
+
+["example.com"]
+"tls.port" = 443
+"#;
+
+		assert_eq!(
+			parse_path(toml, "database.pool").unwrap(),
+			"{\n\t[\"size\"] = 4,\n}"
+		);
+		assert_eq!(
+			parse_path(toml, "servers.1.ports").unwrap(),
+			"{\n\t80,\n\t443,\n}"
+		);
+		assert_eq!(parse_path(toml, "database.host").unwrap(), "\"localhost\"");
+
+		let missing = |path| parse_path(toml, path).unwrap_err().to_string();
+
+		assert_eq!(missing("cache"), "document has no key `cache`");
+		assert_eq!(
+			missing("database.pool.max"),
+			"`database.pool` has no key or index `max`"
+		);
+		assert_eq!(missing("servers.0"), "`servers` has no key or index `0`");
+		assert_eq!(
+			missing("database.host.x"),
+			"`database.host` has no key or index `x`"
+		);
+		assert!(matches!(
+			parse_path(toml, "servers.2"),
+			Err(Error::PathNotFound { ref path, ref missing }) if path == "servers" && missing == "2"
+		));
+
+		// Keys holding a `.` are quoted like in flattened paths
+		assert_eq!(
+			parse_path(toml, r#""example.com"."tls.port""#).unwrap(),
+			"443"
+		);
+		assert_eq!(
+			missing(r#""example.com".port"#),
+			"`\"example.com\"` has no key or index `port`"
+		);
+		assert_eq!(missing("example.com"), "document has no key `example`");
+		assert_eq!(
+			missing(r#""example.com"#),
+			"document has no key `\"example`"
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn bare_key_predicate() {
+		use crate::{parse_with_options, KeyPredicate, ParseOptions};
+
+		let options = ParseOptions {
+			bare_keys: true,
+			bare_key_predicate: Some(KeyPredicate::new(|key| !key.starts_with('_'))),
+			..Default::default()
+		};
+
+		let toml = r#"
+name = 1
+_private = 2
+"_say \"hi\"" = 3
+"not valid" = 4
+
+[_meta]
+version = 5
+"#;
+
+		let lua = r#"{
+	name = 1,
+	["_private"] = 2,
+	["_say \"hi\""] = 3,
+	["not valid"] = 4,
+	["_meta"] = {
+		version = 5,
+	},
+}"#;
+
+		assert_eq!(parse_with_options(toml, &options).unwrap(), lua);
+
+		// Keys that aren't identifiers stay bracketed whatever the predicate says
+		let options = ParseOptions {
+			bare_key_predicate: Some(KeyPredicate::new(|_| true)),
+			..options
+		};
+
+		assert!(parse_with_options(toml, &options)
+			.unwrap()
+			.contains("\t[\"not valid\"] = 4,"));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn warn_case_collisions() {
+		use crate::{parse_with_warnings, ParseOptions, WarningKind};
+
+		let options = ParseOptions {
+			warn_case_collisions: true,
+			..Default::default()
+		};
+
+		let toml = r#"
+Name = "a"
+name = "b"
+title = "c"
+
+[server]
+HOST = "x"
+host = "y"
+Host = "z"
+
+[other]
+host = "w"
+"#;
+
+		let (lua, warnings) = parse_with_warnings(toml, &options).unwrap();
+
+		assert!(lua.contains("[\"Name\"] = \"a\",\n\t[\"name\"] = \"b\","));
+
+		let warnings: Vec<String> = warnings
+			.into_iter()
+			.inspect(|warning| assert_eq!(warning.kind, WarningKind::CaseCollision))
+			.map(|warning| warning.to_string())
+			.collect();
+
+		assert_eq!(
+			warnings,
+			[
+				"`name`: key differs from `Name` only by case",
+				"`server.host`: key differs from `HOST` only by case",
+				"`server.Host`: key differs from `host` only by case",
+			]
+		);
+
+		let (_, warnings) = parse_with_warnings(toml, &ParseOptions::default()).unwrap();
+
+		assert!(warnings.is_empty());
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn quoted_paths() {
+		use crate::{parse_with_warnings, ParseOptions, Redact};
+
+		let toml = r#"
+		["example.com"]
+		port = 80
+		token = "abc"
+		timeout = 5
+
+		[["a.b"]]
+		id = "x"
+"#;
+
+		let options = |paths: [&str; 4]| ParseOptions {
+			doc_comments: [(String::from(paths[0]), String::from("Port"))].into(),
+			redact: Some(Redact {
+				paths: vec![String::from(paths[1])],
+				..Default::default()
+			}),
+			raw_expressions: [(String::from(paths[2]), String::from("30"))].into(),
+			key_by: [(String::from(paths[3]), String::from("id"))].into(),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let converted = |paths| {
+			let (lua, warnings) = parse_with_warnings(toml, &options(paths)).unwrap();
+			let warnings: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+
+			(lua, warnings)
+		};
+
+		// Keys holding a `.` are quoted like in the paths of warnings
+		let (lua, warnings) = converted([
+			r#""example.com".port"#,
+			r#""example.com".token"#,
+			r#""example.com".timeout"#,
+			r#""a.b""#,
+		]);
+
+		assert_eq!(
+			lua,
+			r#"{
+	["example.com"] = {
+		-- Port
+		port = 80,
+		token = "<redacted>",
+		timeout = 30,
+	},
+	["a.b"] = {
+		x = {
+			id = "x",
+		},
+	},
+}"#
+		);
+		assert_eq!(warnings, ["`\"example.com\".token`: redacted"]);
+
+		// Unquoted, the dots split the key
+		let (lua, warnings) = converted([
+			"example.com.port",
+			"example.com.token",
+			"example.com.timeout",
+			"a.b",
+		]);
+
+		assert!(lua.contains("token = \"abc\","));
+		assert!(lua.contains("\t[\"a.b\"] = {\n\t\t{\n"));
+		assert_eq!(
+			warnings,
+			[
+				"`example.com.token`: redact pattern matches no value",
+				"`example.com.timeout`: raw expression matches no value",
+				"`example.com.port`: doc comment matches no entry",
+			]
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn raw_expressions() {
+		use crate::{parse_with_warnings, Error, ParseOptions, WarningKind};
+
+		let options = ParseOptions {
+			raw_expressions: [
+				("server.timeout", r#"tonumber(os.getenv("TIMEOUT")) or 30"#),
+				("server.hosts.2", "os.getenv(\"HOST\")"),
+				("missing.key", "nil"),
+			]
+			.into_iter()
+			.map(|(path, expression)| (path.to_owned(), expression.to_owned()))
+			.collect(),
+			..Default::default()
+		};
+
+		let toml = r#"
+name = "app"
+
+[server]
+timeout = 10
+hosts = ["a", "b"]
+"#;
+
+		let (lua, warnings) = parse_with_warnings(toml, &options).unwrap();
+
+		assert_eq!(
+			lua,
+			r#"{
+	["name"] = "app",
+	["server"] = {
+		["timeout"] = tonumber(os.getenv("TIMEOUT")) or 30,
+		["hosts"] = {
+			"a",
+			os.getenv("HOST"),
+		},
+	},
+}"#
+		);
+
+		assert_eq!(warnings.len(), 1);
+		assert_eq!(warnings[0].kind, WarningKind::Unmatched);
+		assert_eq!(warnings[0].path, "missing.key");
+
+		let fennel = ParseOptions {
+			dialect: crate::Dialect::Fennel,
+			..options
+		};
+
+		assert!(matches!(
+			parse_with_warnings(toml, &fennel),
+			Err(Error::ConflictingOptions {
+				second: "`raw_expressions`",
+				..
+			})
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "comments")]
+	fn ldoc_field_comments() {
+		use crate::{parse_with_comments, CommentStyle, ParseOptions, Wrap};
+
+		let options = ParseOptions {
+			comment_style: CommentStyle::LdocField,
+			wrap: Wrap::Return,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let toml = r#"
+# Name shown to players
+#
+# Defaults to the host name
+name = "lobby"
+# Whether anyone can join
+"is public" = true
+
+# Connection settings
+[server]
+port = 8080
+"#;
+
+		assert_eq!(
+			parse_with_comments(toml, &options).unwrap(),
+			r#"return {
+	--- @field name Name shown to players
+	---
+	--- Defaults to the host name
+	name = "lobby",
+	--- @field ["is public"] Whether anyone can join
+	["is public"] = true,
+	--- @field server Connection settings
+	server = {
+		port = 8080,
+	},
+}"#
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn string_whitespace() {
+		use crate::{parse_with_options, ParseOptions, WhitespacePolicy};
+
+		let options = |string_whitespace| ParseOptions {
+			string_whitespace,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let toml = r#"
+text = "first  \nsecond\t\r\nthird "
+"key " = "a\tbc\td"
+"#;
+
+		assert_eq!(
+			parse_with_options(toml, &options(WhitespacePolicy::TrimTrailing)).unwrap(),
+			"{\n\ttext = \"first\\nsecond\\r\\nthird\",\n\t[\"key \"] = \"a\\tbc\\td\",\n}"
+		);
+
+		assert_eq!(
+			parse_with_options(toml, &options(WhitespacePolicy::TabsToSpaces(4))).unwrap(),
+			"{\n\ttext = \"first  \\nsecond  \\r\\nthird \",\n\t[\"key \"] = \"a   bc  d\",\n}"
+		);
+
+		assert_eq!(
+			parse_with_options(toml, &options(WhitespacePolicy::Preserve)).unwrap(),
+			parse_with_options(toml, &options(Default::default())).unwrap()
+		);
+	}
+
+	#[test]
+	fn load_cost_stats() {
+		use crate::{parse_with_stats, ParseOptions};
+
+		let toml = r#"
+name = "app"
+point = { x = 1, y = 2 }
+matrix = [[1, 2], [3, 4]]
+
+[[items]]
+id = 1
+
+[[items]]
+id = 2
+"#;
+
+		let counts = |options: &ParseOptions| {
+			let (_, stats) = parse_with_stats(toml, options).unwrap();
+			(stats.constructors(), stats.fields)
+		};
+
+		// Root, point, matrix and its rows, items and its tables
+		assert_eq!(counts(&ParseOptions::default()), (8, 16));
+
+		let inline = ParseOptions {
+			max_width: Some(80),
+			flatten_array_of_tables_indent: true,
+			..Default::default()
+		};
+
+		assert_eq!(counts(&inline), (8, 16));
+
+		let packed = ParseOptions {
+			packed_arrays: true,
+			..Default::default()
+		};
+
+		// Every array gains its `n` field
+		assert_eq!(counts(&packed), (8, 20));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn dotted_keys_and_table_headers() {
+		use crate::{parse_with_options, Error, ParseOptions};
+
+		let options = ParseOptions {
+			bare_keys: true,
+			..Default::default()
+		};
+
+		// Sub-tables of a table defined by dotted keys get headers of their own
+		let toml = r#"
+[fruit]
+apple.color = "red"
+apple.taste.sweet = true
+name = "fruit"
+
+[fruit.apple.texture]
+smooth = true
+
+[fruit.apple.taste.notes]
+first = "tart"
+"#;
+
+		assert_eq!(
+			parse_with_options(toml, &options).unwrap(),
+			r#"{
+	fruit = {
+		apple = {
+			color = "red",
+			taste = {
+				sweet = true,
+				notes = {
+					first = "tart",
+				},
+			},
+			texture = {
+				smooth = true,
+			},
+		},
+		name = "fruit",
+	},
+}"#
+		);
+
+		// Dotted keys of the root and of a header define sub-tables of the same parent
+		let toml = r#"
+server.host = "localhost"
+server.limits.connections = 10
+
+[server.tls]
+enabled = false
+
+[database]
+pool.size = 4
+name = "main"
+
+[database.pool.timeouts]
+idle = 30
+"#;
+
+		let lua = parse_with_options(toml, &options).unwrap();
+
+		for key in [
+			"host",
+			"limits",
+			"connections",
+			"tls",
+			"enabled",
+			"database",
+			"pool",
+			"size",
+			"name",
+			"timeouts",
+			"idle",
+		] {
+			assert_eq!(lua.matches(&format!("\t{} = ", key)).count(), 1, "{}", key);
+		}
+
+		assert_eq!(
+			lua,
+			r#"{
+	server = {
+		host = "localhost",
+		limits = {
+			connections = 10,
+		},
+		tls = {
+			enabled = false,
+		},
+	},
+	database = {
+		pool = {
+			size = 4,
+			timeouts = {
+				idle = 30,
+			},
+		},
+		name = "main",
+	},
+}"#
+		);
+
+		// Redefining a table defined by dotted keys is rejected like any duplicate
+		for toml in [
+			"a.b = 1\n[a]\nc = 2",
+			"[a]\nb = 1\n[a]\nc = 2",
+			"[a.b]\nc = 1\n[a]\nb.d = 2",
+		] {
+			assert!(matches!(
+				parse_with_options(toml, &options),
+				Err(Error::Toml { .. })
+			));
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn array_wrap_at() {
+		use crate::{parse_with_options, ParseOptions};
+		use core::num::NonZeroUsize;
+
+		let options = ParseOptions {
+			array_wrap_at: NonZeroUsize::new(4),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let toml = r#"
+palette = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
+even = [1, 2, 3, 4]
+nested = [[1, 2], [3]]
+"#;
+
+		assert_eq!(
+			parse_with_options(toml, &options).unwrap(),
+			r#"{
+	palette = {
+		1, 2, 3, 4,
+		5, 6, 7, 8,
+		9, 10,
+	},
+	even = {
+		1, 2, 3, 4,
+	},
+	nested = {
+		{
+			1, 2,
+		},
+		{
+			3,
+		},
+	},
+}"#
+		);
+
+		// Arrays that fit on a single line are still written on it
+		let inline = ParseOptions {
+			max_width: Some(20),
+			..options.clone()
+		};
+
+		assert_eq!(
+			parse_with_options("palette = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]\neven = [1, 2]", &inline)
+				.unwrap(),
+			"{\n\tpalette = {\n\t\t1, 2, 3, 4,\n\t\t5, 6, 7, 8,\n\t\t9, 10,\n\t},\n\teven = { 1, 2 },\n}"
+		);
+
+		let commented = ParseOptions {
+			type_comments: true,
+			..options
+		};
+
+		assert_eq!(
+			parse_with_options("list = [1, 2]", &commented).unwrap(),
+			"{\n\tlist = {\n\t\t1, -- integer\n\t\t2, -- integer\n\t},\n}"
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn with_accessor() {
+		use crate::{parse_with_options, AccessorModule, Error, ParseOptions, Wrap};
+
+		let options = ParseOptions {
+			with_accessor: Some(String::from("get")),
+			wrap: Wrap::Return,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		let toml = "greeting = \"Hello\"\nfarewell = \"Bye\"";
+
+		assert_eq!(
+			parse_with_options(toml, &options).unwrap(),
+			r#"local data = {
+	greeting = "Hello",
+	farewell = "Bye",
+}
+
+local function get(key)
+	return data[key]
+end
+
+return get"#
+		);
+
+		let local = ParseOptions {
+			wrap: Wrap::Local(String::from("strings")),
+			..options.clone()
+		};
+
+		assert_eq!(
+			parse_with_options(toml, &local).unwrap(),
+			r#"local strings = {
+	greeting = "Hello",
+	farewell = "Bye",
+}
+
+local function get(key)
+	return strings[key]
+end"#
+		);
+
+		let module = ParseOptions {
+			accessor_module: Some(AccessorModule::default()),
+			..options
+		};
+
+		assert!(matches!(
+			parse_with_options(toml, &module),
+			Err(Error::ConflictingOptions {
+				first: "`with_accessor`",
+				second: "`accessor_module`"
+			})
+		));
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn integer_boundaries() {
+		use crate::{
+			parse_with_options, IntegerOverflow, NumberMode, PackNumbers, ParseOptions, Radix,
+		};
+
+		let toml = "min = -9223372036854775808\nmax = 9223372036854775807";
+
+		let output = |options: ParseOptions| {
+			let options = ParseOptions {
+				bare_keys: true,
+				integer_overflow: IntegerOverflow::Allow,
+				..options
+			};
+
+			parse_with_options(toml, &options).unwrap()
+		};
+
+		assert_eq!(
+			output(ParseOptions::default()),
+			"{\n\tmin = (-9223372036854775807 - 1),\n\tmax = 9223372036854775807,\n}"
+		);
+
+		assert_eq!(
+			output(ParseOptions {
+				integer_radix: Radix::Hex,
+				..Default::default()
+			}),
+			"{\n\tmin = (-9223372036854775807 - 1),\n\tmax = 0x7FFFFFFFFFFFFFFF,\n}"
+		);
+
+		assert_eq!(
+			output(ParseOptions {
+				integer_radix: Radix::Binary,
+				..Default::default()
+			})
+			.lines()
+			.nth(2),
+			Some("\tmax = 0x7FFFFFFFFFFFFFFF, -- 0b111111111111111111111111111111111111111111111111111111111111111")
+		);
+
+		assert_eq!(
+			output(ParseOptions {
+				number_mode: NumberMode::AllFloat,
+				..Default::default()
+			}),
+			"{\n\tmin = -9223372036854775808.0,\n\tmax = 9223372036854775807.0,\n}"
+		);
+
+		// Floats beyond the integer range saturate
+		assert_eq!(
+			parse_with_options(
+				"min = -1e300\nmax = 1e300",
+				&ParseOptions {
+					number_mode: NumberMode::AllInteger,
+					integer_overflow: IntegerOverflow::Allow,
+					bare_keys: true,
+					..Default::default()
+				}
+			)
+			.unwrap(),
+			"{\n\tmin = (-9223372036854775807 - 1),\n\tmax = 9223372036854775807,\n}"
+		);
+
+		let packed = parse_with_options(
+			"list = [-9223372036854775808, 9223372036854775807]",
+			&ParseOptions {
+				pack_numbers: Some(PackNumbers {
+					min_len: 1,
+					..Default::default()
+				}),
+				..Default::default()
+			},
+		)
+		.unwrap();
+
+		assert!(packed.contains("(\"0000000000000080ffffffffffffff7f\")"));
+
+		let keyed = parse_with_options(
+			"[[items]]\nid = -9223372036854775808\n[[items]]\nid = 9223372036854775807",
+			&ParseOptions {
+				key_by: [(String::from("items"), String::from("id"))].into(),
+				key_by_remove_field: true,
+				..Default::default()
+			},
+		)
+		.unwrap();
+
+		assert!(keyed.contains("\t\t[(-9223372036854775807 - 1)] = {\n"));
+		assert!(keyed.contains("\t\t[9223372036854775807] = {\n"));
+
+		let strict = ParseOptions {
+			integer_overflow: IntegerOverflow::Error,
+			..Default::default()
+		};
+		let error = parse_with_options(toml, &strict).unwrap_err();
+
+		assert_eq!(
+			error.to_string(),
+			crate::Error::IntegerOverflow {
+				path: String::from("min"),
+				value: i64::MIN
+			}
+			.to_string()
+		);
+	}
+
+	#[test]
+	#[cfg(feature = "preserve_order")]
+	fn readonly() {
+		use crate::{parse_with_options, Error, Freeze, ParseOptions, ReadonlyMode, Wrap};
+
+		let toml = "name = 'server'\n[limits]\nmemory = 512";
+
+		let options = ParseOptions {
+			readonly: ReadonlyMode::Wrapper(String::from("readonly")),
+			wrap: Wrap::Return,
+			bare_keys: true,
+			..Default::default()
+		};
+
+		assert_eq!(
+			parse_with_options(toml, &options).unwrap(),
+			r#"return readonly({
+	name = "server",
+	limits = {
+		memory = 512,
+	},
+})"#
+		);
+
+		let metatable = ParseOptions {
+			readonly: ReadonlyMode::Metatable,
+			..options
+		};
+
+		let lua = parse_with_options(toml, &metatable).unwrap();
+
+		assert_eq!(
+			lua,
+			r#"return setmetatable({
+	name = "server",
+	limits = {
+		memory = 512,
+	},
+}, { __newindex = function() error("attempt to add a field to a readonly table", 2) end })"#
+		);
+
+		#[cfg(any(feature = "verify", feature = "validate"))]
+		mlua::Lua::new()
+			.load(format!(
+				r#"
+				local config = (function() {} end)()
+
+				assert(config.limits.memory == 512)
+
+				local ok, err = pcall(function() config.port = 80 end)
+				assert(not ok and err:find("readonly table", 1, true), err)
+				"#,
+				lua
+			))
+			.exec()
+			.unwrap();
+
+		for options in [
+			ParseOptions {
+				root_metatable: Some(String::from("{}")),
+				..metatable.clone()
+			},
+			ParseOptions {
+				freeze: Freeze::Shallow,
+				..metatable
+			},
+		] {
+			assert!(matches!(
+				parse_with_options(toml, &options),
+				Err(Error::ConflictingOptions {
+					second: "`ReadonlyMode::Metatable`" | "`freeze`",
+					..
+				})
+			));
+		}
+
+		// A single value is only protected by a metatable if it is a table
+		let mut options = ParseOptions {
+			readonly: ReadonlyMode::Metatable,
+			unwrap_single: true,
+			type_comments: true,
+			wrap: Wrap::Return,
+			..Default::default()
+		};
+
+		assert!(matches!(
+			parse_with_options("x = 0x1", &options),
+			Err(Error::ConflictingOptions {
+				first: "`unwrap_single`",
+				second: "`ReadonlyMode::Metatable`"
+			})
+		));
+
+		let lua = parse_with_options("x = [1]", &options).unwrap();
+		assert!(lua.starts_with("return setmetatable({\n\t1, -- integer\n}, {"));
+
+		#[cfg(any(feature = "verify", feature = "validate"))]
+		mlua::Lua::new().load(&lua).exec().unwrap();
+
+		options.readonly = ReadonlyMode::Wrapper(String::from("readonly"));
+
+		assert_eq!(
+			parse_with_options("x = 0x1", &options).unwrap(),
+			"return readonly(1) -- integer"
+		);
+	}
+
+	#[test]
+	fn max_depth() {
+		use crate::{parse_with_options, Error, ParseOptions};
+
+		let toml = r#"
+name = "app"
+
+[server]
+port = 80
+
+[server.limits]
+memory = 512
+ports = [[80, 443]]
+"#;
+
+		let options = |max_depth| ParseOptions {
+			max_depth,
+			..Default::default()
+		};
+
+		assert!(parse_with_options(toml, &options(Some(5))).is_ok());
+
+		let error = parse_with_options(toml, &options(Some(4))).unwrap_err();
+
+		assert!(matches!(
+			&error,
+			Error::TooDeep { path, limit: 4 } if path == "server.limits.ports.1"
+		));
+		assert_eq!(
+			error.to_string(),
+			"`server.limits.ports.1` is nested deeper than the limit of 4 levels"
+		);
+		assert!(matches!(
+			parse_with_options("[a.b]", &options(Some(2))),
+			Err(Error::TooDeep { path, limit: 2 }) if path == "a.b"
+		));
+	}
+
+	#[test]
+	fn validate() {
+		use crate::{parse_with_options, validate, Error, KeyCase, ParseOptions, TealTypes};
+
+		let toml = r#"
+name = "app"
+
+[server]
+port = 80
+
+[server.limits]
+memory = 512
+ports = [[80, 443]]
+"#;
+
+		let options = |max_depth| ParseOptions {
+			max_depth,
+			..Default::default()
+		};
+
+		assert!(validate(toml, &options(None)).is_ok());
+		assert!(validate(toml, &options(Some(5))).is_ok());
+
+		let error = validate(toml, &options(Some(4))).unwrap_err();
+
+		assert!(matches!(
+			&error,
+			Error::TooDeep { path, limit: 4 } if path == "server.limits.ports.1"
+		));
+		assert_eq!(
+			error.to_string(),
+			"`server.limits.ports.1` is nested deeper than the limit of 4 levels"
+		);
+		assert_eq!(
+			parse_with_options(toml, &options(Some(4)))
+				.unwrap_err()
+				.to_string(),
+			error.to_string()
+		);
+
+		// Other errors of the options and of the document are reported too
+		let teal = ParseOptions {
+			teal_types: Some(TealTypes::new("Config")),
+			..Default::default()
+		};
+
+		assert!(matches!(
+			validate("\"my key\" = 1", &teal),
+			Err(Error::InvalidIdentifier { .. })
+		));
+		assert!(matches!(
+			validate("key =", &options(None)),
+			Err(Error::Toml { .. })
+		));
+
+		// Keys of the root table are checked against each other like when parsing
+		let snake = ParseOptions {
+			key_case: KeyCase::Snake,
+			..Default::default()
+		};
+
+		assert!(matches!(
+			validate("my_key = 1\nmy-key = 2", &snake),
+			Err(Error::KeyCollision { .. })
+		));
 	}
 }
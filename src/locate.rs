@@ -0,0 +1,215 @@
+use alloc::{
+	boxed::Box,
+	collections::BTreeMap,
+	string::{String, ToString},
+	vec::Vec,
+};
+use serde::de::DeserializeOwned;
+
+use crate::{walk::join_path, Error};
+
+/// Deserialize the TOML text, errors carry the key path they occurred at
+pub(crate) fn from_str<T: DeserializeOwned>(toml: &str) -> Result<T, Error> {
+	toml::from_str(toml).map_err(|error| Error::Toml {
+		path: error.span().and_then(|span| key_path(toml, span.start)),
+		error: Box::new(error),
+	})
+}
+
+/// Dotted path of the key whose line contains the byte at `offset`, or of the
+/// table whose header does, array of tables items are numbered from 1
+///
+/// The text before `offset` is scanned line by line, so a value spread over
+/// several lines is attributed to the key it starts at
+fn key_path(toml: &str, offset: usize) -> Option<String> {
+	let mut table: Vec<String> = Vec::new();
+	let mut key: Option<Vec<String>> = None;
+	// Items of every array of tables so far, keyed by the numbered path
+	let mut arrays: BTreeMap<String, usize> = BTreeMap::new();
+	// Closing quotes of the multi-line string the line is in
+	let mut string: Option<&str> = None;
+	let mut start = 0;
+
+	for line in toml.split_inclusive('\n') {
+		if start > offset {
+			break;
+		}
+
+		start += line.len();
+
+		if let Some(quotes) = string {
+			if line.matches(quotes).count() % 2 == 1 {
+				string = None;
+			}
+
+			continue;
+		}
+
+		let trimmed = line.trim_start();
+
+		if let Some(header) = trimmed.strip_prefix("[[") {
+			let segments = parse_key(header)?.0;
+			table = numbered(&segments, &mut arrays, true);
+			key = None;
+		} else if let Some(header) = trimmed.strip_prefix('[') {
+			let segments = parse_key(header)?.0;
+			table = numbered(&segments, &mut arrays, false);
+			key = None;
+		} else if let Some((segments, rest)) = parse_key(trimmed) {
+			if let Some(value) = rest.trim_start().strip_prefix('=') {
+				key = Some(segments);
+
+				// A multi-line string opened on this line continues on the next ones
+				for quotes in ["\"\"\"", "'''"] {
+					if value.matches(quotes).count() % 2 == 1 {
+						string = Some(quotes);
+					}
+				}
+			}
+		}
+	}
+
+	let mut path = table;
+	path.extend(key.unwrap_or_default());
+
+	if path.is_empty() {
+		None
+	} else {
+		Some(join_path(&path))
+	}
+}
+
+/// Path of the table with the given header, with the index of the current
+/// item after every array of tables in it. `item` adds an item to the last one
+fn numbered(segments: &[String], arrays: &mut BTreeMap<String, usize>, item: bool) -> Vec<String> {
+	let mut path = Vec::new();
+
+	for (i, segment) in segments.iter().enumerate() {
+		path.push(segment.clone());
+
+		let joined = path.join(".");
+
+		if item && i + 1 == segments.len() {
+			let count = arrays.entry(joined).or_default();
+			*count += 1;
+			path.push(count.to_string());
+		} else if let Some(count) = arrays.get(&joined) {
+			path.push(count.to_string());
+		}
+	}
+
+	path
+}
+
+/// Segments of the dotted key at the start of `text` and the text after it
+fn parse_key(text: &str) -> Option<(Vec<String>, &str)> {
+	let mut segments = Vec::new();
+	let mut rest = text;
+
+	loop {
+		rest = rest.trim_start_matches([' ', '\t']);
+
+		let (segment, after) = match rest.chars().next()? {
+			'"' => basic_string(&rest[1..])?,
+			'\'' => {
+				let end = rest[1..].find('\'')?;
+				(rest[1..end + 1].to_string(), &rest[end + 2..])
+			}
+			_ => {
+				let end = rest
+					.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+					.unwrap_or(rest.len());
+
+				if end == 0 {
+					return None;
+				}
+
+				(rest[..end].to_string(), &rest[end..])
+			}
+		};
+
+		segments.push(segment);
+		rest = after.trim_start_matches([' ', '\t']);
+
+		match rest.strip_prefix('.') {
+			Some(after) => rest = after,
+			None => return Some((segments, rest)),
+		}
+	}
+}
+
+/// Contents of the basic string starting after its opening quote, with the
+/// simple escapes resolved, and the text after its closing quote
+fn basic_string(text: &str) -> Option<(String, &str)> {
+	let mut string = String::new();
+	let mut chars = text.char_indices();
+
+	while let Some((i, c)) = chars.next() {
+		match c {
+			'"' => return Some((string, &text[i + 1..])),
+			'\\' => match chars.next()?.1 {
+				'n' => string.push('\n'),
+				't' => string.push('\t'),
+				'r' => string.push('\r'),
+				'b' => string.push('\u{8}'),
+				'f' => string.push('\u{c}'),
+				escaped => string.push(escaped),
+			},
+			_ => string.push(c),
+		}
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod test {
+	use super::key_path;
+
+	#[test]
+	fn key_paths() {
+		let toml = r#"
+name = "abc"
+a.b = 1
+"quoted.key" = 2
+text = """
+[not.a.header]
+x = 1
+"""
+
+[[servers]]
+host = "a"
+
+[[servers]]
+host = "b"
+
+[servers.limits]
+max = 1
+
+[[servers.ports]]
+number = 80
+"#;
+
+		let path = |line: &str| {
+			let offset = toml.find(line).unwrap();
+			key_path(toml, offset)
+		};
+
+		assert_eq!(path("name"), Some(String::from("name")));
+		assert_eq!(path("a.b"), Some(String::from("a.b")));
+		assert_eq!(path("\"quoted"), Some(String::from("\"quoted.key\"")));
+		assert_eq!(path("x = 1"), Some(String::from("text")));
+		assert_eq!(path("host = \"a\""), Some(String::from("servers.1.host")));
+		assert_eq!(path("host = \"b\""), Some(String::from("servers.2.host")));
+		assert_eq!(path("max"), Some(String::from("servers.2.limits.max")));
+		assert_eq!(
+			path("[servers.limits]"),
+			Some(String::from("servers.2.limits"))
+		);
+		assert_eq!(
+			path("number"),
+			Some(String::from("servers.2.ports.1.number"))
+		);
+		assert_eq!(key_path(toml, 0), None);
+	}
+}
@@ -0,0 +1,349 @@
+use full_moon::{
+	ast::{BinOp, Expression, Field, LastStmt, Stmt, TableConstructor, UnOp},
+	tokenizer::{StringLiteralQuoteType, Symbol, TokenReference, TokenType},
+};
+use toml::{Table, Value};
+
+use crate::Error;
+
+/// Convert a Lua table constructor back into a TOML document
+///
+/// Accepts a bare table, `return { ... }` or `local name = { ... }` made of
+/// nested tables, strings, numbers and booleans, the same subset [`parse`](crate::parse)
+/// emits. Tables with only positional fields become arrays and empty ones
+/// become tables. Function calls, variables and other expressions are rejected
+/// since they are never evaluated
+///
+/// ```rust
+/// use toml2lua::lua_to_toml;
+///
+/// let lua = r#"return {
+/// 	name = "abc",
+/// 	["ports"] = { 80, 443 },
+/// }"#;
+///
+/// assert_eq!(lua_to_toml(lua).unwrap(), "name = \"abc\"\nports = [80, 443]\n");
+/// ```
+pub fn lua_to_toml(lua: &str) -> Result<String, Error> {
+	let source = if lua.trim_start().starts_with('{') {
+		format!("return {}", lua)
+	} else {
+		lua.to_owned()
+	};
+
+	let ast = full_moon::parse(&source).map_err(|errors| {
+		Error::Lua(match errors.first() {
+			Some(error) => error.to_string(),
+			None => String::from("failed to parse Lua"),
+		})
+	})?;
+
+	let block = ast.nodes();
+	let stmts: Vec<&Stmt> = block.stmts().collect();
+
+	let expressions = match (stmts.as_slice(), block.last_stmt()) {
+		([], Some(LastStmt::Return(ret))) => ret.returns(),
+		([Stmt::LocalAssignment(local)], None) => local.expressions(),
+		_ => {
+			return Err(Error::Lua(String::from(
+				"expected `{ ... }`, `return { ... }` or `local name = { ... }`",
+			)))
+		}
+	};
+
+	let table = match expressions.iter().collect::<Vec<_>>().as_slice() {
+		[Expression::TableConstructor(table)] => table_value(table)?,
+		_ => {
+			return Err(Error::Lua(String::from(
+				"expected a single table constructor",
+			)))
+		}
+	};
+
+	match table {
+		Value::Table(table) => toml::to_string(&table).map_err(|err| Error::Lua(err.to_string())),
+		_ => Err(Error::Lua(String::from(
+			"root table has positional fields, TOML documents need keys",
+		))),
+	}
+}
+
+fn value(expression: &Expression) -> Result<Value, Error> {
+	match expression {
+		Expression::TableConstructor(table) => table_value(table),
+		Expression::Number(token) => number(token, false),
+		Expression::UnaryOperator {
+			unop: UnOp::Minus(_),
+			expression,
+		} => match &**expression {
+			Expression::Number(token) => number(token, true),
+			_ => Err(unsupported(expression)),
+		},
+		Expression::Parentheses { expression, .. } => value(expression),
+		// Only the forms emitted for i64::MIN, infinities and NaN
+		Expression::BinaryOperator { lhs, binop, rhs } => match (value(lhs)?, binop, value(rhs)?) {
+			(Value::Integer(lhs), BinOp::Minus(_), Value::Integer(rhs)) => lhs
+				.checked_sub(rhs)
+				.map(Value::Integer)
+				.ok_or_else(|| unsupported(expression)),
+			(Value::Integer(lhs), BinOp::Slash(_), Value::Integer(rhs)) => {
+				Ok(Value::Float(lhs as f64 / rhs as f64))
+			}
+			_ => Err(unsupported(expression)),
+		},
+		Expression::String(token) => string(token).map(Value::String),
+		Expression::Symbol(token) => match token.token_type() {
+			TokenType::Symbol {
+				symbol: Symbol::True,
+			} => Ok(Value::Boolean(true)),
+			TokenType::Symbol {
+				symbol: Symbol::False,
+			} => Ok(Value::Boolean(false)),
+			_ => Err(Error::Lua(format!(
+				"`{}` has no TOML equivalent",
+				token.token()
+			))),
+		},
+		_ => Err(unsupported(expression)),
+	}
+}
+
+fn table_value(table: &TableConstructor) -> Result<Value, Error> {
+	let fields: Vec<&Field> = table.fields().iter().collect();
+
+	if !fields.is_empty() && fields.iter().all(|field| matches!(field, Field::NoKey(_))) {
+		return fields
+			.iter()
+			.map(|field| match field {
+				Field::NoKey(expression) => value(expression),
+				_ => unreachable!(),
+			})
+			.collect::<Result<Vec<Value>, Error>>()
+			.map(Value::Array);
+	}
+
+	let mut entries = Table::new();
+
+	for field in fields {
+		let (key, expression) = match field {
+			Field::NameKey { key, value, .. } => (key.token().to_string(), value),
+			Field::ExpressionKey {
+				key: Expression::String(key),
+				value,
+				..
+			} => (string(key)?, value),
+			Field::ExpressionKey { key, .. } => {
+				return Err(Error::Lua(format!(
+					"key `{}` is not a string",
+					key.to_string().trim()
+				)))
+			}
+			Field::NoKey(_) => {
+				return Err(Error::Lua(String::from(
+					"table mixes positional and keyed fields",
+				)))
+			}
+			_ => return Err(Error::Lua(String::from("unsupported table field"))),
+		};
+
+		entries.insert(key, value(expression)?);
+	}
+
+	Ok(Value::Table(entries))
+}
+
+fn number(token: &TokenReference, negative: bool) -> Result<Value, Error> {
+	let TokenType::Number { text } = token.token_type() else {
+		return Err(Error::Lua(format!("invalid number `{}`", token.token())));
+	};
+
+	let text = text.as_str();
+	let invalid = || Error::Lua(format!("invalid number `{}`", text));
+
+	let value = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+		Value::Integer(i64::from_str_radix(hex, 16).map_err(|_| invalid())?)
+	} else if text.contains(['.', 'e', 'E']) {
+		Value::Float(text.parse().map_err(|_| invalid())?)
+	} else {
+		Value::Integer(text.parse().map_err(|_| invalid())?)
+	};
+
+	Ok(match value {
+		Value::Integer(i) if negative => Value::Integer(-i),
+		Value::Float(f) if negative => Value::Float(-f),
+		value => value,
+	})
+}
+
+fn string(token: &TokenReference) -> Result<String, Error> {
+	match token.token_type() {
+		TokenType::StringLiteral {
+			literal,
+			quote_type: StringLiteralQuoteType::Brackets,
+			..
+		} => Ok(literal
+			.as_str()
+			.strip_prefix('\n')
+			.unwrap_or(literal.as_str())
+			.to_owned()),
+		TokenType::StringLiteral { literal, .. } => unescape(literal.as_str()),
+		_ => Err(Error::Lua(format!("invalid string `{}`", token.token()))),
+	}
+}
+
+/// Resolve escape sequences of a quoted Lua string
+fn unescape(literal: &str) -> Result<String, Error> {
+	let invalid = || Error::Lua(format!("invalid escape sequence in \"{}\"", literal));
+
+	let mut bytes = Vec::with_capacity(literal.len());
+	let mut chars = literal.chars().peekable();
+
+	while let Some(char) = chars.next() {
+		if char != '\\' {
+			let mut buffer = [0; 4];
+			bytes.extend_from_slice(char.encode_utf8(&mut buffer).as_bytes());
+			continue;
+		}
+
+		match chars.next().ok_or_else(invalid)? {
+			'a' => bytes.push(0x07),
+			'b' => bytes.push(0x08),
+			'f' => bytes.push(0x0C),
+			'n' | '\n' => bytes.push(b'\n'),
+			'r' => bytes.push(b'\r'),
+			't' => bytes.push(b'\t'),
+			'v' => bytes.push(0x0B),
+			'\\' => bytes.push(b'\\'),
+			'"' => bytes.push(b'"'),
+			'\'' => bytes.push(b'\''),
+			'z' => while chars.next_if(|char| char.is_ascii_whitespace()).is_some() {},
+			'x' => {
+				let digits: String = chars.by_ref().take(2).collect();
+				bytes.push(u8::from_str_radix(&digits, 16).map_err(|_| invalid())?);
+			}
+			'u' => {
+				if chars.next() != Some('{') {
+					return Err(invalid());
+				}
+
+				let digits: String = chars.by_ref().take_while(|char| *char != '}').collect();
+				let code = u32::from_str_radix(&digits, 16).map_err(|_| invalid())?;
+				let char = char::from_u32(code).ok_or_else(invalid)?;
+
+				let mut buffer = [0; 4];
+				bytes.extend_from_slice(char.encode_utf8(&mut buffer).as_bytes());
+			}
+			digit if digit.is_ascii_digit() => {
+				let mut code = digit.to_digit(10).unwrap_or_default();
+
+				for _ in 0..2 {
+					match chars.next_if(char::is_ascii_digit) {
+						Some(digit) => code = code * 10 + digit.to_digit(10).unwrap_or_default(),
+						None => break,
+					}
+				}
+
+				bytes.push(u8::try_from(code).map_err(|_| invalid())?);
+			}
+			_ => return Err(invalid()),
+		}
+	}
+
+	String::from_utf8(bytes).map_err(|_| Error::Lua(format!("string \"{}\" is not UTF-8", literal)))
+}
+
+fn unsupported(expression: &Expression) -> Error {
+	Error::Lua(format!(
+		"unsupported expression `{}`, only literals and tables are allowed",
+		expression.to_string().trim()
+	))
+}
+
+#[cfg(test)]
+mod test {
+	use toml::{from_str, Table};
+
+	use super::lua_to_toml;
+	use crate::{parse, parse_pretty, parse_with_options, Error, ParseOptions, Wrap};
+
+	const DOCUMENTS: &[&str] = &[
+		r#"
+		string = "str"
+		int = 420
+		float = 6.9
+		bool = true
+		array = ["string", 12345, false]
+
+		[object]
+		key = "value"
+"#,
+		r#"
+		escaped = "quote \" backslash \\ tab \t newline \n unicode é"
+		"key with spaces" = -5
+		negative = -0.5
+
+		[[items]]
+		name = "sword"
+		tags = ["sharp"]
+
+		[[items]]
+		name = "bow"
+		nested = { deep = { deeper = [[1, 2], [3]] } }
+
+		[numbers]
+		min = -9223372036854775808
+		infinite = [inf, -inf]
+"#,
+	];
+
+	#[test]
+	fn round_trip() {
+		let local = ParseOptions {
+			wrap: Wrap::Local(String::from("config")),
+			bare_keys: true,
+			..Default::default()
+		};
+
+		for toml in DOCUMENTS {
+			let original: Table = from_str(toml).unwrap();
+
+			for lua in [
+				parse(toml).unwrap(),
+				parse_pretty(toml).unwrap(),
+				parse_with_options(toml, &ParseOptions::pretty()).unwrap(),
+				parse_with_options(toml, &local).unwrap(),
+			] {
+				let converted: Table = from_str(&lua_to_toml(&lua).unwrap()).unwrap();
+
+				assert_eq!(converted, original, "{}", lua);
+			}
+		}
+	}
+
+	#[test]
+	fn escapes() {
+		let lua = r#"{ a = 'single', b = "\65\x42\u{43}D", c = [[
+long]], d = {} }"#;
+
+		assert_eq!(
+			lua_to_toml(lua).unwrap(),
+			"a = \"single\"\nb = \"ABCD\"\nc = \"long\"\n\n[d]\n"
+		);
+	}
+
+	#[test]
+	fn unsupported() {
+		for lua in [
+			"return { a = foo() }",
+			"return { a = nil }",
+			"return { a = 1 + 2 }",
+			"return { [1] = true }",
+			"return { 1, a = 2 }",
+			"return { 1, 2 }",
+			"print('hi')",
+			"return {",
+		] {
+			assert!(matches!(lua_to_toml(lua), Err(Error::Lua(_))), "{}", lua);
+		}
+	}
+}
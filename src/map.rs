@@ -0,0 +1,46 @@
+#[cfg(not(feature = "preserve_order"))]
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+#[cfg(feature = "preserve_order")]
+use indexmap::IndexMap;
+use toml::Value;
+
+/// Ordered map of TOML values, keys keep the order they were inserted in
+///
+/// Same as `IndexMap<String, Value>` with the `std` feature, without it the
+/// map uses the deterministic `FnvHasher` as no random seed is available
+#[cfg(all(feature = "preserve_order", feature = "std"))]
+pub type Map = IndexMap<String, Value>;
+
+/// Ordered map of TOML values, keys keep the order they were inserted in
+///
+/// Same as `IndexMap<String, Value>` with the `std` feature, without it the
+/// map uses the deterministic [`FnvHasher`] as no random seed is available
+#[cfg(all(feature = "preserve_order", not(feature = "std")))]
+pub type Map = IndexMap<String, Value, core::hash::BuildHasherDefault<FnvHasher>>;
+
+/// Map of TOML values sorted by key, used when the `preserve_order` feature is disabled
+#[cfg(not(feature = "preserve_order"))]
+pub type Map = BTreeMap<String, Value>;
+
+/// 64-bit FNV-1a hasher used by [`Map`] when the `std` feature is disabled
+pub struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+	fn default() -> Self {
+		Self(0xcbf2_9ce4_8422_2325)
+	}
+}
+
+impl core::hash::Hasher for FnvHasher {
+	fn finish(&self) -> u64 {
+		self.0
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 ^= u64::from(*byte);
+			self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+		}
+	}
+}
@@ -0,0 +1,13 @@
+use alloc::string::String;
+use serde::Serialize;
+
+/// Line of the generated Lua a TOML value was written to, see [`parse_with_source_map`](crate::parse_with_source_map)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Mapping {
+	/// Line of the Lua output the value is on, starting at 1
+	pub lua_line: usize,
+	/// Line of the TOML source the value is on, starting at 1
+	pub toml_line: usize,
+	/// Dotted path of the value, array items are keyed by their index starting at 1
+	pub key_path: String,
+}
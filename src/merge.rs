@@ -0,0 +1,126 @@
+use toml::Value;
+
+use crate::Map;
+
+/// Where keys present in both maps end up after [`merge_maps`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeOrder {
+	/// Key keeps the position it had in the first map
+	#[default]
+	FirstSeen,
+	/// Key moves to the position it has in the second map
+	LastSeen,
+}
+
+/// Merge two TOML maps, values from `b` replace values from `a`
+///
+/// `order` has no effect without the `preserve_order` feature, keys are then always sorted
+///
+/// ```rust
+/// use toml::Value;
+/// use toml2lua::{merge_maps, Map, MergeOrder};
+///
+/// let a = Map::from_iter([
+/// 	(String::from("x"), Value::from(1)),
+/// 	(String::from("y"), Value::from(2)),
+/// ]);
+///
+/// let b = Map::from_iter([
+/// 	(String::from("x"), Value::from(3)),
+/// ]);
+///
+/// let merged = merge_maps(a, b, MergeOrder::LastSeen);
+///
+/// # #[cfg(feature = "preserve_order")]
+/// assert_eq!(merged.keys().collect::<Vec<_>>(), ["y", "x"]);
+/// assert_eq!(merged["x"], Value::from(3));
+/// ```
+#[cfg_attr(not(feature = "preserve_order"), allow(unused_variables))]
+pub fn merge_maps(mut a: Map, b: Map, order: MergeOrder) -> Map {
+	for (key, value) in b {
+		#[cfg(feature = "preserve_order")]
+		if order == MergeOrder::LastSeen {
+			a.shift_remove(&key);
+		}
+
+		a.insert(key, value);
+	}
+
+	a
+}
+
+/// Merge `b` into `a`, tables present in both are merged key by key and
+/// every other value from `b` replaces the one from `a`
+///
+/// Keys present in both keep the position they had in `a`
+pub(crate) fn merge_deep(a: &mut Map, b: Map) {
+	for (key, value) in b {
+		match (a.get_mut(&key), value) {
+			(Some(Value::Table(a)), Value::Table(b)) => merge_tables(a, b),
+			(_, value) => {
+				a.insert(key, value);
+			}
+		}
+	}
+}
+
+fn merge_tables(a: &mut toml::Table, b: toml::Table) {
+	for (key, value) in b {
+		match (a.get_mut(&key), value) {
+			(Some(Value::Table(a)), Value::Table(b)) => merge_tables(a, b),
+			(_, value) => {
+				a.insert(key, value);
+			}
+		}
+	}
+}
+
+#[cfg(all(test, feature = "preserve_order"))]
+mod test {
+	use toml::Value;
+
+	use crate::Map;
+
+	fn maps() -> (Map, Map) {
+		let a = Map::from_iter([
+			(String::from("name"), Value::from("first")),
+			(String::from("port"), Value::from(80)),
+			(String::from("debug"), Value::from(false)),
+		]);
+
+		let b = Map::from_iter([
+			(String::from("host"), Value::from("localhost")),
+			(String::from("name"), Value::from("second")),
+		]);
+
+		(a, b)
+	}
+
+	#[test]
+	fn first_seen() {
+		use super::{merge_maps, MergeOrder};
+
+		let (a, b) = maps();
+		let merged = merge_maps(a, b, MergeOrder::FirstSeen);
+
+		assert_eq!(
+			merged.keys().collect::<Vec<_>>(),
+			["name", "port", "debug", "host"]
+		);
+		assert_eq!(merged["name"], Value::from("second"));
+	}
+
+	#[test]
+	fn last_seen() {
+		use super::{merge_maps, MergeOrder};
+
+		let (a, b) = maps();
+		let merged = merge_maps(a, b, MergeOrder::LastSeen);
+
+		assert_eq!(
+			merged.keys().collect::<Vec<_>>(),
+			["port", "debug", "host", "name"]
+		);
+		assert_eq!(merged["name"], Value::from("second"));
+	}
+}
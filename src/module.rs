@@ -0,0 +1,185 @@
+use mlua::{Lua, MultiValue, Result as LuaResult, Table as LuaTable, Value as LuaValue};
+use toml::Value;
+
+use crate::{parse_with_options, Map, ParseOptions};
+
+/// Table of functions making up the `toml2lua` Lua module
+///
+/// - `toml2lua.parse(src)` returns the document as a Lua table
+/// - `toml2lua.to_lua(src [, options])` returns the Lua source of the document,
+///   `options` is a TOML string in the format of `toml2lua.toml`
+///
+/// Both return `nil, message` when the conversion fails. With the `module`
+/// feature this table is what `require("toml2lua")` returns, it can also be
+/// registered in a [`Lua`] state of the host application:
+///
+/// ```rust
+/// let lua = mlua::Lua::new();
+/// let open = lua.create_function(|lua, ()| toml2lua::lua_module(lua)).unwrap();
+///
+/// lua.load_from_function::<mlua::Table>("toml2lua", open).unwrap();
+/// lua.load(r#"assert(require("toml2lua").parse("key = 1").key == 1)"#)
+/// 	.exec()
+/// 	.unwrap();
+/// ```
+pub fn lua_module(lua: &Lua) -> LuaResult<LuaTable> {
+	let module = lua.create_table()?;
+
+	module.set(
+		"parse",
+		lua.create_function(|lua, src: mlua::String| {
+			let map = utf8(&src).and_then(|src| crate::locate::from_str::<Map>(&src));
+
+			match map {
+				Ok(map) => ok(table(lua, map.iter())?),
+				Err(err) => failed(lua, err),
+			}
+		})?,
+	)?;
+
+	module.set(
+		"to_lua",
+		lua.create_function(
+			|lua, (src, options): (mlua::String, Option<mlua::String>)| {
+				let options = match options {
+					Some(options) => {
+						match utf8(&options)
+							.and_then(|options| ParseOptions::from_toml_str(&options))
+						{
+							Ok(options) => options,
+							Err(err) => return failed(lua, err),
+						}
+					}
+					None => ParseOptions::default(),
+				};
+
+				match utf8(&src).and_then(|src| parse_with_options(&src, &options)) {
+					Ok(source) => ok(LuaValue::String(lua.create_string(source)?)),
+					Err(err) => failed(lua, err),
+				}
+			},
+		)?,
+	)?;
+
+	Ok(module)
+}
+
+/// Entry point of the native module, `luaopen_toml2lua`
+#[cfg(feature = "module")]
+#[mlua::lua_module(name = "toml2lua")]
+fn open(lua: &Lua) -> LuaResult<LuaTable> {
+	lua_module(lua)
+}
+
+fn ok(value: LuaValue) -> LuaResult<MultiValue> {
+	Ok(MultiValue::from_iter([value]))
+}
+
+/// Lua convention for expected failures, `nil` followed by the message
+fn failed(lua: &Lua, err: crate::Error) -> LuaResult<MultiValue> {
+	Ok(MultiValue::from_iter([
+		LuaValue::Nil,
+		LuaValue::String(lua.create_string(err.to_string())?),
+	]))
+}
+
+/// Text of a Lua string, which can hold any bytes
+fn utf8(string: &mlua::String) -> Result<String, crate::Error> {
+	core::str::from_utf8(&string.as_bytes())
+		.map(String::from)
+		.map_err(|err| crate::Error::InvalidUtf8 {
+			offset: err.valid_up_to(),
+		})
+}
+
+fn table<'v>(
+	lua: &Lua,
+	entries: impl Iterator<Item = (&'v String, &'v Value)>,
+) -> LuaResult<LuaValue> {
+	let table = lua.create_table()?;
+
+	for (key, value) in entries {
+		table.raw_set(key.as_str(), value_to_lua(lua, value)?)?;
+	}
+
+	Ok(LuaValue::Table(table))
+}
+
+fn value_to_lua(lua: &Lua, value: &Value) -> LuaResult<LuaValue> {
+	Ok(match value {
+		Value::String(string) => LuaValue::String(lua.create_string(string)?),
+		Value::Integer(integer) => LuaValue::Integer(*integer),
+		Value::Float(float) => LuaValue::Number(*float),
+		Value::Boolean(boolean) => LuaValue::Boolean(*boolean),
+		Value::Datetime(datetime) => LuaValue::String(lua.create_string(datetime.to_string())?),
+		Value::Array(array) => {
+			let table = lua.create_table_with_capacity(array.len(), 0)?;
+
+			for item in array {
+				table.raw_push(value_to_lua(lua, item)?)?;
+			}
+
+			LuaValue::Table(table)
+		}
+		Value::Table(nested) => table(lua, nested.iter())?,
+	})
+}
+
+#[cfg(all(test, any(feature = "verify", feature = "validate")))]
+mod test {
+	use mlua::Lua;
+
+	use super::lua_module;
+
+	#[test]
+	fn module() {
+		let lua = Lua::new();
+
+		let open = lua.create_function(|lua, ()| lua_module(lua)).unwrap();
+		lua.load_from_function::<mlua::Table>("toml2lua", open)
+			.unwrap();
+
+		lua.load(
+			r#"
+			local toml2lua = require("toml2lua")
+
+			local config, err = toml2lua.parse([[
+			name = "server"
+			ports = [80, 443]
+			ratio = 0.5
+			started = 1979-05-27T07:32:00Z
+
+			[limits]
+			memory = 512
+			]])
+
+			assert(err == nil, err)
+			assert(config.name == "server")
+			assert(#config.ports == 2 and config.ports[2] == 443)
+			assert(math.type(config.ports[1]) == "integer")
+			assert(math.type(config.ratio) == "float")
+			assert(config.started == "1979-05-27T07:32:00Z")
+			assert(config.limits.memory == 512)
+
+			local source = assert(toml2lua.to_lua("key = 1", "bare_keys = true"))
+			assert(source == "{\n\tkey = 1,\n}", source)
+			assert(load("return " .. source)().key == 1)
+
+			local value, message = toml2lua.parse("key =")
+			assert(value == nil and type(message) == "string" and #message > 0)
+
+			value, message = toml2lua.to_lua("key = 1", "bare_key = true")
+			assert(value == nil and type(message) == "string")
+
+			-- Lua strings aren't always UTF-8
+			value, message = toml2lua.parse("key = '\xff'")
+			assert(value == nil and message:find("offset 7"), message)
+			value, message = toml2lua.to_lua("key = 1", "\xff")
+			assert(value == nil and message:find("offset 0"), message)
+			"#,
+		)
+		.set_name("module_test")
+		.exec()
+		.unwrap();
+	}
+}
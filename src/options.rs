@@ -0,0 +1,1764 @@
+use alloc::{borrow::Cow, collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+use core::{cmp::Ordering, fmt, num::NonZeroUsize};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+#[cfg(feature = "std")]
+use crate::Interpolation;
+use crate::{EmmyLuaTypes, Error, LuauTypes, TealTypes};
+use toml::Value;
+
+/// Options controlling how TOML is converted to Lua
+///
+/// ```rust
+/// use toml2lua::{Indent, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	indent: Indent::Spaces(2),
+/// 	bare_keys: true,
+/// 	..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParseOptions {
+	/// Indentation used for each nesting level
+	pub indent: Indent,
+	/// Write keys that are valid Lua identifiers as `key = ` instead of `["key"] = `
+	pub bare_keys: bool,
+	/// Called with every key that is a valid Lua identifier when `bare_keys` is
+	/// enabled, returning whether it is written bare. Keys that aren't
+	/// identifiers are always bracketed. Not loaded from config files
+	#[serde(skip)]
+	pub bare_key_predicate: Option<KeyPredicate>,
+	/// Only write bare keys made of ASCII letters, digits and underscores,
+	/// Lua versions disagree on whether other letters are valid in identifiers
+	pub ascii_identifiers_only: bool,
+	/// Write non-ASCII characters as `\u{XXXX}` escapes, including the U+2028 and U+2029
+	/// separators that some Lua parsers treat as line breaks
+	pub escape_unicode: bool,
+	/// Quotes used for strings and bracketed keys
+	pub quote_style: QuoteStyle,
+	/// Write string values spanning several lines as long brackets like `[[...]]`,
+	/// strings with control characters other than newlines and tabs stay quoted
+	pub long_strings: bool,
+	/// Write empty tables and arrays as `{}` instead of spreading the braces over two lines
+	pub compact_empty: bool,
+	/// How documents without any keys, including whitespace or comments only, are written
+	pub empty_document: EmptyMode,
+	/// Write arrays on a single line like `{ 1, 2, 3 }` when the line, including
+	/// indentation, key and separator, fits in this many columns with tabs counting as 4.
+	/// Arrays containing tables are always spread over several lines
+	pub max_width: Option<usize>,
+	/// Write arrays of scalars that aren't written on a single line with this
+	/// many items per line. Arrays holding tables or arrays, or items with
+	/// comments, keep one item per line
+	pub array_wrap_at: Option<NonZeroUsize>,
+	/// Write tables and arrays with more entries than this as a function building
+	/// them this many entries at a time, so loading huge documents doesn't exceed
+	/// the constant and constructor limits of a single Lua function
+	pub chunking: Option<NonZeroUsize>,
+	/// Give every array an `n` field holding its length, like the tables returned
+	/// by `table.pack`, so the length survives holes
+	pub packed_arrays: bool,
+	/// Treat empty tables inside arrays as holes, they are left out and the items
+	/// after them are written with explicit indices like `[3] = value` to keep their position
+	pub sparse_arrays: bool,
+	/// Write every array item with its 1-based index like `[1] = value` instead
+	/// of by position. Arrays are then never written on a single line, arrays
+	/// split by `chunking` keep positional items
+	pub explicit_array_indices: bool,
+	/// Pad the keys of every table so their `=` line up in a column, aligned
+	/// separately for each table and not applied to array items
+	pub align_assignments: bool,
+	/// Join the braces of arrays holding only tables with the ones of their items,
+	/// like `{{ ... }, { ... }}`, indenting the entries of the items one level less
+	pub flatten_array_of_tables_indent: bool,
+	/// Put the opening brace of keyed tables and arrays on its own line
+	pub brace_on_new_line: bool,
+	/// Put a separator after the last entry of every table
+	pub trailing_commas: bool,
+	/// Character separating table entries
+	pub separator: Separator,
+	/// Separate top-level tables from preceding entries with a blank line
+	pub section_spacing: bool,
+	/// End the output with a newline
+	pub final_newline: bool,
+	/// Line break every line of the output ends with
+	pub newline: Newline,
+	/// How integers and floats are written
+	pub number_mode: NumberMode,
+	/// What happens to integers beyond 2^53, which Lua versions without an
+	/// integer type (5.1, LuaJIT and Luau) can't represent exactly. Allowed ones
+	/// are reported as warnings by [`parse_with_warnings`](crate::parse_with_warnings)
+	/// unless the `target` has integers
+	pub integer_overflow: IntegerOverflow,
+	/// Radix integers are written in, ignored for integers given by `integer_format`
+	pub integer_radix: Radix,
+	/// Write long arrays of only integers or only floats as a hexadecimal string
+	/// of their packed bytes decoded back into an array when loaded. Needs Lua 5.3+ or Luau
+	pub pack_numbers: Option<PackNumbers>,
+	/// Write floats in hexadecimal like `0x1.999999999999ap-4`, which reads back
+	/// bit for bit. Needs Lua 5.2+ or LuaJIT, Lua 5.1 and Luau can't load them
+	pub hex_floats: bool,
+	/// Called with every integer written, returning the literal written in
+	/// place of the decimal one. Not loaded from config files
+	#[serde(skip)]
+	pub integer_format: Option<IntegerFormat>,
+	/// Called with every float written, returning the literal written in
+	/// place of the default one, takes precedence over `hex_floats`. Not loaded from config files
+	#[serde(skip)]
+	pub float_format: Option<FloatFormat>,
+	/// How booleans are written
+	pub bool_style: BoolStyle,
+	/// Substitute `${VAR}` placeholders in string values before they are escaped
+	#[cfg(feature = "std")]
+	pub interpolation: Option<Interpolation>,
+	/// Called with the key path and value of every scalar before it's written,
+	/// returning a replacement or `None` to keep the value. Not loaded from config files
+	#[serde(skip)]
+	pub value_transform: Option<ValueTransform>,
+	/// Write strings holding an integer, float or boolean literal, like `"8080"`
+	/// or `"true"`, as the value they spell. Every coercion is reported as a warning
+	/// by [`parse_with_warnings`](crate::parse_with_warnings)
+	pub coerce: Option<Coerce>,
+	/// Hide the values at the given key paths, like passwords and tokens. Every
+	/// redacted value is reported as a warning by [`parse_with_warnings`](crate::parse_with_warnings)
+	pub redact: Option<Redact>,
+	/// Lua expressions written verbatim instead of the scalars at the given dotted
+	/// key paths, like `timeout = "os.getenv(\"TIMEOUT\")"`, with `items.1` for the
+	/// first item of an array and `"example.com".port` for a key holding a `.`. Nothing checks that they are valid Lua. Paths matching
+	/// no value are reported as warnings by [`parse_with_warnings`](crate::parse_with_warnings)
+	pub raw_expressions: BTreeMap<String, String>,
+	/// How datetimes are written, see [`DatetimeKind`](crate::DatetimeKind) for their forms
+	pub datetime_mode: DatetimeMode,
+	/// What happens to datetimes the `datetime_mode` can't express
+	pub datetime_fallback: DatetimeFallback,
+	/// Write datetimes written as strings as a call to this function, e.g.
+	/// `DateTime("1979-05-27")`, instead of a plain string
+	pub datetime_wrapper: Option<String>,
+	/// Convert offset datetimes to UTC before the `datetime_mode` writes them,
+	/// so `1979-05-27T00:32:00-07:00` becomes `1979-05-27T07:32:00Z`
+	pub normalize_datetimes_utc: bool,
+	/// What happens to local datetimes and times when `normalize_datetimes_utc`
+	/// is enabled, lacking an offset they can't be converted
+	pub local_datetimes: LocalDatetimes,
+	/// String values equal to this are written as `nil`, standing in for the
+	/// null TOML lacks. Arrays holding it return [`Error::NilInArray`] unless
+	/// `sparse_arrays` is enabled, which leaves it out like other holes
+	pub nil_sentinel: Option<String>,
+	/// How table entries holding the `nil_sentinel` are written
+	pub nil_entries: NilEntries,
+	/// How empty strings are written. As `nil` they follow the rules of the
+	/// `nil_sentinel`, holes in arrays included
+	pub empty_string: EmptyStringMode,
+	/// Whitespace of string values canonicalized before they are written, keys are left as they are
+	pub string_whitespace: WhitespacePolicy,
+	/// Comments written above the entries at the given dotted key paths, like
+	/// `server.port` or `items.1` for the first item of an array, one `--` line per
+	/// line of the text. Paths matching no entry are reported as warnings by
+	/// [`parse_with_warnings`](crate::parse_with_warnings)
+	pub doc_comments: BTreeMap<String, String>,
+	/// Follow every scalar with a comment naming its TOML type, like `-- integer`
+	pub type_comments: bool,
+	/// Write a comment listing the fields above arrays of tables that all have
+	/// the same fields, like `-- name, age, active`, in the order of the first table
+	pub columnar_comments: bool,
+	/// How the comments above entries are written, the ones of `doc_comments`
+	/// and the ones kept from the source by [`parse_with_comments`](crate::parse_with_comments)
+	pub comment_style: CommentStyle,
+	/// Add a field tagging every nested table with its key, see [`TypeTags`]
+	pub type_tags: Option<TypeTags>,
+	/// Case keys are converted to, words are separated by `_`, `-`, spaces and case changes
+	pub key_case: KeyCase,
+	/// Called with pairs of keys of every table, ordering its entries instead of
+	/// the document order. Keys are the ones of the document, before `key_case`,
+	/// and `order_metadata` records the resulting order. Not loaded from config files
+	#[serde(skip)]
+	pub key_order: Option<KeyOrder>,
+	/// Group the entries of every table by whether their value is a scalar, an
+	/// array or a table, entries of a group keep their order, including the one of `key_order`
+	pub group_order: GroupOrder,
+	/// Same as `group_order` set to [`GroupOrder::ScalarsFirst`], used when `group_order` is `None`
+	#[deprecated(note = "use `group_order: GroupOrder::ScalarsFirst` instead")]
+	pub group_scalars_first: bool,
+	/// What happens to keys of a table that are written as the same Lua key,
+	/// like `my_key` and `my-key` with `key_case` or `"a.b"` and `a.b` with `flatten`
+	pub key_collisions: KeyCollisions,
+	/// Report keys of a table that only differ by case, like `Name` and `name`,
+	/// as warnings by [`parse_with_warnings`](crate::parse_with_warnings). Both are still written
+	pub warn_case_collisions: bool,
+	/// Write tables whose keys are exactly `"1"` to `"n"` as arrays
+	pub numeric_keys_as_array: bool,
+	/// Dotted paths of tables always written as arrays, their keys must be `"1"` to `"n"`
+	pub force_array_paths: Vec<String>,
+	/// Dotted paths of tables never written as arrays, takes precedence over `force_array_paths`
+	pub force_map_paths: Vec<String>,
+	/// Dotted paths of arrays of tables written as tables keyed by the given
+	/// field of every entry, e.g. `items = "id"`. String fields become string
+	/// keys and integer fields integer keys
+	pub key_by: BTreeMap<String, String>,
+	/// Leave the field arrays listed in `key_by` are keyed by out of their entries
+	pub key_by_remove_field: bool,
+	/// Dotted paths of arrays of tables whose entries are sorted by a field
+	/// before they are written, see [`SortBy`]. Arrays also listed in `key_by`
+	/// are sorted before being keyed
+	pub sort_arrays_by: BTreeMap<String, SortBy>,
+	/// Keep keys of data converted from JSON recognizable as map keys: every key
+	/// is written as a string like `["12"]`, taking precedence over `bare_keys`,
+	/// and tables are never written as arrays, taking precedence over `numeric_keys_as_array`
+	pub json_compat: bool,
+	/// Tell empty arrays and empty tables apart once loaded, for JSON encoders
+	/// like dkjson that read the `__jsontype` metatable field: empty arrays are
+	/// written as `setmetatable({}, { __jsontype = "array" })` and empty tables
+	/// with `"object"`. Other tables need no marker, their keys tell them apart
+	pub json_bridge: bool,
+	/// Write every document as a single-level table of its leaf values keyed by
+	/// their joined path, e.g. `["object.key"]`. Keys joined into the same path,
+	/// like `"a.b"` and `a.b`, are handled by `key_collisions`
+	pub flatten: bool,
+	/// Flatten arrays too when `flatten` is enabled, keying elements by their
+	/// index like `["list.1"]` instead of keeping arrays as values
+	pub flatten_arrays: bool,
+	/// Separator joining path segments when `flatten` is enabled
+	pub flatten_separator: String,
+	/// Record the original key order of every table, making `__order` a reserved key
+	pub order_metadata: OrderMetadata,
+	/// Wrap tables in Luau's `table.freeze`, which does not exist in other Lua versions
+	pub freeze: Freeze,
+	/// Language the document is written in
+	pub dialect: Dialect,
+	/// Statement the root table is wrapped in
+	pub wrap: Wrap,
+	/// Write a module holding the table as `M.data` together with accessor
+	/// functions, see [`AccessorModule`]. Takes precedence over `wrap`
+	pub accessor_module: Option<AccessorModule>,
+	/// Follow the table with a local function of this name returning the value
+	/// of a top-level key, like `get("title")`. The table is written as
+	/// `local data = { ... }`, or under the name of [`Wrap::Local`], and with
+	/// [`Wrap::Return`] the function is returned in its place. The name is written verbatim
+	pub with_accessor: Option<String>,
+	/// Write every top-level table of a document as its own `local` and
+	/// `return` a table assembling them, implies [`Wrap::Return`]. Only applies
+	/// to single documents, not [`parse_many`](crate::parse_many) or directories.
+	/// Lua functions can have 200 locals, so past 180 locals, counting the
+	/// ones of `dedupe`, the remaining tables are written in the returned table
+	pub split_locals: bool,
+	/// Write only the value of documents with a single top-level key, e.g. `42`
+	/// for `value = 42`, instead of a table holding it. Only applies to single
+	/// documents and takes precedence over `split_locals` and `flatten`
+	pub unwrap_single: bool,
+	/// Write tables and strings occurring more than once as `local __shared_N`
+	/// declarations above the root table, referred to by name wherever they
+	/// occur, implies [`Wrap::Return`]. Off by default since the uses then share
+	/// a single table, see [`Dedupe`]. Only applies to single documents
+	pub dedupe: Option<Dedupe>,
+	/// Comment written above everything else, marking the file as generated
+	pub header: Option<Header>,
+	/// Add a hash of the source document to the header when the source is known
+	pub header_hash: bool,
+	/// End the output with a `-- sha256:<hex>` comment holding the SHA-256 hash
+	/// of the source document when it is known, for build systems regenerating
+	/// Lua only when the TOML changed
+	#[cfg(feature = "content_hash")]
+	pub content_hash: bool,
+	/// Lua version the output must be valid for, `None` allows everything the
+	/// other options enable
+	pub target: Option<LuaTarget>,
+	/// How [`parse_to_luau_bytecode`](crate::parse_to_luau_bytecode) compiles the output
+	#[cfg(feature = "luau")]
+	pub luau_bytecode: LuauBytecode,
+	/// Nest the whole document under this key of an outer table
+	pub root_key: Option<String>,
+	/// Lua expression written verbatim as the metatable of the outermost table,
+	/// e.g. `{ __index = Defaults }` for `setmetatable({ ... }, { __index = Defaults })`
+	pub root_metatable: Option<String>,
+	/// Protect the outermost table against changes at runtime, see [`ReadonlyMode`]
+	pub readonly: ReadonlyMode,
+	/// Emit a Luau type declaration inferred from the document above the table
+	pub luau_types: Option<LuauTypes>,
+	/// Emit lua-language-server `---@class` annotations inferred from the document
+	/// above the table and annotate the table with `---@type`
+	pub emmylua_types: Option<EmmyLuaTypes>,
+	/// Emit a Teal record inferred from the document above the table and annotate the table with it
+	pub teal_types: Option<TealTypes>,
+	/// How invalid UTF-8 is handled by [`parse_bytes_with_options`](crate::parse_bytes_with_options)
+	pub utf8_mode: Utf8Mode,
+	/// Return [`Error::DuplicateKey`](crate::Error::DuplicateKey) when two keys of the same
+	/// table are written as the same Lua key, like keys converted by `key_case`.
+	/// Takes precedence over `key_collisions`
+	pub check_duplicate_keys: bool,
+	/// Return an error when an array mixes elements of different types,
+	/// arrays of tables are not compared any deeper
+	pub homogeneous_arrays: bool,
+	/// Let integers and floats share an array when `homogeneous_arrays` is enabled
+	pub mixed_numbers: bool,
+	/// Abort with an error once the output grows past this many bytes
+	pub max_output_bytes: Option<usize>,
+	/// Fail with [`Error::TooDeep`](crate::Error::TooDeep) on tables and arrays
+	/// nested deeper than this, the root table is at depth 1
+	pub max_depth: Option<usize>,
+	/// Fail with [`Error::DeniedWarning`](crate::Error::DeniedWarning) instead
+	/// of reporting the first warning of the conversion
+	pub deny_warnings: bool,
+	/// Follow every scalar with a `-- toml:<line>:<column>` comment pointing
+	/// at it in the source, when converting TOML text
+	#[cfg(feature = "comments")]
+	pub source_comments: bool,
+	/// Write tables that are inline in the source, like `point = { x = 1, y = 2 }`,
+	/// on a single line as long as the line fits in `max_width`, when converting
+	/// TOML text. `[section]` tables stay spread over several lines
+	#[cfg(feature = "comments")]
+	pub keep_inline_tables: bool,
+}
+
+impl ParseOptions {
+	/// Preset producing readable output, used by [`parse_pretty`](crate::parse_pretty)
+	///
+	/// Sets `indent` to 4 spaces and enables `bare_keys`, `section_spacing`
+	/// and `final_newline` while disabling `trailing_commas`
+	pub fn pretty() -> Self {
+		Self {
+			indent: Indent::Spaces(4),
+			bare_keys: true,
+			trailing_commas: false,
+			section_spacing: true,
+			final_newline: true,
+			..Default::default()
+		}
+	}
+
+	/// Preset matching StyLua's default formatting, so running StyLua over the output changes nothing
+	///
+	/// Checked against StyLua 2.6 with its default tab indentation and
+	/// `AutoPreferDouble` quotes. Wraps the table in `return` since a bare table
+	/// is not a valid chunk, writes empty tables as `{}` and ends with a newline
+	pub fn stylua_default() -> Self {
+		Self {
+			quote_style: QuoteStyle::PreferDouble,
+			compact_empty: true,
+			final_newline: true,
+			wrap: Wrap::Return,
+			..Default::default()
+		}
+	}
+}
+
+impl ParseOptions {
+	/// Load options from the contents of a config file like `toml2lua.toml`
+	///
+	/// Fields are named like the struct fields and enum variants are written in
+	/// snake case, missing fields keep their default and unknown ones are rejected
+	///
+	/// ```rust
+	/// use toml2lua::{Indent, ParseOptions, Wrap};
+	///
+	/// let options = ParseOptions::from_toml_str(r#"
+	/// bare_keys = true
+	/// indent = { spaces = 2 }
+	/// wrap = "return"
+	/// "#).unwrap();
+	///
+	/// assert_eq!(options.indent, Indent::Spaces(2));
+	/// assert_eq!(options.wrap, Wrap::Return);
+	///
+	/// assert!(ParseOptions::from_toml_str("bare_key = true").is_err());
+	/// ```
+	pub fn from_toml_str(toml: &str) -> Result<Self, Error> {
+		crate::locate::from_str(toml)
+	}
+
+	/// Load options from [`CONFIG_FILE`](Self::CONFIG_FILE) in the given
+	/// directory, or the defaults if there is no such file
+	#[cfg(feature = "std")]
+	pub fn discover(dir: &Path) -> Result<Self, Error> {
+		let path = dir.join(Self::CONFIG_FILE);
+
+		if path.is_file() {
+			Self::from_toml_str(&crate::read_file(&path)?)
+		} else {
+			Ok(Self::default())
+		}
+	}
+
+	/// Name of the config file looked up by [`discover`](Self::discover)
+	pub const CONFIG_FILE: &'static str = "toml2lua.toml";
+
+	/// Defaults suited for the given Lua version
+	///
+	/// Integers that would lose precision are rejected on versions without an
+	/// integer type. Fields can still be overridden, but options producing output
+	/// the target can't load, like `freeze` outside of Luau, return an error
+	pub fn for_target(target: LuaTarget) -> Self {
+		Self {
+			target: Some(target),
+			integer_overflow: if target.has_integers() {
+				IntegerOverflow::Allow
+			} else {
+				IntegerOverflow::Error
+			},
+			..Default::default()
+		}
+	}
+
+	/// Check that the enabled options produce output valid for [`target`](Self::target)
+	pub(crate) fn check_target(&self) -> Result<(), Error> {
+		let Some(target) = self.target else {
+			return Ok(());
+		};
+
+		let luau = target == LuaTarget::Luau;
+
+		let feature = if !luau && self.freeze != Freeze::Off {
+			"`table.freeze`"
+		} else if !luau && self.luau_types.is_some() {
+			"type annotations"
+		} else if self.hex_floats && !target.has_hex_floats() {
+			"hexadecimal floats"
+		} else if !luau && self.dedupe.as_ref().is_some_and(|dedupe| dedupe.clone) {
+			"`table.clone`"
+		} else if !luau && !target.has_integers() && self.pack_numbers.is_some() {
+			"`string.unpack`"
+		} else {
+			return Ok(());
+		};
+
+		Err(Error::Unsupported { target, feature })
+	}
+
+	/// Order of the groups of entries, including the deprecated `group_scalars_first`
+	#[allow(deprecated)]
+	pub(crate) fn group_order(&self) -> GroupOrder {
+		if self.group_order == GroupOrder::None && self.group_scalars_first {
+			GroupOrder::ScalarsFirst
+		} else {
+			self.group_order
+		}
+	}
+
+	/// Check that the value written alone by `unwrap_single` can be given the
+	/// metatable of the outermost table, which only tables can have
+	pub(crate) fn check_unwrapped(&self, value: &Value) -> Result<(), Error> {
+		if matches!(value, Value::Table(_) | Value::Array(_)) {
+			return Ok(());
+		}
+
+		let second = if self.root_metatable.is_some() {
+			"`root_metatable`"
+		} else if self.readonly == ReadonlyMode::Metatable {
+			"`ReadonlyMode::Metatable`"
+		} else {
+			return Ok(());
+		};
+
+		Err(Error::ConflictingOptions {
+			first: "`unwrap_single`",
+			second,
+		})
+	}
+
+	/// Check that no two enabled options contradict each other
+	pub(crate) fn check_conflicts(&self) -> Result<(), Error> {
+		if self.json_compat && !self.force_array_paths.is_empty() {
+			return Err(Error::ConflictingOptions {
+				first: "`json_compat`",
+				second: "`force_array_paths`",
+			});
+		}
+
+		if self.json_bridge && self.order_metadata == OrderMetadata::Metatable {
+			return Err(Error::ConflictingOptions {
+				first: "`json_bridge`",
+				second: "`order_metadata = \"metatable\"`",
+			});
+		}
+
+		if self.chunking.is_some() {
+			let second = if self.order_metadata != OrderMetadata::None {
+				"`order_metadata`"
+			} else if self.sparse_arrays {
+				"`sparse_arrays`"
+			} else if self.type_tags.is_some() {
+				"`type_tags`"
+			} else {
+				""
+			};
+
+			if !second.is_empty() {
+				return Err(Error::ConflictingOptions {
+					first: "`chunking`",
+					second,
+				});
+			}
+		}
+
+		// Frozen tables and tables with a metatable recording their order can't be given another one
+		let metatable = match (&self.root_metatable, &self.readonly) {
+			(Some(_), ReadonlyMode::Metatable) => {
+				return Err(Error::ConflictingOptions {
+					first: "`root_metatable`",
+					second: "`ReadonlyMode::Metatable`",
+				})
+			}
+			(Some(_), _) => Some("`root_metatable`"),
+			(None, ReadonlyMode::Metatable) => Some("`ReadonlyMode::Metatable`"),
+			(None, _) => None,
+		};
+
+		if let Some(first) = metatable {
+			let second = if self.freeze != Freeze::Off {
+				"`freeze`"
+			} else if self.order_metadata == OrderMetadata::Metatable {
+				"`OrderMetadata::Metatable`"
+			} else {
+				""
+			};
+
+			if !second.is_empty() {
+				return Err(Error::ConflictingOptions { first, second });
+			}
+		}
+
+		#[cfg(feature = "std")]
+		let interpolation = self.interpolation.is_some();
+		#[cfg(not(feature = "std"))]
+		let interpolation = false;
+
+		if self.dialect == Dialect::Fennel {
+			let second = if self.wrap != Wrap::None {
+				"`wrap`"
+			} else if self.accessor_module.is_some() {
+				"`accessor_module`"
+			} else if self.with_accessor.is_some() {
+				"`with_accessor`"
+			} else if self.readonly != ReadonlyMode::None {
+				"`readonly`"
+			} else if self.split_locals {
+				"`split_locals`"
+			} else if !self.raw_expressions.is_empty() {
+				"`raw_expressions`"
+			} else if self.redact.is_some() {
+				"`redact`"
+			} else if self.value_transform.is_some() {
+				"`value_transform`"
+			} else if self.key_case != KeyCase::Preserve {
+				"`key_case`"
+			} else if self.coerce.is_some() {
+				"`coerce`"
+			} else if interpolation {
+				"`interpolation`"
+			} else if self.datetime_mode != DatetimeMode::String {
+				"`datetime_mode`"
+			} else if self.luau_types.is_some()
+				|| self.emmylua_types.is_some()
+				|| self.teal_types.is_some()
+			{
+				"type declarations"
+			} else {
+				""
+			};
+
+			if !second.is_empty() {
+				return Err(Error::ConflictingOptions {
+					first: "`Dialect::Fennel`",
+					second,
+				});
+			}
+		}
+
+		// Type declarations are written in a single language
+		let types = [
+			(self.luau_types.is_some(), "`luau_types`"),
+			(self.emmylua_types.is_some(), "`emmylua_types`"),
+			(self.teal_types.is_some(), "`teal_types`"),
+		];
+
+		if let [(_, first), (_, second), ..] = types
+			.iter()
+			.filter(|(enabled, _)| *enabled)
+			.collect::<Vec<_>>()[..]
+		{
+			return Err(Error::ConflictingOptions { first, second });
+		}
+
+		if self.with_accessor.is_some() && self.accessor_module.is_some() {
+			return Err(Error::ConflictingOptions {
+				first: "`with_accessor`",
+				second: "`accessor_module`",
+			});
+		}
+
+		if !self.split_locals {
+			return Ok(());
+		}
+
+		let second = match self.wrap {
+			Wrap::Local(_) => "`Wrap::Local`",
+			_ if self.flatten => "`flatten`",
+			_ => return Ok(()),
+		};
+
+		Err(Error::ConflictingOptions {
+			first: "`split_locals`",
+			second,
+		})
+	}
+}
+
+impl Default for ParseOptions {
+	#[allow(deprecated)]
+	fn default() -> Self {
+		Self {
+			indent: Indent::Tabs,
+			bare_keys: false,
+			bare_key_predicate: None,
+			ascii_identifiers_only: true,
+			escape_unicode: false,
+			quote_style: QuoteStyle::Double,
+			long_strings: false,
+			compact_empty: false,
+			empty_document: EmptyMode::EmptyTable,
+			max_width: None,
+			array_wrap_at: None,
+			chunking: None,
+			packed_arrays: false,
+			sparse_arrays: false,
+			explicit_array_indices: false,
+			align_assignments: false,
+			flatten_array_of_tables_indent: false,
+			brace_on_new_line: false,
+			trailing_commas: true,
+			separator: Separator::Comma,
+			section_spacing: false,
+			final_newline: false,
+			newline: Newline::Lf,
+			number_mode: NumberMode::Preserve,
+			integer_overflow: IntegerOverflow::Allow,
+			integer_radix: Radix::Decimal,
+			pack_numbers: None,
+			hex_floats: false,
+			integer_format: None,
+			float_format: None,
+			bool_style: BoolStyle::Lua,
+			#[cfg(feature = "std")]
+			interpolation: None,
+			value_transform: None,
+			coerce: None,
+			redact: None,
+			raw_expressions: BTreeMap::new(),
+			datetime_mode: DatetimeMode::String,
+			datetime_fallback: DatetimeFallback::Error,
+			datetime_wrapper: None,
+			normalize_datetimes_utc: false,
+			local_datetimes: LocalDatetimes::Keep,
+			nil_sentinel: None,
+			nil_entries: NilEntries::Keep,
+			empty_string: EmptyStringMode::Keep,
+			string_whitespace: WhitespacePolicy::Preserve,
+			doc_comments: BTreeMap::new(),
+			type_comments: false,
+			columnar_comments: false,
+			comment_style: CommentStyle::Line,
+			type_tags: None,
+			key_case: KeyCase::Preserve,
+			key_collisions: KeyCollisions::Error,
+			warn_case_collisions: false,
+			key_order: None,
+			group_order: GroupOrder::None,
+			group_scalars_first: false,
+			numeric_keys_as_array: false,
+			force_array_paths: Vec::new(),
+			json_compat: false,
+			json_bridge: false,
+			force_map_paths: Vec::new(),
+			key_by: BTreeMap::new(),
+			key_by_remove_field: false,
+			sort_arrays_by: BTreeMap::new(),
+			flatten: false,
+			flatten_arrays: false,
+			flatten_separator: String::from("."),
+			order_metadata: OrderMetadata::None,
+			freeze: Freeze::Off,
+			dialect: Dialect::Lua,
+			wrap: Wrap::None,
+			split_locals: false,
+			unwrap_single: false,
+			dedupe: None,
+			header: None,
+			header_hash: false,
+			#[cfg(feature = "content_hash")]
+			content_hash: false,
+			target: None,
+			#[cfg(feature = "luau")]
+			luau_bytecode: LuauBytecode::default(),
+			root_key: None,
+			root_metatable: None,
+			readonly: ReadonlyMode::None,
+			accessor_module: None,
+			with_accessor: None,
+			luau_types: None,
+			emmylua_types: None,
+			teal_types: None,
+			utf8_mode: Utf8Mode::Strict,
+			check_duplicate_keys: false,
+			homogeneous_arrays: false,
+			mixed_numbers: false,
+			max_output_bytes: None,
+			max_depth: None,
+			deny_warnings: false,
+			#[cfg(feature = "comments")]
+			source_comments: false,
+			#[cfg(feature = "comments")]
+			keep_inline_tables: false,
+		}
+	}
+}
+
+/// Callback replacing scalars before they are written, see [`ParseOptions::value_transform`]
+///
+/// ```rust
+/// use toml::Value;
+/// use toml2lua::{parse_with_options, ParseOptions, ValueTransform};
+///
+/// let options = ParseOptions {
+/// 	value_transform: Some(ValueTransform::new(|path, _| {
+/// 		(path.last() == Some(&"password")).then(|| Value::from("***"))
+/// 	})),
+/// 	..Default::default()
+/// };
+///
+/// assert_eq!(
+/// 	parse_with_options("password = 'hunter2'", &options).unwrap(),
+/// 	"{\n\t[\"password\"] = \"***\",\n}"
+/// );
+/// ```
+#[derive(Clone)]
+pub struct ValueTransform(Arc<TransformFn>);
+
+type TransformFn = dyn Fn(&[&str], &Value) -> Option<Value> + Send + Sync;
+
+impl ValueTransform {
+	/// Wrap a function receiving the key path and value, array items are keyed by their index
+	pub fn new<F>(transform: F) -> Self
+	where
+		F: Fn(&[&str], &Value) -> Option<Value> + Send + Sync + 'static,
+	{
+		Self(Arc::new(transform))
+	}
+
+	pub(crate) fn call(&self, path: &[&str], value: &Value) -> Option<Value> {
+		(self.0)(path, value)
+	}
+}
+
+impl fmt::Debug for ValueTransform {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("ValueTransform(..)")
+	}
+}
+
+/// Callback writing integers, see [`ParseOptions::integer_format`]
+///
+/// The literal must be one the target Lua reads back as the same integer,
+/// e.g. underscores like `1_000_000` are only accepted by Luau
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, IntegerFormat, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	integer_format: Some(IntegerFormat::new(|integer| format!("0x{:X}", integer))),
+/// 	..Default::default()
+/// };
+///
+/// assert_eq!(parse_with_options("color = 16711680", &options).unwrap(), "{\n\t[\"color\"] = 0xFF0000,\n}");
+/// ```
+#[derive(Clone)]
+pub struct IntegerFormat(Arc<dyn Fn(i64) -> String + Send + Sync>);
+
+impl IntegerFormat {
+	pub fn new<F>(format: F) -> Self
+	where
+		F: Fn(i64) -> String + Send + Sync + 'static,
+	{
+		Self(Arc::new(format))
+	}
+
+	pub(crate) fn call(&self, integer: i64) -> String {
+		(self.0)(integer)
+	}
+}
+
+impl fmt::Debug for IntegerFormat {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("IntegerFormat(..)")
+	}
+}
+
+/// Callback writing floats, see [`ParseOptions::float_format`]
+///
+/// The literal must be one the target Lua reads back as a float, like
+/// `1e6` or `1.5`, since `1` would be read as an integer by Lua 5.3+
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, FloatFormat, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	float_format: Some(FloatFormat::new(|float| format!("{:e}", float))),
+/// 	..Default::default()
+/// };
+///
+/// assert_eq!(parse_with_options("speed = 1500.0", &options).unwrap(), "{\n\t[\"speed\"] = 1.5e3,\n}");
+/// ```
+#[derive(Clone)]
+pub struct FloatFormat(Arc<dyn Fn(f64) -> String + Send + Sync>);
+
+impl FloatFormat {
+	pub fn new<F>(format: F) -> Self
+	where
+		F: Fn(f64) -> String + Send + Sync + 'static,
+	{
+		Self(Arc::new(format))
+	}
+
+	pub(crate) fn call(&self, float: f64) -> String {
+		(self.0)(float)
+	}
+}
+
+impl fmt::Debug for FloatFormat {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("FloatFormat(..)")
+	}
+}
+
+/// Settings of [`ParseOptions::accessor_module`]
+///
+/// The table is written as `local data = { ... }` followed by a module `M`
+/// returned in its place. `M.get("server.port")` walks a dotted path, reading
+/// integer segments like `items.1` as array indices, and raises an error naming
+/// the missing key instead of returning `nil`. The module is plain Lua loading
+/// on every target and the table is still reachable as `M.data`
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, AccessorModule, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	accessor_module: Some(AccessorModule { getters: true }),
+/// 	bare_keys: true,
+/// 	..Default::default()
+/// };
+///
+/// let lua = parse_with_options("port = 80", &options).unwrap();
+///
+/// assert!(lua.starts_with("local data = {\n\tport = 80,\n}\n\nlocal M = { data = data }\n"));
+/// assert!(lua.ends_with("function M.port()\n\treturn data.port\nend\n\nreturn M"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccessorModule {
+	/// Also write a function returning the value of every top-level key, like
+	/// `M.server()`. Keys that are not identifiers or are named `get` or `data` get none
+	pub getters: bool,
+}
+
+/// Comparator ordering the entries of tables, see [`ParseOptions::key_order`]
+///
+/// Entries are sorted stably, keys comparing equal keep their document order
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, KeyOrder, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	key_order: Some(KeyOrder::new(|a, b| b.cmp(a))),
+/// 	..Default::default()
+/// };
+///
+/// assert_eq!(
+/// 	parse_with_options("a = 1\nb = 2", &options).unwrap(),
+/// 	"{\n\t[\"b\"] = 2,\n\t[\"a\"] = 1,\n}"
+/// );
+/// ```
+#[derive(Clone)]
+pub struct KeyOrder(Arc<CompareFn>);
+
+type CompareFn = dyn Fn(&str, &str) -> Ordering + Send + Sync;
+
+impl KeyOrder {
+	pub fn new<F>(compare: F) -> Self
+	where
+		F: Fn(&str, &str) -> Ordering + Send + Sync + 'static,
+	{
+		Self(Arc::new(compare))
+	}
+
+	pub(crate) fn call(&self, a: &str, b: &str) -> Ordering {
+		(self.0)(a, b)
+	}
+}
+
+impl fmt::Debug for KeyOrder {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("KeyOrder(..)")
+	}
+}
+
+/// Predicate choosing the keys written bare, see [`ParseOptions::bare_key_predicate`]
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, KeyPredicate, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	bare_keys: true,
+/// 	bare_key_predicate: Some(KeyPredicate::new(|key| !key.starts_with('_'))),
+/// 	..Default::default()
+/// };
+///
+/// assert_eq!(
+/// 	parse_with_options("_a = 1\nb = 2", &options).unwrap(),
+/// 	"{\n\t[\"_a\"] = 1,\n\tb = 2,\n}"
+/// );
+/// ```
+#[derive(Clone)]
+pub struct KeyPredicate(Arc<PredicateFn>);
+
+type PredicateFn = dyn Fn(&str) -> bool + Send + Sync;
+
+impl KeyPredicate {
+	pub fn new<F>(predicate: F) -> Self
+	where
+		F: Fn(&str) -> bool + Send + Sync + 'static,
+	{
+		Self(Arc::new(predicate))
+	}
+
+	pub(crate) fn call(&self, key: &str) -> bool {
+		(self.0)(key)
+	}
+}
+
+impl fmt::Debug for KeyPredicate {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("KeyPredicate(..)")
+	}
+}
+
+/// Settings of [`ParseOptions::type_tags`]
+///
+/// Every table but the root one gets a string field holding its tag, tables
+/// already having the field return [`Error::ReservedKey`]
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, ParseOptions, TypeTags};
+///
+/// let options = ParseOptions {
+/// 	type_tags: Some(TypeTags::default()),
+/// 	bare_keys: true,
+/// 	..Default::default()
+/// };
+///
+/// assert_eq!(
+/// 	parse_with_options("[weapon]\ndamage = 10", &options).unwrap(),
+/// 	"{\n\tweapon = {\n\t\tdamage = 10,\n\t\t__type = \"weapon\",\n\t},\n}"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TypeTags {
+	/// Name of the field, `__type` by default
+	pub field: String,
+	/// Tags of the tables at the given dotted paths, `*` matches any single key
+	/// or array index. Other tables are tagged with their key and tables in
+	/// arrays with the key of the array
+	pub tags: BTreeMap<String, String>,
+}
+
+impl Default for TypeTags {
+	fn default() -> Self {
+		Self {
+			field: String::from("__type"),
+			tags: BTreeMap::new(),
+		}
+	}
+}
+
+/// Order of the entries of an array of tables, see [`ParseOptions::sort_arrays_by`]
+///
+/// Entries are sorted stably, entries with equal fields keep their document order
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, ParseOptions, SortBy};
+///
+/// let options = ParseOptions {
+/// 	sort_arrays_by: [(String::from("plugins"), SortBy::new("name"))].into(),
+/// 	bare_keys: true,
+/// 	..Default::default()
+/// };
+///
+/// let lua = parse_with_options("[[plugins]]\nname = 'b'\n[[plugins]]\nname = 'a'", &options).unwrap();
+///
+/// assert!(lua.find("\"a\"").unwrap() < lua.find("\"b\"").unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SortBy {
+	/// Field of the entries they are sorted by
+	pub field: String,
+	/// Sort from the greatest to the smallest field
+	#[serde(default)]
+	pub descending: bool,
+	/// How fields are compared, fields of another type return [`Error::InvalidSortField`]
+	#[serde(default)]
+	pub compare: SortCompare,
+	/// What happens to entries without the field, or that aren't tables
+	#[serde(default)]
+	pub missing: MissingSortField,
+}
+
+impl SortBy {
+	/// Sort ascending by the given string field, entries without it last
+	pub fn new(field: &str) -> Self {
+		Self {
+			field: String::from(field),
+			descending: false,
+			compare: SortCompare::String,
+			missing: MissingSortField::Last,
+		}
+	}
+}
+
+/// How the fields of [`SortBy`] are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortCompare {
+	/// Strings compared byte by byte
+	#[default]
+	String,
+	/// Integers and floats compared by value
+	Numeric,
+}
+
+/// What happens to entries of a [`SortBy`] array without the field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingSortField {
+	/// Written after every other entry, in document order, also when descending
+	#[default]
+	Last,
+	/// Return [`Error::MissingSortField`]
+	Error,
+}
+
+/// Settings of [`ParseOptions::coerce`]
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, Coerce, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	coerce: Some(Coerce {
+/// 		paths: vec![String::from("servers.*.port")],
+/// 	}),
+/// 	bare_keys: true,
+/// 	..Default::default()
+/// };
+///
+/// let lua = parse_with_options("[servers.main]\nport = '8080'\nversion = '1.0'", &options).unwrap();
+///
+/// assert!(lua.contains("port = 8080,"));
+/// assert!(lua.contains("version = \"1.0\","));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Coerce {
+	/// Dotted key paths of the strings to coerce, `*` matches any single key or
+	/// array index. Strings at these paths that are no literal return
+	/// [`Error::NotCoercible`]. Every string that is a literal is coerced when empty
+	pub paths: Vec<String>,
+}
+
+/// Settings of [`ParseOptions::redact`]
+///
+/// Patterns are dotted key paths, `*` matches any single key or array index
+/// and `**` any number of them. Every value nested in a matched table or
+/// array is redacted too. Patterns matching no value are reported as a warning
+///
+/// ```rust
+/// use toml2lua::{parse_with_warnings, ParseOptions, Redact};
+///
+/// let options = ParseOptions {
+/// 	redact: Some(Redact {
+/// 		paths: vec![String::from("**.password")],
+/// 		..Default::default()
+/// 	}),
+/// 	bare_keys: true,
+/// 	..Default::default()
+/// };
+///
+/// let (lua, warnings) = parse_with_warnings("[db]\nuser = 'admin'\npassword = 'hunter2'", &options).unwrap();
+///
+/// assert!(lua.contains("password = \"<redacted>\","));
+/// assert_eq!(warnings[0].to_string(), "`db.password`: redacted");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Redact {
+	/// Key path patterns of the values to redact, case sensitive, keys holding a `.` are quoted
+	pub paths: Vec<String>,
+	/// String written in place of redacted strings
+	pub placeholder: String,
+	/// What happens to redacted values that aren't strings
+	pub non_strings: RedactNonStrings,
+}
+
+impl Default for Redact {
+	fn default() -> Self {
+		Self {
+			paths: Vec::new(),
+			placeholder: String::from("<redacted>"),
+			non_strings: RedactNonStrings::Neutral,
+		}
+	}
+}
+
+/// What happens to redacted values that aren't strings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactNonStrings {
+	/// Written as `0`, `0.0`, `false` or the Unix epoch in the form of the datetime
+	#[default]
+	Neutral,
+	/// Return [`Error::NotRedactable`](crate::Error::NotRedactable)
+	Error,
+}
+
+/// Settings of [`ParseOptions::pack_numbers`]
+///
+/// Lua 5.3+ decodes the bytes with `string.unpack` and Luau with the `buffer`
+/// library, integers keep their integer subtype on Lua 5.3+. Number formats,
+/// `number_mode` and the value transform don't apply to packed arrays, arrays
+/// with a value matched by `redact` are never packed
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, PackNumbers, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	pack_numbers: Some(PackNumbers {
+/// 		min_len: 2,
+/// 		..Default::default()
+/// 	}),
+/// 	..Default::default()
+/// };
+///
+/// let lua = parse_with_options("samples = [0.5, 1.5]", &options).unwrap();
+///
+/// assert!(lua.contains("string.unpack(\"<d\", s, i)"));
+/// assert!(lua.contains("\"000000000000e03f000000000000f83f\""));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PackNumbers {
+	/// Shortest array packed
+	pub min_len: usize,
+	/// Width floats are packed with, integers always take 8 bytes
+	pub precision: PackPrecision,
+}
+
+impl Default for PackNumbers {
+	fn default() -> Self {
+		Self {
+			min_len: 256,
+			precision: PackPrecision::F64,
+		}
+	}
+}
+
+/// Settings of the Luau compiler used by [`parse_to_luau_bytecode`](crate::parse_to_luau_bytecode)
+#[cfg(feature = "luau")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LuauBytecode {
+	/// 0 for none, 1 for optimizations that keep the code debuggable and 2 for
+	/// all of them, including inlining
+	pub optimization_level: u8,
+	/// 0 for no debug information, 1 for line info and function names and 2
+	/// for local and upvalue names too
+	pub debug_level: u8,
+}
+
+#[cfg(feature = "luau")]
+impl Default for LuauBytecode {
+	fn default() -> Self {
+		Self {
+			optimization_level: 1,
+			debug_level: 1,
+		}
+	}
+}
+
+/// Width floats are packed with by [`ParseOptions::pack_numbers`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackPrecision {
+	/// 8 bytes, every float is kept as is
+	#[default]
+	F64,
+	/// 4 bytes, floats are rounded to single precision
+	F32,
+}
+
+/// Settings of [`ParseOptions::dedupe`]
+///
+/// Values are compared as written in the document, so key order matters and
+/// `1` differs from `1.0`. Interpolation and the value transform see the key
+/// path of the first occurrence of a shared value. At most 150 values are
+/// shared, keeping the locals of the chunk below the Lua limit
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, Dedupe, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	dedupe: Some(Dedupe { min_size: 8, clone: false }),
+/// 	..Default::default()
+/// };
+///
+/// let lua = parse_with_options("a = 'repeated text'\nb = 'repeated text'", &options).unwrap();
+///
+/// assert_eq!(
+/// 	lua,
+/// 	"local __shared_1 = \"repeated text\"\nreturn {\n\t[\"a\"] = __shared_1,\n\t[\"b\"] = __shared_1,\n}"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Dedupe {
+	/// Smallest value shared, in bytes of string contents or of a compact
+	/// form of tables close to their length in Lua
+	pub min_size: usize,
+	/// Give every use of a shared table its own shallow copy with `table.clone`,
+	/// only available on Luau. Tables nested in a shared one are still shared
+	pub clone: bool,
+}
+
+impl Default for Dedupe {
+	fn default() -> Self {
+		Self {
+			min_size: 32,
+			clone: false,
+		}
+	}
+}
+
+/// Indentation style of the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Indent {
+	/// One tab per nesting level
+	#[default]
+	Tabs,
+	/// Given number of spaces per nesting level
+	Spaces(usize),
+}
+
+/// Whitespace of string values, see [`ParseOptions::string_whitespace`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WhitespacePolicy {
+	/// Strings are written as they are
+	#[default]
+	Preserve,
+	/// Whitespace at the end of every line is removed, line breaks are kept
+	TrimTrailing,
+	/// Tabs are expanded to spaces up to the next multiple of the given width
+	/// in their line, a width of 0 removes them
+	TabsToSpaces(usize),
+}
+
+impl WhitespacePolicy {
+	pub(crate) fn apply(self, string: &str) -> Cow<'_, str> {
+		match self {
+			WhitespacePolicy::Preserve => Cow::Borrowed(string),
+			WhitespacePolicy::TrimTrailing => {
+				let mut trimmed = String::with_capacity(string.len());
+
+				for (i, line) in string.split('\n').enumerate() {
+					if i > 0 {
+						trimmed.push('\n');
+					}
+
+					// Carriage returns of `\r\n` line breaks are kept
+					match line.strip_suffix('\r') {
+						Some(line) => {
+							trimmed.push_str(line.trim_end());
+							trimmed.push('\r');
+						}
+						None => trimmed.push_str(line.trim_end()),
+					}
+				}
+
+				Cow::Owned(trimmed)
+			}
+			WhitespacePolicy::TabsToSpaces(_) if !string.contains('\t') => Cow::Borrowed(string),
+			WhitespacePolicy::TabsToSpaces(width) => {
+				let mut expanded = String::with_capacity(string.len());
+				let mut column = 0;
+
+				for char in string.chars() {
+					match char {
+						'\t' if width > 0 => {
+							let spaces = width - column % width;
+							expanded.push_str(&" ".repeat(spaces));
+							column += spaces;
+						}
+						'\t' => {}
+						'\n' => {
+							expanded.push(char);
+							column = 0;
+						}
+						_ => {
+							expanded.push(char);
+							column += 1;
+						}
+					}
+				}
+
+				Cow::Owned(expanded)
+			}
+		}
+	}
+}
+
+/// Case of the keys written, see [`ParseOptions::key_case`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyCase {
+	/// Keys are written as in the document
+	#[default]
+	Preserve,
+	/// `myKey`
+	Camel,
+	/// `MyKey`
+	Pascal,
+	/// `my_key`
+	Snake,
+}
+
+/// What happens to keys written as the same Lua key, see [`ParseOptions::key_collisions`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyCollisions {
+	/// Return [`Error::KeyCollision`](crate::Error::KeyCollision)
+	#[default]
+	Error,
+	/// Keep the first entry and leave out later ones, each reported as a warning
+	/// by [`parse_with_warnings`](crate::parse_with_warnings)
+	KeepFirst,
+}
+
+/// Quotes used for string literals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoteStyle {
+	/// Always `"double"`
+	#[default]
+	Double,
+	/// `"double"` unless the string has more double than single quotes,
+	/// like StyLua's `AutoPreferDouble`
+	PreferDouble,
+}
+
+/// Character separating table entries, Lua accepts both
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Separator {
+	/// `,`
+	#[default]
+	Comma,
+	/// `;`
+	Semicolon,
+}
+
+/// Line break of the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Newline {
+	/// `\n`
+	#[default]
+	Lf,
+	/// `\r\n`, Lua reads line breaks in long strings as `\n` either way
+	CrLf,
+}
+
+impl Newline {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Newline::Lf => "\n",
+			Newline::CrLf => "\r\n",
+		}
+	}
+
+	/// Replace the `\n` line breaks Lua is written with, leaving `\r\n` ones
+	/// taken from the document as they are
+	pub(crate) fn apply(self, lua: String) -> String {
+		if self == Newline::Lf {
+			return lua;
+		}
+
+		let mut replaced = String::with_capacity(lua.len() + lua.len() / 16);
+		let mut previous = '\0';
+
+		for c in lua.chars() {
+			if c == '\n' && previous != '\r' {
+				replaced.push_str(self.as_str());
+			} else {
+				replaced.push(c);
+			}
+
+			previous = c;
+		}
+
+		replaced
+	}
+}
+
+/// How integers and floats are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberMode {
+	/// Integers stay integers and floats stay floats
+	#[default]
+	Preserve,
+	/// Every number is written as a float, integers get a `.0` suffix
+	AllFloat,
+	/// Every number is written as an integer, floats are truncated toward zero
+	/// (saturating at the `i64` bounds, `NaN` becomes `0`)
+	AllInteger,
+}
+
+/// How booleans are written
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoolStyle {
+	/// `true` and `false`
+	#[default]
+	Lua,
+	/// `1` and `0`
+	Numeric,
+	/// Given expressions for `true` and `false`, written verbatim
+	Custom(String, String),
+}
+
+/// What happens to integers that can't be represented exactly by a double
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegerOverflow {
+	/// Written as is, loaded with lost precision by Lua versions without integers
+	#[default]
+	Allow,
+	/// Return [`Error::IntegerOverflow`](crate::Error::IntegerOverflow)
+	Error,
+}
+
+/// Radix integers are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Radix {
+	/// `255`
+	#[default]
+	Decimal,
+	/// `0xFF`, negative integers are negated literals like `-0xFF`
+	Hex,
+	/// `0b11111111` for Luau, other versions have no binary literals so it is
+	/// written in hexadecimal followed by the binary one as a comment
+	Binary,
+}
+
+/// Lua version the output is generated for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LuaTarget {
+	/// Lua 5.1, no integers and no `\x` or `\u{}` escapes
+	Lua51,
+	/// Lua 5.3
+	Lua53,
+	/// Lua 5.4
+	Lua54,
+	/// LuaJIT 2.1, no integers
+	LuaJit,
+	/// Roblox Luau, no integers
+	Luau,
+}
+
+impl LuaTarget {
+	/// Whether integers are a distinct subtype instead of doubles
+	pub fn has_integers(self) -> bool {
+		matches!(self, LuaTarget::Lua53 | LuaTarget::Lua54)
+	}
+
+	/// Whether floats can be written in hexadecimal like `0x1.8p+1`
+	pub fn has_hex_floats(self) -> bool {
+		!matches!(self, LuaTarget::Lua51 | LuaTarget::Luau)
+	}
+
+	/// Whether `\u{XXXX}` escapes are supported
+	pub fn has_unicode_escapes(self) -> bool {
+		self != LuaTarget::Lua51
+	}
+}
+
+impl fmt::Display for LuaTarget {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			LuaTarget::Lua51 => "Lua 5.1",
+			LuaTarget::Lua53 => "Lua 5.3",
+			LuaTarget::Lua54 => "Lua 5.4",
+			LuaTarget::LuaJit => "LuaJIT",
+			LuaTarget::Luau => "Luau",
+		})
+	}
+}
+
+/// Where the key order of tables is recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderMetadata {
+	/// Key order is not recorded
+	#[default]
+	None,
+	/// `__order = { "first", "second" }` entry at the end of every table
+	Field,
+	/// `setmetatable({ ... }, { __order = { "first", "second" } })` around every table
+	Metatable,
+}
+
+/// How documents without any keys are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyMode {
+	/// Like any other empty table, `{` and `}` on separate lines unless `compact_empty` is enabled
+	#[default]
+	EmptyTable,
+	/// Always `{}`
+	CompactEmpty,
+	/// Return [`Error::EmptyDocument`]
+	Error,
+}
+
+/// How datetimes are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatetimeMode {
+	/// Quoted string as written in the document, e.g. `"1979-05-27"`
+	#[default]
+	String,
+	/// Table of the fields the datetime has, named like the ones of `os.date("*t")`:
+	/// `year`, `month` and `day` for dates, `hour`, `min`, `sec` and `nsec` for
+	/// times and `utc_offset` in minutes for offset datetimes
+	Table,
+	/// Number of seconds since the Unix epoch for offset datetimes and since
+	/// midnight for local times, a float when there are fractional seconds.
+	/// Local dates and datetimes lack the time zone and use the `datetime_fallback`
+	Epoch,
+}
+
+/// What happens to datetimes the [`DatetimeMode`] can't express
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatetimeFallback {
+	/// Return [`Error::UnrepresentableDatetime`]
+	#[default]
+	Error,
+	/// Write them as strings like [`DatetimeMode::String`]
+	String,
+}
+
+/// What happens to datetimes without an offset when they are normalized to UTC,
+/// see [`ParseOptions::normalize_datetimes_utc`]. Local dates are never converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalDatetimes {
+	/// Written as in the document
+	#[default]
+	Keep,
+	/// Return [`Error::NotNormalizable`]
+	Error,
+}
+
+/// How table entries set to [`ParseOptions::nil_sentinel`] are written,
+/// both leave the key unset once loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NilEntries {
+	/// Written as `key = nil`, documenting that the key is unset on purpose
+	#[default]
+	Keep,
+	/// Left out of the table
+	Drop,
+}
+
+/// How empty string values are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyStringMode {
+	/// Written as `""`
+	#[default]
+	Keep,
+	/// Table entries are left out, array items are written as `nil`
+	Omit,
+	/// Written as `nil`, table entries are kept as `key = nil`
+	Nil,
+}
+
+/// Which table constructors are wrapped in `table.freeze` (Luau only)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Freeze {
+	/// Tables stay mutable
+	#[default]
+	Off,
+	/// Only the outermost table is frozen
+	Shallow,
+	/// Every table and array is frozen
+	Deep,
+}
+
+/// How byte input that is not valid UTF-8 is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Utf8Mode {
+	/// Return [`Error::InvalidUtf8`](crate::Error::InvalidUtf8)
+	#[default]
+	Strict,
+	/// Replace invalid sequences with U+FFFD and report a [`Warning`](crate::Warning)
+	Lossy,
+}
+
+/// Comment block above the output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Header {
+	/// `-- Generated by toml2lua vX.Y.Z from <file>; do not edit.`, the file
+	/// name is only included when converting a file
+	Generated,
+	/// Custom text, every line is prefixed with `--`
+	Custom(String),
+}
+
+/// Language of the output
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, Dialect, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	dialect: Dialect::Fennel,
+/// 	..Default::default()
+/// };
+///
+/// assert_eq!(
+/// 	parse_with_options("name = 'abc'\nports = [80, 443]", &options).unwrap(),
+/// 	"{\n\t:name \"abc\"\n\t:ports [80 443]\n}"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dialect {
+	/// Lua table constructor
+	#[default]
+	Lua,
+	/// Fennel table literal, with keys that are identifiers written as `:key`
+	/// and arrays as `[ ... ]`. Datetimes are written as strings and NaN and
+	/// infinities as divisions by zero like `(/ 1 0)`
+	///
+	/// Only `indent`, `escape_unicode`, `nil_sentinel`, `nil_entries`,
+	/// `empty_string`, `header` and `final_newline` apply, and only to single
+	/// documents: entries keep their document order and keys are written as they
+	/// are. Options that change keys or values, like `redact` or `key_case`, fail
+	/// with [`Error::ConflictingOptions`](crate::Error::ConflictingOptions). Nested tables are
+	/// written like the root table, but nothing checks that Fennel macros or
+	/// special forms aren't shadowed by keys, which Fennel reads as plain strings
+	/// when written as `:key`
+	Fennel,
+}
+
+/// Form of the comments above entries, see [`ParseOptions::comment_style`]
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, CommentStyle, ParseOptions};
+///
+/// let options = ParseOptions {
+/// 	doc_comments: [(String::from("port"), String::from("Port to listen on"))].into(),
+/// 	comment_style: CommentStyle::LdocField,
+/// 	bare_keys: true,
+/// 	..Default::default()
+/// };
+///
+/// let lua = "{\n\t--- @field port Port to listen on\n\tport = 80,\n}";
+///
+/// assert_eq!(parse_with_options("port = 80", &options).unwrap(), lua);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentStyle {
+	/// `-- ` comments, one per line of the text
+	#[default]
+	Line,
+	/// LDoc field documentation, the first line of the text as
+	/// `--- @field key text` and the following ones as `--- text`, documenting
+	/// modules for editors reading LuaDoc or LDoc. Keys that aren't identifiers
+	/// are named like `["my key"]`
+	LdocField,
+}
+
+/// Order of the groups of entries of [`ParseOptions::group_order`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupOrder {
+	/// Entries aren't grouped
+	#[default]
+	None,
+	/// Scalars first, then arrays and then nested tables
+	ScalarsFirst,
+	/// Nested tables first, then arrays and then scalars
+	TablesFirst,
+}
+
+/// How the outermost table is protected, see [`ParseOptions::readonly`]
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, ParseOptions, ReadonlyMode};
+///
+/// let options = ParseOptions {
+/// 	readonly: ReadonlyMode::Wrapper(String::from("readonly")),
+/// 	bare_keys: true,
+/// 	..Default::default()
+/// };
+///
+/// assert_eq!(parse_with_options("a = 1", &options).unwrap(), "readonly({\n\ta = 1,\n})");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadonlyMode {
+	/// The table can be changed
+	#[default]
+	None,
+	/// Pass the table to the function of this name, like `readonly({ ... })`,
+	/// which the loading environment provides. The name is written verbatim
+	Wrapper(String),
+	/// Give the table a metatable whose `__newindex` raises an error, like
+	/// `setmetatable({ ... }, { __newindex = ... })`. Only adding keys is
+	/// caught, fields of the document can still be reassigned, and nested
+	/// tables aren't protected. Use [`Freeze`] in Luau
+	Metatable,
+}
+
+/// Statement wrapping the root table
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Wrap {
+	/// Bare table constructor
+	#[default]
+	None,
+	/// `return { ... }`, ready to be loaded as a module
+	Return,
+	/// `local <name> = { ... }`, name is written verbatim
+	Local(String),
+}
@@ -0,0 +1,165 @@
+use alloc::{
+	format,
+	string::{String, ToString},
+};
+use core::fmt::Write;
+use toml::Value;
+
+use crate::{PackNumbers, PackPrecision};
+
+/// Globals the decoders call
+pub(crate) const GLOBALS: [&str; 4] = ["string", "tonumber", "buffer", "table"];
+
+/// Lua 5.3+ function decoding the hexadecimal string into an array, `%w` is
+/// the width of an element in bytes and `%s` its `string.unpack` format
+const UNPACK: &str = "(function(s) local t = {} s = s:gsub(\"%x%x\", function(h) return string.char(tonumber(h, 16)) end) for i = 1, #s, %w do t[#t + 1] = string.unpack(\"%s\", s, i) end return t end)";
+
+/// Luau function decoding the hexadecimal string into an array, `%w` is the
+/// width of an element in bytes and `%s` its `buffer` read function
+const READ_BUFFER: &str = "(function(s) local b = buffer.fromstring((s:gsub(\"%x%x\", function(h) return string.char(tonumber(h, 16)) end))) local t = table.create(buffer.len(b) // %w) for i = 0, buffer.len(b) - %w, %w do table.insert(t, buffer.%s(b, i)) end return t end)";
+
+/// Expression decoding into the array, if it holds enough elements and only
+/// integers or only floats
+pub(crate) fn packed_array(array: &[Value], settings: &PackNumbers, luau: bool) -> Option<String> {
+	if array.is_empty() || array.len() < settings.min_len {
+		return None;
+	}
+
+	let floats = match array[0] {
+		Value::Integer(_) => false,
+		Value::Float(_) => true,
+		_ => return None,
+	};
+
+	let single = floats && settings.precision == PackPrecision::F32;
+
+	let (width, format, read) = match (floats, single) {
+		(true, true) => (4, "<f", "readf32"),
+		(true, false) => (8, "<d", "readf64"),
+		// Luau numbers are doubles, so integers are read as such
+		(false, _) if luau => (8, "", "readf64"),
+		(false, _) => (8, "<i8", ""),
+	};
+
+	let mut hex = String::with_capacity(array.len() * width * 2);
+
+	for value in array {
+		let bytes = match (value, single) {
+			(Value::Float(float), true) if floats => u64::from((*float as f32).to_bits()),
+			(Value::Float(float), false) if floats => float.to_bits(),
+			(Value::Integer(integer), _) if !floats && luau => (*integer as f64).to_bits(),
+			(Value::Integer(integer), _) if !floats => *integer as u64,
+			_ => return None,
+		};
+
+		for byte in &bytes.to_le_bytes()[..width] {
+			let _ = write!(hex, "{:02x}", byte);
+		}
+	}
+
+	let decoder = if luau {
+		READ_BUFFER
+			.replace("%w", &width.to_string())
+			.replace("%s", read)
+	} else {
+		UNPACK
+			.replace("%w", &width.to_string())
+			.replace("%s", format)
+	};
+
+	Some(format!("{}(\"{}\")", decoder, hex))
+}
+
+#[cfg(test)]
+mod test {
+	use toml::Value;
+
+	use super::packed_array;
+	use crate::{PackNumbers, PackPrecision};
+
+	#[test]
+	fn packed() {
+		let settings = PackNumbers {
+			min_len: 2,
+			precision: PackPrecision::F32,
+		};
+
+		let floats = [Value::Float(1.0), Value::Float(-2.0)];
+		let lua = packed_array(&floats, &settings, false).unwrap();
+		assert!(lua.ends_with("(\"0000803f000000c0\")"));
+		assert!(lua.contains("for i = 1, #s, 4 do t[#t + 1] = string.unpack(\"<f\", s, i)"));
+
+		let integers = [Value::Integer(1), Value::Integer(-1)];
+		let lua = packed_array(&integers, &settings, true).unwrap();
+		assert!(lua.ends_with("(\"000000000000f03f000000000000f0bf\")"));
+		assert!(lua.contains("buffer.readf64(b, i)"));
+
+		// Too short or mixing integers and floats
+		assert!(packed_array(&floats[..1], &settings, false).is_none());
+		assert!(packed_array(&[Value::Integer(1), Value::Float(1.0)], &settings, false).is_none());
+		assert!(packed_array(&[Value::Float(1.0), Value::Integer(1)], &settings, false).is_none());
+	}
+
+	#[cfg(feature = "verify")]
+	#[test]
+	fn roundtrip() {
+		use mlua::Lua;
+
+		let lua = Lua::new();
+
+		// Deterministic noise spanning several magnitudes and both signs
+		let floats: Vec<f64> = (0..5000)
+			.map(|i| ((i as f64) * 12.9898).sin() * 10f64.powi(i % 7 - 3))
+			.collect();
+		let integers: Vec<i64> = (0..5000)
+			.map(|i| (i * 7919) % 100_003 - 50_000)
+			.chain([i64::MAX, i64::MIN])
+			.collect();
+
+		for (precision, epsilon) in [(PackPrecision::F64, 0.0), (PackPrecision::F32, 1e-6)] {
+			let settings = PackNumbers {
+				min_len: 1,
+				precision,
+			};
+
+			let array: Vec<Value> = floats.iter().map(|float| Value::Float(*float)).collect();
+			let decoded: Vec<f64> = lua
+				.load(format!(
+					"return {}",
+					packed_array(&array, &settings, false).unwrap()
+				))
+				.eval()
+				.unwrap();
+
+			assert_eq!(decoded.len(), floats.len());
+
+			for (decoded, float) in decoded.iter().zip(&floats) {
+				assert!(
+					(decoded - float).abs() <= float.abs() * epsilon,
+					"{} {}",
+					decoded,
+					float
+				);
+			}
+
+			let array: Vec<Value> = integers
+				.iter()
+				.map(|integer| Value::Integer(*integer))
+				.collect();
+			let decoded: Vec<mlua::Value> = lua
+				.load(format!(
+					"return {}",
+					packed_array(&array, &settings, false).unwrap()
+				))
+				.eval()
+				.unwrap();
+
+			let decoded: Vec<i64> = decoded
+				.into_iter()
+				.map(|value| value.as_integer().expect("integer subtype"))
+				.collect();
+
+			assert_eq!(decoded, integers);
+		}
+	}
+}
@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+use toml_edit::{DocumentMut, Value};
+
+use crate::{source::visit_values, LuaTarget, ParseOptions};
+
+/// Collect integers written in another radix or with digit separators, keyed by key path
+///
+/// Each entry holds the value of the integer, the Lua literal to emit and a
+/// comment to place after it when the source radix can't be kept
+pub(crate) fn integer_literals(
+	toml: &str,
+	options: &ParseOptions,
+) -> BTreeMap<Vec<String>, (i64, String, Option<String>)> {
+	let mut literals = BTreeMap::new();
+
+	// Invalid documents are rejected by the regular deserializer beforehand
+	let Ok(document) = toml.parse::<DocumentMut>() else {
+		return literals;
+	};
+
+	let luau = options.target == Some(LuaTarget::Luau);
+
+	visit_values(&document, &mut |path, value| {
+		let Value::Integer(integer) = value else {
+			return;
+		};
+
+		let raw = integer
+			.as_repr()
+			.and_then(|repr| repr.as_raw().as_str())
+			.unwrap_or_default();
+
+		if let Some((literal, comment)) = literal(raw, *integer.value(), luau) {
+			literals.insert(path.to_vec(), (*integer.value(), literal, comment));
+		}
+	});
+
+	literals
+}
+
+/// Lua spelling of the integer, `None` when it is plain decimal anyway
+///
+/// Hexadecimal is valid everywhere, binary and digit separators only in Luau.
+/// Octal has no Lua equivalent, so it is written in decimal with the source as a comment
+fn literal(raw: &str, value: i64, luau: bool) -> Option<(String, Option<String>)> {
+	if raw.starts_with("0x") {
+		let digits = if luau {
+			raw.to_owned()
+		} else {
+			raw.replace('_', "")
+		};
+
+		Some((digits, None))
+	} else if raw.starts_with("0b") && luau {
+		Some((raw.to_owned(), None))
+	} else if raw.starts_with("0b") || raw.starts_with("0o") {
+		Some((value.to_string(), Some(raw.to_owned())))
+	} else if luau && raw.contains('_') && value != i64::MIN {
+		// Lua has no unary plus
+		Some((raw.trim_start_matches('+').to_owned(), None))
+	} else {
+		None
+	}
+}
@@ -0,0 +1,381 @@
+use alloc::{
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::{
+	fmt::{self, Display, Formatter},
+	ops::{Bound, RangeBounds},
+};
+use toml::Value;
+
+use crate::{
+	walk::{join_path, path_segment, split_path},
+	Map, Warning, WarningKind,
+};
+
+/// Expected structure of a document, checked by [`parse_with_schema`](crate::parse_with_schema)
+///
+/// Paths are dotted keys, `*` matches every element of an array or every
+/// value of a table. A required key is only reported missing when its parent
+/// exists, require the parent too if it must be present
+///
+/// ```rust
+/// use toml2lua::{Schema, UnknownKeys, ValueType};
+///
+/// let schema = Schema::new()
+/// 	.required("name", ValueType::String)
+/// 	.optional("port", ValueType::Integer)
+/// 	.range("port", 1.0..=65535.0)
+/// 	.one_of("mode", ["debug", "release"])
+/// 	.required("servers.*.host", ValueType::String)
+/// 	.unknown_keys(UnknownKeys::Error);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+	rules: Vec<Rule>,
+	unknown_keys: UnknownKeys,
+}
+
+/// What happens to keys no schema path leads to
+///
+/// Only tables the schema describes keys of are checked, tables without rules
+/// below them may contain anything
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownKeys {
+	/// Accept them silently
+	#[default]
+	Ignore,
+	/// Accept them and report a [`Warning`]
+	Warn,
+	/// Report a [`Violation`]
+	Error,
+}
+
+/// Type a schema path must have
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+	String,
+	Integer,
+	Float,
+	/// Integer or float
+	Number,
+	Boolean,
+	Datetime,
+	Array,
+	Table,
+	/// Any value, only checks that the key exists
+	Any,
+}
+
+/// Value that doesn't match the schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+	/// Dotted path of the value, array elements are numbered from 1
+	pub path: String,
+	/// What the schema expects
+	pub expected: String,
+	/// What the document contains
+	pub found: String,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+	path: Vec<String>,
+	required: bool,
+	kind: ValueType,
+	allowed: Vec<Value>,
+	range: Option<(Bound<f64>, Bound<f64>)>,
+}
+
+impl Schema {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Require the key to exist with the given type
+	pub fn required(mut self, path: &str, kind: ValueType) -> Self {
+		let rule = self.rule(path);
+		rule.required = true;
+		rule.kind = kind;
+		self
+	}
+
+	/// Check the type of the key if it exists
+	pub fn optional(mut self, path: &str, kind: ValueType) -> Self {
+		self.rule(path).kind = kind;
+		self
+	}
+
+	/// Only allow the given values
+	pub fn one_of<I, V>(mut self, path: &str, values: I) -> Self
+	where
+		I: IntoIterator<Item = V>,
+		V: Into<Value>,
+	{
+		self.rule(path).allowed = values.into_iter().map(Into::into).collect();
+		self
+	}
+
+	/// Only allow integers and floats within the range
+	pub fn range<R: RangeBounds<f64>>(mut self, path: &str, range: R) -> Self {
+		self.rule(path).range = Some((range.start_bound().cloned(), range.end_bound().cloned()));
+		self
+	}
+
+	/// Set what happens to keys not described by the schema, ignored by default
+	pub fn unknown_keys(mut self, unknown_keys: UnknownKeys) -> Self {
+		self.unknown_keys = unknown_keys;
+		self
+	}
+
+	/// Check the document, returning every violation and the unknown keys to warn about
+	pub fn validate(&self, map: &Map) -> (Vec<Violation>, Vec<Warning>) {
+		let mut validation = Validation {
+			schema: self,
+			violations: Vec::new(),
+			warnings: Vec::new(),
+			path: Vec::new(),
+		};
+
+		validation.table(&map.iter().collect::<Vec<_>>());
+
+		(validation.violations, validation.warnings)
+	}
+
+	fn rule(&mut self, path: &str) -> &mut Rule {
+		let path = split_path(path);
+
+		match self.rules.iter().position(|rule| rule.path == path) {
+			Some(i) => &mut self.rules[i],
+			None => {
+				self.rules.push(Rule {
+					path,
+					required: false,
+					kind: ValueType::Any,
+					allowed: Vec::new(),
+					range: None,
+				});
+
+				self.rules.last_mut().unwrap()
+			}
+		}
+	}
+}
+
+struct Validation<'a> {
+	schema: &'a Schema,
+	violations: Vec<Violation>,
+	warnings: Vec<Warning>,
+	path: Vec<String>,
+}
+
+impl Validation<'_> {
+	fn table(&mut self, entries: &[(&String, &Value)]) {
+		let schema = self.schema;
+		let depth = self.path.len();
+		let below: Vec<&Rule> = schema
+			.rules
+			.iter()
+			.filter(|rule| rule.path.len() > depth && self.matches(&rule.path[..depth]))
+			.collect();
+
+		for rule in &below {
+			let segment = &rule.path[depth];
+
+			if rule.required
+				&& rule.path.len() == depth + 1
+				&& segment != "*"
+				&& !entries.iter().any(|(key, _)| *key == segment)
+			{
+				self.violations.push(Violation {
+					path: self.child(segment),
+					expected: rule.kind.to_string(),
+					found: String::from("nothing"),
+				});
+			}
+		}
+
+		for &(key, value) in entries {
+			let known = below
+				.iter()
+				.any(|rule| rule.path[depth] == "*" || rule.path[depth] == *key);
+
+			if !known && !below.is_empty() {
+				self.unknown(key, value);
+			}
+
+			self.path.push(key.clone());
+			self.value(value);
+			self.path.pop();
+		}
+	}
+
+	fn value(&mut self, value: &Value) {
+		let schema = self.schema;
+
+		for rule in &schema.rules {
+			if rule.path.len() == self.path.len() && self.matches(&rule.path) {
+				self.check(rule, value);
+			}
+		}
+
+		match value {
+			Value::Table(table) => self.table(&table.iter().collect::<Vec<_>>()),
+			Value::Array(array) => {
+				for (i, item) in array.iter().enumerate() {
+					self.path.push((i + 1).to_string());
+					self.value(item);
+					self.path.pop();
+				}
+			}
+			_ => {}
+		}
+	}
+
+	fn check(&mut self, rule: &Rule, value: &Value) {
+		if !rule.kind.accepts(value) {
+			self.violation(rule.kind.to_string(), value.type_str().to_string());
+			return;
+		}
+
+		if !rule.allowed.is_empty() && !rule.allowed.contains(value) {
+			let allowed: Vec<String> = rule.allowed.iter().map(describe).collect();
+			self.violation(format!("one of {}", allowed.join(", ")), describe(value));
+		}
+
+		if let Some((start, end)) = rule.range {
+			let number = match value {
+				Value::Integer(i) => *i as f64,
+				Value::Float(f) => *f,
+				_ => {
+					self.violation(String::from("number"), value.type_str().to_string());
+					return;
+				}
+			};
+
+			if !(start, end).contains(&number) {
+				self.violation(
+					format!("number {}", range_text(start, end)),
+					describe(value),
+				);
+			}
+		}
+	}
+
+	fn unknown(&mut self, key: &str, value: &Value) {
+		match self.schema.unknown_keys {
+			UnknownKeys::Ignore => {}
+			UnknownKeys::Warn => self.warnings.push(Warning {
+				kind: WarningKind::UnknownKey,
+				path: self.child(key),
+				message: String::from("key is not described by the schema"),
+			}),
+			UnknownKeys::Error => self.violations.push(Violation {
+				path: self.child(key),
+				expected: String::from("no key"),
+				found: value.type_str().to_string(),
+			}),
+		}
+	}
+
+	fn violation(&mut self, expected: String, found: String) {
+		self.violations.push(Violation {
+			path: join_path(&self.path),
+			expected,
+			found,
+		});
+	}
+
+	/// Whether the schema segments match the current path, `*` matching any key or index
+	fn matches(&self, segments: &[String]) -> bool {
+		segments
+			.iter()
+			.zip(&self.path)
+			.all(|(segment, key)| segment == "*" || segment == key)
+	}
+
+	fn child(&self, key: &str) -> String {
+		if self.path.is_empty() {
+			path_segment(key)
+		} else {
+			format!("{}.{}", join_path(&self.path), path_segment(key))
+		}
+	}
+}
+
+impl ValueType {
+	fn accepts(self, value: &Value) -> bool {
+		matches!(
+			(self, value),
+			(ValueType::Any, _)
+				| (ValueType::String, Value::String(_))
+				| (ValueType::Integer, Value::Integer(_))
+				| (ValueType::Float, Value::Float(_))
+				| (ValueType::Number, Value::Integer(_) | Value::Float(_))
+				| (ValueType::Boolean, Value::Boolean(_))
+				| (ValueType::Datetime, Value::Datetime(_))
+				| (ValueType::Array, Value::Array(_))
+				| (ValueType::Table, Value::Table(_))
+		)
+	}
+}
+
+impl Display for ValueType {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			ValueType::String => "string",
+			ValueType::Integer => "integer",
+			ValueType::Float => "float",
+			ValueType::Number => "number",
+			ValueType::Boolean => "boolean",
+			ValueType::Datetime => "datetime",
+			ValueType::Array => "array",
+			ValueType::Table => "table",
+			ValueType::Any => "any value",
+		})
+	}
+}
+
+impl Display for Violation {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"`{}`: expected {}, found {}",
+			self.path, self.expected, self.found
+		)
+	}
+}
+
+/// Short description of a value for violation messages
+fn describe(value: &Value) -> String {
+	match value {
+		Value::String(string) => format!("{:?}", string),
+		Value::Integer(i) => i.to_string(),
+		Value::Float(f) => f.to_string(),
+		Value::Boolean(b) => b.to_string(),
+		Value::Datetime(datetime) => datetime.to_string(),
+		_ => value.type_str().to_string(),
+	}
+}
+
+/// Bounds of a range like `>= 1 and <= 65535`
+fn range_text(start: Bound<f64>, end: Bound<f64>) -> String {
+	let bounds: Vec<String> = [
+		match start {
+			Bound::Included(value) => Some(format!(">= {}", value)),
+			Bound::Excluded(value) => Some(format!("> {}", value)),
+			Bound::Unbounded => None,
+		},
+		match end {
+			Bound::Included(value) => Some(format!("<= {}", value)),
+			Bound::Excluded(value) => Some(format!("< {}", value)),
+			Bound::Unbounded => None,
+		},
+	]
+	.into_iter()
+	.flatten()
+	.collect();
+
+	bounds.join(" and ")
+}
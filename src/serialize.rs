@@ -0,0 +1,279 @@
+use core::fmt::Display;
+use serde::ser::{self, Error as _, Serialize};
+use toml::ser::Error;
+
+/// Check that no array of the value holds `None`
+///
+/// `toml::Value::try_from` skips every table key whose value fails with an
+/// unsupported `None`, which hides a `None` array item together with its array
+pub(crate) fn check_none<T: Serialize + ?Sized>(value: &T) -> Result<(), Error> {
+	value.serialize(TABLE)
+}
+
+/// Serializer producing nothing, only failing on `None` directly in an array
+#[derive(Clone, Copy)]
+struct Checker {
+	in_array: bool,
+}
+
+const TABLE: Checker = Checker { in_array: false };
+const ARRAY: Checker = Checker { in_array: true };
+
+impl ser::Serializer for Checker {
+	type Ok = ();
+	type Error = Error;
+
+	type SerializeSeq = Self;
+	type SerializeTuple = Self;
+	type SerializeTupleStruct = Self;
+	type SerializeTupleVariant = Self;
+	type SerializeMap = Self;
+	type SerializeStruct = Self;
+	type SerializeStructVariant = Self;
+
+	fn serialize_bool(self, _: bool) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_i8(self, _: i8) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_i16(self, _: i16) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_i32(self, _: i32) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_i64(self, _: i64) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_u8(self, _: u8) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_u16(self, _: u16) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_u32(self, _: u32) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_u64(self, _: u64) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_f32(self, _: f32) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_f64(self, _: f64) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_char(self, _: char) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_str(self, _: &str) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_bytes(self, _: &[u8]) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_none(self) -> Result<(), Error> {
+		if self.in_array {
+			return Err(Error::custom("`None` can't be an array item"));
+		}
+
+		Ok(())
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_unit_struct(self, _: &'static str) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(
+		self,
+		_: &'static str,
+		value: &T,
+	) -> Result<(), Error> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		value: &T,
+	) -> Result<(), Error> {
+		// Written as a table with the variant as its only key
+		value.serialize(TABLE)
+	}
+
+	fn serialize_seq(self, _: Option<usize>) -> Result<Self, Error> {
+		Ok(ARRAY)
+	}
+
+	fn serialize_tuple(self, _: usize) -> Result<Self, Error> {
+		Ok(ARRAY)
+	}
+
+	fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, Error> {
+		Ok(ARRAY)
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: usize,
+	) -> Result<Self, Error> {
+		Ok(ARRAY)
+	}
+
+	fn serialize_map(self, _: Option<usize>) -> Result<Self, Error> {
+		Ok(TABLE)
+	}
+
+	fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, Error> {
+		Ok(TABLE)
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: usize,
+	) -> Result<Self, Error> {
+		Ok(TABLE)
+	}
+
+	fn collect_str<T: Display + ?Sized>(self, _: &T) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+impl ser::SerializeSeq for Checker {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+		value.serialize(*self)
+	}
+
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+impl ser::SerializeTuple for Checker {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+		value.serialize(*self)
+	}
+
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+impl ser::SerializeTupleStruct for Checker {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+		value.serialize(*self)
+	}
+
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+impl ser::SerializeTupleVariant for Checker {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+		value.serialize(*self)
+	}
+
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+impl ser::SerializeMap for Checker {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+		key.serialize(TABLE)
+	}
+
+	fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+		value.serialize(TABLE)
+	}
+
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+impl ser::SerializeStruct for Checker {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		_: &'static str,
+		value: &T,
+	) -> Result<(), Error> {
+		value.serialize(TABLE)
+	}
+
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+impl ser::SerializeStructVariant for Checker {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		_: &'static str,
+		value: &T,
+	) -> Result<(), Error> {
+		value.serialize(TABLE)
+	}
+
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
@@ -0,0 +1,165 @@
+use alloc::{
+	collections::BTreeMap,
+	format,
+	string::{String, ToString},
+	vec,
+	vec::Vec,
+};
+use core::cmp::Reverse;
+use toml::Value;
+
+use crate::Dedupe;
+
+/// Most values shared, leaving room below the 200 locals a Lua function can have
+const MAX_SHARED: usize = 150;
+
+/// Table, array or string occurring several times in a document
+pub(crate) struct Shared<'v> {
+	pub value: &'v Value,
+	/// Key path of the first occurrence
+	pub path: Vec<String>,
+	/// Addresses of every occurrence
+	pub uses: Vec<usize>,
+}
+
+struct Candidate<'v> {
+	shared: Shared<'v>,
+	size: usize,
+	/// Position in post-order, values nested in another come first
+	order: usize,
+}
+
+/// Values of the document worth sharing, ordered so every value comes after
+/// the values nested in it
+///
+/// Occurrences nested in a repeated value are only counted once, since the
+/// repeated value is written once anyway
+pub(crate) fn find_shared<'v>(
+	entries: &[(&String, &'v Value)],
+	dedupe: &Dedupe,
+) -> Vec<Shared<'v>> {
+	let mut finder = Finder::default();
+
+	for (key, value) in entries {
+		finder.path.push((*key).clone());
+		finder.visit(value);
+		finder.path.pop();
+	}
+
+	let mut candidates: Vec<Candidate> = finder
+		.candidates
+		.into_values()
+		.filter(|candidate| candidate.shared.uses.len() > 1 && candidate.size >= dedupe.min_size)
+		.collect();
+
+	// Keep the values saving the most when there are too many
+	candidates.sort_by_key(|candidate| Reverse(candidate.size * (candidate.shared.uses.len() - 1)));
+	candidates.truncate(MAX_SHARED);
+	candidates.sort_by_key(|candidate| candidate.order);
+
+	candidates
+		.into_iter()
+		.map(|candidate| candidate.shared)
+		.collect()
+}
+
+#[derive(Default)]
+struct Finder<'v> {
+	/// Keyed by fingerprint
+	candidates: BTreeMap<String, Candidate<'v>>,
+	path: Vec<String>,
+	visited: usize,
+}
+
+impl<'v> Finder<'v> {
+	fn visit(&mut self, value: &'v Value) {
+		if !matches!(value, Value::String(_) | Value::Array(_) | Value::Table(_)) {
+			return;
+		}
+
+		let fingerprint = fingerprint(value);
+		let address = value as *const Value as usize;
+
+		if let Some(candidate) = self.candidates.get_mut(&fingerprint) {
+			candidate.shared.uses.push(address);
+			return;
+		}
+
+		match value {
+			Value::Array(array) => {
+				for (i, item) in array.iter().enumerate() {
+					self.path.push((i + 1).to_string());
+					self.visit(item);
+					self.path.pop();
+				}
+			}
+			Value::Table(table) => {
+				for (key, value) in table {
+					self.path.push(key.clone());
+					self.visit(value);
+					self.path.pop();
+				}
+			}
+			_ => {}
+		}
+
+		let size = match value {
+			Value::String(string) => string.len(),
+			_ => fingerprint.len(),
+		};
+
+		self.candidates.insert(
+			fingerprint,
+			Candidate {
+				shared: Shared {
+					value,
+					path: self.path.clone(),
+					uses: vec![address],
+				},
+				size,
+				order: self.visited,
+			},
+		);
+
+		self.visited += 1;
+	}
+}
+
+/// Compact form of the value, equal only for structurally equal values
+fn fingerprint(value: &Value) -> String {
+	let mut fingerprint = String::new();
+	push_fingerprint(value, &mut fingerprint);
+	fingerprint
+}
+
+fn push_fingerprint(value: &Value, fingerprint: &mut String) {
+	match value {
+		Value::String(string) => fingerprint.push_str(&format!("{:?}", string)),
+		Value::Integer(integer) => fingerprint.push_str(&integer.to_string()),
+		// Bits tell apart `1` and `1.0`, `0.0` and `-0.0` and every NaN
+		Value::Float(float) => fingerprint.push_str(&format!("f{:x}", float.to_bits())),
+		Value::Boolean(boolean) => fingerprint.push_str(&boolean.to_string()),
+		Value::Datetime(datetime) => fingerprint.push_str(&format!("d{}", datetime)),
+		Value::Array(array) => {
+			fingerprint.push('[');
+
+			for item in array {
+				push_fingerprint(item, fingerprint);
+				fingerprint.push(',');
+			}
+
+			fingerprint.push(']');
+		}
+		Value::Table(table) => {
+			fingerprint.push('{');
+
+			for (key, value) in table {
+				fingerprint.push_str(&format!("{:?}=", key));
+				push_fingerprint(value, fingerprint);
+				fingerprint.push(',');
+			}
+
+			fingerprint.push('}');
+		}
+	}
+}
@@ -0,0 +1,92 @@
+use alloc::{string::String, vec::Vec};
+use core::cmp::Ordering;
+use toml::Value;
+
+use crate::{Error, MissingSortField, SortBy, SortCompare};
+
+/// Field an entry is sorted by
+enum Field<'v> {
+	String(&'v str),
+	Number(&'v Value),
+	Missing,
+}
+
+/// Entries of the array sorted by the field of [`SortBy`], entries without it
+/// come last in document order
+pub(crate) fn sorted_array(
+	array: &[Value],
+	sort: &SortBy,
+	path: &str,
+) -> Result<Vec<Value>, Error> {
+	let mut fields = Vec::with_capacity(array.len());
+
+	for (i, item) in array.iter().enumerate() {
+		let field = match item.as_table().and_then(|table| table.get(&sort.field)) {
+			None if sort.missing == MissingSortField::Error => {
+				return Err(Error::MissingSortField {
+					path: String::from(path),
+					index: i + 1,
+					field: sort.field.clone(),
+				})
+			}
+			None => Field::Missing,
+			Some(Value::String(string)) if sort.compare == SortCompare::String => {
+				Field::String(string)
+			}
+			Some(number @ (Value::Integer(_) | Value::Float(_)))
+				if sort.compare == SortCompare::Numeric =>
+			{
+				Field::Number(number)
+			}
+			Some(other) => {
+				return Err(Error::InvalidSortField {
+					path: String::from(path),
+					index: i + 1,
+					found: other.type_str(),
+					expected: match sort.compare {
+						SortCompare::String => "a string",
+						SortCompare::Numeric => "a number",
+					},
+				})
+			}
+		};
+
+		fields.push((field, item));
+	}
+
+	// Stable, so equal fields keep their document order
+	fields.sort_by(|(a, _), (b, _)| {
+		let ordering = match (a, b) {
+			(Field::Missing, Field::Missing) => return Ordering::Equal,
+			(Field::Missing, _) => return Ordering::Greater,
+			(_, Field::Missing) => return Ordering::Less,
+			(Field::String(a), Field::String(b)) => a.cmp(b),
+			(Field::Number(a), Field::Number(b)) => compare_numbers(a, b),
+			_ => Ordering::Equal,
+		};
+
+		if sort.descending {
+			ordering.reverse()
+		} else {
+			ordering
+		}
+	});
+
+	Ok(fields.into_iter().map(|(_, item)| item.clone()).collect())
+}
+
+/// Integers are compared exactly, floats and mixed pairs by their total order as floats
+fn compare_numbers(a: &Value, b: &Value) -> Ordering {
+	match (a, b) {
+		(Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+		_ => as_float(a).total_cmp(&as_float(b)),
+	}
+}
+
+fn as_float(value: &Value) -> f64 {
+	match value {
+		Value::Integer(integer) => *integer as f64,
+		Value::Float(float) => *float,
+		_ => f64::NAN,
+	}
+}
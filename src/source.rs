@@ -0,0 +1,73 @@
+use toml_edit::{Item, Table, Value};
+
+/// Call `visit` with the dotted path of every value that is not an array or
+/// inline table, array indices start at 1 like in Lua
+pub(crate) fn visit_values<F>(table: &Table, visit: &mut F)
+where
+	F: FnMut(&[String], &Value),
+{
+	visit_all_values(table, &mut |path, value| {
+		if !matches!(value, Value::Array(_) | Value::InlineTable(_)) {
+			visit(path, value);
+		}
+	});
+}
+
+/// Same as [`visit_values`] including arrays and inline tables, which are
+/// visited before the values in them
+#[cfg_attr(not(feature = "comments"), allow(dead_code))]
+pub(crate) fn visit_all_values<F>(table: &Table, visit: &mut F)
+where
+	F: FnMut(&[String], &Value),
+{
+	visit_table(table, &mut Vec::new(), visit);
+}
+
+fn visit_table<F>(table: &Table, path: &mut Vec<String>, visit: &mut F)
+where
+	F: FnMut(&[String], &Value),
+{
+	for (key, item) in table.iter() {
+		path.push(key.to_owned());
+
+		match item {
+			Item::Value(value) => visit_value(value, path, visit),
+			Item::Table(table) => visit_table(table, path, visit),
+			Item::ArrayOfTables(array) => {
+				for (i, table) in array.iter().enumerate() {
+					path.push((i + 1).to_string());
+					visit_table(table, path, visit);
+					path.pop();
+				}
+			}
+			Item::None => {}
+		}
+
+		path.pop();
+	}
+}
+
+fn visit_value<F>(value: &Value, path: &mut Vec<String>, visit: &mut F)
+where
+	F: FnMut(&[String], &Value),
+{
+	visit(path, value);
+
+	match value {
+		Value::Array(array) => {
+			for (i, value) in array.iter().enumerate() {
+				path.push((i + 1).to_string());
+				visit_value(value, path, visit);
+				path.pop();
+			}
+		}
+		Value::InlineTable(table) => {
+			for (key, value) in table.iter() {
+				path.push(key.to_owned());
+				visit_value(value, path, visit);
+				path.pop();
+			}
+		}
+		_ => {}
+	}
+}
@@ -0,0 +1,353 @@
+use alloc::{
+	borrow::ToOwned,
+	string::{String, ToString},
+	vec,
+	vec::Vec,
+};
+
+use crate::Error;
+
+/// Replaces generated regions of handwritten Lua files, leaving the rest of
+/// the file as it is
+///
+/// A region starts at a line holding only the begin marker with its name and
+/// ends at the next line holding only the end marker, the lines in between
+/// are replaced. The generated Lua gets the `\r\n` line endings of the file if
+/// it has them and, with [`match_indent`](Self::match_indent), the
+/// indentation of the begin marker
+///
+/// ```rust
+/// use toml2lua::{parse, Splicer};
+///
+/// let lua = "local config = require(\"config\")\n\n-- <toml2lua:begin defaults>\n-- <toml2lua:end>\n";
+///
+/// let spliced = Splicer::new()
+/// 	.splice(lua, "defaults", &format!("local defaults = {}", parse("port = 80").unwrap()))
+/// 	.unwrap();
+///
+/// assert_eq!(
+/// 	spliced,
+/// 	"local config = require(\"config\")\n\n-- <toml2lua:begin defaults>\nlocal defaults = {\n\t[\"port\"] = 80,\n}\n-- <toml2lua:end>\n"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Splicer {
+	/// Begin marker, `{name}` stands for the name of the region
+	begin: String,
+	end: String,
+	match_indent: bool,
+}
+
+impl Default for Splicer {
+	fn default() -> Self {
+		Self {
+			begin: String::from("-- <toml2lua:begin {name}>"),
+			end: String::from("-- <toml2lua:end>"),
+			match_indent: false,
+		}
+	}
+}
+
+/// Region between a begin and an end marker
+struct Region<'a> {
+	name: &'a str,
+	indent: &'a str,
+	/// Byte range of the lines between the markers
+	start: usize,
+	end: usize,
+}
+
+impl Splicer {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Use other markers, `{name}` in the begin marker stands for the name of the region
+	pub fn markers(mut self, begin: &str, end: &str) -> Self {
+		self.begin = begin.to_owned();
+		self.end = end.to_owned();
+		self
+	}
+
+	/// Indent every line of the generated Lua like the begin marker, except
+	/// lines continuing a long string or comment, whose contents would change
+	pub fn match_indent(mut self, match_indent: bool) -> Self {
+		self.match_indent = match_indent;
+		self
+	}
+
+	/// Replace the region with the given name by the generated Lua
+	///
+	/// Every marker of the file is checked, so unmatched, nested and
+	/// duplicate markers are rejected even outside of the region
+	pub fn splice(&self, lua: &str, name: &str, generated: &str) -> Result<String, Error> {
+		let regions = self.regions(lua)?;
+
+		let Some(region) = regions.iter().find(|region| region.name == name) else {
+			return Err(Error::MissingMarker {
+				name: name.to_owned(),
+			});
+		};
+
+		let newline = if lua.contains("\r\n") { "\r\n" } else { "\n" };
+		let indent = if self.match_indent { region.indent } else { "" };
+
+		let mut spliced = String::with_capacity(lua.len() + generated.len());
+		spliced.push_str(&lua[..region.start]);
+
+		let continued = continued_lines(generated);
+
+		for (line, continued) in generated.lines().zip(continued) {
+			if !line.is_empty() && !continued {
+				spliced.push_str(indent);
+			}
+
+			spliced.push_str(line);
+			spliced.push_str(newline);
+		}
+
+		spliced.push_str(&lua[region.end..]);
+
+		Ok(spliced)
+	}
+
+	/// Whether splicing the generated Lua would change the file, without
+	/// splicing it. Meant for CI, like [`is_up_to_date`](crate::is_up_to_date)
+	pub fn is_stale(&self, lua: &str, name: &str, generated: &str) -> Result<bool, Error> {
+		Ok(self.splice(lua, name, generated)? != lua)
+	}
+
+	fn regions<'a>(&self, lua: &'a str) -> Result<Vec<Region<'a>>, Error> {
+		let (prefix, suffix) = self.begin.split_once("{name}").unwrap_or((&self.begin, ""));
+
+		let mut regions: Vec<Region> = Vec::new();
+		let mut open: Option<(Region, usize)> = None;
+		let mut offset = 0;
+
+		for (i, line) in lua.split_inclusive('\n').enumerate() {
+			let start = offset;
+			offset += line.len();
+
+			let trimmed = line.trim();
+			let number = i + 1;
+
+			if trimmed == self.end {
+				let Some((mut region, _)) = open.take() else {
+					return Err(Error::UnmatchedMarker { line: number });
+				};
+
+				region.end = start;
+				regions.push(region);
+				continue;
+			}
+
+			let Some(name) = trimmed
+				.strip_prefix(prefix)
+				.and_then(|rest| rest.strip_suffix(suffix))
+				.map(str::trim)
+				.filter(|name| !name.is_empty())
+			else {
+				continue;
+			};
+
+			if open.is_some() {
+				return Err(Error::NestedMarker { line: number });
+			}
+
+			if regions.iter().any(|region| region.name == name) {
+				return Err(Error::DuplicateMarker {
+					name: name.to_string(),
+				});
+			}
+
+			let indent = &line[..line.len() - line.trim_start().len()];
+
+			open = Some((
+				Region {
+					name,
+					indent,
+					start: offset,
+					end: offset,
+				},
+				number,
+			));
+		}
+
+		match open {
+			Some((_, line)) => Err(Error::UnmatchedMarker { line }),
+			None => Ok(regions),
+		}
+	}
+}
+
+/// Whether each line of the Lua code starts inside a long bracket or a short
+/// string continued by `\` at the end of the previous line
+fn continued_lines(lua: &str) -> Vec<bool> {
+	let bytes = lua.as_bytes();
+	let mut continued = vec![false];
+
+	// Level of the open long bracket, quote of the open short string
+	let mut long: Option<usize> = None;
+	let mut quote: Option<u8> = None;
+	let mut comment = false;
+	let mut i = 0;
+
+	while let Some(&byte) = bytes.get(i) {
+		i += 1;
+
+		if byte == b'\n' {
+			continued.push(long.is_some() || quote.is_some());
+			comment = false;
+			quote = None;
+		} else if let Some(level) = long {
+			if byte == b']' && closes_long_bracket(&bytes[i..], level) {
+				long = None;
+				i += level + 1;
+			}
+		} else if let Some(open) = quote {
+			if byte == b'\\' && bytes.get(i) == Some(&b'\n') {
+				continued.push(true);
+				i += 1;
+			} else if byte == b'\\' {
+				i += 1;
+			} else if byte == open {
+				quote = None;
+			}
+		} else if comment {
+		} else if byte == b'-' && bytes.get(i) == Some(&b'-') {
+			i += 1;
+			long = long_bracket_level(&bytes[i..]);
+			comment = long.is_none();
+			i += long.map_or(0, |level| level + 2);
+		} else if byte == b'"' || byte == b'\'' {
+			quote = Some(byte);
+		} else if byte == b'[' {
+			long = long_bracket_level(&bytes[i - 1..]);
+			i += long.map_or(0, |level| level + 1);
+		}
+	}
+
+	continued
+}
+
+/// Level of the long bracket like `[==[` the bytes start with
+fn long_bracket_level(bytes: &[u8]) -> Option<usize> {
+	let rest = bytes.strip_prefix(b"[")?;
+	let level = rest.iter().take_while(|byte| **byte == b'=').count();
+
+	(rest.get(level) == Some(&b'[')).then_some(level)
+}
+
+/// Whether the bytes following a `]` close a long bracket of the level
+fn closes_long_bracket(bytes: &[u8], level: usize) -> bool {
+	bytes.len() > level && bytes[..level].iter().all(|byte| *byte == b'=') && bytes[level] == b']'
+}
+
+#[cfg(test)]
+mod test {
+	use super::Splicer;
+	use crate::Error;
+
+	const LUA: &str = "local M = {}\n\n\t-- <toml2lua:begin defaults>\n\tM.old = true\n\t-- <toml2lua:end>\n\nreturn M\n";
+
+	#[test]
+	fn splice() {
+		let splicer = Splicer::new().match_indent(true);
+		let spliced = splicer
+			.splice(LUA, "defaults", "M.port = 80\n\nM.host = \"a\"")
+			.unwrap();
+
+		assert_eq!(
+			spliced,
+			"local M = {}\n\n\t-- <toml2lua:begin defaults>\n\tM.port = 80\n\n\tM.host = \"a\"\n\t-- <toml2lua:end>\n\nreturn M\n"
+		);
+
+		// Splicing the same Lua again changes nothing
+		assert_eq!(
+			splicer
+				.splice(&spliced, "defaults", "M.port = 80\n\nM.host = \"a\"")
+				.unwrap(),
+			spliced
+		);
+		assert!(!splicer
+			.is_stale(&spliced, "defaults", "M.port = 80\n\nM.host = \"a\"")
+			.unwrap());
+		assert!(splicer
+			.is_stale(&spliced, "defaults", "M.port = 81")
+			.unwrap());
+
+		let crlf = LUA.replace('\n', "\r\n");
+		assert_eq!(
+			Splicer::new().splice(&crlf, "defaults", "M.port = 80\n").unwrap(),
+			"local M = {}\r\n\r\n\t-- <toml2lua:begin defaults>\r\nM.port = 80\r\n\t-- <toml2lua:end>\r\n\r\nreturn M\r\n"
+		);
+
+		let custom = Splicer::new().markers("--[[ gen {name} ]]", "--[[ /gen ]]");
+		assert_eq!(
+			custom
+				.splice("--[[ gen a ]]\n--[[ /gen ]]", "a", "x = 1")
+				.unwrap(),
+			"--[[ gen a ]]\nx = 1\n--[[ /gen ]]"
+		);
+	}
+
+	#[test]
+	fn long_strings() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let options = ParseOptions {
+			long_strings: true,
+			..Default::default()
+		};
+
+		let toml = "s = \"line1\\nline2\"\nt = \"[[a]]\"\nu = \"x\\n]]\"";
+		let generated = format!("M.config = {}", parse_with_options(toml, &options).unwrap());
+		let spliced = Splicer::new()
+			.match_indent(true)
+			.splice(LUA, "defaults", &generated)
+			.unwrap();
+
+		assert!(spliced.contains("\n\t\t[\"s\"] = [[\nline1\nline2]],\n"));
+
+		#[cfg(any(feature = "verify", feature = "validate"))]
+		{
+			let lua = mlua::Lua::new();
+			let config: mlua::Table = lua
+				.load(&spliced)
+				.call::<mlua::Table>(())
+				.unwrap()
+				.get("config")
+				.unwrap();
+
+			assert_eq!(config.get::<String>("s").unwrap(), "line1\nline2");
+			assert_eq!(config.get::<String>("t").unwrap(), "[[a]]");
+			assert_eq!(config.get::<String>("u").unwrap(), "x\n]]");
+		}
+	}
+
+	#[test]
+	fn marker_errors() {
+		let splice = |lua: &str| Splicer::new().splice(lua, "a", "x = 1").unwrap_err();
+
+		assert!(matches!(
+			splice("-- <toml2lua:begin b>\n-- <toml2lua:end>\n"),
+			Error::MissingMarker { name } if name == "a"
+		));
+		assert!(matches!(
+			splice("x = 1\n-- <toml2lua:begin a>\n"),
+			Error::UnmatchedMarker { line: 2 }
+		));
+		assert!(matches!(
+			splice("-- <toml2lua:end>\n"),
+			Error::UnmatchedMarker { line: 1 }
+		));
+		assert!(matches!(
+			splice("-- <toml2lua:begin a>\n-- <toml2lua:begin b>\n-- <toml2lua:end>\n-- <toml2lua:end>\n"),
+			Error::NestedMarker { line: 2 }
+		));
+		assert!(matches!(
+			splice("-- <toml2lua:begin a>\n-- <toml2lua:end>\n-- <toml2lua:begin a>\n-- <toml2lua:end>\n"),
+			Error::DuplicateMarker { name } if name == "a"
+		));
+	}
+}
@@ -0,0 +1,356 @@
+use std::{
+	collections::BTreeMap,
+	fs,
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+
+use toml::Value;
+
+use crate::{locate::from_str, walk::Walker, Error, Map, ParseOptions, Wrap};
+
+/// Name of the index module, `require(prefix)` loads it as `prefix/init.lua`
+const INDEX: &str = "init";
+
+/// Converts a document into several Lua modules and an index module
+/// assembling them into the table [`parse_with_options`](crate::parse_with_options) writes
+///
+/// Every top-level table becomes its own module, or with
+/// [`split_array`](Self::split_array) every item of a top-level array of
+/// tables. The index is `init.lua` and `require`s them, other values are
+/// written in it. Module files are named after their key with everything but
+/// ASCII letters, digits, `_` and `-` replaced by `_`
+///
+/// `dedupe` and `split_locals` are not used by the index, the index is written
+/// as `return { ... }` unless `wrap` is `Local`
+///
+/// ```rust
+/// use toml2lua::{ParseOptions, Splitter};
+///
+/// let toml = r#"
+/// format = 2
+///
+/// [swords]
+/// damage = 10
+/// "#;
+///
+/// let files = Splitter::new(ParseOptions::default())
+/// 	.require_prefix("data.items")
+/// 	.split(toml)
+/// 	.unwrap();
+///
+/// assert_eq!(files[0].path.to_str(), Some("init.lua"));
+/// assert_eq!(
+/// 	files[0].lua,
+/// 	"return {\n\t[\"format\"] = 2,\n\t[\"swords\"] = require(\"data.items.swords\"),\n}"
+/// );
+/// assert_eq!(files[1].path.to_str(), Some("swords.lua"));
+/// assert_eq!(files[1].lua, "return {\n\t[\"damage\"] = 10,\n}");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Splitter {
+	options: ParseOptions,
+	require_prefix: String,
+	array: Option<String>,
+}
+
+/// Value written as its own module
+struct Part<'v> {
+	/// Module path relative to the output directory
+	module: Vec<String>,
+	/// Key path of the value in the document
+	key: Vec<String>,
+	value: &'v Value,
+}
+
+/// Lua module written by a [`Splitter`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitFile {
+	/// Path relative to the output directory
+	pub path: PathBuf,
+	pub lua: String,
+}
+
+impl Splitter {
+	pub fn new(options: ParseOptions) -> Self {
+		Self {
+			options,
+			require_prefix: String::new(),
+			array: None,
+		}
+	}
+
+	/// Module path of the output directory, the index requires `<prefix>.<name>`
+	pub fn require_prefix(mut self, prefix: &str) -> Self {
+		self.require_prefix = prefix.to_owned();
+		self
+	}
+
+	/// Write every item of the top-level array of tables with the given key
+	/// as a module in a directory named after the key, instead of every top-level table
+	pub fn split_array(mut self, key: &str) -> Self {
+		self.array = Some(key.to_owned());
+		self
+	}
+
+	/// Convert the document into the index module followed by the modules it requires
+	pub fn split(&self, toml: &str) -> Result<Vec<SplitFile>, Error> {
+		let map: Map = from_str(toml)?;
+
+		let mut files = vec![SplitFile {
+			path: PathBuf::from(format!("{}.lua", INDEX)),
+			lua: String::new(),
+		}];
+		let mut references = BTreeMap::new();
+
+		for Part { module, key, value } in self.parts(&map)? {
+			let file = PathBuf::from(format!("{}.lua", module.join("/")));
+
+			let mut walker = Walker::new(&self.options).with_path(key);
+			walker.walk_fragment(value, 0)?;
+
+			let mut lua = format!("return {}", walker.finish());
+
+			if self.options.final_newline {
+				lua.push_str(self.options.newline.as_str());
+			}
+
+			let name = match self.require_prefix.as_str() {
+				"" => module.join("."),
+				prefix => format!("{}.{}", prefix, module.join(".")),
+			};
+
+			references.insert(
+				value as *const Value as usize,
+				format!("require({:?})", name),
+			);
+			files.push(SplitFile { path: file, lua });
+		}
+
+		let options = ParseOptions {
+			dedupe: None,
+			split_locals: false,
+			wrap: match &self.options.wrap {
+				Wrap::Local(name) => Wrap::Local(name.clone()),
+				_ => Wrap::Return,
+			},
+			..self.options.clone()
+		};
+
+		let mut walker = Walker::new(&options).with_references(references);
+		walker.walk_root(&map)?;
+		files[0].lua = walker.finish();
+
+		Ok(files)
+	}
+
+	/// Write the modules to the given directory, creating it and the
+	/// directories of split arrays if needed
+	pub fn write_dir(&self, toml: &str, dir: &Path) -> Result<(), Error> {
+		self.write_with(toml, |path| {
+			let path = dir.join(path);
+
+			if let Some(parent) = path.parent() {
+				fs::create_dir_all(parent)?;
+			}
+
+			fs::File::create(path)
+		})
+	}
+
+	/// Write every module to the writer `create` returns for its relative path
+	pub fn write_with<F, W>(&self, toml: &str, mut create: F) -> Result<(), Error>
+	where
+		F: FnMut(&Path) -> io::Result<W>,
+		W: Write,
+	{
+		for file in self.split(toml)? {
+			create(&file.path)
+				.and_then(|mut writer| writer.write_all(file.lua.as_bytes()))
+				.map_err(|error| Error::Write {
+					path: file.path.clone(),
+					error,
+				})?;
+		}
+
+		Ok(())
+	}
+
+	fn parts<'v>(&self, map: &'v Map) -> Result<Vec<Part<'v>>, Error> {
+		let mut parts = Vec::new();
+		// Key of every module name, told apart case insensitively for file systems that don't
+		let mut names: BTreeMap<String, String> = BTreeMap::new();
+
+		let mut claim =
+			|key: &str, name: String| match names.insert(name.to_lowercase(), key.to_owned()) {
+				Some(first) => Err(Error::ModuleCollision {
+					first,
+					second: key.to_owned(),
+					module: name,
+				}),
+				None => Ok(name),
+			};
+
+		match &self.array {
+			Some(key) => {
+				let items = match map.get(key) {
+					Some(Value::Array(items)) if items.iter().all(Value::is_table) => items,
+					_ => return Err(Error::NotArrayOfTables { key: key.clone() }),
+				};
+
+				let directory = claim(key, module_name(key))?;
+
+				for (i, item) in items.iter().enumerate() {
+					let index = (i + 1).to_string();
+
+					parts.push(Part {
+						module: vec![directory.clone(), index.clone()],
+						key: vec![key.clone(), index],
+						value: item,
+					});
+				}
+			}
+			None => {
+				for (key, value) in map.iter().filter(|(_, value)| value.is_table()) {
+					let name = module_name(key);
+
+					if name.to_lowercase() == INDEX {
+						return Err(Error::ReservedKey {
+							path: key.clone(),
+							key: key.clone(),
+						});
+					}
+
+					parts.push(Part {
+						module: vec![claim(key, name)?],
+						key: vec![key.clone()],
+						value,
+					});
+				}
+			}
+		}
+
+		Ok(parts)
+	}
+}
+
+/// File and module name of the key
+fn module_name(key: &str) -> String {
+	let name: String = key
+		.chars()
+		.map(|c| match c {
+			'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+			_ => '_',
+		})
+		.collect();
+
+	if name.is_empty() {
+		String::from("_")
+	} else {
+		name
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::module_name;
+	#[cfg(feature = "verify")]
+	use super::Splitter;
+	#[cfg(feature = "verify")]
+	use crate::{parse_with_options, ParseOptions};
+
+	#[test]
+	fn module_names() {
+		assert_eq!(module_name("swords"), "swords");
+		assert_eq!(module_name("long-bows_2"), "long-bows_2");
+		assert_eq!(module_name("a.b c"), "a_b_c");
+		assert_eq!(module_name("épée"), "_p_e");
+		assert_eq!(module_name(""), "_");
+	}
+
+	#[cfg(feature = "verify")]
+	#[test]
+	fn assembled() {
+		let toml = r#"
+version = 2
+tags = ["a", "b"]
+
+[swords]
+damage = 10
+names = { short = "sw" }
+
+[bows.long]
+range = 30
+
+[[arrows]]
+tip = "iron"
+
+[[arrows]]
+tip = "flint"
+"#;
+
+		let options = ParseOptions {
+			key_by: [(String::from("arrows"), String::from("tip"))].into(),
+			..Default::default()
+		};
+
+		let lua = mlua::Lua::new();
+		let single = parse_with_options(toml, &options).unwrap();
+
+		for splitter in [
+			Splitter::new(options.clone()).require_prefix("items"),
+			Splitter::new(options.clone()).split_array("arrows"),
+		] {
+			let preload: mlua::Table = lua.load("package.preload").eval().unwrap();
+			let mut index = String::new();
+
+			for file in splitter.split(toml).unwrap() {
+				let module = file
+					.path
+					.with_extension("")
+					.to_str()
+					.unwrap()
+					.replace('/', ".");
+
+				if module == "init" {
+					index = file.lua;
+					continue;
+				}
+
+				let module = match &splitter.require_prefix[..] {
+					"" => module,
+					prefix => format!("{}.{}", prefix, module),
+				};
+
+				preload
+					.set(module, lua.load(file.lua).into_function().unwrap())
+					.unwrap();
+			}
+
+			lua.load(format!(
+				r#"
+				local function equal(a, b)
+					if type(a) ~= "table" or type(b) ~= "table" then
+						return a == b
+					end
+
+					for k, v in pairs(a) do
+						if not equal(v, b[k]) then return false end
+					end
+
+					for k in pairs(b) do
+						if a[k] == nil then return false end
+					end
+
+					return true
+				end
+
+				assert(equal((function() {} end)(), {}))
+				"#,
+				index, single
+			))
+			.exec()
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,222 @@
+use alloc::string::{String, ToString};
+use toml::Value;
+
+use crate::{
+	walk::{float_literal, is_valid_lua_identifier},
+	DatetimeMode, Indent, Map, Newline, ParseOptions,
+};
+
+/// Counts of what a conversion produced, see [`parse_with_stats`](crate::parse_with_stats)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConversionStats {
+	/// Table constructors with keys, including the root table
+	pub tables: usize,
+	/// Array constructors
+	pub arrays: usize,
+	/// Strings, numbers, booleans and datetimes
+	pub scalars: usize,
+	/// Deepest nesting of constructors, the root table is at depth 1
+	pub max_depth: usize,
+	/// Entries and items of every constructor, including the `n` field of
+	/// packed arrays and the order metadata
+	pub fields: usize,
+}
+
+impl ConversionStats {
+	/// Table and array constructors, together with [`fields`](Self::fields)
+	/// an estimate of how long the output takes to load
+	pub fn constructors(&self) -> usize {
+		self.tables + self.arrays
+	}
+}
+
+/// Statistics of a TOML document, see [`analyze`](crate::analyze)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+	/// Keys of every table, including the root table
+	pub keys: usize,
+	/// Deepest nesting of tables and arrays, the root table is at depth 1
+	pub max_depth: usize,
+	/// Items of the longest array
+	pub max_array_len: usize,
+	/// Bytes of every string value, keys are not included
+	pub string_bytes: usize,
+	/// Tables, including the root table
+	pub tables: usize,
+	pub arrays: usize,
+	pub strings: usize,
+	pub integers: usize,
+	pub floats: usize,
+	pub booleans: usize,
+	pub datetimes: usize,
+	/// Items of every array
+	items: usize,
+	/// Bytes of every key
+	key_bytes: usize,
+	/// Keys that can be written as bare identifiers
+	identifier_keys: usize,
+	/// Bytes of the literals of numbers, booleans and datetimes
+	literal_bytes: usize,
+	/// Bytes added by escaping strings and keys
+	escape_bytes: usize,
+	/// Levels of indentation summed over every line
+	indent_levels: usize,
+	/// Tables and arrays without entries
+	empty: usize,
+	/// Levels of indentation summed over the closing braces of empty tables and arrays
+	empty_indent_levels: usize,
+}
+
+impl Stats {
+	pub(crate) fn of(map: &Map) -> Self {
+		let mut stats = Stats {
+			max_depth: 1,
+			..Stats::default()
+		};
+
+		stats.visit_table(map.iter(), 1);
+		stats
+	}
+
+	/// Estimate of the length of the output with the given options
+	///
+	/// Exact for the multiline layout of the default options, except for the
+	/// escapes of control and non-ASCII characters. Options changing the layout,
+	/// like `max_width`, `align_assignments`, `flatten` or `header`, are not
+	/// accounted for
+	pub fn estimated_output_len(&self, options: &ParseOptions) -> usize {
+		let indent = match options.indent {
+			Indent::Tabs => 1,
+			Indent::Spaces(spaces) => spaces,
+		};
+
+		let quoted_keys = if options.bare_keys && !options.json_compat {
+			self.keys - self.identifier_keys
+		} else {
+			self.keys
+		};
+
+		let constructors = self.tables + self.arrays;
+
+		// Entries are `key = value,\n` and items `value,\n` after their indentation,
+		// tables and arrays `{\n` followed by their closing brace
+		let mut len = self.key_bytes
+			+ quoted_keys * 4
+			+ self.keys * 3
+			+ (self.keys + self.items) * 2
+			+ self.string_bytes
+			+ self.strings * 2
+			+ self.literal_bytes
+			+ self.escape_bytes
+			+ constructors * 3
+			+ self.indent_levels * indent;
+
+		if options.datetime_mode == DatetimeMode::String {
+			len += self.datetimes * 2;
+		}
+
+		if options.compact_empty {
+			len -= self.empty + self.empty_indent_levels * indent;
+		}
+
+		if !options.trailing_commas {
+			len -= constructors - self.empty;
+		}
+
+		if options.final_newline {
+			len += options.newline.as_str().len();
+		}
+
+		// Every entry, item and opening brace ends its line
+		if options.newline == Newline::CrLf {
+			len += self.keys + self.items + constructors;
+
+			if options.compact_empty {
+				len -= self.empty;
+			}
+		}
+
+		len
+	}
+
+	/// Count a table whose entries are written at `depth`
+	fn visit_table<'v>(
+		&mut self,
+		entries: impl ExactSizeIterator<Item = (&'v String, &'v Value)>,
+		depth: usize,
+	) {
+		self.tables += 1;
+		self.close(entries.len() == 0, depth);
+
+		for (key, value) in entries {
+			self.keys += 1;
+			self.key_bytes += key.len();
+			self.escape_bytes += escapes(key);
+			self.indent_levels += depth;
+
+			if is_valid_lua_identifier(key, true) {
+				self.identifier_keys += 1;
+			}
+
+			self.visit(value, depth);
+		}
+	}
+
+	/// Count a value written on a line at `depth`
+	fn visit(&mut self, value: &Value, depth: usize) {
+		match value {
+			Value::String(string) => {
+				self.strings += 1;
+				self.string_bytes += string.len();
+				self.escape_bytes += escapes(string);
+			}
+			Value::Integer(integer) => {
+				self.integers += 1;
+				self.literal_bytes += integer.to_string().len();
+			}
+			Value::Float(float) => {
+				self.floats += 1;
+				self.literal_bytes += float_literal(*float).len();
+			}
+			Value::Boolean(boolean) => {
+				self.booleans += 1;
+				self.literal_bytes += if *boolean { 4 } else { 5 };
+			}
+			Value::Datetime(datetime) => {
+				self.datetimes += 1;
+				self.literal_bytes += datetime.to_string().len();
+			}
+			Value::Array(array) => {
+				self.arrays += 1;
+				self.items += array.len();
+				self.max_array_len = self.max_array_len.max(array.len());
+				self.close(array.is_empty(), depth + 1);
+
+				for item in array {
+					self.indent_levels += depth + 1;
+					self.visit(item, depth + 1);
+				}
+			}
+			Value::Table(table) => self.visit_table(table.iter(), depth + 1),
+		}
+	}
+
+	/// Count the closing brace of a table or array whose entries are at `depth`
+	fn close(&mut self, empty: bool, depth: usize) {
+		self.max_depth = self.max_depth.max(depth);
+		self.indent_levels += depth - 1;
+
+		if empty {
+			self.empty += 1;
+			self.empty_indent_levels += depth - 1;
+		}
+	}
+}
+
+/// Bytes added by escaping the string in a double quoted literal
+fn escapes(string: &str) -> usize {
+	string
+		.bytes()
+		.filter(|byte| matches!(byte, b'"' | b'\\' | b'\n' | b'\r' | b'\t'))
+		.count()
+}
@@ -0,0 +1,654 @@
+use std::{
+	collections::HashSet,
+	io::{BufRead, BufReader, Read, Write},
+};
+use toml::{from_str, Table, Value};
+
+use crate::{walk::Walker, with_types, Error, Map, OrderMetadata, ParseOptions};
+
+/// Iterator converting TOML into a Lua table one top-level entry at a time
+///
+/// Every item is the Lua written for the next top-level entry, the first one
+/// also holds everything above the root table and the last one everything
+/// below it. Joined together they equal the output of
+/// [`parse_with_options`](crate::parse_with_options). The document is parsed
+/// up front, the caller drives the pacing of the conversion
+///
+/// Options that shape the root table as a whole, like `split_locals`,
+/// `dedupe`, `flatten` or `order_metadata`, produce the whole output as a
+/// single item. After an error, including a parse error, no items follow
+///
+/// ```rust
+/// use toml2lua::{parse, ParseChunks, ParseOptions};
+///
+/// let toml = "a = 1\nb = 2";
+/// let options = ParseOptions::default();
+///
+/// let chunks: Vec<String> = ParseChunks::new(toml, &options)
+/// 	.collect::<Result<_, _>>()
+/// 	.unwrap();
+///
+/// assert_eq!(chunks, ["{\n\t[\"a\"] = 1,\n", "\t[\"b\"] = 2,\n}"]);
+/// assert_eq!(chunks.concat(), parse(toml).unwrap());
+/// ```
+pub struct ParseChunks<'a> {
+	walker: Walker<'a>,
+	options: &'a ParseOptions,
+	map: Map,
+	/// Keys of the top-level entries in the order they are written, set once
+	/// the root table is opened
+	keys: Vec<String>,
+	/// Next top-level entry to write, `None` until the root table is opened
+	next: Option<usize>,
+	error: Option<Error>,
+	done: bool,
+}
+
+impl<'a> ParseChunks<'a> {
+	pub fn new(toml: &str, options: &'a ParseOptions) -> Self {
+		let walker = || Walker::new(options).with_source(None, Some(toml));
+
+		let (walker, map, error) = match crate::locate::from_str::<Map>(toml) {
+			Ok(map) => match with_types(&map, options, walker()) {
+				Ok(walker) => (walker, map, None),
+				Err(err) => (walker(), map, Some(err)),
+			},
+			Err(err) => (walker(), Map::new(), Some(err)),
+		};
+
+		Self {
+			walker,
+			options,
+			map,
+			keys: Vec::new(),
+			next: None,
+			error,
+			done: false,
+		}
+	}
+
+	/// Whether the root table can be written entry by entry with the same result
+	fn streams(&self) -> bool {
+		let options = self.options;
+		let len = self.map.len();
+
+		!options.split_locals
+			&& options.dedupe.is_none()
+			&& !options.flatten
+			&& options.order_metadata == OrderMetadata::None
+			&& !(options.unwrap_single && len == 1)
+			&& len > 0
+			&& options.chunking.is_none_or(|size| len <= size.get())
+	}
+
+	fn advance(&mut self) -> Result<String, Error> {
+		let index = match self.next {
+			Some(index) => index,
+			None if !self.streams() => {
+				self.done = true;
+				self.walker.walk_root(&self.map)?;
+
+				return Ok(self.walker.take());
+			}
+			None => {
+				self.keys = self.walker.open_stream_entries(&self.map)?;
+
+				// Every entry was left out, the root table is closed already
+				if self.keys.is_empty() {
+					self.done = true;
+					return Ok(self.walker.take());
+				}
+
+				0
+			}
+		};
+
+		let key = &self.keys[index];
+		let last = index + 1 == self.keys.len();
+
+		self.walker
+			.stream_entry(index, (key, &self.map[key]), last)?;
+		self.next = Some(index + 1);
+
+		if last {
+			self.done = true;
+			self.walker.close_stream(&Map::new())?;
+		}
+
+		Ok(self.walker.take())
+	}
+}
+
+impl Iterator for ParseChunks<'_> {
+	type Item = Result<String, Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+
+		if let Some(err) = self.error.take() {
+			self.done = true;
+			return Some(Err(err));
+		}
+
+		let chunk = self.advance();
+		self.done |= chunk.is_err();
+
+		Some(chunk)
+	}
+}
+
+/// Convert TOML read from `reader` into a Lua table written to `writer` as it goes
+///
+/// Top-level arrays of tables (`[[name]]` sections) are converted and written
+/// one element at a time, so memory stays roughly constant no matter how many
+/// elements they have. Everything else is kept in memory and written at the
+/// end, after the streamed arrays. Output is otherwise the same as [`parse`](crate::parse),
+/// without the `preserve_order` feature the streamed arrays still come first
+///
+/// Sub-tables of an element (`[name.sub]`) must directly follow it and an
+/// array can't be continued after another array of tables started, returning
+/// [`Error::NotStreamable`]. Parse errors are reported relative to the section
+/// they occurred in
+///
+/// ```rust
+/// use toml2lua::write_lua_streaming;
+///
+/// let toml = r#"
+/// [[records]]
+/// id = 1
+///
+/// [[records]]
+/// id = 2
+/// "#;
+///
+/// let mut lua = Vec::new();
+/// write_lua_streaming(toml.as_bytes(), &mut lua).unwrap();
+///
+/// assert_eq!(
+/// 	String::from_utf8(lua).unwrap(),
+/// 	toml2lua::parse(toml).unwrap()
+/// );
+/// ```
+pub fn write_lua_streaming<R: Read, W: Write>(reader: R, mut writer: W) -> Result<(), Error> {
+	let options = ParseOptions::default();
+	let mut walker = Walker::new(&options);
+
+	let mut scanner = Scanner::default();
+	let mut rest = String::new();
+	let mut element = String::new();
+	let mut in_element = false;
+
+	// Key of the array being streamed and the number of its written elements
+	let mut current: Option<(String, usize)> = None;
+	let mut streamed = HashSet::new();
+
+	walker.open_stream()?;
+	write(&mut writer, walker.take())?;
+
+	for line in BufReader::new(reader).lines() {
+		let line = line.map_err(Error::Stream)?;
+
+		if scanner.is_header(&line) {
+			let (key, is_array) = header(&line)?;
+			let continues = matches!(&current, Some((current, _)) if *current == key);
+
+			// Sub-tables of the last element stay part of it
+			if !(in_element && continues && !is_array) {
+				if in_element {
+					flush_element(&mut walker, &mut current, &mut element)?;
+					write(&mut writer, walker.take())?;
+				}
+
+				in_element = is_array;
+			}
+
+			if is_array && !continues {
+				if current.take().is_some() {
+					walker.close_stream_array();
+				}
+
+				if !streamed.insert(key.clone()) {
+					return Err(Error::NotStreamable { key });
+				}
+
+				walker.open_stream_array(&key)?;
+				current = Some((key, 0));
+			}
+		}
+
+		let target = if in_element { &mut element } else { &mut rest };
+		target.push_str(&line);
+		target.push('\n');
+	}
+
+	if in_element {
+		flush_element(&mut walker, &mut current, &mut element)?;
+	}
+
+	if current.is_some() {
+		walker.close_stream_array();
+	}
+
+	let rest: Map = from_str(&rest)?;
+
+	if let Some(key) = rest.keys().find(|key| streamed.contains(*key)) {
+		return Err(Error::NotStreamable { key: key.clone() });
+	}
+
+	walker.close_stream(&rest)?;
+	write(&mut writer, walker.take())?;
+
+	writer.flush().map_err(Error::Stream)
+}
+
+/// Convert the buffered element of the streamed array and clear the buffer
+fn flush_element(
+	walker: &mut Walker,
+	current: &mut Option<(String, usize)>,
+	element: &mut String,
+) -> Result<(), Error> {
+	let Some((key, written)) = current else {
+		return Ok(());
+	};
+
+	let mut section: Table = from_str(element)?;
+	element.clear();
+
+	if let Some(Value::Array(items)) = section.remove(key.as_str()) {
+		for item in &items {
+			*written += 1;
+			walker.stream_item(*written, item)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// First key of a `[table]` or `[[array]]` header and whether it is a top-level array of tables
+fn header(line: &str) -> Result<(String, bool), Error> {
+	let table: Table = from_str(line)?;
+
+	Ok(match table.into_iter().next() {
+		Some((key, value)) => (key, value.is_array()),
+		None => (String::new(), false),
+	})
+}
+
+fn write<W: Write>(writer: &mut W, lua: String) -> Result<(), Error> {
+	writer.write_all(lua.as_bytes()).map_err(Error::Stream)
+}
+
+/// Tracks multi-line strings and brackets across lines to tell headers from values
+#[derive(Default)]
+struct Scanner {
+	multiline: Option<&'static [u8]>,
+	depth: usize,
+}
+
+impl Scanner {
+	/// Whether the line is a section header, other lines are scanned for strings and brackets
+	fn is_header(&mut self, line: &str) -> bool {
+		if self.multiline.is_none() && self.depth == 0 && line.trim_start().starts_with('[') {
+			return true;
+		}
+
+		self.scan(line.as_bytes());
+
+		false
+	}
+
+	fn scan(&mut self, line: &[u8]) {
+		let mut i = 0;
+
+		while i < line.len() {
+			if let Some(delimiter) = self.multiline {
+				if line[i] == b'\\' && delimiter == b"\"\"\"" {
+					i += 2;
+				} else if line[i..].starts_with(delimiter) {
+					self.multiline = None;
+					i += delimiter.len();
+				} else {
+					i += 1;
+				}
+
+				continue;
+			}
+
+			match line[i] {
+				b'#' => return,
+				b'[' | b'{' => self.depth += 1,
+				b']' | b'}' => self.depth = self.depth.saturating_sub(1),
+				quote @ (b'"' | b'\'') => {
+					let delimiter: &'static [u8] = if quote == b'"' { b"\"\"\"" } else { b"'''" };
+
+					if line[i..].starts_with(delimiter) {
+						self.multiline = Some(delimiter);
+						i += delimiter.len();
+						continue;
+					}
+
+					i += 1;
+
+					while i < line.len() && line[i] != quote {
+						if quote == b'"' && line[i] == b'\\' {
+							i += 1;
+						}
+
+						i += 1;
+					}
+				}
+				_ => {}
+			}
+
+			i += 1;
+		}
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use std::{fmt::Write as _, io};
+
+	use super::{write_lua_streaming, ParseChunks};
+	use crate::{parse, Error};
+
+	/// Writer counting the calls it received
+	#[derive(Default)]
+	struct Chunks {
+		lua: Vec<u8>,
+		writes: usize,
+	}
+
+	impl io::Write for Chunks {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			self.writes += 1;
+			self.lua.extend_from_slice(buf);
+			Ok(buf.len())
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+
+	fn stream(toml: &str) -> Result<String, Error> {
+		let mut lua = Vec::new();
+		write_lua_streaming(toml.as_bytes(), &mut lua)?;
+
+		Ok(String::from_utf8(lua).unwrap())
+	}
+
+	#[test]
+	fn large_input() {
+		let mut toml = String::new();
+
+		for i in 0..10_000 {
+			write!(
+				toml,
+				"[[records]]\nid = {}\nname = \"record {}\"\ntags = [\n\t\"a\",\n\t\"b\",\n]\n\n",
+				i, i
+			)
+			.unwrap();
+		}
+
+		toml.push_str("[summary]\ncount = 10000\n");
+
+		let mut chunks = Chunks::default();
+		write_lua_streaming(toml.as_bytes(), &mut chunks).unwrap();
+
+		assert!(chunks.writes > 10_000);
+		assert_eq!(
+			String::from_utf8(chunks.lua).unwrap(),
+			parse(&toml).unwrap()
+		);
+	}
+
+	#[test]
+	fn sections() {
+		let toml = r#"
+[[items]]
+name = "sword"
+description = """
+[[not a header]]
+"""
+grid = [
+	[1, 2],
+	[3, 4],
+]
+
+[items.stats]
+damage = 10
+
+[[items.enchantments]]
+name = "fire"
+
+[[items]]
+name = "bow"
+"#;
+
+		assert_eq!(stream(toml).unwrap(), parse(toml).unwrap());
+
+		// Streamed arrays are written before the other keys
+		let toml = "title = 'list'\n\n[[items]]\nid = 1\n\n[[items]]\nid = 2\n";
+
+		let lua = r#"{
+	["items"] = {
+		{
+			["id"] = 1,
+		},
+		{
+			["id"] = 2,
+		},
+	},
+	["title"] = "list",
+}"#;
+
+		assert_eq!(stream(toml).unwrap(), lua);
+		assert_eq!(stream("").unwrap(), parse("").unwrap());
+	}
+
+	#[test]
+	fn not_streamable() {
+		for toml in [
+			"[[a]]\nx = 1\n[[b]]\ny = 1\n[[a]]\nx = 2\n",
+			"[[a]]\nx = 1\n[[b]]\ny = 1\n[a.sub]\nz = 1\n",
+		] {
+			assert!(
+				matches!(stream(toml), Err(Error::NotStreamable { key }) if key == "a"),
+				"{}",
+				toml
+			);
+		}
+
+		assert!(matches!(stream("[[a]]\nx = "), Err(Error::Toml { .. })));
+	}
+
+	#[test]
+	fn parse_chunks() {
+		use crate::{parse_with_options, Indent, IntegerOverflow, LuauTypes, ParseOptions, Wrap};
+
+		let toml = r#"
+		title = "chunks"
+		ports = [80, 443]
+
+		[server]
+		host = "localhost"
+
+		[[items]]
+		id = 1
+"#;
+
+		for options in [
+			ParseOptions::default(),
+			ParseOptions::pretty(),
+			ParseOptions {
+				indent: Indent::Spaces(2),
+				root_key: Some(String::from("config")),
+				wrap: Wrap::Local(String::from("config")),
+				luau_types: Some(LuauTypes::new("Config")),
+				align_assignments: true,
+				..Default::default()
+			},
+			ParseOptions {
+				split_locals: true,
+				..Default::default()
+			},
+		] {
+			let chunks: Vec<String> = ParseChunks::new(toml, &options)
+				.collect::<Result<_, _>>()
+				.unwrap();
+
+			let expected = if options.split_locals { 1 } else { 4 };
+
+			assert_eq!(chunks.len(), expected);
+			assert_eq!(chunks.concat(), parse_with_options(toml, &options).unwrap());
+		}
+
+		let options = ParseOptions::default();
+		let chunks: Vec<String> = ParseChunks::new("", &options)
+			.collect::<Result<_, _>>()
+			.unwrap();
+
+		assert_eq!(chunks.concat(), parse("").unwrap());
+
+		// Errors end the iteration
+		let options = ParseOptions {
+			integer_overflow: IntegerOverflow::Error,
+			..Default::default()
+		};
+
+		let mut chunks = ParseChunks::new("a = 1\nb = 9007199254740993\nc = 3", &options);
+
+		assert!(chunks.next().unwrap().is_ok());
+		assert!(matches!(
+			chunks.next(),
+			Some(Err(Error::IntegerOverflow { .. }))
+		));
+		assert!(chunks.next().is_none());
+
+		let mut chunks = ParseChunks::new("a = ", &options);
+
+		assert!(matches!(chunks.next(), Some(Err(Error::Toml { .. }))));
+		assert!(chunks.next().is_none());
+	}
+
+	#[test]
+	fn parse_chunks_max_width() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let toml = "long_key_name_here = [1, 2]\nc = [3, 4]\n[server]\nports = [80, 443]";
+
+		let options = ParseOptions {
+			max_width: Some(80),
+			..Default::default()
+		};
+
+		let chunks: Vec<String> = ParseChunks::new(toml, &options)
+			.collect::<Result<_, _>>()
+			.unwrap();
+
+		assert_eq!(chunks.len(), 3);
+		assert_eq!(chunks.concat(), parse_with_options(toml, &options).unwrap());
+	}
+
+	#[test]
+	fn parse_chunks_entries() {
+		use crate::{
+			parse_with_options, EmptyStringMode, GroupOrder, KeyCase, KeyOrder, NilEntries,
+			ParseOptions,
+		};
+
+		let toml = r#"
+		zebra = ""
+		apple = "NIL"
+		mango = 1
+
+		[server]
+		host = "localhost"
+"#;
+
+		// Root entries are ordered and left out like in a whole conversion
+		for options in [
+			ParseOptions {
+				key_order: Some(KeyOrder::new(|a, b| a.cmp(b))),
+				align_assignments: true,
+				..Default::default()
+			},
+			ParseOptions {
+				group_order: GroupOrder::TablesFirst,
+				..Default::default()
+			},
+			ParseOptions {
+				empty_string: EmptyStringMode::Omit,
+				..Default::default()
+			},
+			ParseOptions {
+				nil_sentinel: Some(String::from("NIL")),
+				nil_entries: NilEntries::Drop,
+				..Default::default()
+			},
+		] {
+			let chunks: Vec<String> = ParseChunks::new(toml, &options)
+				.collect::<Result<_, _>>()
+				.unwrap();
+
+			assert_eq!(chunks.concat(), parse_with_options(toml, &options).unwrap());
+		}
+
+		let options = ParseOptions {
+			empty_string: EmptyStringMode::Omit,
+			compact_empty: true,
+			..Default::default()
+		};
+
+		let chunks: Vec<String> = ParseChunks::new("a = \"\"", &options)
+			.collect::<Result<_, _>>()
+			.unwrap();
+
+		assert_eq!(chunks, ["{}"]);
+
+		// Keys written as the same Lua key collide across chunks
+		let options = ParseOptions {
+			key_case: KeyCase::Snake,
+			..Default::default()
+		};
+
+		let mut chunks = ParseChunks::new("my_key = 1\nmy-key = 2", &options);
+
+		assert!(matches!(
+			chunks.next(),
+			Some(Err(Error::KeyCollision { .. }))
+		));
+		assert!(chunks.next().is_none());
+	}
+
+	#[test]
+	fn output_limit() {
+		use crate::ParseOptions;
+
+		let toml: String = (0..100).map(|i| format!("key_{} = {}\n", i, i)).collect();
+		let options = ParseOptions {
+			max_output_bytes: Some(200),
+			..Default::default()
+		};
+
+		let mut written = 0;
+		let mut chunks = ParseChunks::new(&toml, &options);
+
+		for chunk in chunks.by_ref() {
+			match chunk {
+				Ok(chunk) => written += chunk.len(),
+				Err(err) => {
+					assert!(matches!(err, Error::OutputTooLarge { limit: 200 }));
+					break;
+				}
+			}
+		}
+
+		// Aborted on the entry passing the limit, not after the whole document
+		assert!(written > 150 && written <= 200, "{}", written);
+		assert!(chunks.next().is_none());
+	}
+}
@@ -0,0 +1,372 @@
+use std::{
+	collections::BTreeMap,
+	fmt::{self, Display, Formatter},
+	fs,
+	hash::Hasher,
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+
+use crate::{map::FnvHasher, parse_file, read_file, Error, ParseOptions};
+
+/// Keeps a directory of `.lua` files in sync with a directory tree of TOML files
+///
+/// Every `*.toml` file under the input directory is written to the same
+/// relative path under the output directory with a `.lua` extension. Files
+/// are only converted again when their contents change and outputs of
+/// removed files are deleted
+///
+/// ```rust
+/// use std::fs;
+/// use toml2lua::{DirSync, ParseOptions, SyncStatus};
+///
+/// let input = std::env::temp_dir().join(format!("toml2lua_dir_sync_doc_{}", std::process::id()));
+/// let output = input.join("out");
+/// fs::create_dir_all(&input).unwrap();
+/// fs::write(input.join("config.toml"), "key = 1").unwrap();
+///
+/// let mut sync = DirSync::new(&input, &output, ParseOptions::default());
+///
+/// assert!(matches!(sync.sync()[0].status, SyncStatus::Written));
+/// assert!(sync.sync().is_empty());
+/// ```
+#[derive(Debug)]
+pub struct DirSync {
+	input: PathBuf,
+	output: PathBuf,
+	options: ParseOptions,
+	/// Content hashes of the sources last converted successfully, keyed by relative path
+	hashes: BTreeMap<PathBuf, u64>,
+}
+
+/// What [`DirSync::sync`] did with a single file
+#[derive(Debug)]
+pub struct SyncEvent {
+	/// Path of the source relative to the input directory
+	pub path: PathBuf,
+	pub status: SyncStatus,
+	/// Time spent on the file
+	pub duration: Duration,
+}
+
+/// Outcome of syncing a single file
+#[derive(Debug)]
+pub enum SyncStatus {
+	/// Source is new or changed and its output was written
+	Written,
+	/// Source was removed and so was its output
+	Removed,
+	/// Source could not be converted or its output could not be written,
+	/// it's tried again on the next sync
+	Failed(Error),
+}
+
+impl Display for SyncEvent {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let millis = self.duration.as_secs_f64() * 1000.0;
+
+		match &self.status {
+			SyncStatus::Written => write!(f, "wrote    {} ({:.1} ms)", self.path.display(), millis),
+			SyncStatus::Removed => write!(f, "removed  {}", self.path.display()),
+			SyncStatus::Failed(err) => write!(f, "failed   {}: {}", self.path.display(), err),
+		}
+	}
+}
+
+impl DirSync {
+	pub fn new(input: &Path, output: &Path, options: ParseOptions) -> Self {
+		Self {
+			input: input.to_owned(),
+			output: output.to_owned(),
+			options,
+			hashes: BTreeMap::new(),
+		}
+	}
+
+	/// Convert every new or changed source and delete outputs of removed ones,
+	/// returning what was done sorted by path. Unchanged files are not reported
+	pub fn sync(&mut self) -> Vec<SyncEvent> {
+		let mut sources = Vec::new();
+		let mut events = Vec::new();
+
+		if let Err(err) = list_sources(&self.input, &self.output, &mut sources) {
+			events.push(SyncEvent {
+				path: PathBuf::new(),
+				status: SyncStatus::Failed(err),
+				duration: Duration::ZERO,
+			});
+
+			return events;
+		}
+
+		let removed: Vec<PathBuf> = self
+			.hashes
+			.keys()
+			.filter(|path| !sources.contains(&self.input.join(path)))
+			.cloned()
+			.collect();
+
+		for path in removed {
+			let start = Instant::now();
+
+			self.hashes.remove(&path);
+
+			let status = match fs::remove_file(self.output_path(&path)) {
+				Ok(()) => SyncStatus::Removed,
+				Err(err) if err.kind() == std::io::ErrorKind::NotFound => SyncStatus::Removed,
+				Err(error) => SyncStatus::Failed(Error::Write {
+					path: self.output_path(&path),
+					error,
+				}),
+			};
+
+			events.push(SyncEvent {
+				path,
+				status,
+				duration: start.elapsed(),
+			});
+		}
+
+		for source in sources {
+			let start = Instant::now();
+			let path = source
+				.strip_prefix(&self.input)
+				.unwrap_or(&source)
+				.to_owned();
+
+			let status = match self.convert(&source, &path) {
+				Ok(true) => SyncStatus::Written,
+				Ok(false) => continue,
+				Err(err) => {
+					self.hashes.remove(&path);
+					SyncStatus::Failed(err)
+				}
+			};
+
+			events.push(SyncEvent {
+				path,
+				status,
+				duration: start.elapsed(),
+			});
+		}
+
+		events.sort_by(|a, b| a.path.cmp(&b.path));
+		events
+	}
+
+	/// Convert the source if its contents changed, returns whether it did
+	fn convert(&mut self, source: &Path, path: &Path) -> Result<bool, Error> {
+		let toml = read_file(source)?;
+
+		let mut hasher = FnvHasher::default();
+		hasher.write(toml.as_bytes());
+		let hash = hasher.finish();
+
+		if self.hashes.get(path) == Some(&hash) {
+			return Ok(false);
+		}
+
+		let lua = parse_file(source, &self.options)?;
+		let output = self.output_path(path);
+
+		if let Some(parent) = output.parent() {
+			fs::create_dir_all(parent).map_err(|error| Error::Write {
+				path: parent.to_owned(),
+				error,
+			})?;
+		}
+
+		fs::write(&output, lua).map_err(|error| Error::Write {
+			path: output.clone(),
+			error,
+		})?;
+
+		self.hashes.insert(path.to_owned(), hash);
+
+		Ok(true)
+	}
+
+	fn output_path(&self, path: &Path) -> PathBuf {
+		self.output.join(path).with_extension("lua")
+	}
+}
+
+/// Every `*.toml` file under `dir`, symlinked directories and the output directory are skipped
+fn list_sources(dir: &Path, output: &Path, sources: &mut Vec<PathBuf>) -> Result<(), Error> {
+	let io_error = |error| Error::Io {
+		path: dir.to_owned(),
+		error,
+	};
+
+	let mut entries = fs::read_dir(dir)
+		.map_err(io_error)?
+		.collect::<Result<Vec<_>, _>>()
+		.map_err(io_error)?;
+
+	entries.sort_by_key(|entry| entry.path());
+
+	for entry in entries {
+		let path = entry.path();
+
+		if entry.file_type().map_err(io_error)?.is_dir() {
+			if path != output {
+				list_sources(&path, output, sources)?;
+			}
+		} else if path.extension().is_some_and(|ext| ext == "toml") {
+			sources.push(path);
+		}
+	}
+
+	Ok(())
+}
+
+/// Keep the output directory in sync with the input directory until `on_event` breaks
+///
+/// Runs [`DirSync::sync`] once at the start and again after every change to the
+/// input tree. Changes arriving within `debounce` of each other are handled by
+/// a single sync. Failed files are reported to `on_event` and retried on the
+/// next change, only failing to watch the input directory returns an error
+#[cfg(feature = "watch")]
+pub fn watch<F>(
+	sync: &mut DirSync,
+	debounce: Duration,
+	mut on_event: F,
+) -> Result<(), notify::Error>
+where
+	F: FnMut(&SyncEvent) -> std::ops::ControlFlow<()>,
+{
+	use notify::{RecursiveMode, Watcher};
+	use std::sync::mpsc;
+
+	let (sender, receiver) = mpsc::channel();
+	let mut watcher = notify::recommended_watcher(sender)?;
+
+	watcher.watch(&sync.input, RecursiveMode::Recursive)?;
+
+	loop {
+		for event in sync.sync() {
+			if on_event(&event).is_break() {
+				return Ok(());
+			}
+		}
+
+		// Wait for a change, then until the changes stop
+		let mut result = receiver.recv().map_err(|_| closed())?;
+
+		loop {
+			result?;
+
+			match receiver.recv_timeout(debounce) {
+				Ok(next) => result = next,
+				Err(mpsc::RecvTimeoutError::Timeout) => break,
+				Err(mpsc::RecvTimeoutError::Disconnected) => return Err(closed()),
+			}
+		}
+	}
+}
+
+#[cfg(feature = "watch")]
+fn closed() -> notify::Error {
+	notify::Error::generic("watcher stopped")
+}
+
+#[cfg(test)]
+mod test {
+	use std::{fs, path::PathBuf};
+
+	use super::{DirSync, SyncStatus};
+	use crate::ParseOptions;
+
+	#[test]
+	fn incremental() {
+		let input = crate::test::temp_dir("sync");
+		let output = input.join("out");
+
+		fs::create_dir_all(input.join("npcs")).unwrap();
+		fs::write(input.join("items.toml"), "count = 2").unwrap();
+		fs::write(input.join("npcs/goblin.toml"), "hp = 10").unwrap();
+		fs::write(input.join("readme.md"), "# not toml").unwrap();
+
+		let mut sync = DirSync::new(&input, &output, ParseOptions::default());
+
+		let events = sync.sync();
+		let paths: Vec<_> = events.iter().map(|event| event.path.clone()).collect();
+
+		assert_eq!(paths, ["items.toml", "npcs/goblin.toml"].map(PathBuf::from));
+		assert!(events
+			.iter()
+			.all(|event| matches!(event.status, SyncStatus::Written)));
+		assert_eq!(
+			fs::read_to_string(output.join("npcs/goblin.lua")).unwrap(),
+			"{\n\t[\"hp\"] = 10,\n}"
+		);
+
+		// Rewriting the same contents is not a change
+		fs::write(input.join("items.toml"), "count = 2").unwrap();
+		assert!(sync.sync().is_empty());
+
+		fs::write(input.join("items.toml"), "count = 3").unwrap();
+		fs::remove_file(input.join("npcs/goblin.toml")).unwrap();
+
+		let events = sync.sync();
+
+		assert_eq!(events.len(), 2);
+		assert!(matches!(events[0].status, SyncStatus::Written));
+		assert!(matches!(events[1].status, SyncStatus::Removed));
+		assert!(!output.join("npcs/goblin.lua").exists());
+		assert_eq!(
+			fs::read_to_string(output.join("items.lua")).unwrap(),
+			"{\n\t[\"count\"] = 3,\n}"
+		);
+
+		// Broken files are reported and retried until they convert
+		fs::write(input.join("items.toml"), "count =").unwrap();
+
+		let events = sync.sync();
+
+		assert!(matches!(events[0].status, SyncStatus::Failed(_)));
+		assert!(events[0].to_string().starts_with("failed   items.toml: "));
+		assert!(matches!(sync.sync()[0].status, SyncStatus::Failed(_)));
+
+		fs::write(input.join("items.toml"), "count = 3").unwrap();
+
+		assert!(matches!(sync.sync()[0].status, SyncStatus::Written));
+	}
+
+	#[test]
+	#[cfg(feature = "watch")]
+	fn watch() {
+		use std::{ops::ControlFlow, time::Duration};
+
+		let input = crate::test::temp_dir("watch");
+		let output = input.join("out");
+
+		fs::write(input.join("config.toml"), "version = 1").unwrap();
+
+		let mut sync = DirSync::new(&input, &output, ParseOptions::default());
+		let mut written = 0;
+
+		super::watch(&mut sync, Duration::from_millis(100), |event| {
+			assert!(matches!(event.status, SyncStatus::Written));
+			written += 1;
+
+			// The input is watched before the first sync, so saves made while
+			// handling its events are seen. Every later sync reads the last save
+			if written == 1 {
+				for version in 2..=5 {
+					fs::write(input.join("config.toml"), format!("version = {}", version)).unwrap();
+				}
+
+				ControlFlow::Continue(())
+			} else {
+				ControlFlow::Break(())
+			}
+		})
+		.unwrap();
+
+		assert_eq!(
+			fs::read_to_string(output.join("config.lua")).unwrap(),
+			"{\n\t[\"version\"] = 5,\n}"
+		);
+	}
+}
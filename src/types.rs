@@ -0,0 +1,672 @@
+use alloc::{borrow::ToOwned, boxed::Box, format, string::String, vec, vec::Vec};
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+use crate::{
+	case::{convert_case, lua_key},
+	walk::{flatten, is_valid_lua_identifier, join_path, validate_string},
+	BoolStyle, DatetimeFallback, DatetimeMode, Error, Indent, KeyCase, Map, ParseOptions,
+};
+
+/// Type inferred from TOML values
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Shape {
+	String,
+	Integer,
+	Float,
+	Boolean,
+	Datetime,
+	/// Element type of an empty array
+	Unknown,
+	Array(Box<Shape>),
+	Table(Vec<Field>),
+	Union(Vec<Shape>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Field {
+	pub key: String,
+	pub shape: Shape,
+	/// Key is missing from some of the tables merged into this shape
+	pub optional: bool,
+}
+
+impl Shape {
+	/// Shape of the table the document is written as
+	pub fn of_document(map: &Map, options: &ParseOptions) -> Self {
+		if options.flatten {
+			let leaves = flatten(map, options);
+			Self::of_entries(leaves.iter().map(|leaf| (&leaf.key, leaf.value)))
+		} else {
+			Self::of_entries(map)
+		}
+	}
+
+	fn of_entries<'v, I>(entries: I) -> Self
+	where
+		I: IntoIterator<Item = (&'v String, &'v Value)>,
+	{
+		Shape::Table(
+			entries
+				.into_iter()
+				.map(|(key, value)| Field {
+					key: key.clone(),
+					shape: Shape::of(value),
+					optional: false,
+				})
+				.collect(),
+		)
+	}
+
+	/// Table shape with a single field holding `shape`
+	pub fn wrap(key: &str, shape: Shape) -> Self {
+		Shape::Table(vec![Field {
+			key: key.to_owned(),
+			shape,
+			optional: false,
+		}])
+	}
+
+	pub fn of(value: &Value) -> Self {
+		match value {
+			Value::String(_) => Shape::String,
+			Value::Integer(_) => Shape::Integer,
+			Value::Float(_) => Shape::Float,
+			Value::Boolean(_) => Shape::Boolean,
+			Value::Datetime(_) => Shape::Datetime,
+			Value::Array(array) => {
+				let element = array
+					.iter()
+					.map(Shape::of)
+					.reduce(Shape::merge)
+					.unwrap_or(Shape::Unknown);
+
+				Shape::Array(Box::new(element))
+			}
+			Value::Table(table) => Self::of_entries(table),
+		}
+	}
+
+	/// Combine two shapes into one describing both
+	fn merge(self, other: Shape) -> Shape {
+		match (self, other) {
+			(a, b) if a == b => a,
+			(Shape::Unknown, other) | (other, Shape::Unknown) => other,
+			(Shape::Array(a), Shape::Array(b)) => Shape::Array(Box::new(a.merge(*b))),
+			(Shape::Table(a), Shape::Table(b)) => Shape::Table(merge_fields(a, b)),
+			(Shape::Union(mut shapes), other) | (other, Shape::Union(mut shapes)) => {
+				match shapes.iter().position(|shape| shape.mergeable(&other)) {
+					Some(index) => {
+						let shape = shapes.remove(index);
+						shapes.insert(index, shape.merge(other));
+					}
+					None => shapes.push(other),
+				}
+
+				Shape::Union(shapes)
+			}
+			(a, b) => Shape::Union(vec![a, b]),
+		}
+	}
+
+	fn mergeable(&self, other: &Shape) -> bool {
+		matches!(
+			(self, other),
+			(Shape::Array(_), Shape::Array(_)) | (Shape::Table(_), Shape::Table(_))
+		) || self == other
+	}
+}
+
+fn merge_fields(mut a: Vec<Field>, b: Vec<Field>) -> Vec<Field> {
+	for field in &mut a {
+		if !b.iter().any(|other| other.key == field.key) {
+			field.optional = true;
+		}
+	}
+
+	for field in b {
+		match a.iter_mut().find(|existing| existing.key == field.key) {
+			Some(existing) => {
+				existing.shape = existing.shape.clone().merge(field.shape);
+				existing.optional |= field.optional;
+			}
+			None => a.push(Field {
+				optional: true,
+				..field
+			}),
+		}
+	}
+
+	a
+}
+
+/// How arrays mixing several types are typed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MixedArrays {
+	/// Union of element types, e.g. `{ string | number }`
+	#[default]
+	Union,
+	/// `{ any }`
+	Any,
+}
+
+/// Luau type declaration generated alongside the table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LuauTypes {
+	/// Name of the exported type
+	pub name: String,
+	/// How arrays mixing several types are typed
+	#[serde(default)]
+	pub mixed_arrays: MixedArrays,
+	/// Annotate the emitted table with the type, e.g. `return { ... } :: Config`
+	#[serde(default = "annotate_default")]
+	pub annotate: bool,
+}
+
+fn annotate_default() -> bool {
+	true
+}
+
+impl LuauTypes {
+	/// Annotated type with the given name, typing mixed arrays as unions
+	pub fn new(name: &str) -> Self {
+		Self {
+			name: name.to_owned(),
+			mixed_arrays: MixedArrays::Union,
+			annotate: true,
+		}
+	}
+}
+
+/// lua-language-server annotations generated alongside the table
+///
+/// Every table becomes a `---@class` named after its key path under `name`,
+/// like `Config.server`, with a `---@field` for each key. TOML can't tell
+/// which keys are optional, so every field is required
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, EmmyLuaTypes, ParseOptions, Wrap};
+///
+/// let options = ParseOptions {
+/// 	emmylua_types: Some(EmmyLuaTypes::new("Config")),
+/// 	wrap: Wrap::Return,
+/// 	bare_keys: true,
+/// 	..Default::default()
+/// };
+///
+/// let lua = r#"---@class Config
+/// ---@field ports integer[]
+///
+/// ---@type Config
+/// return {
+/// 	ports = {
+/// 		80,
+/// 	},
+/// }"#;
+///
+/// assert_eq!(parse_with_options("ports = [80]", &options).unwrap(), lua);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmmyLuaTypes {
+	/// Name of the root class, nested classes are prefixed with it
+	pub name: String,
+	/// How arrays mixing several types are typed
+	#[serde(default)]
+	pub mixed_arrays: MixedArrays,
+}
+
+impl EmmyLuaTypes {
+	/// Annotations with the given root class name, typing mixed arrays as unions
+	pub fn new(name: &str) -> Self {
+		Self {
+			name: name.to_owned(),
+			mixed_arrays: MixedArrays::Union,
+		}
+	}
+}
+
+/// Teal record generated alongside the table
+///
+/// Every table becomes a record, nested ones named after their key in
+/// PascalCase, and the table is annotated with it. Teal records only have
+/// identifier fields, other keys return [`Error::InvalidIdentifier`]. Keys are
+/// written as `["key"]` unless [`ParseOptions::bare_keys`] is enabled
+///
+/// ```rust
+/// use toml2lua::{parse_with_options, ParseOptions, TealTypes, Wrap};
+///
+/// let options = ParseOptions {
+/// 	teal_types: Some(TealTypes::new("Config")),
+/// 	wrap: Wrap::Local(String::from("config")),
+/// 	bare_keys: true,
+/// 	..Default::default()
+/// };
+///
+/// let teal = r#"local record Config
+/// 	port: integer
+/// end
+///
+/// local config: Config = {
+/// 	port = 80,
+/// }"#;
+///
+/// assert_eq!(parse_with_options("port = 80", &options).unwrap(), teal);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TealTypes {
+	/// Name of the record of the root table
+	pub name: String,
+}
+
+impl TealTypes {
+	pub fn new(name: &str) -> Self {
+		Self {
+			name: name.to_owned(),
+		}
+	}
+}
+
+/// Render `export type <name> = ...` declaration for the shape
+pub(crate) fn luau_declaration(shape: &Shape, types: &LuauTypes, options: &ParseOptions) -> String {
+	let renderer = LuauRenderer { types, options };
+
+	format!(
+		"export type {} = {}\n",
+		types.name,
+		renderer.render(shape, 0)
+	)
+}
+
+struct LuauRenderer<'a> {
+	types: &'a LuauTypes,
+	options: &'a ParseOptions,
+}
+
+impl LuauRenderer<'_> {
+	fn render(&self, shape: &Shape, depth: usize) -> String {
+		match shape {
+			Shape::String => String::from("string"),
+			Shape::Integer | Shape::Float => String::from("number"),
+			Shape::Boolean => match self.options.bool_style {
+				BoolStyle::Lua => String::from("boolean"),
+				BoolStyle::Numeric => String::from("number"),
+				BoolStyle::Custom(..) => String::from("any"),
+			},
+			Shape::Datetime => match (self.options.datetime_mode, self.options.datetime_fallback) {
+				(DatetimeMode::String, _) if self.options.datetime_wrapper.is_none() => {
+					String::from("string")
+				}
+				(DatetimeMode::Table, _) => String::from("{ [string]: number }"),
+				(DatetimeMode::Epoch, DatetimeFallback::Error) => String::from("number"),
+				_ => String::from("any"),
+			},
+			Shape::Unknown => String::from("any"),
+			Shape::Array(element) => {
+				if self.is_mixed(element) && self.types.mixed_arrays == MixedArrays::Any {
+					String::from("{ any }")
+				} else {
+					format!("{{ {} }}", self.render(element, depth))
+				}
+			}
+			Shape::Table(fields) => {
+				if fields.is_empty() {
+					return String::from("{}");
+				}
+
+				let mut luau = String::from("{\n");
+
+				for field in fields {
+					luau.push_str(&self.indent(depth + 1));
+
+					let key = lua_key(&field.key, self.options);
+
+					// Luau identifiers are always ASCII
+					if is_valid_lua_identifier(&key, true) {
+						luau.push_str(&key);
+					} else {
+						luau.push_str(&format!("[\"{}\"]", validate_string(&key, self.options)));
+					}
+
+					luau.push_str(": ");
+
+					let rendered = self.render(&field.shape, depth + 1);
+
+					if field.optional {
+						if self.is_mixed(&field.shape) {
+							luau.push_str(&format!("({})?", rendered));
+						} else {
+							luau.push_str(&format!("{}?", rendered));
+						}
+					} else {
+						luau.push_str(&rendered);
+					}
+
+					luau.push_str(",\n");
+				}
+
+				luau.push_str(&self.indent(depth));
+				luau.push('}');
+				luau
+			}
+			Shape::Union(shapes) => self.render_members(shapes, depth).join(" | "),
+		}
+	}
+
+	/// Render union members, dropping the ones that end up identical
+	fn render_members(&self, shapes: &[Shape], depth: usize) -> Vec<String> {
+		let mut rendered: Vec<String> = Vec::new();
+
+		for shape in shapes {
+			let shape = self.render(shape, depth);
+
+			if !rendered.contains(&shape) {
+				rendered.push(shape);
+			}
+		}
+
+		rendered
+	}
+
+	fn is_mixed(&self, shape: &Shape) -> bool {
+		match shape {
+			Shape::Union(shapes) => self.render_members(shapes, 0).len() > 1,
+			_ => false,
+		}
+	}
+
+	fn indent(&self, depth: usize) -> String {
+		match self.options.indent {
+			Indent::Tabs => "\t".repeat(depth),
+			Indent::Spaces(width) => " ".repeat(width * depth),
+		}
+	}
+}
+
+/// Render `---@class` annotations for the shape, returns them together with the type of the root
+///
+/// Classes come in document order, a class before the classes of its fields
+pub(crate) fn emmylua_declaration(
+	shape: &Shape,
+	types: &EmmyLuaTypes,
+	options: &ParseOptions,
+) -> (String, String) {
+	let renderer = EmmyLuaRenderer { types, options };
+	let mut classes = Vec::new();
+	let root = renderer.render(shape, &types.name, &mut classes);
+
+	(classes.concat(), root)
+}
+
+struct EmmyLuaRenderer<'a> {
+	types: &'a EmmyLuaTypes,
+	options: &'a ParseOptions,
+}
+
+impl EmmyLuaRenderer<'_> {
+	/// Type of the shape, the class of a table is added to `classes` under the name `class`
+	fn render(&self, shape: &Shape, class: &str, classes: &mut Vec<String>) -> String {
+		match shape {
+			Shape::String => String::from("string"),
+			Shape::Integer => String::from("integer"),
+			Shape::Float => String::from("number"),
+			Shape::Boolean => match self.options.bool_style {
+				BoolStyle::Lua => String::from("boolean"),
+				BoolStyle::Numeric => String::from("integer"),
+				BoolStyle::Custom(..) => String::from("any"),
+			},
+			Shape::Datetime => match (self.options.datetime_mode, self.options.datetime_fallback) {
+				(DatetimeMode::String, _) if self.options.datetime_wrapper.is_none() => {
+					String::from("string")
+				}
+				(DatetimeMode::Table, _) => String::from("table<string, integer>"),
+				(DatetimeMode::Epoch, DatetimeFallback::Error) => String::from("number"),
+				_ => String::from("any"),
+			},
+			Shape::Unknown => String::from("any"),
+			Shape::Array(element) => match element.as_ref() {
+				Shape::Union(_) if self.types.mixed_arrays == MixedArrays::Any => {
+					String::from("any[]")
+				}
+				Shape::Union(_) => format!("({})[]", self.render(element, class, classes)),
+				_ => format!("{}[]", self.render(element, class, classes)),
+			},
+			Shape::Table(fields) => {
+				// Reserve the place of the class so it comes before the ones of its fields
+				let index = classes.len();
+				classes.push(String::new());
+
+				let mut annotations = format!("---@class {}\n", class);
+
+				for field in fields {
+					let key = lua_key(&field.key, self.options);
+					let nested = format!("{}.{}", class, class_segment(&key));
+					let rendered = self.render(&field.shape, &nested, classes);
+
+					if is_valid_lua_identifier(&key, true) {
+						annotations.push_str(&format!("---@field {} {}\n", key, rendered));
+					} else {
+						annotations.push_str(&format!(
+							"---@field [\"{}\"] {}\n",
+							validate_string(&key, self.options),
+							rendered
+						));
+					}
+				}
+
+				annotations.push('\n');
+				classes[index] = annotations;
+
+				class.to_owned()
+			}
+			Shape::Union(shapes) => {
+				let mut members: Vec<String> = Vec::new();
+
+				for shape in shapes {
+					let member = self.render(shape, class, classes);
+
+					if !members.contains(&member) {
+						members.push(member);
+					}
+				}
+
+				members.join("|")
+			}
+		}
+	}
+}
+
+/// Key as part of a class name, characters other than letters, digits and `_` become `_`
+fn class_segment(key: &str) -> String {
+	key.chars()
+		.map(|char| {
+			if char.is_ascii_alphanumeric() || char == '_' {
+				char
+			} else {
+				'_'
+			}
+		})
+		.collect()
+}
+
+/// Render `local record <name> ... end` declaration for the shape of a document
+///
+/// Tables become nested records named after their key in PascalCase, declared
+/// right before the field holding them. Keys must be ASCII identifiers
+pub(crate) fn teal_declaration(
+	shape: &Shape,
+	name: &str,
+	options: &ParseOptions,
+) -> Result<String, Error> {
+	let renderer = TealRenderer { options };
+
+	if let Shape::Table(fields) = shape {
+		return Ok(format!(
+			"local {}\n",
+			renderer.record(name, fields, 0, &mut Vec::new())?
+		));
+	}
+
+	// Value of a document unwrapped by `unwrap_single`, a table nested in it is the only record
+	let mut records = String::new();
+	let rendered = renderer.render(
+		shape,
+		&format!("{}Item", name),
+		0,
+		&mut Vec::new(),
+		&mut records,
+	)?;
+
+	if !records.is_empty() {
+		records.insert_str(0, "local ");
+	}
+
+	Ok(format!("{}local type {} = {}\n", records, name, rendered))
+}
+
+struct TealRenderer<'a> {
+	options: &'a ParseOptions,
+}
+
+impl TealRenderer<'_> {
+	fn record(
+		&self,
+		name: &str,
+		fields: &[Field],
+		depth: usize,
+		path: &mut Vec<String>,
+	) -> Result<String, Error> {
+		let mut teal = format!("record {}\n", name);
+		let mut names: Vec<String> = Vec::new();
+
+		for field in fields {
+			let key = lua_key(&field.key, self.options);
+
+			// Teal records have no syntax for other keys
+			if !is_valid_lua_identifier(&key, true) {
+				return Err(Error::InvalidIdentifier {
+					path: join_path(path),
+					key: key.into_owned(),
+				});
+			}
+
+			let mut record = convert_case(&key, KeyCase::Pascal);
+
+			if !is_valid_lua_identifier(&record, true) {
+				record.insert_str(0, "Record");
+			}
+
+			let mut suffix = 1;
+			let base = record.clone();
+
+			while names.contains(&record) {
+				suffix += 1;
+				record = format!("{}{}", base, suffix);
+			}
+
+			let mut records = String::new();
+
+			path.push(field.key.clone());
+			let rendered = self.render(&field.shape, &record, depth + 1, path, &mut records)?;
+			path.pop();
+
+			if !records.is_empty() {
+				names.push(record);
+			}
+
+			teal.push_str(&records);
+			teal.push_str(&format!(
+				"{}{}: {}\n",
+				self.indent(depth + 1),
+				key,
+				rendered
+			));
+		}
+
+		teal.push_str(&self.indent(depth));
+		teal.push_str("end");
+
+		Ok(teal)
+	}
+
+	/// Type of the shape, the record of a table is written to `records` under the name `record`
+	fn render(
+		&self,
+		shape: &Shape,
+		record: &str,
+		depth: usize,
+		path: &mut Vec<String>,
+		records: &mut String,
+	) -> Result<String, Error> {
+		Ok(match shape {
+			Shape::String => String::from("string"),
+			Shape::Integer => String::from("integer"),
+			Shape::Float => String::from("number"),
+			Shape::Boolean => match self.options.bool_style {
+				BoolStyle::Lua => String::from("boolean"),
+				BoolStyle::Numeric => String::from("integer"),
+				BoolStyle::Custom(..) => String::from("any"),
+			},
+			Shape::Datetime => match (self.options.datetime_mode, self.options.datetime_fallback) {
+				(DatetimeMode::String, _) if self.options.datetime_wrapper.is_none() => {
+					String::from("string")
+				}
+				(DatetimeMode::Table, _) => String::from("{string:integer}"),
+				(DatetimeMode::Epoch, DatetimeFallback::Error) => String::from("number"),
+				_ => String::from("any"),
+			},
+			Shape::Unknown => String::from("any"),
+			Shape::Array(element) => {
+				format!(
+					"{{{}}}",
+					self.render(element, record, depth, path, records)?
+				)
+			}
+			Shape::Table(fields) => {
+				records.push_str(&self.indent(depth));
+				records.push_str(&self.record(record, fields, depth, path)?);
+				records.push('\n');
+
+				record.to_owned()
+			}
+			Shape::Union(shapes) => {
+				// Teal tells union members apart by their Lua type, so a union holds at
+				// most one table type and can't have both `integer` and `number`
+				let tables = shapes
+					.iter()
+					.filter(|shape| matches!(shape, Shape::Array(_) | Shape::Table(_)))
+					.count();
+
+				if tables > 1 {
+					return Ok(String::from("any"));
+				}
+
+				let mut members: Vec<String> = Vec::new();
+
+				for shape in shapes {
+					let member = match self.render(shape, record, depth, path, records)? {
+						member if member == "integer" => String::from("number"),
+						member if member == "any" => return Ok(member),
+						member => member,
+					};
+
+					if !members.contains(&member) {
+						members.push(member);
+					}
+				}
+
+				members.join(" | ")
+			}
+		})
+	}
+
+	fn indent(&self, depth: usize) -> String {
+		match self.options.indent {
+			Indent::Tabs => "\t".repeat(depth),
+			Indent::Spaces(width) => " ".repeat(width * depth),
+		}
+	}
+}
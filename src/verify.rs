@@ -0,0 +1,659 @@
+use mlua::{Lua, Table as LuaTable, Value as LuaValue};
+use std::fmt::{self, Display, Formatter};
+use toml::{Table, Value};
+
+use crate::{
+	interpolate::interpolate, parse_with_options, walk::join_path, BoolStyle, DatetimeMode,
+	Dialect, Error, Freeze, KeyCase, NumberMode, OrderMetadata, PackPrecision, ParseOptions,
+	ReadonlyMode, WhitespacePolicy, Wrap,
+};
+
+/// Errors returned by [`verify_roundtrip`]
+#[derive(Debug)]
+pub enum VerifyError {
+	/// Conversion itself failed
+	Convert(Error),
+	/// Generated chunk failed to load or run
+	Load(mlua::Error),
+	/// Loaded value differs from the TOML one
+	Mismatch {
+		path: String,
+		expected: String,
+		found: String,
+	},
+	/// Output of the given options can't be loaded by the embedded Lua 5.4
+	Unsupported(&'static str),
+}
+
+impl Display for VerifyError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			VerifyError::Convert(err) => write!(f, "{}", err),
+			VerifyError::Load(err) => write!(f, "generated Lua failed to load: {}", err),
+			VerifyError::Mismatch {
+				path,
+				expected,
+				found,
+			} => {
+				if path.is_empty() {
+					write!(f, "expected {} at root, found {}", expected, found)
+				} else {
+					write!(f, "expected {} at `{}`, found {}", expected, path, found)
+				}
+			}
+			VerifyError::Unsupported(reason) => write!(f, "can't verify {}", reason),
+		}
+	}
+}
+
+impl std::error::Error for VerifyError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			VerifyError::Convert(err) => Some(err),
+			VerifyError::Load(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<Error> for VerifyError {
+	fn from(err: Error) -> Self {
+		VerifyError::Convert(err)
+	}
+}
+
+impl From<mlua::Error> for VerifyError {
+	fn from(err: mlua::Error) -> Self {
+		VerifyError::Load(err)
+	}
+}
+
+/// Convert TOML, load the result in Lua 5.4 and compare it with the parsed document
+///
+/// Numbers are compared with integer/float awareness according to
+/// [`ParseOptions::number_mode`], booleans according to [`ParseOptions::bool_style`]
+/// and datetimes as strings, also when passed through [`ParseOptions::datetime_wrapper`]
+///
+/// ```rust
+/// use toml2lua::{verify_roundtrip, ParseOptions};
+///
+/// let toml = r#"
+/// quote = "say \"hi\"\n"
+/// ratio = 1.0
+/// "#;
+///
+/// verify_roundtrip(toml, &ParseOptions::default()).unwrap();
+/// ```
+pub fn verify_roundtrip(toml: &str, options: &ParseOptions) -> Result<(), VerifyError> {
+	if options.dialect != Dialect::Lua {
+		return Err(VerifyError::Unsupported("output in another dialect"));
+	}
+
+	if options.luau_types.is_some() {
+		return Err(VerifyError::Unsupported("Luau type declarations"));
+	}
+
+	if options.teal_types.is_some() {
+		return Err(VerifyError::Unsupported("Teal records"));
+	}
+
+	if options.datetime_mode != DatetimeMode::String {
+		return Err(VerifyError::Unsupported("datetimes not written as strings"));
+	}
+
+	if options.normalize_datetimes_utc {
+		return Err(VerifyError::Unsupported("datetimes normalized to UTC"));
+	}
+
+	if options.key_case != KeyCase::Preserve {
+		return Err(VerifyError::Unsupported("keys converted to another case"));
+	}
+
+	if options.type_tags.is_some() {
+		return Err(VerifyError::Unsupported("type tags"));
+	}
+
+	if options.coerce.is_some() {
+		return Err(VerifyError::Unsupported("coerced strings"));
+	}
+
+	if options
+		.pack_numbers
+		.is_some_and(|pack| pack.precision == PackPrecision::F32)
+	{
+		return Err(VerifyError::Unsupported(
+			"floats packed with single precision",
+		));
+	}
+
+	if options.redact.is_some() {
+		return Err(VerifyError::Unsupported("redacted values"));
+	}
+
+	if options.with_accessor.is_some() {
+		return Err(VerifyError::Unsupported(
+			"tables behind an accessor function",
+		));
+	}
+
+	if options.string_whitespace != WhitespacePolicy::Preserve {
+		return Err(VerifyError::Unsupported("canonicalized string whitespace"));
+	}
+
+	if !options.raw_expressions.is_empty() {
+		return Err(VerifyError::Unsupported("raw Lua expressions"));
+	}
+
+	if !options.key_by.is_empty() {
+		return Err(VerifyError::Unsupported("arrays keyed by a field"));
+	}
+
+	if !options.sort_arrays_by.is_empty() {
+		return Err(VerifyError::Unsupported("arrays sorted by a field"));
+	}
+
+	let lua = parse_with_options(toml, options)?;
+	let expected: Table = crate::locate::from_str(toml)?;
+
+	let state = Lua::new();
+
+	if options.freeze != Freeze::Off {
+		state
+			.load("table.freeze = function(t) return t end")
+			.exec()?;
+	}
+
+	if let Some(wrapper) = &options.datetime_wrapper {
+		state
+			.load(format!(
+				"{} = function(datetime) return datetime end",
+				wrapper
+			))
+			.exec()?;
+	}
+
+	if let ReadonlyMode::Wrapper(wrapper) = &options.readonly {
+		state
+			.load(format!("{} = function(t) return t end", wrapper))
+			.exec()?;
+	}
+
+	if options.dedupe.as_ref().is_some_and(|dedupe| dedupe.clone) {
+		state
+			.load("table.clone = function(t) local copy = {} for k, v in pairs(t) do copy[k] = v end return copy end")
+			.exec()?;
+	}
+
+	let chunk = match &options.wrap {
+		Wrap::None if options.split_locals || options.dedupe.is_some() => lua,
+		Wrap::None => format!("return {}", lua),
+		Wrap::Return => lua,
+		Wrap::Local(name) => format!("{}\nreturn {}", lua, name),
+	};
+
+	let mut loaded: LuaValue = state.load(chunk).set_name("toml2lua").eval()?;
+	let mut path = Vec::new();
+
+	// Outer table only holds the document, without any order metadata
+	if let (Some(root_key), LuaValue::Table(outer)) = (&options.root_key, &loaded) {
+		loaded = outer.raw_get(root_key.as_str())?;
+		path.push(root_key.clone());
+	}
+
+	let verifier = Verifier {
+		state: &state,
+		options,
+	};
+
+	verifier.compare(&Value::Table(expected), &loaded, &mut path)
+}
+
+struct Verifier<'a> {
+	state: &'a Lua,
+	options: &'a ParseOptions,
+}
+
+impl Verifier<'_> {
+	fn compare(
+		&self,
+		expected: &Value,
+		found: &LuaValue,
+		path: &mut Vec<String>,
+	) -> Result<(), VerifyError> {
+		let matches = match (expected, found) {
+			(Value::String(s), LuaValue::String(found)) => match &self.options.interpolation {
+				Some(interpolation) => {
+					interpolate(s, interpolation).is_ok_and(|s| *found.as_bytes() == *s.as_bytes())
+				}
+				None => *found.as_bytes() == *s.as_bytes(),
+			},
+			(Value::Integer(i), found) => match self.options.number_mode {
+				NumberMode::AllFloat => matches!(found, LuaValue::Number(f) if *f == *i as f64),
+				_ => matches!(found, LuaValue::Integer(found) if found == i),
+			},
+			(Value::Float(f), found) => match self.options.number_mode {
+				NumberMode::AllInteger => {
+					matches!(found, LuaValue::Integer(found) if *found == *f as i64)
+				}
+				_ => {
+					matches!(found, LuaValue::Number(found) if found == f || (found.is_nan() && f.is_nan()))
+				}
+			},
+			(Value::Boolean(b), found) => match &self.options.bool_style {
+				BoolStyle::Lua => matches!(found, LuaValue::Boolean(found) if found == b),
+				BoolStyle::Numeric => {
+					matches!(found, LuaValue::Integer(found) if *found == i64::from(*b))
+				}
+				BoolStyle::Custom(truthy, falsy) => {
+					let custom: LuaValue = self
+						.state
+						.load(format!("return {}", if *b { truthy } else { falsy }))
+						.eval()?;
+
+					custom == *found
+				}
+			},
+			(Value::Datetime(d), LuaValue::String(found)) => {
+				*found.as_bytes() == *d.to_string().as_bytes()
+			}
+			(Value::Array(array), LuaValue::Table(found)) => {
+				return self.compare_array(array.iter(), found, path)
+			}
+			(Value::Table(table), LuaValue::Table(found)) => {
+				return self.compare_table(table, found, path)
+			}
+			_ => false,
+		};
+
+		if matches {
+			Ok(())
+		} else {
+			Err(mismatch(path, expected, describe(found)))
+		}
+	}
+
+	fn compare_array<'v, I>(
+		&self,
+		items: I,
+		found: &LuaTable,
+		path: &mut Vec<String>,
+	) -> Result<(), VerifyError>
+	where
+		I: ExactSizeIterator<Item = &'v Value>,
+	{
+		let len = items.len();
+		let packed = self.options.packed_arrays;
+		let entries = found.pairs::<LuaValue, LuaValue>().count();
+
+		if found.raw_len() != len || entries != len + usize::from(packed) {
+			return Err(VerifyError::Mismatch {
+				path: join_path(path),
+				expected: format!("array of {} elements", len),
+				found: format!("table with {} entries", entries),
+			});
+		}
+
+		if packed && found.raw_get::<Option<usize>>("n")? != Some(len) {
+			return Err(VerifyError::Mismatch {
+				path: format!("{}.n", join_path(path)),
+				expected: format!("array length {}", len),
+				found: describe(&found.raw_get("n")?),
+			});
+		}
+
+		for (i, item) in items.enumerate() {
+			path.push((i + 1).to_string());
+			self.compare(item, &found.raw_get(i + 1)?, path)?;
+			path.pop();
+		}
+
+		Ok(())
+	}
+
+	fn compare_table(
+		&self,
+		table: &Table,
+		found: &LuaTable,
+		path: &mut Vec<String>,
+	) -> Result<(), VerifyError> {
+		let mut len = table.len();
+
+		if self.options.order_metadata == OrderMetadata::Field {
+			len += 1;
+		}
+
+		// Tables written as arrays keep their values under integer keys instead
+		if found.raw_len() > 0 && found.raw_len() == table.len() {
+			let mut items: Vec<(usize, &Value)> = table
+				.iter()
+				.filter_map(|(key, value)| Some((key.parse().ok()?, value)))
+				.collect();
+
+			items.sort_by_key(|(index, _)| *index);
+
+			return self.compare_array(items.into_iter().map(|(_, value)| value), found, path);
+		}
+
+		let entries = found.pairs::<LuaValue, LuaValue>().count();
+
+		if entries != len {
+			return Err(VerifyError::Mismatch {
+				path: join_path(path),
+				expected: format!("table with {} entries", len),
+				found: format!("table with {} entries", entries),
+			});
+		}
+
+		for (key, value) in table {
+			path.push(key.clone());
+			self.compare(value, &found.raw_get(key.as_str())?, path)?;
+			path.pop();
+		}
+
+		Ok(())
+	}
+}
+
+fn mismatch(path: &[String], expected: &Value, found: String) -> VerifyError {
+	VerifyError::Mismatch {
+		path: join_path(path),
+		expected: match expected {
+			Value::Array(_) | Value::Table(_) => expected.type_str().to_owned(),
+			_ => format!("{} `{}`", expected.type_str(), expected),
+		},
+		found,
+	}
+}
+
+fn describe(value: &LuaValue) -> String {
+	match value {
+		LuaValue::String(s) => format!("string {:?}", s.to_string_lossy()),
+		LuaValue::Integer(i) => format!("integer `{}`", i),
+		LuaValue::Number(f) => format!("float `{}`", f),
+		LuaValue::Boolean(b) => format!("boolean `{}`", b),
+		value => value.type_name().to_owned(),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use mlua::Lua;
+	use toml::Value;
+
+	use super::{verify_roundtrip, Verifier, VerifyError};
+	use crate::{
+		BoolStyle, Dedupe, Freeze, Indent, NumberMode, OrderMetadata, ParseOptions, ReadonlyMode,
+		Wrap,
+	};
+
+	const CORPUS: &[&str] = &[
+		r#"
+		string = "str"
+		int = 420
+		float = 6.9
+		whole = 1.0
+		exponent = 1e300
+		tenth = 0.1
+		subnormal = 5e-324
+		negative = -0.0
+		infinite = [inf, -inf]
+		not_a_number = nan
+		bool = true
+		datetime = 1979-05-27T00:32:00.999999-07:00
+		date = 1979-05-27
+		array = ["string", 12345, false]
+		empty = []
+
+		[object]
+		key = "value"
+"#,
+		r#"
+		quotes = "double \" and 'single'"
+		backslash = 'C:\path\to\file'
+		control = "tab\tnew\nline\rreturn\u0007bell\u001b\u0000null\u007fdelete 1"
+		unicode = "émoji 😀 and separators \u2028 \u2029"
+		multiline = """
+first line
+  second line"""
+		"key with \"quotes\"" = 1
+		"" = "empty key"
+		"]]" = "brackets"
+		"a]b" = { "a[b" = 1, 'a"b' = 2 }
+		"end" = "keyword"
+		leading = "\n\nstarts with newlines ]] and ]=] then [[ [=[ ]"
+		trailing = "ends with a bracket\n]"
+"#,
+		r#"
+		[[items]]
+		name = "sword"
+		stats = { damage = 10, speed = 1.5 }
+
+		[[items]]
+		name = "bow"
+		tags = [["nested", "arrays"], []]
+
+		[deep.deeper.deepest]
+		value = -9223372036854775808
+"#,
+	];
+
+	fn options() -> Vec<ParseOptions> {
+		vec![
+			ParseOptions::default(),
+			ParseOptions::pretty(),
+			ParseOptions::stylua_default(),
+			ParseOptions {
+				indent: Indent::Spaces(2),
+				escape_unicode: true,
+				brace_on_new_line: true,
+				max_width: Some(40),
+				wrap: Wrap::Return,
+				..Default::default()
+			},
+			ParseOptions {
+				number_mode: NumberMode::AllFloat,
+				bool_style: BoolStyle::Numeric,
+				wrap: Wrap::Local(String::from("config")),
+				readonly: ReadonlyMode::Wrapper(String::from("readonly")),
+				..Default::default()
+			},
+			ParseOptions {
+				number_mode: NumberMode::AllInteger,
+				bool_style: BoolStyle::Custom(String::from("not nil"), String::from("not 1")),
+				datetime_wrapper: Some(String::from("DateTime")),
+				freeze: Freeze::Deep,
+				max_width: Some(usize::MAX),
+				..Default::default()
+			},
+			ParseOptions {
+				order_metadata: OrderMetadata::Field,
+				root_key: Some(String::from("config")),
+				..Default::default()
+			},
+			ParseOptions {
+				order_metadata: OrderMetadata::Metatable,
+				numeric_keys_as_array: true,
+				..Default::default()
+			},
+			ParseOptions {
+				hex_floats: true,
+				..Default::default()
+			},
+			ParseOptions {
+				chunking: core::num::NonZeroUsize::new(2),
+				bare_keys: true,
+				max_width: Some(80),
+				..Default::default()
+			},
+			ParseOptions {
+				split_locals: true,
+				root_key: Some(String::from("config")),
+				..Default::default()
+			},
+			ParseOptions {
+				dedupe: Some(Dedupe {
+					min_size: 0,
+					clone: false,
+				}),
+				split_locals: true,
+				max_width: Some(80),
+				..Default::default()
+			},
+			ParseOptions {
+				long_strings: true,
+				flatten_array_of_tables_indent: true,
+				..Default::default()
+			},
+			ParseOptions {
+				packed_arrays: true,
+				compact_empty: true,
+				max_width: Some(60),
+				..Default::default()
+			},
+		]
+	}
+
+	#[test]
+	fn corpus() {
+		for toml in CORPUS {
+			for options in options() {
+				if let Err(err) = verify_roundtrip(toml, &options) {
+					panic!("{}\n{:?}\n{}", err, options, toml);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn chunking() {
+		use core::num::NonZeroUsize;
+
+		let items: Vec<String> = (0..100_000).map(|i| format!("\"item {}\"", i)).collect();
+
+		let toml = format!(
+			"list = [{}]\n[lookup]\n{}",
+			items.join(", "),
+			(0..20_000)
+				.map(|i| format!("key_{} = {}.5", i, i))
+				.collect::<Vec<_>>()
+				.join("\n")
+		);
+
+		let options = ParseOptions {
+			chunking: NonZeroUsize::new(1000),
+			wrap: Wrap::Return,
+			..Default::default()
+		};
+
+		verify_roundtrip(&toml, &options).unwrap();
+	}
+
+	#[test]
+	fn floats() {
+		let mut floats = vec![
+			0.1,
+			0.1 + 0.2,
+			1.0 / 3.0,
+			1e15,
+			1e16,
+			1e17,
+			123_456_789.123_456_79,
+			9_007_199_254_740_993.0,
+			1e-5,
+			1e-7,
+			1e308,
+			f64::MAX,
+			f64::MIN_POSITIVE,
+			f64::EPSILON,
+			5e-324,
+			2.225_073_858_507_201e-308,
+		];
+
+		// Powers of ten and their neighbours across the whole exponent range
+		for exponent in (-320..=308).step_by(7) {
+			let power = 10f64.powi(exponent);
+			floats.extend([
+				power,
+				f64::from_bits(power.to_bits() + 1),
+				power * 1.234_567_890_123_456_7,
+			]);
+		}
+
+		let toml: String = floats
+			.iter()
+			.enumerate()
+			.map(|(i, float)| format!("f{} = {:?}\nn{} = {:?}\n", i, float, i, -float))
+			.collect();
+
+		for options in [ParseOptions::default(), ParseOptions::stylua_default()] {
+			verify_roundtrip(&toml, &options).unwrap();
+		}
+	}
+
+	#[test]
+	fn dedupe() {
+		let toml: String = (0..2000)
+			.map(|i| {
+				format!(
+					"[[npcs]]\nid = {}\nbio = 'Wanders the northern roads selling maps'\n\
+					[npcs.stats]\nhp = 100\nresist = {{ fire = 0.5, ice = 0.25 }}\ndrops = ['map', 'compass']\n",
+					i % 3
+				)
+			})
+			.collect();
+
+		let options = ParseOptions {
+			dedupe: Some(Dedupe::default()),
+			..Default::default()
+		};
+
+		let lua = crate::parse_with_options(&toml, &options).unwrap();
+
+		assert!(lua.starts_with("local __shared_1 = "));
+		verify_roundtrip(&toml, &options).unwrap();
+	}
+
+	#[test]
+	fn mismatch() {
+		let state = Lua::new();
+		let options = ParseOptions::default();
+		let verifier = Verifier {
+			state: &state,
+			options: &options,
+		};
+
+		let expected: Value = toml::from_str("[a]\nb = false").unwrap();
+		let found = state.load("return { a = { b = 1 } }").eval().unwrap();
+
+		let err = verifier
+			.compare(&expected, &found, &mut Vec::new())
+			.unwrap_err();
+
+		assert!(matches!(&err, VerifyError::Mismatch { path, .. } if path == "a.b"));
+		assert_eq!(
+			err.to_string(),
+			"expected boolean `false` at `a.b`, found integer `1`"
+		);
+
+		let found = state
+			.load("return { a = { b = false, c = 1 } }")
+			.eval()
+			.unwrap();
+
+		assert!(matches!(
+			verifier.compare(&expected, &found, &mut Vec::new()),
+			Err(VerifyError::Mismatch { path, .. }) if path == "a"
+		));
+
+		assert!(matches!(
+			verify_roundtrip(
+				"a = 1",
+				&ParseOptions {
+					wrap: Wrap::Return,
+					..Default::default()
+				}
+			),
+			Ok(())
+		));
+	}
+}
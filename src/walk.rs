@@ -0,0 +1,3445 @@
+use alloc::{
+	borrow::{Cow, ToOwned},
+	collections::{BTreeMap, BTreeSet},
+	format,
+	string::{String, ToString},
+	vec,
+	vec::Vec,
+};
+use core::num::NonZeroUsize;
+use toml::{value::Datetime, Table, Value};
+
+#[cfg(feature = "std")]
+use crate::dir::DirEntry;
+use crate::{
+	accessors,
+	case::lua_key,
+	datetime::{self, epoch_seconds},
+	fennel,
+	header::{header, lines, Source},
+	pack,
+	share::find_shared,
+	sort, warning, BoolStyle, CommentStyle, ConversionStats, DatetimeFallback, DatetimeKind,
+	DatetimeMode, Dedupe, Dialect, EmptyMode, EmptyStringMode, Error, Freeze, GroupOrder, Indent,
+	IntegerOverflow, KeyCase, KeyCollisions, LocalDatetimes, LuaTarget, Map, Mapping, Newline,
+	NilEntries, NumberMode, OrderMetadata, ParseOptions, QuoteStyle, Radix, ReadonlyMode,
+	RedactNonStrings, Separator, Warning, WarningKind, Wrap,
+};
+
+const ORDER_KEY: &str = "__order";
+
+/// Most locals of the shared values and top-level tables together, leaving
+/// room below the 200 locals a Lua function can have for the other ones
+const MAX_LOCALS: usize = 180;
+
+/// Value tree walker that accumulates Lua source
+pub(crate) struct Walker<'a> {
+	options: &'a ParseOptions,
+	path: Vec<String>,
+	comments: BTreeMap<String, Vec<String>>,
+	/// Paths comments were written for
+	commented: BTreeSet<String>,
+	/// Paths raw expressions were written for, see [`ParseOptions::raw_expressions`]
+	expressed: BTreeSet<String>,
+	integer_literals: BTreeMap<Vec<String>, (i64, String, Option<String>)>,
+	positions: BTreeMap<Vec<String>, (usize, usize)>,
+	/// Paths of the tables written inline in the source, see [`ParseOptions::keep_inline_tables`]
+	inline_tables: BTreeSet<Vec<String>>,
+	/// Lines of the output scalars were written to, recorded with [`with_source_map`](Self::with_source_map)
+	mappings: Option<Vec<Mapping>>,
+	/// Newlines in `lua` before the `scanned` byte
+	lines: usize,
+	scanned: usize,
+	trailing_comment: Option<String>,
+	root_depth: usize,
+	type_annotation: Option<String>,
+	/// LuaLS type of the root table, written as `---@type` right above it
+	type_comment: Option<String>,
+	/// Name and member access of the getters of the accessor module
+	getters: Vec<(String, String)>,
+	source: Source,
+	preamble: String,
+	/// Names of the locals top-level tables were written as, keyed by their key
+	locals: BTreeMap<String, String>,
+	/// Names of the locals shared values were written as, keyed by the address of every use
+	shared: BTreeMap<usize, String>,
+	in_local: bool,
+	/// Indices of the `redact` patterns that matched a value
+	redacted: BTreeSet<usize>,
+	/// Width the next key is padded to, see [`ParseOptions::align_assignments`]
+	key_width: usize,
+	/// Columns of the separator following the value being walked, counted as part of its line
+	separator_width: usize,
+	stats: ConversionStats,
+	warnings: Vec<Warning>,
+	lua: String,
+	/// Bytes already handed out by [`take`](Self::take)
+	flushed: usize,
+	/// Keys written so far and the key width of a root table written entry by entry
+	#[cfg(feature = "std")]
+	stream_scope: KeyScope,
+	#[cfg(feature = "std")]
+	stream_width: usize,
+	/// Bytes [`Newline::CrLf`] adds to `lua` before the `measured` byte
+	crlf_bytes: usize,
+	measured: usize,
+}
+
+impl<'a> Walker<'a> {
+	pub fn new(options: &'a ParseOptions) -> Self {
+		Self {
+			options,
+			path: Vec::new(),
+			comments: options
+				.doc_comments
+				.iter()
+				.map(|(path, text)| (path.clone(), lines(text).map(String::from).collect()))
+				.collect(),
+			commented: BTreeSet::new(),
+			expressed: BTreeSet::new(),
+			integer_literals: BTreeMap::new(),
+			positions: BTreeMap::new(),
+			inline_tables: BTreeSet::new(),
+			mappings: None,
+			lines: 0,
+			scanned: 0,
+			trailing_comment: None,
+			root_depth: 1,
+			type_annotation: None,
+			type_comment: None,
+			getters: Vec::new(),
+			source: Source::default(),
+			preamble: String::new(),
+			locals: BTreeMap::new(),
+			shared: BTreeMap::new(),
+			in_local: false,
+			redacted: BTreeSet::new(),
+			key_width: 0,
+			separator_width: 0,
+			stats: ConversionStats::default(),
+			warnings: Vec::new(),
+			lua: String::new(),
+			flushed: 0,
+			#[cfg(feature = "std")]
+			stream_scope: KeyScope::default(),
+			#[cfg(feature = "std")]
+			stream_width: 0,
+			crlf_bytes: 0,
+			measured: 0,
+		}
+	}
+
+	/// Emit the given comment lines above entries, keyed by dotted key path
+	#[cfg_attr(not(feature = "comments"), allow(dead_code))]
+	pub fn with_comments(mut self, comments: BTreeMap<String, Vec<String>>) -> Self {
+		// Below the doc comments of the same entry
+		for (path, lines) in comments {
+			self.comments.entry(path).or_default().extend(lines);
+		}
+
+		self
+	}
+
+	/// Emit integers as the given literals followed by optional comments, keyed
+	/// by key path along with the value they spell
+	#[cfg_attr(not(feature = "radix"), allow(dead_code))]
+	pub fn with_integer_literals(
+		mut self,
+		literals: BTreeMap<Vec<String>, (i64, String, Option<String>)>,
+	) -> Self {
+		self.integer_literals = literals;
+		self
+	}
+
+	/// Describe the converted document in the header and source comments
+	pub fn with_source(mut self, name: Option<&str>, toml: Option<&str>) -> Self {
+		self.source = Source::new(name, toml, self.options);
+
+		#[cfg(feature = "comments")]
+		if let Some(toml) = toml.filter(|_| self.options.source_comments) {
+			self.positions = crate::comments::value_positions(toml);
+		}
+
+		#[cfg(feature = "comments")]
+		if let Some(toml) = toml.filter(|_| self.options.keep_inline_tables) {
+			self.inline_tables = crate::comments::inline_tables(toml);
+		}
+
+		self
+	}
+
+	/// Write the given Lua in place of the values at the given addresses, like
+	/// the uses of shared values
+	#[cfg(feature = "std")]
+	pub fn with_references(mut self, references: BTreeMap<usize, String>) -> Self {
+		self.shared.extend(references);
+		self
+	}
+
+	/// Key path of the value given to [`walk_fragment`](Self::walk_fragment),
+	/// for the options keyed by path
+	#[cfg(feature = "std")]
+	pub fn with_path(mut self, path: Vec<String>) -> Self {
+		self.path = path;
+		self
+	}
+
+	/// Record the output line of every scalar, see [`mappings`](Self::mappings)
+	#[cfg(feature = "comments")]
+	pub fn with_source_map(mut self, toml: &str) -> Self {
+		if self.positions.is_empty() {
+			self.positions = crate::comments::value_positions(toml);
+		}
+
+		self.mappings = Some(Vec::new());
+		self
+	}
+
+	/// Write the given Lua below the header and above the root table
+	pub fn with_preamble(mut self, preamble: String) -> Self {
+		self.preamble = preamble;
+		self
+	}
+
+	/// Annotate the root table with the given Luau or Teal type
+	pub fn with_type_annotation(mut self, name: String) -> Self {
+		self.type_annotation = Some(name);
+		self
+	}
+
+	/// Annotate the root table with the given LuaLS type
+	pub fn with_type_comment(mut self, name: String) -> Self {
+		self.type_comment = Some(name);
+		self
+	}
+
+	/// Warnings of the values walked so far, handed out once
+	pub fn take_warnings(&mut self) -> Vec<Warning> {
+		core::mem::take(&mut self.warnings)
+	}
+
+	pub fn stats(&self) -> ConversionStats {
+		self.stats
+	}
+
+	#[cfg(feature = "comments")]
+	pub fn mappings(&mut self) -> Vec<Mapping> {
+		self.mappings.take().unwrap_or_default()
+	}
+
+	pub fn finish(self) -> String {
+		self.options.newline.apply(self.lua)
+	}
+
+	pub fn walk_root<'v, I>(&mut self, entries: I) -> Result<(), Error>
+	where
+		I: IntoIterator<Item = (&'v String, &'v Value)>,
+	{
+		let entries: Vec<(&String, &Value)> = entries.into_iter().collect();
+
+		if entries.is_empty() && self.options.empty_document == EmptyMode::Error {
+			return Err(Error::EmptyDocument);
+		}
+
+		if self.options.dialect == Dialect::Fennel {
+			return self.walk_fennel(entries);
+		}
+
+		if let [(key, value)] = entries.as_slice() {
+			if self.options.unwrap_single {
+				self.options.check_unwrapped(value)?;
+
+				let depth = self.open_root(Some(&entries))?;
+
+				self.path.push((*key).clone());
+				self.walk_value(value, depth)?;
+				self.path.pop();
+
+				return self.close_root();
+			}
+		}
+
+		let depth = self.open_root(Some(&entries))?;
+		self.walk_document(entries, depth)?;
+		self.close_root()?;
+
+		Ok(())
+	}
+
+	/// Write the document as a Fennel table, see [`Dialect::Fennel`]
+	fn walk_fennel(&mut self, entries: Vec<(&String, &Value)>) -> Result<(), Error> {
+		self.options.check_conflicts()?;
+
+		self.lua
+			.push_str(&fennel::comments(&header(self.options, &self.source)));
+		self.lua.push_str(&fennel::document(entries, self.options));
+
+		if self.options.final_newline {
+			self.lua.push('\n');
+		}
+
+		self.check_size()
+	}
+
+	/// Write a single value as a bare expression, without the header, wrapping or root key of documents
+	pub fn walk_fragment(&mut self, value: &Value, depth: usize) -> Result<(), Error> {
+		self.options.check_target()?;
+		self.options.check_conflicts()?;
+
+		self.walk_value(value, depth)?;
+
+		warning::check_denied(&self.warnings, self.options.deny_warnings)?;
+
+		self.check_size()
+	}
+
+	/// Write every leaf value as an assignment statement to its path under `root`,
+	/// like `config.object.key = "value"`
+	pub fn walk_assignments<'v, I>(&mut self, root: &str, entries: I) -> Result<(), Error>
+	where
+		I: IntoIterator<Item = (&'v String, &'v Value)>,
+	{
+		self.options.check_target()?;
+
+		for (key, value) in entries {
+			let target = format!("{}{}", root, self.member(key));
+
+			self.path.push(key.clone());
+			self.walk_assignment(&target, value)?;
+			self.path.pop();
+		}
+
+		self.check_size()
+	}
+
+	/// Walk several documents, each becoming an entry of the root table
+	pub fn walk_documents<'v, I>(&mut self, documents: I) -> Result<(), Error>
+	where
+		I: IntoIterator<Item = (&'v String, &'v Map)>,
+	{
+		let mut scope = KeyScope::default();
+		let mut documents = documents.into_iter().peekable();
+
+		let depth = self.open_root(None)?;
+		self.stats.tables += 1;
+		self.count_depth()?;
+		self.open_constructor(depth);
+
+		while let Some((name, document)) = documents.next() {
+			scope.insert(&lua_key(name, self.options), &self.path)?;
+
+			self.path.push(name.clone());
+			self.push_indent(depth + 1);
+			self.push_table_key(name, depth + 1);
+			self.walk_document(document, depth + 1)?;
+			self.path.pop();
+
+			self.push_separator(documents.peek().is_none());
+		}
+
+		self.close_constructor(depth);
+		self.close_root()?;
+
+		Ok(())
+	}
+
+	/// Open the root table of a document written piece by piece
+	#[cfg(feature = "std")]
+	pub fn open_stream(&mut self) -> Result<(), Error> {
+		let depth = self.open_root(None)?;
+		self.stats.tables += 1;
+		self.count_depth()?;
+		self.open_constructor(depth);
+
+		Ok(())
+	}
+
+	/// Open the root table of a document written entry by entry with
+	/// [`stream_entry`](Self::stream_entry), returning the keys of the entries
+	/// in the order they are written. The entries are ordered and left out like
+	/// with [`walk_root`](Self::walk_root), a root table left without any is
+	/// written and closed right away
+	#[cfg(feature = "std")]
+	pub fn open_stream_entries<'v, I>(&mut self, entries: I) -> Result<Vec<String>, Error>
+	where
+		I: IntoIterator<Item = (&'v String, &'v Value)>,
+	{
+		let entries: Vec<(&String, &Value)> = entries.into_iter().collect();
+
+		let depth = self.open_root(Some(&entries))?;
+		let entries = self.table_entries(entries)?;
+
+		if entries.is_empty() {
+			self.walk_table(entries, depth)?;
+			self.close_root()?;
+
+			return Ok(Vec::new());
+		}
+
+		self.stats.tables += 1;
+		self.count_depth()?;
+		self.open_constructor(depth);
+		self.stream_width = self.key_column(entries.iter().map(|(key, _)| key.as_str()));
+
+		Ok(entries.into_iter().map(|(key, _)| key.clone()).collect())
+	}
+
+	/// Start a root entry holding an array of tables, followed by [`stream_item`](Self::stream_item) calls
+	#[cfg(feature = "std")]
+	pub fn open_stream_array(&mut self, key: &str) -> Result<(), Error> {
+		let depth = self.root_depth;
+
+		self.path.push(key.to_owned());
+		self.push_indent(depth);
+		self.push_table_key(key, depth);
+		self.stats.arrays += 1;
+		self.count_depth()?;
+		self.open_constructor(depth);
+
+		Ok(())
+	}
+
+	/// Write the item at `index` of the open array, separators are written
+	/// as if more items follow
+	#[cfg(feature = "std")]
+	pub fn stream_item(&mut self, index: usize, item: &Value) -> Result<(), Error> {
+		self.path.push(index.to_string());
+		self.walk(None, item, self.root_depth + 1)?;
+		self.path.pop();
+		self.push_separator(false);
+
+		Ok(())
+	}
+
+	/// Write the root entry at `index` of the ones returned by
+	/// [`open_stream_entries`](Self::open_stream_entries), failing if it takes
+	/// the output past `max_output_bytes` so it is never handed out
+	#[cfg(feature = "std")]
+	pub fn stream_entry(
+		&mut self,
+		index: usize,
+		entry: (&String, &Value),
+		last: bool,
+	) -> Result<(), Error> {
+		let mut scope = core::mem::take(&mut self.stream_scope);
+		let walked = self.walk_entry(
+			&mut scope,
+			index,
+			entry,
+			self.root_depth,
+			self.stream_width,
+			last,
+		);
+		self.stream_scope = scope;
+
+		walked?;
+		self.check_size()
+	}
+
+	#[cfg(feature = "std")]
+	pub fn close_stream_array(&mut self) {
+		self.close_constructor(self.root_depth);
+		self.path.pop();
+		self.push_separator(false);
+	}
+
+	/// Write the remaining root entries and close the root table
+	#[cfg(feature = "std")]
+	pub fn close_stream(&mut self, rest: &Map) -> Result<(), Error> {
+		let entries: Vec<(&String, &Value)> = rest.iter().collect();
+
+		self.walk_entries(&entries, self.root_depth, false)?;
+		self.close_constructor(self.root_depth - 1);
+		self.close_root()?;
+
+		Ok(())
+	}
+
+	/// Take the Lua written so far, leaving the walker empty
+	#[cfg(feature = "std")]
+	pub fn take(&mut self) -> String {
+		// Count the lines handed out, later lines are scanned from the start of the emptied output
+		self.line();
+		self.scanned = 0;
+
+		let lua = self.options.newline.apply(core::mem::take(&mut self.lua));
+		self.flushed += lua.len();
+		self.crlf_bytes = 0;
+		self.measured = 0;
+		lua
+	}
+
+	/// Walk a directory tree, nesting documents under their directories
+	#[cfg(feature = "std")]
+	pub fn walk_directory(&mut self, entries: &[(String, DirEntry)]) -> Result<(), Error> {
+		let depth = self.open_root(None)?;
+		self.walk_dir_table(entries, depth)?;
+		self.close_root()?;
+
+		Ok(())
+	}
+
+	#[cfg(feature = "std")]
+	fn walk_dir_table(
+		&mut self,
+		entries: &[(String, DirEntry)],
+		depth: usize,
+	) -> Result<(), Error> {
+		self.stats.tables += 1;
+		self.count_depth()?;
+		self.open_constructor(depth);
+
+		for (i, (name, entry)) in entries.iter().enumerate() {
+			self.path.push(name.clone());
+			self.push_indent(depth + 1);
+			self.push_table_key(name, depth + 1);
+
+			match entry {
+				DirEntry::Directory(children) => self.walk_dir_table(children, depth + 1)?,
+				DirEntry::Document(document) => self.walk_document(document, depth + 1)?,
+			}
+
+			self.path.pop();
+			self.push_separator(i + 1 == entries.len());
+		}
+
+		self.close_constructor(depth);
+
+		Ok(())
+	}
+
+	/// Write everything preceding the root table and return its depth
+	///
+	/// Shared values and top-level tables of a single document's `root` entries
+	/// are written as locals first when enabled, making the root table returned
+	fn open_root(&mut self, root: Option<&[(&String, &Value)]>) -> Result<usize, Error> {
+		let options = self.options;
+
+		options.check_target()?;
+		options.check_conflicts()?;
+
+		self.lua.push_str(&header(options, &self.source));
+		self.lua.push_str(&self.preamble);
+
+		let mut locals = false;
+
+		if let Some(entries) = root {
+			if let Some(dedupe) = &options.dedupe {
+				self.walk_shared(entries, dedupe)?;
+				locals = true;
+			}
+
+			// The value of an unwrapped document is not a table to split
+			if options.split_locals && !(options.unwrap_single && entries.len() == 1) {
+				self.walk_locals(entries)?;
+				locals = true;
+			}
+		}
+
+		// Below the locals, annotating the statement of the root table
+		if let Some(name) = &self.type_comment {
+			self.lua.push_str(&format!("---@type {}\n", name));
+		}
+
+		let local = match &self.options.wrap {
+			_ if self.options.accessor_module.is_some() => Some("data"),
+			Wrap::Local(name) => Some(name.as_str()),
+			_ if self.options.with_accessor.is_some() => Some("data"),
+			_ => None,
+		};
+
+		match local {
+			Some(name) => match &self.type_annotation {
+				Some(annotation) => self
+					.lua
+					.push_str(&format!("local {}: {} = ", name, annotation)),
+				None => self.lua.push_str(&format!("local {} = ", name)),
+			},
+			None if locals || self.options.wrap == Wrap::Return => self.lua.push_str("return "),
+			None => {}
+		}
+
+		if options.accessor_module.is_some() {
+			self.getters = self.getters(root);
+		}
+
+		match &self.options.readonly {
+			ReadonlyMode::None => {}
+			ReadonlyMode::Wrapper(name) => self.lua.push_str(&format!("{}(", name)),
+			ReadonlyMode::Metatable => self.lua.push_str("setmetatable("),
+		}
+
+		if self.options.root_metatable.is_some() {
+			self.lua.push_str("setmetatable(");
+		}
+
+		let depth = if let Some(root_key) = &self.options.root_key {
+			self.open_constructor(0);
+			self.push_indent(1);
+			self.push_table_key(root_key, 1);
+			1
+		} else {
+			0
+		};
+
+		self.root_depth = depth + 1;
+
+		Ok(depth)
+	}
+
+	/// Write top-level tables as locals, root entries then refer to them by name
+	fn walk_locals(&mut self, entries: &[(&String, &Value)]) -> Result<(), Error> {
+		// Globals the output may call can't be shadowed
+		let mut taken: BTreeSet<String> = ["table", "setmetatable"]
+			.into_iter()
+			.map(String::from)
+			.chain(self.options.datetime_wrapper.clone())
+			.chain(self.shared.values().cloned())
+			.chain(
+				self.options
+					.pack_numbers
+					.iter()
+					.flat_map(|_| pack::GLOBALS.iter().map(|name| String::from(*name))),
+			)
+			.chain(
+				self.options
+					.accessor_module
+					.iter()
+					.flat_map(|_| accessors::NAMES.iter().map(|name| String::from(*name))),
+			)
+			.chain(
+				self.options
+					.with_accessor
+					.iter()
+					.flat_map(|name| [name.clone(), String::from("data")]),
+			)
+			.chain(match &self.options.readonly {
+				ReadonlyMode::None => None,
+				ReadonlyMode::Wrapper(name) => Some(name.clone()),
+				ReadonlyMode::Metatable => Some(String::from("error")),
+			})
+			.collect();
+
+		// Tables past the limit stay in the returned table
+		let shared: BTreeSet<&String> = self.shared.values().collect();
+		let limit = MAX_LOCALS.saturating_sub(shared.len());
+
+		self.in_local = true;
+
+		for (key, value) in entries {
+			if !value.is_table() || self.locals.len() >= limit {
+				continue;
+			}
+
+			let name = local_name(key, &mut taken);
+
+			self.lua.push_str(&format!("local {} = ", name));
+			self.path.push((*key).clone());
+			self.walk(None, value, 0)?;
+			self.path.pop();
+			self.lua.push('\n');
+
+			self.locals.insert((*key).clone(), name);
+		}
+
+		self.in_local = false;
+
+		Ok(())
+	}
+
+	/// Write values occurring several times as locals, their uses then refer to them by name
+	fn walk_shared(&mut self, entries: &[(&String, &Value)], dedupe: &Dedupe) -> Result<(), Error> {
+		self.in_local = true;
+
+		for (i, shared) in find_shared(entries, dedupe).into_iter().enumerate() {
+			let name = format!("__shared_{}", i + 1);
+
+			// Values nested in this one are already shared, the value itself isn't until it's written
+			self.lua.push_str(&format!("local {} = ", name));
+			self.path = shared.path;
+			self.walk_value(shared.value, 0)?;
+			self.path.clear();
+
+			if let Some(comment) = self.trailing_comment.take() {
+				self.lua.push_str(&format!(" -- {}", comment));
+			}
+
+			self.lua.push('\n');
+
+			for address in shared.uses {
+				self.shared.insert(address, name.clone());
+			}
+		}
+
+		self.in_local = false;
+
+		Ok(())
+	}
+
+	/// Lua referring to the local the value was written as by [`walk_shared`](Self::walk_shared),
+	/// if it's shared
+	fn shared_reference(&self, value: &Value, depth: usize) -> Option<String> {
+		if self.shared.is_empty() {
+			return None;
+		}
+
+		let name = self.shared.get(&(value as *const Value as usize))?;
+		let clone = self
+			.options
+			.dedupe
+			.as_ref()
+			.is_some_and(|dedupe| dedupe.clone);
+
+		Some(match value {
+			Value::Array(_) | Value::Table(_) if clone && self.freezes(depth) => {
+				format!("table.freeze(table.clone({}))", name)
+			}
+			Value::Array(_) | Value::Table(_) if clone => format!("table.clone({})", name),
+			_ => name.clone(),
+		})
+	}
+
+	fn close_root(&mut self) -> Result<(), Error> {
+		if self.options.root_key.is_some() {
+			self.push_separator(true);
+			self.close_constructor(0);
+		}
+
+		if let Some(metatable) = &self.options.root_metatable {
+			self.lua.push_str(&format!(", {})", metatable));
+		}
+
+		match self.options.readonly {
+			ReadonlyMode::None => {}
+			ReadonlyMode::Wrapper(_) => self.lua.push(')'),
+			ReadonlyMode::Metatable => self.lua.push_str(READONLY_METATABLE),
+		}
+
+		if let Some(annotation) = &self.type_annotation {
+			if !matches!(self.options.wrap, Wrap::Local(_))
+				&& self.options.accessor_module.is_none()
+				&& self.options.with_accessor.is_none()
+			{
+				let cast = if self.options.teal_types.is_some() {
+					"as"
+				} else {
+					"::"
+				};
+
+				self.lua.push_str(&format!(" {} {}", cast, annotation));
+			}
+		}
+
+		// Left by a value written alone, the comment has to follow whatever closes the root
+		if let Some(comment) = self.trailing_comment.take() {
+			self.lua.push_str(&format!(" -- {}", comment));
+		}
+
+		if let Some(redact) = &self.options.redact {
+			for (i, pattern) in redact.paths.iter().enumerate() {
+				if !self.redacted.contains(&i) {
+					self.warnings.push(Warning {
+						kind: WarningKind::Unmatched,
+						path: pattern.clone(),
+						message: String::from("redact pattern matches no value"),
+					});
+				}
+			}
+		}
+
+		for path in self.options.raw_expressions.keys() {
+			if !self.expressed.contains(path) {
+				self.warnings.push(Warning {
+					kind: WarningKind::Unmatched,
+					path: path.clone(),
+					message: String::from("raw expression matches no value"),
+				});
+			}
+		}
+
+		for path in self.options.doc_comments.keys() {
+			if !self.commented.contains(path) {
+				self.warnings.push(Warning {
+					kind: WarningKind::Unmatched,
+					path: path.clone(),
+					message: String::from("doc comment matches no entry"),
+				});
+			}
+		}
+
+		if let Some(module) = &self.options.accessor_module {
+			self.lua.push('\n');
+			self.lua
+				.push_str(&accessors::module_code(&self.getters, module, self.options));
+		}
+
+		if let Some(name) = &self.options.with_accessor {
+			self.lua.push('\n');
+			self.lua
+				.push_str(&accessors::accessor_function(name, self.options));
+		}
+
+		#[cfg(feature = "content_hash")]
+		self.lua.push_str(&crate::header::footer(&self.source));
+
+		if self.options.final_newline {
+			self.lua.push('\n');
+		}
+
+		warning::check_denied(&self.warnings, self.options.deny_warnings)?;
+
+		self.check_size()
+	}
+
+	/// Getters of the accessor module for the keys of the root table, documents
+	/// written as anything but a table of their `root` entries get none
+	fn getters(&self, root: Option<&[(&String, &Value)]>) -> Vec<(String, String)> {
+		let keys: Vec<&str> = match (&self.options.root_key, root) {
+			(Some(root_key), _) => vec![root_key.as_str()],
+			(None, Some([_])) if self.options.unwrap_single => Vec::new(),
+			(None, Some(entries)) if !self.options.flatten => {
+				entries.iter().map(|(key, _)| key.as_str()).collect()
+			}
+			_ => Vec::new(),
+		};
+
+		keys.into_iter()
+			.filter_map(|key| {
+				let name = lua_key(key, self.options);
+
+				(is_valid_lua_identifier(&name, true) && !matches!(&*name, "get" | "data"))
+					.then(|| (name.into_owned(), self.member(key)))
+			})
+			.collect()
+	}
+
+	/// Fail once the output grows past [`max_output_bytes`](ParseOptions::max_output_bytes),
+	/// checked before every value so at most one value is written past the limit
+	fn check_size(&mut self) -> Result<(), Error> {
+		let Some(limit) = self.options.max_output_bytes else {
+			return Ok(());
+		};
+
+		if self.options.newline == Newline::CrLf {
+			self.measure_newlines();
+		}
+
+		if self.flushed + self.lua.len() + self.crlf_bytes > limit {
+			return Err(Error::OutputTooLarge { limit });
+		}
+
+		Ok(())
+	}
+
+	/// Count the newlines written since the last call that [`Newline::CrLf`]
+	/// turns into two bytes, so checking the size doesn't rescan the output
+	fn measure_newlines(&mut self) {
+		let bytes = self.lua.as_bytes();
+
+		for i in self.measured..bytes.len() {
+			if bytes[i] == b'\n' && (i == 0 || bytes[i - 1] != b'\r') {
+				self.crlf_bytes += 1;
+			}
+		}
+
+		self.measured = bytes.len();
+	}
+
+	/// Walk the root table of a document, flattening it if enabled
+	fn walk_document<'v, I>(&mut self, entries: I, depth: usize) -> Result<(), Error>
+	where
+		I: IntoIterator<Item = (&'v String, &'v Value)>,
+	{
+		if !self.options.flatten {
+			return self.walk_table(entries, depth);
+		}
+
+		let mut leaves = flatten(entries, self.options);
+
+		let table = self.path.clone();
+		// Leaf paths are already joined with their keys quoted
+		self.resolve_collisions(&mut leaves, |leaf| {
+			let path = if table.is_empty() {
+				leaf.path.clone()
+			} else {
+				format!("{}.{}", join_path(&table), leaf.path)
+			};
+			(&leaf.key, path)
+		})?;
+		self.walk_table(leaves.iter().map(|leaf| (&leaf.key, leaf.value)), depth)
+	}
+
+	/// Walk table whose braces are at `depth`, the cursor is already placed after its key
+	fn walk_table<'v, I>(&mut self, entries: I, depth: usize) -> Result<(), Error>
+	where
+		I: IntoIterator<Item = (&'v String, &'v Value)>,
+	{
+		let entries = self.table_entries(entries)?;
+		let metadata = self.options.order_metadata;
+
+		self.stats.tables += 1;
+		self.count_depth()?;
+
+		if let Some(size) = self.chunk_size(entries.len()) {
+			let entries = entries.iter().map(|(key, value)| (Some(*key), *value));
+			return self.walk_chunked(entries.collect(), depth, size);
+		}
+
+		if self.freezes(depth) {
+			self.lua.push_str("table.freeze(");
+		}
+
+		if metadata == OrderMetadata::Metatable {
+			self.lua.push_str("setmetatable(");
+		}
+
+		let compact = self.options.compact_empty
+			|| (self.path.is_empty() && self.options.empty_document == EmptyMode::CompactEmpty);
+
+		let tag = self.type_tag(&entries)?;
+
+		let empty = entries.is_empty() && metadata != OrderMetadata::Field && tag.is_none();
+
+		if empty && self.options.json_bridge {
+			self.lua.push_str(&json_empty("object"));
+		} else if empty && compact {
+			self.lua.push_str("{}");
+		} else {
+			self.lua.push_str("{\n");
+			self.walk_entries(
+				&entries,
+				depth + 1,
+				metadata == OrderMetadata::Field || tag.is_some(),
+			)?;
+
+			if let Some((field, tag)) = tag {
+				self.push_type_tag(&entries, (field, &tag), depth + 1);
+			}
+
+			if metadata == OrderMetadata::Field {
+				let keys = entries.iter().map(|(key, _)| key.as_str());
+
+				self.key_width = self.key_column(keys.chain([ORDER_KEY]));
+				self.push_indent(depth + 1);
+				self.push_key(ORDER_KEY);
+				self.lua.push_str(&order_list(&entries, self.options));
+				self.push_separator(true);
+			}
+
+			self.push_indent(depth);
+			self.lua.push('}');
+		}
+
+		if metadata == OrderMetadata::Metatable {
+			self.lua.push_str(&format!(
+				", {{ {} = {} }})",
+				ORDER_KEY,
+				order_list(&entries, self.options)
+			));
+		}
+
+		if self.freezes(depth) {
+			self.lua.push(')');
+		}
+
+		Ok(())
+	}
+
+	/// Entries of the table in the order they are written, without the ones left out
+	fn table_entries<'v, I>(&mut self, entries: I) -> Result<Vec<(&'v String, &'v Value)>, Error>
+	where
+		I: IntoIterator<Item = (&'v String, &'v Value)>,
+	{
+		let mut entries: Vec<(&String, &Value)> = entries.into_iter().collect();
+
+		// `toml` keeps nested tables in document order whenever `std` is enabled
+		#[cfg(not(feature = "preserve_order"))]
+		entries.sort_by_key(|(key, _)| *key);
+
+		if let Some(order) = &self.options.key_order {
+			entries.sort_by(|(a, _), (b, _)| order.call(a, b));
+		}
+
+		let group_order = self.options.group_order();
+
+		if group_order != GroupOrder::None {
+			let tables_first = group_order == GroupOrder::TablesFirst;
+
+			entries.sort_by_key(|(_, value)| {
+				let group = match value {
+					Value::Array(_) => 1,
+					Value::Table(_) => 2,
+					_ => 0,
+				};
+
+				if tables_first {
+					2 - group
+				} else {
+					group
+				}
+			});
+		}
+
+		if self.options.warn_case_collisions {
+			self.check_case_collisions(&entries);
+		}
+
+		if self.options.nil_entries == NilEntries::Drop {
+			entries.retain(|(_, value)| !is_nil(value, self.options));
+		}
+
+		if self.options.empty_string == EmptyStringMode::Omit {
+			entries
+				.retain(|(_, value)| !matches!(value, Value::String(string) if string.is_empty()));
+		}
+
+		// Keys of a table are distinct Lua keys unless transformed, `check_duplicate_keys`
+		// rejects the transformed ones while writing them
+		if self.options.key_case != KeyCase::Preserve && !self.options.check_duplicate_keys {
+			let table = self.path.clone();
+			self.resolve_collisions(&mut entries, |(key, _)| (key, key_path(&table, key)))?;
+		}
+
+		Ok(entries)
+	}
+
+	/// Warn about keys of the table that only differ by case, see [`ParseOptions::warn_case_collisions`]
+	fn check_case_collisions(&mut self, entries: &[(&String, &Value)]) {
+		let mut seen: BTreeMap<String, &str> = BTreeMap::new();
+
+		for (key, _) in entries {
+			let Some(first) = seen.insert(key.to_lowercase(), key) else {
+				continue;
+			};
+
+			self.warnings.push(Warning {
+				kind: WarningKind::CaseCollision,
+				path: key_path(&self.path, key),
+				message: format!("key differs from `{}` only by case", first),
+			});
+		}
+	}
+
+	/// Field and tag of the nested table with the given entries, see [`ParseOptions::type_tags`]
+	fn type_tag(&self, entries: &[(&String, &Value)]) -> Result<Option<(&'a str, String)>, Error> {
+		let Some(tags) = &self.options.type_tags else {
+			return Ok(None);
+		};
+
+		if self.path.is_empty() {
+			return Ok(None);
+		}
+
+		if entries.iter().any(|(key, _)| **key == tags.field) {
+			return Err(Error::ReservedKey {
+				path: join_path(&self.path),
+				key: tags.field.clone(),
+			});
+		}
+
+		let tag = tags
+			.tags
+			.iter()
+			.find(|(pattern, _)| matches_path(pattern, &self.path))
+			.map(|(_, tag)| tag.clone())
+			.or_else(|| {
+				// Items of arrays are named after the array
+				self.path
+					.iter()
+					.rev()
+					.find(|key| !key.bytes().all(|byte| byte.is_ascii_digit()))
+					.cloned()
+			});
+
+		Ok(tag.map(|tag| (tags.field.as_str(), tag)))
+	}
+
+	/// Write the tag entry after the other entries at `depth`
+	fn push_type_tag(
+		&mut self,
+		entries: &[(&String, &Value)],
+		(field, tag): (&str, &str),
+		depth: usize,
+	) {
+		let keys = entries.iter().map(|(key, _)| key.as_str());
+
+		self.key_width = self.key_column(keys.chain([field]));
+		self.push_indent(depth);
+		self.push_key(field);
+		self.lua.push_str(&quote_string(tag, self.options));
+		self.push_separator(self.options.order_metadata != OrderMetadata::Field);
+	}
+
+	/// Reject entries written as the Lua key of an earlier entry or leave them
+	/// out, see [`ParseOptions::key_collisions`]. `key` gives the key and the
+	/// path of an entry
+	fn resolve_collisions<T, F>(&mut self, entries: &mut Vec<T>, key: F) -> Result<(), Error>
+	where
+		F: Fn(&T) -> (&str, String),
+	{
+		let mut taken: BTreeMap<String, String> = BTreeMap::new();
+		let mut keep = Vec::with_capacity(entries.len());
+
+		for entry in entries.iter() {
+			let (key, path) = key(entry);
+			let lua_key = lua_key(key, self.options).into_owned();
+
+			let Some(first) = taken.get(&lua_key) else {
+				taken.insert(lua_key, path);
+				keep.push(true);
+				continue;
+			};
+
+			match self.options.key_collisions {
+				KeyCollisions::Error => {
+					return Err(Error::KeyCollision {
+						first: first.clone(),
+						second: path,
+						key: lua_key,
+					})
+				}
+				KeyCollisions::KeepFirst => {
+					self.warnings.push(Warning {
+						kind: WarningKind::KeyLeftOut,
+						message: format!("left out, its key `{}` is taken by `{}`", lua_key, first),
+						path,
+					});
+					keep.push(false);
+				}
+			}
+		}
+
+		let mut keep = keep.into_iter();
+		entries.retain(|_| keep.next().unwrap_or(true));
+
+		Ok(())
+	}
+
+	/// Walk table entries, `more` tells whether other entries follow the last one
+	fn walk_entries(
+		&mut self,
+		entries: &[(&String, &Value)],
+		depth: usize,
+		more: bool,
+	) -> Result<(), Error> {
+		let mut scope = KeyScope::default();
+		let width = self.key_column(entries.iter().map(|(key, _)| key.as_str()));
+
+		for (i, entry) in entries.iter().enumerate() {
+			let last = i + 1 == entries.len() && !more;
+			self.walk_entry(&mut scope, i, *entry, depth, width, last)?;
+		}
+
+		Ok(())
+	}
+
+	/// Walk the entry at index `i` of its table at `depth`, with keys padded to `width`
+	fn walk_entry(
+		&mut self,
+		scope: &mut KeyScope,
+		i: usize,
+		(key, value): (&String, &Value),
+		depth: usize,
+		width: usize,
+		last: bool,
+	) -> Result<(), Error> {
+		self.open_entry(scope, i, (key, value), depth)?;
+
+		self.key_width = width;
+		self.separator_width = self.separator_width(last);
+		self.walk(Some(key), value, depth)?;
+		self.path.pop();
+
+		self.push_separator(last);
+
+		Ok(())
+	}
+
+	/// Check the key of an entry and write what goes above it, kept out of
+	/// [`walk_entry`](Self::walk_entry) so nesting uses less stack
+	fn open_entry(
+		&mut self,
+		scope: &mut KeyScope,
+		i: usize,
+		(key, value): (&String, &Value),
+		depth: usize,
+	) -> Result<(), Error> {
+		if self.options.check_duplicate_keys {
+			scope.insert(&lua_key(key, self.options), &self.path)?;
+		}
+
+		if self.options.order_metadata != OrderMetadata::None && *key == ORDER_KEY {
+			return Err(Error::ReservedKey {
+				path: join_path(&self.path),
+				key: ORDER_KEY.to_owned(),
+			});
+		}
+
+		if depth == self.root_depth && self.options.section_spacing && i > 0 && is_section(value) {
+			self.lua.push('\n');
+		}
+
+		self.path.push(key.clone());
+		self.push_comments(depth);
+
+		if self.options.columnar_comments {
+			self.push_columns(value, depth);
+		}
+
+		Ok(())
+	}
+
+	fn walk(&mut self, key: Option<&str>, value: &Value, depth: usize) -> Result<(), Error> {
+		self.check_size()?;
+
+		if let Some((key, name)) = self.local(key, depth) {
+			self.push_indent(depth);
+			self.push_key(key);
+			self.lua.push_str(&name);
+
+			return Ok(());
+		}
+		self.push_indent(depth);
+
+		let collection =
+			(value.is_table() || value.is_array()) && self.shared_reference(value, depth).is_none();
+
+		if let Some(key) = key {
+			if collection {
+				self.push_table_key(key, depth);
+			} else {
+				self.push_key(key);
+			}
+		}
+
+		self.walk_value(value, depth)
+	}
+
+	/// Write the value whose braces are at `depth`, the cursor is already placed after its key
+	fn walk_value(&mut self, value: &Value, depth: usize) -> Result<(), Error> {
+		if let Some(reference) = self.shared_reference(value, depth) {
+			self.lua.push_str(&reference);
+			return Ok(());
+		}
+
+		// Arms are kept in their own functions, the frame of this one is on the stack for every level of nesting
+		match value {
+			Value::Array(a) => self.walk_array_value(a, depth),
+			Value::Table(t) => self.walk_table_value(t, depth),
+			_ => {
+				self.stats.scalars += 1;
+				self.push_scalar(value)?;
+				self.push_position();
+
+				Ok(())
+			}
+		}
+	}
+
+	/// Write array of tables as `{{ ... }, { ... }}`, the braces of the items at the depth of the array
+	fn walk_joined_tables(&mut self, items: &[&Value], depth: usize) -> Result<(), Error> {
+		if self.freezes(depth) {
+			self.lua.push_str("table.freeze(");
+		}
+
+		self.lua.push('{');
+
+		for (i, item) in items.iter().enumerate() {
+			if i > 0 {
+				self.lua.push(match self.options.separator {
+					Separator::Comma => ',',
+					Separator::Semicolon => ';',
+				});
+				self.lua.push(' ');
+			}
+
+			self.path.push((i + 1).to_string());
+			self.walk_value(item, depth)?;
+			self.path.pop();
+		}
+
+		self.stats.fields += items.len();
+		self.lua.push('}');
+
+		if self.freezes(depth) {
+			self.lua.push(')');
+		}
+
+		Ok(())
+	}
+
+	fn walk_array_value(&mut self, array: &[Value], depth: usize) -> Result<(), Error> {
+		if !self.options.sort_arrays_by.is_empty() {
+			if let Some(sorted) = self.sorted_array(array)? {
+				return self.walk_sorted_array(&sorted, depth);
+			}
+		}
+
+		self.walk_sorted_array(array, depth)
+	}
+
+	/// Entries of the array in the order of [`ParseOptions::sort_arrays_by`], if it's listed
+	fn sorted_array(&self, array: &[Value]) -> Result<Option<Vec<Value>>, Error> {
+		let path = join_path(&self.path);
+
+		match self.options.sort_arrays_by.get(&path) {
+			Some(sort) => sort::sorted_array(array, sort, &path).map(Some),
+			None => Ok(None),
+		}
+	}
+
+	fn walk_sorted_array(&mut self, array: &[Value], depth: usize) -> Result<(), Error> {
+		if !self.options.key_by.is_empty() {
+			if let Some(field) = self.options.key_by.get(&join_path(&self.path)) {
+				return self.walk_keyed(array, field, depth);
+			}
+		}
+
+		if self.options.homogeneous_arrays {
+			self.check_homogeneous(array)?;
+		}
+
+		if self.options.pack_numbers.is_some() && self.push_packed(array, depth)? {
+			return Ok(());
+		}
+
+		self.walk_array(array, depth)
+	}
+
+	/// Write the array as packed bytes if it is packed, see
+	/// [`ParseOptions::pack_numbers`], returns whether it is
+	fn push_packed(&mut self, array: &[Value], depth: usize) -> Result<bool, Error> {
+		let Some(settings) = &self.options.pack_numbers else {
+			return Ok(false);
+		};
+
+		// Packed numbers are read back as they are, without being checked or converted
+		if self.options.value_transform.is_some()
+			|| self.options.number_mode != NumberMode::Preserve
+			|| self.options.integer_overflow == IntegerOverflow::Error
+		{
+			return Ok(false);
+		}
+
+		if !self.options.raw_expressions.is_empty() {
+			let path = join_path(&self.path);
+
+			if (1..=array.len()).any(|i| {
+				self.options
+					.raw_expressions
+					.contains_key(&format!("{}.{}", path, i))
+			}) {
+				return Ok(false);
+			}
+		}
+
+		if self.options.redact.is_some() {
+			for i in 1..=array.len() {
+				self.path.push(i.to_string());
+				let redacted = self.redacts();
+				self.path.pop();
+
+				if redacted {
+					return Ok(false);
+				}
+			}
+		}
+
+		let luau = self.options.target == Some(LuaTarget::Luau);
+
+		let Some(packed) = pack::packed_array(array, settings, luau) else {
+			return Ok(false);
+		};
+
+		let freezes = self.freezes(depth);
+
+		if freezes {
+			self.lua.push_str("table.freeze(");
+		}
+
+		self.lua.push_str(&packed);
+
+		if freezes {
+			self.lua.push(')');
+		}
+
+		self.stats.arrays += 1;
+		self.stats.scalars += array.len();
+		self.count_depth()?;
+
+		Ok(true)
+	}
+
+	fn walk_table_value(&mut self, table: &Table, depth: usize) -> Result<(), Error> {
+		match self.sequence(table)? {
+			Some(items) => self.walk_array(items, depth),
+			None if !self.inline_tables.is_empty() && self.walk_inline_table(table, depth)? => {
+				Ok(())
+			}
+			None => self.walk_table(table, depth),
+		}
+	}
+
+	/// Write the table on a single line if it is inline in the source and fits, returns whether it did
+	fn walk_inline_table(&mut self, table: &Table, depth: usize) -> Result<bool, Error> {
+		if !self.inline_tables.contains(&self.path) || !self.is_inline_table(table)? {
+			return Ok(false);
+		}
+
+		let max_width = self.options.max_width.unwrap_or(usize::MAX);
+
+		self.push_fitting(max_width, |walker| walker.push_inline_table(table, depth))
+	}
+
+	/// Write assignments for the value and everything nested in it, empty tables and arrays are assigned `{}`
+	fn walk_assignment(&mut self, target: &str, value: &Value) -> Result<(), Error> {
+		self.check_size()?;
+
+		match value {
+			Value::Table(table) if !table.is_empty() => {
+				for (key, value) in table {
+					let target = format!("{}{}", target, self.member(key));
+
+					self.path.push(key.clone());
+					self.walk_assignment(&target, value)?;
+					self.path.pop();
+				}
+			}
+			Value::Array(array) if !array.is_empty() => {
+				for (i, value) in array.iter().enumerate() {
+					let target = format!("{}[{}]", target, i + 1);
+
+					self.path.push((i + 1).to_string());
+					self.walk_assignment(&target, value)?;
+					self.path.pop();
+				}
+			}
+			Value::Table(_) | Value::Array(_) => {
+				self.lua.push_str(&format!("{} = {{}}\n", target));
+			}
+			_ => {
+				self.lua.push_str(&format!("{} = ", target));
+				self.stats.scalars += 1;
+				self.push_scalar(value)?;
+
+				if let Some(comment) = self.trailing_comment.take() {
+					self.lua.push_str(&format!(" -- {}", comment));
+				}
+
+				self.lua.push('\n');
+			}
+		}
+
+		Ok(())
+	}
+
+	fn push_scalar(&mut self, value: &Value) -> Result<(), Error> {
+		if !self.options.raw_expressions.is_empty() && self.push_raw_expression() {
+			return Ok(());
+		}
+
+		if is_nil(value, self.options) {
+			self.lua.push_str("nil");
+			return Ok(());
+		}
+
+		if !(self.options.redact.is_some() && self.push_redacted(value)?) {
+			let transformed = self.transform(value)?;
+
+			self.push_literal(transformed.as_ref().unwrap_or(value))?;
+		}
+
+		if self.options.type_comments {
+			// Ahead of the comments of the literal and of the source position
+			self.trailing_comment = Some(match self.trailing_comment.take() {
+				Some(comment) => format!("{}, {}", value.type_str(), comment),
+				None => value.type_str().to_owned(),
+			});
+		}
+
+		Ok(())
+	}
+
+	/// Write the expression given for the current path in place of the scalar, see
+	/// [`ParseOptions::raw_expressions`], returns whether there is one
+	fn push_raw_expression(&mut self) -> bool {
+		let path = join_path(&self.path);
+
+		let Some(expression) = self.options.raw_expressions.get(&path) else {
+			return false;
+		};
+
+		self.lua.push_str(expression);
+		self.expressed.insert(path);
+
+		true
+	}
+
+	/// Whether the value at the current path is redacted, see [`ParseOptions::redact`]
+	fn redacts(&self) -> bool {
+		self.options.redact.as_ref().is_some_and(|redact| {
+			redact
+				.paths
+				.iter()
+				.any(|pattern| self.matches_redact(pattern))
+		})
+	}
+
+	/// Whether the `redact` pattern matches the current path or a path it is nested in
+	fn matches_redact(&self, pattern: &str) -> bool {
+		let pattern = split_path(pattern);
+
+		(1..=self.path.len()).any(|len| matches_glob(&pattern, &self.path[..len]))
+	}
+
+	/// Write the placeholder or neutral value if the scalar is redacted, see
+	/// [`ParseOptions::redact`], returns whether it is
+	fn push_redacted(&mut self, value: &Value) -> Result<bool, Error> {
+		let Some(redact) = &self.options.redact else {
+			return Ok(false);
+		};
+
+		let matched: Vec<usize> = (0..redact.paths.len())
+			.filter(|i| self.matches_redact(&redact.paths[*i]))
+			.collect();
+
+		if matched.is_empty() {
+			return Ok(false);
+		}
+
+		self.redacted.extend(matched);
+
+		let neutral = match value {
+			Value::String(_) => {
+				self.lua
+					.push_str(&quote_string(&redact.placeholder, self.options));
+				None
+			}
+			_ if redact.non_strings == RedactNonStrings::Error => {
+				return Err(Error::NotRedactable {
+					path: join_path(&self.path),
+					found: value.type_str(),
+				});
+			}
+			Value::Integer(_) => Some(Value::Integer(0)),
+			Value::Float(_) => Some(Value::Float(0.0)),
+			Value::Datetime(datetime) => Some(Value::Datetime(datetime::epoch(datetime))),
+			_ => Some(Value::Boolean(false)),
+		};
+
+		if let Some(neutral) = neutral {
+			self.push_literal(&neutral)?;
+		}
+
+		self.warnings.push(Warning {
+			kind: WarningKind::Redacted,
+			path: join_path(&self.path),
+			message: String::from("redacted"),
+		});
+
+		Ok(true)
+	}
+
+	/// Write the scalar after the nil sentinel and the value transform were handled
+	fn push_literal(&mut self, value: &Value) -> Result<(), Error> {
+		match value {
+			Value::String(s) => {
+				#[cfg(feature = "std")]
+				let s = &*self.interpolate(s)?;
+
+				if let Some(coerced) = self.coerce(s)? {
+					return self.push_literal(&coerced);
+				}
+
+				let s = &*self.options.string_whitespace.apply(s);
+
+				match long_string(s, self.options) {
+					Some(long) => self.lua.push_str(&long),
+					None => self.lua.push_str(&quote_string(s, self.options)),
+				}
+			}
+			Value::Integer(i) => match self.options.number_mode {
+				NumberMode::AllFloat => {
+					self.check_overflow(*i)?;
+					self.lua.push_str(&format!("{}.0", i));
+				}
+				_ => self.push_integer(*i)?,
+			},
+			Value::Float(f) => match (self.options.number_mode, &self.options.float_format) {
+				// Saturates at the integer bounds, NaN becomes 0
+				(NumberMode::AllInteger, _) => {
+					self.check_finite(*f, |f| (f as i64).to_string());
+					self.push_integer(*f as i64)?
+				}
+				(_, Some(format)) => self.lua.push_str(&format.call(*f)),
+				(_, None) if self.options.hex_floats => {
+					self.check_finite(*f, float_literal);
+					self.lua.push_str(&hex_float_literal(*f))
+				}
+				(_, None) => {
+					self.check_finite(*f, float_literal);
+					self.lua.push_str(&float_literal(*f))
+				}
+			},
+			Value::Boolean(b) => match (&self.options.bool_style, b) {
+				(BoolStyle::Lua, _) => self.lua.push_str(&b.to_string()),
+				(BoolStyle::Numeric, true) => self.lua.push('1'),
+				(BoolStyle::Numeric, false) => self.lua.push('0'),
+				(BoolStyle::Custom(truthy, _), true) => self.lua.push_str(truthy),
+				(BoolStyle::Custom(_, falsy), false) => self.lua.push_str(falsy),
+			},
+			Value::Datetime(d) => self.push_datetime(d)?,
+			Value::Array(_) | Value::Table(_) => unreachable!(),
+		}
+
+		Ok(())
+	}
+
+	fn push_datetime(&mut self, datetime: &Datetime) -> Result<(), Error> {
+		let normalized;
+		let datetime = if self.options.normalize_datetimes_utc {
+			match DatetimeKind::of(datetime) {
+				DatetimeKind::OffsetDatetime => {
+					normalized = datetime::to_utc(datetime).unwrap_or(*datetime);
+					&normalized
+				}
+				kind @ (DatetimeKind::LocalDatetime | DatetimeKind::LocalTime)
+					if self.options.local_datetimes == LocalDatetimes::Error =>
+				{
+					return Err(Error::NotNormalizable {
+						path: join_path(&self.path),
+						kind,
+					});
+				}
+				_ => datetime,
+			}
+		} else {
+			datetime
+		};
+
+		match self.options.datetime_mode {
+			DatetimeMode::String => {}
+			DatetimeMode::Table => {
+				let fields: Vec<String> = datetime::fields(datetime)
+					.into_iter()
+					.map(|(name, value)| format!("{} = {}", name, value))
+					.collect();
+
+				let table = format!(
+					"{{ {} }}",
+					fields.join(match self.options.separator {
+						Separator::Comma => ", ",
+						Separator::Semicolon => "; ",
+					})
+				);
+
+				if self.options.freeze == Freeze::Deep {
+					self.lua.push_str(&format!("table.freeze({})", table));
+				} else {
+					self.lua.push_str(&table);
+				}
+
+				return Ok(());
+			}
+			DatetimeMode::Epoch => match epoch_seconds(datetime) {
+				Some((seconds, 0)) => {
+					self.lua.push_str(&seconds.to_string());
+					return Ok(());
+				}
+				Some((seconds, nanoseconds)) => {
+					let seconds = seconds as f64 + f64::from(nanoseconds) / 1e9;
+
+					self.lua.push_str(&float_literal(seconds));
+					return Ok(());
+				}
+				None if self.options.datetime_fallback == DatetimeFallback::String => {
+					self.warnings.push(Warning {
+						kind: WarningKind::DatetimeAsString,
+						path: join_path(&self.path),
+						message: format!("{} written as a string", DatetimeKind::of(datetime)),
+					});
+				}
+				None => {
+					return Err(Error::UnrepresentableDatetime {
+						path: join_path(&self.path),
+						kind: DatetimeKind::of(datetime),
+					})
+				}
+			},
+		}
+
+		match &self.options.datetime_wrapper {
+			Some(wrapper) => self.lua.push_str(&format!("{}(\"{}\")", wrapper, datetime)),
+			None => self.lua.push_str(&format!("\"{}\"", datetime)),
+		}
+
+		Ok(())
+	}
+
+	/// Replacement for the scalar from [`ParseOptions::value_transform`], if any
+	fn transform(&self, value: &Value) -> Result<Option<Value>, Error> {
+		let Some(transform) = &self.options.value_transform else {
+			return Ok(None);
+		};
+
+		let path: Vec<&str> = self.path.iter().map(String::as_str).collect();
+
+		match transform.call(&path, value) {
+			Some(Value::Array(_) | Value::Table(_)) => Err(Error::InvalidTransform {
+				path: join_path(&self.path),
+			}),
+			replacement => Ok(replacement),
+		}
+	}
+
+	/// Value of the string if it's coerced, see [`ParseOptions::coerce`]
+	fn coerce(&mut self, string: &str) -> Result<Option<Value>, Error> {
+		let Some(coerce) = &self.options.coerce else {
+			return Ok(None);
+		};
+
+		let listed = coerce
+			.paths
+			.iter()
+			.any(|pattern| matches_path(pattern, &self.path));
+
+		if !coerce.paths.is_empty() && !listed {
+			return Ok(None);
+		}
+
+		let Some(coerced) = coerce_literal(string) else {
+			if listed {
+				return Err(Error::NotCoercible {
+					path: join_path(&self.path),
+					value: string.to_owned(),
+				});
+			}
+
+			return Ok(None);
+		};
+
+		self.warnings.push(Warning {
+			kind: WarningKind::Coerced,
+			path: join_path(&self.path),
+			message: format!("coerced string {:?} to {}", string, coerced.type_str()),
+		});
+
+		Ok(Some(coerced))
+	}
+
+	/// Queue a comment with the source position of the current value and record its line
+	fn push_position(&mut self) {
+		if self.positions.is_empty() {
+			return;
+		}
+
+		let Some(&(line, column)) = self.positions.get(&self.path) else {
+			return;
+		};
+
+		if self.mappings.is_some() {
+			let lua_line = self.line();
+
+			if let Some(mappings) = &mut self.mappings {
+				mappings.push(Mapping {
+					lua_line,
+					toml_line: line,
+					key_path: join_path(&self.path),
+				});
+			}
+		}
+
+		#[cfg(feature = "comments")]
+		if !self.options.source_comments {
+			return;
+		}
+
+		let position = format!("toml:{}:{}", line, column);
+
+		self.trailing_comment = Some(match self.trailing_comment.take() {
+			Some(comment) => format!("{}, {}", comment, position),
+			None => position,
+		});
+	}
+
+	/// Line the cursor is on, starting at 1
+	fn line(&mut self) -> usize {
+		self.lines += self.lua[self.scanned..].matches('\n').count();
+		self.scanned = self.lua.len();
+
+		self.lines + 1
+	}
+
+	/// Name of the local a root entry was written as by [`walk_locals`](Self::walk_locals)
+	fn local<'k>(&self, key: Option<&'k str>, depth: usize) -> Option<(&'k str, String)> {
+		let key = key.filter(|_| depth == self.root_depth && !self.in_local)?;
+
+		self.locals.get(key).map(|name| (key, name.clone()))
+	}
+
+	/// Record the depth of a constructor opened for the current path, failing
+	/// past [`max_depth`](ParseOptions::max_depth)
+	fn count_depth(&mut self) -> Result<(), Error> {
+		let depth = self.path.len() + 1;
+
+		if let Some(limit) = self.options.max_depth.filter(|limit| depth > *limit) {
+			return Err(Error::TooDeep {
+				path: join_path(&self.path),
+				limit,
+			});
+		}
+
+		self.stats.max_depth = self.stats.max_depth.max(depth);
+
+		Ok(())
+	}
+
+	fn check_overflow(&mut self, integer: i64) -> Result<(), Error> {
+		const MAX_SAFE: u64 = 1 << 53;
+
+		if integer.unsigned_abs() <= MAX_SAFE {
+			return Ok(());
+		}
+
+		if self.options.integer_overflow == IntegerOverflow::Error {
+			return Err(Error::IntegerOverflow {
+				path: join_path(&self.path),
+				value: integer,
+			});
+		}
+
+		if self.options.target.is_some_and(LuaTarget::has_integers) {
+			return Ok(());
+		}
+
+		self.warnings.push(Warning {
+			kind: WarningKind::PrecisionLoss,
+			path: join_path(&self.path),
+			message: format!(
+				"integer {} loses precision in Lua versions without integers",
+				integer
+			),
+		});
+
+		Ok(())
+	}
+
+	/// Warn about NaN and infinities, which are written as divisions by zero
+	/// or, with [`NumberMode::AllInteger`], as the integer they saturate to
+	fn check_finite(&mut self, float: f64, written: fn(f64) -> String) {
+		if float.is_finite() {
+			return;
+		}
+
+		self.warnings.push(Warning {
+			kind: WarningKind::NonFinite,
+			path: join_path(&self.path),
+			message: format!("{} written as {}", float, written(float)),
+		});
+	}
+
+	/// Walk array whose braces are at `depth`, the cursor is already placed after its key
+	fn walk_array<'v, I>(&mut self, items: I, depth: usize) -> Result<(), Error>
+	where
+		I: IntoIterator<Item = &'v Value>,
+		I::IntoIter: ExactSizeIterator,
+	{
+		let items = items.into_iter();
+		let len = items.len();
+
+		self.stats.arrays += 1;
+		self.count_depth()?;
+
+		if len == 0 && (self.options.compact_empty || self.options.json_bridge) {
+			return self.push_inline(&[], depth);
+		}
+
+		let items: Vec<&Value> = items.collect();
+
+		if !self.options.sparse_arrays && items.iter().any(|item| is_nil(item, self.options)) {
+			return Err(Error::NilInArray {
+				path: join_path(&self.path),
+			});
+		}
+
+		if let Some(size) = self.chunk_size(len) {
+			let items = items.into_iter().map(|item| (None, item));
+			return self.walk_chunked(items.collect(), depth, size);
+		}
+
+		// Holes, explicit indices and the `n` field need entries of the array itself
+		if self.options.flatten_array_of_tables_indent
+			&& !self.options.sparse_arrays
+			&& !self.options.packed_arrays
+			&& !self.options.explicit_array_indices
+			&& len > 0
+			&& items.iter().all(|item| item.is_table())
+		{
+			return self.walk_joined_tables(&items, depth);
+		}
+
+		let options = self.options;
+		let is_hole = |item: &Value| {
+			options.sparse_arrays
+				&& (item.as_table().is_some_and(Table::is_empty) || is_nil(item, options))
+		};
+
+		// Holes need explicit indices, which a single line doesn't have
+		let inline = self.options.max_width.filter(|_| {
+			!self.options.explicit_array_indices && !items.iter().any(|item| is_hole(item))
+		});
+
+		if let Some(max_width) = inline {
+			if self.push_inline_array(&items, depth, max_width)? {
+				return Ok(());
+			}
+		}
+
+		if let Some(per_line) = self.wrap_at(&items) {
+			return self.walk_wrapped_array(&items, depth, per_line);
+		}
+
+		self.open_constructor(depth);
+
+		let last = items.iter().rposition(|item| !is_hole(item));
+		let mut keyed = self.options.explicit_array_indices;
+
+		for (i, v) in items.into_iter().enumerate() {
+			// Positions after a hole are only kept by explicit indices
+			if is_hole(v) {
+				keyed = true;
+				continue;
+			}
+
+			self.path.push((i + 1).to_string());
+			self.push_comments(depth + 1);
+			self.separator_width =
+				self.separator_width(Some(i) == last && !self.options.packed_arrays);
+
+			if keyed {
+				self.push_indent(depth + 1);
+				self.lua.push_str(&format!("[{}] = ", i + 1));
+				self.walk_value(v, depth + 1)?;
+			} else {
+				self.walk(None, v, depth + 1)?;
+			}
+
+			self.path.pop();
+
+			self.push_separator(Some(i) == last && !self.options.packed_arrays);
+		}
+
+		if self.options.packed_arrays {
+			self.push_indent(depth + 1);
+			self.push_key("n");
+			self.lua.push_str(&len.to_string());
+			self.push_separator(true);
+		}
+
+		self.close_constructor(depth);
+
+		Ok(())
+	}
+
+	/// Items per line of the array, if it's wrapped, see [`ParseOptions::array_wrap_at`]
+	fn wrap_at(&self, items: &[&Value]) -> Option<usize> {
+		let per_line = self.options.array_wrap_at?.get();
+
+		if self.options.explicit_array_indices
+			|| items
+				.iter()
+				.any(|item| item.is_table() || item.is_array() || is_nil(item, self.options))
+		{
+			return None;
+		}
+
+		// Comments above items need lines of their own
+		if !self.comments.is_empty() {
+			let prefix = format!("{}.", join_path(&self.path));
+
+			if self.comments.keys().any(|path| path.starts_with(&prefix)) {
+				return None;
+			}
+		}
+
+		Some(per_line)
+	}
+
+	/// Write the scalars of the array `per_line` at a time, a line also ends
+	/// after an item followed by a comment
+	fn walk_wrapped_array(
+		&mut self,
+		items: &[&Value],
+		depth: usize,
+		per_line: usize,
+	) -> Result<(), Error> {
+		self.open_constructor(depth);
+
+		let mut column = 0;
+
+		for (i, item) in items.iter().enumerate() {
+			let last = i + 1 == items.len();
+
+			if column == 0 {
+				self.push_indent(depth + 1);
+			} else {
+				self.lua.push(' ');
+			}
+
+			// Items sharing a line are followed by a separator too
+			self.separator_width = self.separator_width(last && !self.options.packed_arrays);
+			self.path.push((i + 1).to_string());
+			self.walk_value(item, depth + 1)?;
+			self.path.pop();
+
+			column += 1;
+
+			if column == per_line || last || self.trailing_comment.is_some() {
+				self.push_separator(last && !self.options.packed_arrays);
+				column = 0;
+			} else {
+				self.lua.push(match self.options.separator {
+					Separator::Comma => ',',
+					Separator::Semicolon => ';',
+				});
+				self.stats.fields += 1;
+			}
+		}
+
+		if self.options.packed_arrays {
+			self.push_indent(depth + 1);
+			self.push_key("n");
+			self.lua.push_str(&items.len().to_string());
+			self.push_separator(true);
+		}
+
+		self.close_constructor(depth);
+
+		Ok(())
+	}
+
+	/// Write the array of tables as a table keyed by the `field` of every entry,
+	/// see [`ParseOptions::key_by`]
+	fn walk_keyed(&mut self, items: &[Value], field: &str, depth: usize) -> Result<(), Error> {
+		if items.is_empty() {
+			return self.walk_table(core::iter::empty(), depth);
+		}
+
+		let path = join_path(&self.path);
+		let mut written = BTreeSet::new();
+		let mut entries = Vec::new();
+
+		for (i, item) in items.iter().enumerate() {
+			let index = i + 1;
+			let missing = || Error::MissingKeyField {
+				path: path.clone(),
+				index,
+				field: field.to_owned(),
+			};
+
+			let table = item.as_table().ok_or_else(missing)?;
+
+			let (key, segment) = match table.get(field).ok_or_else(missing)? {
+				Value::String(string) => (self.written_key(string), string.clone()),
+				Value::Integer(integer) => (
+					format!("[{}]", integer_literal(*integer)),
+					integer.to_string(),
+				),
+				other => {
+					return Err(Error::InvalidKeyField {
+						path,
+						index,
+						found: other.type_str(),
+					})
+				}
+			};
+
+			if !written.insert(key.clone()) {
+				return Err(Error::DuplicateKeyField {
+					path,
+					index,
+					key: segment,
+				});
+			}
+
+			let entry = if self.options.key_by_remove_field {
+				let mut table = table.clone();
+				table.remove(field);
+				Cow::Owned(Value::Table(table))
+			} else {
+				Cow::Borrowed(item)
+			};
+
+			entries.push((key, segment, entry));
+		}
+
+		self.stats.tables += 1;
+		self.count_depth()?;
+		self.open_constructor(depth);
+
+		let width = if self.options.align_assignments {
+			entries
+				.iter()
+				.map(|(key, _, _)| key.chars().count())
+				.max()
+				.unwrap_or(0)
+		} else {
+			0
+		};
+
+		let last = entries.len() - 1;
+
+		for (i, (key, segment, entry)) in entries.into_iter().enumerate() {
+			self.path.push(segment);
+			self.push_indent(depth + 1);
+			self.key_width = width;
+			self.push_written_key(&key);
+
+			if self.shared_reference(&entry, depth + 1).is_none() {
+				self.break_brace(depth + 1);
+			}
+
+			self.separator_width = self.separator_width(i == last);
+			self.walk_value(&entry, depth + 1)?;
+			self.path.pop();
+
+			self.push_separator(i == last);
+		}
+
+		self.close_constructor(depth);
+
+		Ok(())
+	}
+
+	/// Size of the batches a table or array with `len` entries is built in, if it's chunked
+	fn chunk_size(&self, len: usize) -> Option<usize> {
+		self.options
+			.chunking
+			.map(NonZeroUsize::get)
+			.filter(|size| len > *size)
+	}
+
+	/// Write a table, or an array when entries have no key, as a function building
+	/// it `size` entries at a time so no function exceeds the constant limits of Lua
+	///
+	/// The first entries are written in a constructor and every further batch
+	/// of entries is assigned in a function of its own
+	fn walk_chunked(
+		&mut self,
+		entries: Vec<(Option<&String>, &Value)>,
+		depth: usize,
+		size: usize,
+	) -> Result<(), Error> {
+		if self.options.check_duplicate_keys {
+			let mut scope = KeyScope::default();
+
+			for key in entries.iter().filter_map(|(key, _)| *key) {
+				scope.insert(&lua_key(key, self.options), &self.path)?;
+			}
+		}
+
+		let freezes = self.freezes(depth);
+
+		if freezes {
+			self.lua.push_str("table.freeze(");
+		}
+
+		self.lua.push_str("(function()\n");
+		self.push_indent(depth + 1);
+		self.lua.push_str("local t = {\n");
+
+		let (first, rest) = entries.split_at(size);
+
+		if first.iter().all(|(key, _)| key.is_some()) {
+			let first: Vec<(&String, &Value)> = first
+				.iter()
+				.filter_map(|(key, value)| Some(((*key)?, *value)))
+				.collect();
+
+			self.walk_entries(&first, depth + 2, false)?;
+		} else {
+			for (i, (_, value)) in first.iter().enumerate() {
+				self.path.push((i + 1).to_string());
+				self.separator_width = self.separator_width(i + 1 == size);
+				self.walk(None, value, depth + 2)?;
+				self.path.pop();
+
+				self.push_separator(i + 1 == size);
+			}
+		}
+
+		self.push_indent(depth + 1);
+		self.lua.push_str("}\n");
+
+		for (chunk, entries) in rest.chunks(size).enumerate() {
+			self.push_indent(depth + 1);
+			self.lua.push_str(";(function()\n");
+
+			for (i, (key, value)) in entries.iter().enumerate() {
+				let target = match key {
+					Some(key) if self.bare_keys() => self.member(key),
+					Some(key) => format!(
+						"[{}]",
+						quote_string(&lua_key(key, self.options), self.options)
+					),
+					None => format!("[{}]", size * (chunk + 1) + i + 1),
+				};
+
+				self.path.push(match key {
+					Some(key) => (*key).clone(),
+					None => (size * (chunk + 1) + i + 1).to_string(),
+				});
+				self.push_comments(depth + 2);
+				self.push_indent(depth + 2);
+				self.lua.push_str(&format!("t{} = ", target));
+				self.walk_value(value, depth + 2)?;
+				self.path.pop();
+
+				if let Some(comment) = self.trailing_comment.take() {
+					self.lua.push_str(&format!(" -- {}", comment));
+				}
+
+				self.lua.push('\n');
+			}
+
+			self.push_indent(depth + 1);
+			self.lua.push_str("end)()\n");
+		}
+
+		if self.options.packed_arrays && entries.iter().all(|(key, _)| key.is_none()) {
+			self.push_indent(depth + 1);
+			self.lua.push_str(&format!("t.n = {}\n", entries.len()));
+		}
+
+		self.push_indent(depth + 1);
+		self.lua.push_str("return t\n");
+		self.push_indent(depth);
+		self.lua.push_str("end)()");
+
+		if freezes {
+			self.lua.push(')');
+		}
+
+		Ok(())
+	}
+
+	/// Write the array on the current line if the line fits in `max_width`, returns whether it did
+	fn push_inline_array(
+		&mut self,
+		items: &[&Value],
+		depth: usize,
+		max_width: usize,
+	) -> Result<bool, Error> {
+		if !items.iter().all(|item| is_inline(item)) {
+			return Ok(false);
+		}
+
+		self.push_fitting(max_width, |walker| walker.push_inline(items, depth))
+	}
+
+	/// Write with `push` and undo it unless the line fits in `max_width`, returns whether it did
+	fn push_fitting<F>(&mut self, max_width: usize, push: F) -> Result<bool, Error>
+	where
+		F: FnOnce(&mut Self) -> Result<(), Error>,
+	{
+		let start = self.lua.len();
+		let stats = self.stats;
+		let line = self.line();
+		let measured = (self.measured, self.crlf_bytes);
+		let mappings = self.mappings.as_ref().map(Vec::len);
+		let warnings = self.warnings.len();
+
+		push(self)?;
+
+		let line_start = self.lua[..start].rfind('\n').map_or(0, |i| i + 1);
+		let width: usize = self.lua[line_start..]
+			.chars()
+			.map(|char| if char == '\t' { 4 } else { 1 })
+			.sum::<usize>()
+			+ self.separator_width;
+
+		// Comments need the end of the line, so commented items can't share one
+		let fits = width <= max_width && self.trailing_comment.is_none();
+
+		if !fits {
+			self.lua.truncate(start);
+			self.stats = stats;
+			self.trailing_comment = None;
+			self.lines = line - 1;
+			self.scanned = start;
+			(self.measured, self.crlf_bytes) = measured;
+			self.warnings.truncate(warnings);
+
+			if let (Some(mappings), Some(len)) = (&mut self.mappings, mappings) {
+				mappings.truncate(len);
+			}
+		}
+
+		Ok(fits)
+	}
+
+	fn push_inline(&mut self, items: &[&Value], depth: usize) -> Result<(), Error> {
+		let freezes = self.freezes(depth);
+
+		if freezes {
+			self.lua.push_str("table.freeze(");
+		}
+
+		let separator = match self.options.separator {
+			Separator::Comma => ", ",
+			Separator::Semicolon => "; ",
+		};
+
+		if items.is_empty() && !self.options.packed_arrays {
+			if self.options.json_bridge {
+				self.lua.push_str(&json_empty("array"));
+			} else {
+				self.lua.push_str("{}");
+			}
+		} else {
+			self.lua.push_str("{ ");
+
+			for (i, item) in items.iter().enumerate() {
+				if i > 0 {
+					self.lua.push_str(separator);
+				}
+
+				self.path.push((i + 1).to_string());
+
+				if let Some(reference) = self.shared_reference(item, depth + 1) {
+					self.lua.push_str(&reference);
+				} else if let Value::Array(array) = item {
+					if self.options.homogeneous_arrays {
+						self.check_homogeneous(array)?;
+					}
+
+					self.stats.arrays += 1;
+					self.count_depth()?;
+					self.push_inline(&array.iter().collect::<Vec<_>>(), depth + 1)?;
+				} else {
+					self.stats.scalars += 1;
+					self.push_scalar(item)?;
+					self.push_position();
+				}
+
+				self.path.pop();
+			}
+
+			self.stats.fields += items.len();
+
+			if self.options.packed_arrays {
+				if !items.is_empty() {
+					self.lua.push_str(separator);
+				}
+
+				self.push_key("n");
+				self.lua.push_str(&items.len().to_string());
+				self.stats.fields += 1;
+			}
+
+			self.lua.push_str(" }");
+		}
+
+		if freezes {
+			self.lua.push(')');
+		}
+
+		Ok(())
+	}
+
+	/// Whether the table can be written on a single line by [`push_inline_table`](Self::push_inline_table)
+	fn is_inline_table(&self, table: &Table) -> Result<bool, Error> {
+		// Metadata, tags, chunks and comments need entries of their own
+		if self.options.order_metadata != OrderMetadata::None
+			|| self.options.type_tags.is_some()
+			|| !self.options.key_by.is_empty()
+			|| self.chunk_size(table.len()).is_some()
+		{
+			return Ok(false);
+		}
+
+		let path = join_path(&self.path);
+		let nested = format!("{}.", path);
+
+		if self
+			.comments
+			.keys()
+			.any(|key| *key == path || key.starts_with(&nested))
+		{
+			return Ok(false);
+		}
+
+		for value in table.values() {
+			let inline = match value {
+				Value::Table(table) => match self.sequence(table)? {
+					Some(items) => items.iter().all(|item| self.is_inline_item(item)),
+					None => self.is_inline_table(table)?,
+				},
+				_ => self.is_inline_item(value),
+			};
+
+			if !inline {
+				return Ok(false);
+			}
+		}
+
+		Ok(true)
+	}
+
+	/// Whether the value can be written by [`push_inline`](Self::push_inline) without leaving holes
+	fn is_inline_item(&self, value: &Value) -> bool {
+		match value {
+			Value::Array(array) => array
+				.iter()
+				.all(|item| is_inline(item) && !is_nil(item, self.options)),
+			_ => is_inline(value),
+		}
+	}
+
+	/// Write the table on the current line like `{ x = 1, y = 2 }`
+	fn push_inline_table(&mut self, table: &Table, depth: usize) -> Result<(), Error> {
+		let entries = self.table_entries(table)?;
+		let freezes = self.freezes(depth);
+
+		self.stats.tables += 1;
+		self.count_depth()?;
+
+		if freezes {
+			self.lua.push_str("table.freeze(");
+		}
+
+		if entries.is_empty() {
+			if self.options.json_bridge {
+				self.lua.push_str(&json_empty("object"));
+			} else {
+				self.lua.push_str("{}");
+			}
+		} else {
+			let mut scope = KeyScope::default();
+
+			self.stats.fields += entries.len();
+			self.lua.push_str("{ ");
+
+			for (i, (key, value)) in entries.iter().enumerate() {
+				if i > 0 {
+					self.lua.push_str(match self.options.separator {
+						Separator::Comma => ", ",
+						Separator::Semicolon => "; ",
+					});
+				}
+
+				if self.options.check_duplicate_keys {
+					scope.insert(&lua_key(key, self.options), &self.path)?;
+				}
+
+				self.path.push((*key).clone());
+				self.push_key(key);
+
+				if let Some(reference) = self.shared_reference(value, depth + 1) {
+					self.lua.push_str(&reference);
+				} else {
+					match value {
+						Value::Array(array) => {
+							if self.options.homogeneous_arrays {
+								self.check_homogeneous(array)?;
+							}
+
+							self.stats.arrays += 1;
+							self.count_depth()?;
+							self.push_inline(&array.iter().collect::<Vec<_>>(), depth + 1)?;
+						}
+						Value::Table(table) => match self.sequence(table)? {
+							Some(items) => {
+								self.stats.arrays += 1;
+								self.count_depth()?;
+								self.push_inline(&items, depth + 1)?;
+							}
+							None => self.push_inline_table(table, depth + 1)?,
+						},
+						_ => {
+							self.stats.scalars += 1;
+							self.push_scalar(value)?;
+							self.push_position();
+						}
+					}
+				}
+
+				self.path.pop();
+			}
+
+			self.lua.push_str(" }");
+		}
+
+		if freezes {
+			self.lua.push(')');
+		}
+
+		Ok(())
+	}
+
+	/// Values of the table in index order if it should be written as an array
+	fn sequence<'v>(&self, table: &'v Table) -> Result<Option<Vec<&'v Value>>, Error> {
+		let path = join_path(&self.path);
+
+		if self.options.json_compat || self.options.force_map_paths.contains(&path) {
+			return Ok(None);
+		}
+
+		let forced = self.options.force_array_paths.contains(&path);
+
+		if !forced && !self.options.numeric_keys_as_array {
+			return Ok(None);
+		}
+
+		match sequence_items(table) {
+			None if forced => Err(Error::NotSequence { path }),
+			items => Ok(items),
+		}
+	}
+
+	#[cfg(feature = "std")]
+	fn interpolate<'s>(&self, s: &'s str) -> Result<std::borrow::Cow<'s, str>, Error> {
+		match &self.options.interpolation {
+			Some(interpolation) => {
+				crate::interpolate::interpolate(s, interpolation).map_err(|variable| {
+					Error::UnresolvedVariable {
+						path: join_path(&self.path),
+						variable,
+					}
+				})
+			}
+			None => Ok(std::borrow::Cow::Borrowed(s)),
+		}
+	}
+
+	fn check_homogeneous(&self, array: &[Value]) -> Result<(), Error> {
+		let kind = |value: &Value| match value {
+			Value::Integer(_) | Value::Float(_) if self.options.mixed_numbers => "number",
+			_ => value.type_str(),
+		};
+
+		let Some(first) = array.first().map(kind) else {
+			return Ok(());
+		};
+
+		match array.iter().map(kind).find(|other| *other != first) {
+			Some(second) => Err(Error::MixedArray {
+				path: join_path(&self.path),
+				first,
+				second,
+			}),
+			None => Ok(()),
+		}
+	}
+
+	/// Whether the constructor with braces at `depth` is wrapped in `table.freeze`
+	fn freezes(&self, depth: usize) -> bool {
+		match self.options.freeze {
+			Freeze::Off => false,
+			Freeze::Shallow => depth == 0 && !self.in_local,
+			Freeze::Deep => true,
+		}
+	}
+
+	fn open_constructor(&mut self, depth: usize) {
+		if self.freezes(depth) {
+			self.lua.push_str("table.freeze(");
+		}
+
+		self.lua.push_str("{\n");
+	}
+
+	fn close_constructor(&mut self, depth: usize) {
+		self.push_indent(depth);
+		self.lua.push('}');
+
+		if self.freezes(depth) {
+			self.lua.push(')');
+		}
+	}
+
+	fn push_comments(&mut self, depth: usize) {
+		if self.comments.is_empty() {
+			return;
+		}
+
+		let path = join_path(&self.path);
+
+		let Some(lines) = self.comments.get(&path) else {
+			return;
+		};
+
+		let indent = self.indent(depth);
+
+		if self.options.comment_style == CommentStyle::LdocField {
+			let key = self.field_name();
+			let mut lines = lines.iter();
+
+			let first = lines.next().map_or("", String::as_str);
+			self.lua.push_str(&indent);
+			self.lua
+				.push_str(format!("--- @field {} {}", key, first).trim_end());
+			self.lua.push('\n');
+
+			for line in lines {
+				self.lua.push_str(&indent);
+				self.lua.push_str(format!("--- {}", line).trim_end());
+				self.lua.push('\n');
+			}
+		} else {
+			for line in lines {
+				self.lua.push_str(&indent);
+
+				if line.is_empty() {
+					self.lua.push_str("--\n");
+				} else {
+					self.lua.push_str(&format!("-- {}\n", line));
+				}
+			}
+		}
+
+		self.commented.insert(path);
+	}
+
+	/// Key of the current entry as a `@field` name, written like `["key"]` if it isn't an identifier
+	fn field_name(&self) -> String {
+		let key = lua_key(self.path.last().map_or("", String::as_str), self.options);
+
+		if is_valid_lua_identifier(&key, true) {
+			key.into_owned()
+		} else {
+			format!("[\"{}\"]", validate_string(&key, self.options))
+		}
+	}
+
+	/// Write the fields of the tables of the array as a comment if they all
+	/// have the same ones, see [`ParseOptions::columnar_comments`]
+	fn push_columns(&mut self, value: &Value, depth: usize) {
+		let Some([first, rest @ ..]) = value.as_array().map(Vec::as_slice) else {
+			return;
+		};
+
+		let Some(columns) = first.as_table().filter(|table| !table.is_empty()) else {
+			return;
+		};
+
+		let uniform = rest.iter().all(|item| {
+			item.as_table().is_some_and(|table| {
+				table.len() == columns.len() && columns.keys().all(|key| table.contains_key(key))
+			})
+		});
+
+		if !uniform {
+			return;
+		}
+
+		// Fields that aren't identifiers are quoted so that a line break in
+		// one can't end the comment
+		let columns: Vec<String> = columns
+			.keys()
+			.map(|key| {
+				let key = lua_key(key, self.options);
+
+				if is_valid_lua_identifier(&key, true) {
+					key.into_owned()
+				} else {
+					quote_string(&key, self.options)
+				}
+			})
+			.collect();
+
+		self.push_indent(depth);
+		self.lua.push_str(&format!("-- {}\n", columns.join(", ")));
+	}
+
+	fn push_key(&mut self, key: &str) {
+		let key = self.key(key);
+		self.push_written_key(&key);
+	}
+
+	/// Write the key as given, padded to the aligned width
+	fn push_written_key(&mut self, key: &str) {
+		let padding = core::mem::take(&mut self.key_width).saturating_sub(key.chars().count());
+
+		self.lua.push_str(key);
+		self.lua.push_str(&" ".repeat(padding));
+		self.lua.push_str(" = ");
+	}
+
+	/// Key as written in a constructor, bare for identifiers if enabled and `["key"]` otherwise
+	fn key(&self, key: &str) -> String {
+		self.written_key(&lua_key(key, self.options))
+	}
+
+	/// Same as [`key`](Self::key) without [`ParseOptions::key_case`]
+	fn written_key(&self, key: &str) -> String {
+		if self.bare_keys()
+			&& is_valid_lua_identifier(key, self.options.ascii_identifiers_only)
+			&& (self.options.bare_key_predicate)
+				.as_ref()
+				.is_none_or(|predicate| predicate.call(key))
+		{
+			key.to_owned()
+		} else {
+			format!("[{}]", quote_string(key, self.options))
+		}
+	}
+
+	fn bare_keys(&self) -> bool {
+		self.options.bare_keys && !self.options.json_compat
+	}
+
+	/// Width keys of a table are padded to so their `=` line up, 0 unless aligning
+	fn key_column<'k>(&self, keys: impl IntoIterator<Item = &'k str>) -> usize {
+		if !self.options.align_assignments {
+			return 0;
+		}
+
+		keys.into_iter()
+			.map(|key| self.key(key).chars().count())
+			.max()
+			.unwrap_or(0)
+	}
+
+	/// Member access of the key, `.key` for identifiers and `["key"]` otherwise
+	fn member(&self, key: &str) -> String {
+		let key = lua_key(key, self.options);
+
+		if is_valid_lua_identifier(&key, self.options.ascii_identifiers_only) {
+			format!(".{}", key)
+		} else {
+			format!("[{}]", quote_string(&key, self.options))
+		}
+	}
+
+	/// Write key of a table or array placed at `depth`, moving the brace to a new line if enabled
+	fn push_table_key(&mut self, key: &str, depth: usize) {
+		self.push_key(key);
+		self.break_brace(depth);
+	}
+
+	/// Move the brace of a table or array placed at `depth` to a new line if enabled
+	fn break_brace(&mut self, depth: usize) {
+		if self.options.brace_on_new_line {
+			self.lua.pop();
+			self.lua.push('\n');
+			self.push_indent(depth);
+		}
+	}
+
+	fn push_indent(&mut self, depth: usize) {
+		let indent = self.indent(depth);
+		self.lua.push_str(&indent);
+	}
+
+	fn indent(&self, depth: usize) -> String {
+		match self.options.indent {
+			Indent::Tabs => "\t".repeat(depth),
+			Indent::Spaces(width) => " ".repeat(width * depth),
+		}
+	}
+
+	fn push_integer(&mut self, integer: i64) -> Result<(), Error> {
+		self.check_overflow(integer)?;
+
+		if integer == i64::MIN {
+			self.lua.push_str(&integer_literal(integer));
+			return Ok(());
+		}
+
+		let literal = if self.integer_literals.is_empty() {
+			None
+		} else {
+			// Transformed values no longer match the source
+			self.integer_literals
+				.get(&self.path)
+				.filter(|(value, ..)| *value == integer)
+		};
+
+		match literal {
+			Some((_, literal, comment)) => {
+				self.lua.push_str(literal);
+				self.trailing_comment = comment.clone();
+			}
+			None => match (&self.options.integer_format, self.options.integer_radix) {
+				(Some(format), _) => self.lua.push_str(&format.call(integer)),
+				(None, Radix::Decimal) => self.lua.push_str(&integer.to_string()),
+				(None, radix) => {
+					let luau = self.options.target == Some(LuaTarget::Luau);
+					let (literal, comment) = radix_literal(integer, radix, luau);
+
+					self.lua.push_str(&literal);
+					self.trailing_comment = comment;
+				}
+			},
+		}
+
+		Ok(())
+	}
+
+	fn push_separator(&mut self, last: bool) {
+		// Every entry and item written on its own line ends with one
+		self.stats.fields += 1;
+		self.separator_width = 0;
+
+		if self.separator_width(last) > 0 {
+			self.lua.push(match self.options.separator {
+				Separator::Comma => ',',
+				Separator::Semicolon => ';',
+			});
+		}
+
+		if let Some(comment) = self.trailing_comment.take() {
+			self.lua.push_str(&format!(" -- {}", comment));
+		}
+
+		self.lua.push('\n');
+	}
+
+	/// Columns of the separator [`push_separator`](Self::push_separator) writes
+	fn separator_width(&self, last: bool) -> usize {
+		usize::from(!last || self.options.trailing_commas)
+	}
+}
+
+/// Whether the value is the [`nil_sentinel`](ParseOptions::nil_sentinel) or
+/// an empty string written as `nil`
+pub(crate) fn is_nil(value: &Value, options: &ParseOptions) -> bool {
+	match (value, &options.nil_sentinel) {
+		(Value::String(string), _)
+			if string.is_empty() && options.empty_string != EmptyStringMode::Keep =>
+		{
+			true
+		}
+		(Value::String(string), Some(sentinel)) => string == sentinel,
+		_ => false,
+	}
+}
+
+/// Empty table marked as a JSON `array` or `object`, see [`ParseOptions::json_bridge`]
+fn json_empty(kind: &str) -> String {
+	format!("setmetatable({{}}, {{ __jsontype = \"{}\" }})", kind)
+}
+
+/// Whether the value can be written on a single line, tables never are
+fn is_inline(value: &Value) -> bool {
+	match value {
+		Value::Table(_) => false,
+		Value::Array(array) => array.iter().all(is_inline),
+		_ => true,
+	}
+}
+
+/// Lua keys already emitted in a single table
+#[derive(Default)]
+struct KeyScope(BTreeSet<String>);
+
+impl KeyScope {
+	fn insert(&mut self, key: &str, path: &[String]) -> Result<(), Error> {
+		if self.0.insert(key.to_owned()) {
+			Ok(())
+		} else {
+			Err(Error::DuplicateKey {
+				path: join_path(path),
+				key: key.to_owned(),
+			})
+		}
+	}
+}
+
+/// Dotted path of the key in the table at `path`, see [`join_path`]
+fn key_path(path: &[String], key: &str) -> String {
+	if path.is_empty() {
+		path_segment(key)
+	} else {
+		format!("{}.{}", join_path(path), path_segment(key))
+	}
+}
+
+/// Whether the dotted pattern matches the key path, `*` matches any single key
+fn matches_path(pattern: &str, path: &[String]) -> bool {
+	let mut segments = split_path(pattern).into_iter();
+
+	path.iter().all(|key| {
+		segments
+			.next()
+			.is_some_and(|segment| segment == "*" || segment == *key)
+	}) && segments.next().is_none()
+}
+
+/// Whether the dotted pattern split into its segments matches the key path,
+/// `*` matches any single key and `**` any number of keys
+fn matches_glob(pattern: &[String], path: &[String]) -> bool {
+	match pattern.split_first() {
+		None => path.is_empty(),
+		Some((segment, rest)) if segment == "**" => {
+			(0..=path.len()).any(|skip| matches_glob(rest, &path[skip..]))
+		}
+		Some((segment, rest)) => path.split_first().is_some_and(|(key, path)| {
+			(segment == "*" || segment == key) && matches_glob(rest, path)
+		}),
+	}
+}
+
+/// Integer, float or boolean the string is a literal of, in the TOML syntax
+/// without underscores, so strings like `"007"` or `"1."` are left alone
+fn coerce_literal(string: &str) -> Option<Value> {
+	match string {
+		"true" => return Some(Value::Boolean(true)),
+		"false" => return Some(Value::Boolean(false)),
+		_ => {}
+	}
+
+	let unsigned = string.strip_prefix(['+', '-']).unwrap_or(string);
+	let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+		Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+		None => (unsigned, None),
+	};
+	let (whole, fraction) = match mantissa.split_once('.') {
+		Some((whole, fraction)) => (whole, Some(fraction)),
+		None => (mantissa, None),
+	};
+
+	let digits = |part: &str| !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit());
+
+	if !digits(whole) || (whole.len() > 1 && whole.starts_with('0')) {
+		return None;
+	}
+
+	if fraction.is_some_and(|fraction| !digits(fraction)) {
+		return None;
+	}
+
+	if let Some(exponent) = exponent {
+		if !digits(exponent.strip_prefix(['+', '-']).unwrap_or(exponent)) {
+			return None;
+		}
+	}
+
+	if fraction.is_none() && exponent.is_none() {
+		string.parse().ok().map(Value::Integer)
+	} else {
+		string.parse().ok().map(Value::Float)
+	}
+}
+
+/// Array of keys in the order they were written, e.g. `{ "a", "b" }`
+fn order_list(entries: &[(&String, &Value)], options: &ParseOptions) -> String {
+	if entries.is_empty() {
+		return String::from("{}");
+	}
+
+	let keys: Vec<String> = entries
+		.iter()
+		.map(|(key, _)| quote_string(&lua_key(key, options), options))
+		.collect();
+
+	format!("{{ {} }}", keys.join(", "))
+}
+
+/// Format float so Lua always reads it back as a float
+pub(crate) fn float_literal(float: f64) -> String {
+	// Lua has no literals for these, divisions by zero produce them in every version
+	if float.is_nan() {
+		return String::from("0/0");
+	} else if float.is_infinite() {
+		return String::from(if float > 0.0 { "1/0" } else { "-1/0" });
+	}
+
+	// Shortest digits reading back as the same float like `{}`, but in exponent form for very large
+	// and small magnitudes, `{}` writes `1e308` with all of its 309 digits and subnormals with over 300
+	let literal = format!("{:?}", float);
+
+	if literal.contains(['.', 'e']) {
+		literal
+	} else {
+		literal + ".0"
+	}
+}
+
+/// Closes `setmetatable(` around the root table of [`ReadonlyMode::Metatable`]
+const READONLY_METATABLE: &str =
+	", { __newindex = function() error(\"attempt to add a field to a readonly table\", 2) end })";
+
+/// Decimal literal of the integer
+pub(crate) fn integer_literal(integer: i64) -> String {
+	// `-9223372036854775808` is negation of a literal that overflows into a float
+	if integer == i64::MIN {
+		String::from("(-9223372036854775807 - 1)")
+	} else {
+		integer.to_string()
+	}
+}
+
+/// Integer in the given radix and the comment written after it, binary is
+/// only valid in Luau and written in hexadecimal with the binary as the comment
+fn radix_literal(integer: i64, radix: Radix, luau: bool) -> (String, Option<String>) {
+	let sign = if integer < 0 { "-" } else { "" };
+	let magnitude = integer.unsigned_abs();
+	let hex = format!("{}0x{:X}", sign, magnitude);
+
+	match radix {
+		Radix::Decimal => (integer.to_string(), None),
+		Radix::Hex => (hex, None),
+		Radix::Binary if luau => (format!("{}0b{:b}", sign, magnitude), None),
+		Radix::Binary => (hex, Some(format!("{}0b{:b}", sign, magnitude))),
+	}
+}
+
+/// Format float in hexadecimal with its exact mantissa bits, e.g. `0x1.8p+1` for `3.0`
+fn hex_float_literal(float: f64) -> String {
+	if !float.is_finite() {
+		return float_literal(float);
+	}
+
+	let bits = float.to_bits();
+	let sign = if float.is_sign_negative() { "-" } else { "" };
+	let exponent = ((bits >> 52) & 0x7FF) as i64;
+	let mantissa = bits & ((1 << 52) - 1);
+
+	let (lead, exponent) = match exponent {
+		0 if mantissa == 0 => return format!("{}0x0p+0", sign),
+		// Subnormals have no implicit leading one
+		0 => (0, -1022),
+		_ => (1, exponent - 1023),
+	};
+
+	let fraction = format!("{:013x}", mantissa);
+	let fraction = fraction.trim_end_matches('0');
+
+	if fraction.is_empty() {
+		format!("{}0x{}p{:+}", sign, lead, exponent)
+	} else {
+		format!("{}0x{}.{}p{:+}", sign, lead, fraction, exponent)
+	}
+}
+
+/// Whether the value is rendered like a TOML `[section]` (a table or an array of tables)
+fn is_section(value: &Value) -> bool {
+	match value {
+		Value::Table(_) => true,
+		Value::Array(a) => !a.is_empty() && a.iter().all(Value::is_table),
+		_ => false,
+	}
+}
+
+/// Values ordered by key if the keys are exactly `"1"` to `"n"`
+fn sequence_items(table: &Table) -> Option<Vec<&Value>> {
+	if table.is_empty() {
+		return None;
+	}
+
+	let mut items = vec![None; table.len()];
+
+	for (key, value) in table {
+		let index: usize = key.parse().ok()?;
+
+		// Rejects keys like `01` or `+1` that Lua would keep as strings
+		if index == 0 || index > items.len() || index.to_string() != *key {
+			return None;
+		}
+
+		items[index - 1] = Some(value);
+	}
+
+	items.into_iter().collect()
+}
+
+/// Leaf value of a flattened document
+pub(crate) struct Leaf<'v> {
+	/// Key path joined with the `flatten_separator`
+	pub key: String,
+	/// Dotted key path in the document, keys holding a `.` are quoted
+	pub path: String,
+	pub value: &'v Value,
+}
+
+/// Leaf values of a document keyed by their path joined with `flatten_separator`,
+/// empty tables and arrays count as leaves, as do all arrays unless `flatten_arrays` is enabled
+pub(crate) fn flatten<'v, I>(entries: I, options: &ParseOptions) -> Vec<Leaf<'v>>
+where
+	I: IntoIterator<Item = (&'v String, &'v Value)>,
+{
+	let mut leaves = Vec::new();
+
+	for (key, value) in entries {
+		let leaf = Leaf {
+			key: key.clone(),
+			path: path_segment(key),
+			value,
+		};
+
+		flatten_into(leaf, options, &mut leaves);
+	}
+
+	leaves
+}
+
+fn flatten_into<'v>(leaf: Leaf<'v>, options: &ParseOptions, leaves: &mut Vec<Leaf<'v>>) {
+	let separator = &options.flatten_separator;
+	let nested = |key: &str, value| Leaf {
+		key: format!("{}{}{}", leaf.key, separator, key),
+		path: format!("{}.{}", leaf.path, path_segment(key)),
+		value,
+	};
+
+	match leaf.value {
+		Value::Table(table) if !table.is_empty() => {
+			for (key, value) in table {
+				flatten_into(nested(key, value), options, leaves);
+			}
+		}
+		Value::Array(array) if !array.is_empty() && options.flatten_arrays => {
+			for (i, value) in array.iter().enumerate() {
+				flatten_into(nested(&(i + 1).to_string(), value), options, leaves);
+			}
+		}
+		_ => leaves.push(leaf),
+	}
+}
+
+/// Key as a segment of a dotted path, quoted if it holds a `.`
+pub(crate) fn path_segment(key: &str) -> String {
+	if key.contains('.') {
+		format!("{:?}", key)
+	} else {
+		key.to_owned()
+	}
+}
+
+/// Keys of the path joined with dots, the ones holding a `.` quoted by [`path_segment`]
+pub(crate) fn join_path(path: &[String]) -> String {
+	let segments: Vec<String> = path.iter().map(|key| path_segment(key)).collect();
+
+	segments.join(".")
+}
+
+/// Keys of a dotted path written with [`path_segment`], a quoted segment is
+/// unescaped and one without a closing quote is taken as it is
+pub(crate) fn split_path(path: &str) -> Vec<String> {
+	let mut segments = Vec::new();
+	let mut rest = path;
+
+	loop {
+		if let Some((key, next)) = rest.strip_prefix('"').and_then(unquote) {
+			if next.is_empty() {
+				segments.push(key);
+				return segments;
+			}
+
+			if let Some(next) = next.strip_prefix('.') {
+				segments.push(key);
+				rest = next;
+				continue;
+			}
+		}
+
+		match rest.split_once('.') {
+			Some((segment, next)) => {
+				segments.push(segment.to_owned());
+				rest = next;
+			}
+			None => {
+				segments.push(rest.to_owned());
+				return segments;
+			}
+		}
+	}
+}
+
+/// Key quoted by [`path_segment`] up to its closing quote and the text after
+/// it, the opening quote already stripped
+fn unquote(quoted: &str) -> Option<(String, &str)> {
+	let mut key = String::new();
+	let mut chars = quoted.char_indices();
+
+	while let Some((i, c)) = chars.next() {
+		match c {
+			'"' => return Some((key, &quoted[i + 1..])),
+			'\\' => key.push(match chars.next()?.1 {
+				'n' => '\n',
+				'r' => '\r',
+				't' => '\t',
+				'0' => '\0',
+				'u' => {
+					let hex: String = chars
+						.by_ref()
+						.map(|(_, c)| c)
+						.skip_while(|c| *c == '{')
+						.take_while(|c| *c != '}')
+						.collect();
+					char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+				}
+				escaped => escaped,
+			}),
+			c => key.push(c),
+		}
+	}
+
+	None
+}
+
+/// Unused local variable name derived from the key, non-identifier characters become `_`
+fn local_name(key: &str, taken: &mut BTreeSet<String>) -> String {
+	let mut name: String = key
+		.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+		.collect();
+
+	if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+		name.insert(0, '_');
+	}
+
+	if LUA_KEYWORDS.contains(&name.as_str()) {
+		name.push('_');
+	}
+
+	let mut unique = name.clone();
+	let mut suffix = 2;
+
+	while taken.contains(&unique) {
+		unique = format!("{}_{}", name, suffix);
+		suffix += 1;
+	}
+
+	taken.insert(unique.clone());
+
+	unique
+}
+
+/// Whether the key can be written as a bare Lua identifier
+///
+/// Non-ASCII letters are only accepted when `ascii_only` is disabled
+pub(crate) fn is_valid_lua_identifier(key: &str, ascii_only: bool) -> bool {
+	let letter =
+		|c: char| c.is_ascii_alphabetic() || c == '_' || (!ascii_only && c.is_alphabetic());
+	let mut chars = key.chars();
+
+	match chars.next() {
+		Some(c) if letter(c) => {}
+		_ => return false,
+	}
+
+	chars.all(|c| letter(c) || c.is_ascii_digit()) && !LUA_KEYWORDS.contains(&key)
+}
+
+const LUA_KEYWORDS: [&str; 22] = [
+	"and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+	"local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// String literal in the quotes picked by [`ParseOptions::quote_style`]
+pub(crate) fn quote_string(string: &str, options: &ParseOptions) -> String {
+	let quote = match options.quote_style {
+		QuoteStyle::Double => '"',
+		// Same rule as StyLua, single quotes only when they need fewer escapes
+		QuoteStyle::PreferDouble => {
+			if string.matches('"').count() > string.matches('\'').count() {
+				'\''
+			} else {
+				'"'
+			}
+		}
+	};
+
+	format!(
+		"{}{}{}",
+		quote,
+		escape_string(string, quote, options),
+		quote
+	)
+}
+
+/// Long bracket literal of a string spanning several lines if enabled and
+/// the string can be written as one
+fn long_string(string: &str, options: &ParseOptions) -> Option<String> {
+	let writable = |char: char| !char.is_control() || char == '\n' || char == '\t';
+
+	if !options.long_strings
+		|| !string.contains('\n')
+		|| !string.chars().all(writable)
+		|| (options.escape_unicode && !string.is_ascii())
+	{
+		return None;
+	}
+
+	// Lowest level whose closing bracket is first found at the end, also
+	// avoiding the opening bracket which Lua 5.1 rejects inside level 0
+	let level = (0..).map(|level| "=".repeat(level)).find(|equals| {
+		let close = format!("]{}]", equals);
+
+		format!("{}{}", string, close).find(&close) == Some(string.len())
+			&& !string.contains(&format!("[{}[", equals))
+	})?;
+
+	// Lua skips a newline right after the opening bracket, so one is always
+	// written and newlines the string starts with are kept
+	Some(format!("[{0}[\n{1}]{0}]", level, string))
+}
+
+/// Contents of a double quoted string literal
+pub(crate) fn validate_string(string: &str, options: &ParseOptions) -> String {
+	escape_string(string, '"', options)
+}
+
+pub(crate) fn escape_string(string: &str, quote: char, options: &ParseOptions) -> String {
+	let mut validated = String::new();
+
+	for char in string.chars() {
+		match char {
+			'\n' => validated.push_str("\\n"),
+			'\t' => validated.push_str("\\t"),
+			'\r' => validated.push_str("\\r"),
+			'\u{8}' => validated.push_str("\\b"),
+			'\u{c}' => validated.push_str("\\f"),
+			'\\' => validated.push_str("\\\\"),
+			// Other control characters are kept out of the source with decimal escapes
+			_ if char.is_ascii_control() => validated.push_str(&format!("\\{:03}", char as u8)),
+			_ if char == quote => {
+				validated.push('\\');
+				validated.push(char);
+			}
+			// U+2028 and U+2029 are treated as line breaks by some Lua parsers
+			_ if options.escape_unicode && !char.is_ascii() => match options.target {
+				// Decimal escapes of the UTF-8 bytes are the only ones Lua 5.1 has
+				Some(target) if !target.has_unicode_escapes() => {
+					let mut buffer = [0; 4];
+
+					for byte in char.encode_utf8(&mut buffer).bytes() {
+						validated.push_str(&format!("\\{:03}", byte));
+					}
+				}
+				_ => validated.push_str(&format!("\\u{{{:X}}}", char as u32)),
+			},
+			_ => validated.push(char),
+		}
+	}
+
+	validated
+}
+
+#[cfg(test)]
+mod test {
+	#[test]
+	fn duplicate_keys() {
+		use super::KeyScope;
+		use crate::Error;
+
+		let mut scope = KeyScope::default();
+		let path = vec![String::from("object")];
+
+		assert!(scope.insert("a", &path).is_ok());
+		assert!(scope.insert("b", &path).is_ok());
+
+		match scope.insert("a", &path) {
+			Err(Error::DuplicateKey { path, key }) => {
+				assert_eq!(path, "object");
+				assert_eq!(key, "a");
+			}
+			_ => panic!("expected duplicate key error"),
+		}
+	}
+
+	#[test]
+	fn output_limit() {
+		use super::Walker;
+		use crate::{Error, Map, ParseOptions};
+		use toml::{Table, Value};
+
+		// Indentation makes deep nesting grow quadratically, about 400 KB here
+		let mut value = Value::from("leaf");
+
+		for _ in 0..600 {
+			value = Value::Table(Table::from_iter([("k".repeat(32), value)]));
+		}
+
+		let map = Map::from_iter([(String::from("root"), value)]);
+		let options = ParseOptions {
+			max_output_bytes: Some(64 * 1024),
+			..Default::default()
+		};
+
+		let mut walker = Walker::new(&options);
+
+		assert!(matches!(
+			walker.walk_root(&map),
+			Err(Error::OutputTooLarge { limit }) if limit == 64 * 1024
+		));
+
+		// Stopped within a line of the limit
+		assert!(walker.lua.len() < 65 * 1024);
+	}
+
+	#[test]
+	#[cfg(not(feature = "preserve_order"))]
+	fn sorted_keys() {
+		use crate::parse;
+
+		let toml = r#"
+		zebra = 1
+		apple = 2
+
+		[nested]
+		b = true
+		a = false
+"#;
+
+		let lua = r#"{
+	["apple"] = 2,
+	["nested"] = {
+		["a"] = false,
+		["b"] = true,
+	},
+	["zebra"] = 1,
+}"#;
+
+		assert_eq!(parse(toml).unwrap(), lua);
+	}
+
+	#[test]
+	fn lua_identifiers() {
+		use super::is_valid_lua_identifier;
+
+		assert!(is_valid_lua_identifier("key", true));
+		assert!(is_valid_lua_identifier("_private", true));
+		assert!(is_valid_lua_identifier("key_2", true));
+		assert!(is_valid_lua_identifier("café", false));
+
+		assert!(!is_valid_lua_identifier("", true));
+		assert!(!is_valid_lua_identifier("", false));
+		assert!(!is_valid_lua_identifier("2key", true));
+		assert!(!is_valid_lua_identifier("some-key", true));
+		assert!(!is_valid_lua_identifier("some key", true));
+		assert!(!is_valid_lua_identifier("end", true));
+		assert!(!is_valid_lua_identifier("nil", true));
+		assert!(!is_valid_lua_identifier("café", true));
+		assert!(!is_valid_lua_identifier("end", false));
+
+		for key in ["a]b", "a\"b", "a[b", "a'b", "]]"] {
+			assert!(!is_valid_lua_identifier(key, true), "{}", key);
+			assert!(!is_valid_lua_identifier(key, false), "{}", key);
+		}
+	}
+
+	#[test]
+	fn lua51_escapes() {
+		use super::escape_string;
+		use crate::{LuaTarget, ParseOptions};
+
+		let escaped = |target| {
+			let options = ParseOptions {
+				escape_unicode: true,
+				..ParseOptions::for_target(target)
+			};
+
+			escape_string("\u{1F600}1 é", '"', &options)
+		};
+
+		// Always three digits so a following digit can't be read as part of the escape
+		assert_eq!(
+			escaped(LuaTarget::Lua51),
+			"\\240\\159\\152\\1281 \\195\\169"
+		);
+		assert_eq!(escaped(LuaTarget::Lua53), "\\u{1F600}1 \\u{E9}");
+	}
+}
@@ -0,0 +1,59 @@
+use alloc::string::String;
+use core::fmt::{self, Display, Formatter};
+
+use crate::Error;
+
+/// Notable event that did not stop the conversion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+	pub kind: WarningKind,
+	/// Dotted path of the affected key, empty when the whole document is affected
+	pub path: String,
+	/// Human-readable description
+	pub message: String,
+}
+
+/// What a [`Warning`] is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+	/// Invalid UTF-8 bytes of the input were replaced with U+FFFD
+	InvalidUtf8,
+	/// Key is not described by the schema
+	UnknownKey,
+	/// Entry was left out, its Lua key is taken by another one
+	KeyLeftOut,
+	/// String was written as the integer, float or boolean it spells
+	Coerced,
+	/// Value was written as the `redact` placeholder or a neutral value
+	Redacted,
+	/// Datetime the `datetime_mode` can't express was written as a string
+	DatetimeAsString,
+	/// Doc comment or `redact` pattern matches nothing in the document
+	Unmatched,
+	/// Integer beyond 2^53 is loaded with lost precision by Lua versions
+	/// without integers, unless the `target` has them
+	PrecisionLoss,
+	/// NaN or an infinity was written as a division by zero, or as an integer
+	/// with [`NumberMode::AllInteger`](crate::NumberMode::AllInteger)
+	NonFinite,
+	/// Keys of a table only differ by case, see [`ParseOptions::warn_case_collisions`](crate::ParseOptions::warn_case_collisions)
+	CaseCollision,
+}
+
+/// Fail with the first of the warnings if they are denied, see [`ParseOptions::deny_warnings`](crate::ParseOptions::deny_warnings)
+pub(crate) fn check_denied(warnings: &[Warning], deny: bool) -> Result<(), Error> {
+	match warnings.first() {
+		Some(warning) if deny => Err(Error::DeniedWarning(warning.clone())),
+		_ => Ok(()),
+	}
+}
+
+impl Display for Warning {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		if self.path.is_empty() {
+			write!(f, "{}", self.message)
+		} else {
+			write!(f, "`{}`: {}", self.path, self.message)
+		}
+	}
+}
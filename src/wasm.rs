@@ -0,0 +1,10 @@
+use alloc::string::{String, ToString};
+use wasm_bindgen::prelude::*;
+
+use crate::parse;
+
+/// Parse TOML string into a Lua table, errors are returned as JS strings
+#[wasm_bindgen]
+pub fn parse_wasm(toml: &str) -> Result<String, JsValue> {
+	parse(toml).map_err(|err| JsValue::from_str(&err.to_string()))
+}